@@ -42,6 +42,11 @@ pub struct Args {
     /// Cache password
     #[arg(long, default_value = "")]
     pub cache_password: String,
+
+    /// Production mode, refuse to start while the default API secret encrypt key is in use.
+    /// Rotate the key in non-production mode first.
+    #[arg(long, default_value_t = false)]
+    pub production: bool,
 }
 
 impl Args {
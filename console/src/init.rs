@@ -1,12 +1,15 @@
 //use crate::config::config;
 //use crate::config::config::AppConfig;
 use crate::args::Args;
+use crate::server::db::models::system_config::{ConfigKey, SystemConfig};
 use crate::server::{db, task};
+use aiway_protocol::gateway::Firewall;
 use anyhow::Context;
 use common::dir::AppDir;
 use common::id;
-use logging::LogAppender;
+use logging::{LogAppender, log};
 use std::fs;
+use std::process::exit;
 
 pub async fn init(args: &Args) {
     // 初始化日志
@@ -36,6 +39,9 @@ pub async fn init(args: &Args) {
     #[cfg(feature = "standalone")]
     cache::init_share_cache().await.unwrap();
 
+    // 校验API Key的加密密钥
+    check_encrypt_key(args).await;
+
     // 初始化定时任务
     task::start().await.unwrap();
 
@@ -43,6 +49,25 @@ pub async fn init(args: &Args) {
     alert::init(format!("{}:{}", args.address, args.port));
 }
 
+/// 生产模式下使用默认的加密密钥时拒绝启动，默认密钥是公开的，可以用来伪造API Key
+async fn check_encrypt_key(args: &Args) {
+    let firewall = match SystemConfig::get::<Firewall>(ConfigKey::Firewall).await {
+        Ok(firewall) => firewall,
+        Err(e) => {
+            log::error!("读取防火墙配置失败，无法检查API Key的加密密钥：{}", e);
+            exit(1);
+        }
+    };
+    if !firewall.is_default_encrypt_key() {
+        return;
+    }
+    if args.production {
+        log::error!("API Key的加密密钥为默认密钥，生产模式下无法启动，请先在非生产模式下轮换密钥");
+        exit(1);
+    }
+    log::warn!("API Key的加密密钥为默认密钥，请在防火墙配置中轮换密钥");
+}
+
 fn init_dir() -> anyhow::Result<()> {
     AppDir::init_all();
 
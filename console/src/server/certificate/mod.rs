@@ -0,0 +1,6 @@
+pub mod api;
+mod request;
+mod response;
+mod service;
+
+pub use request::CertificateListReq;
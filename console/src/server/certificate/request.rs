@@ -0,0 +1,51 @@
+use crate::server::db::models::certificate::CertificateStatus;
+use busi::impl_pagination;
+use busi::req::PageReq;
+use rocket::serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CertificateAddReq {
+    /// 证书名称，全局唯一
+    pub name: String,
+    /// 描述
+    pub description: Option<String>,
+    /// 证书适用的域名，支持通配符，如：example.com、*.example.com
+    pub hosts: Vec<String>,
+    /// 证书链，PEM格式，第一个为服务端证书
+    pub cert: String,
+    /// 私钥，PEM格式
+    pub key: String,
+    /// 验证客户端证书的CA证书，PEM格式，可包含多个，为空时不请求客户端证书
+    pub client_ca: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CertificateUpdateReq {
+    pub id: i64,
+    /// 描述
+    pub description: Option<String>,
+    /// 证书适用的域名
+    pub hosts: Option<Vec<String>>,
+    /// 证书链，PEM格式，更换证书时需同时传入私钥
+    pub cert: Option<String>,
+    /// 私钥，PEM格式
+    pub key: Option<String>,
+    /// 验证客户端证书的CA证书，PEM格式，传入空字符串时清除
+    pub client_ca: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CertificateListReq {
+    pub page: PageReq,
+    /// 模糊搜索：证书名/描述/域名
+    pub filter_text: Option<String>,
+    /// 状态
+    pub status: Option<CertificateStatus>,
+}
+impl_pagination!(CertificateListReq);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateStatusReq {
+    pub id: i64,
+    pub status: CertificateStatus,
+}
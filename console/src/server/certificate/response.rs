@@ -0,0 +1,9 @@
+use crate::server::db::models::certificate::Certificate;
+use rocket::serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CertificateListRes {
+    /// 证书信息，不返回私钥
+    #[serde(flatten)]
+    pub inner: Certificate,
+}
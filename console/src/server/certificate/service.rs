@@ -0,0 +1,205 @@
+use crate::server::auth::UserPrincipal;
+use crate::server::certificate::request::{
+    CertificateAddReq, CertificateListReq, CertificateUpdateReq, UpdateStatusReq,
+};
+use crate::server::certificate::response::CertificateListRes;
+use crate::server::db::models::certificate;
+use crate::server::db::models::certificate::{Certificate, CertificateBuilder, CertificateStatus};
+use crate::server::db::{Pool, tools};
+use anyhow::bail;
+use busi::req::{IdsReq, Pagination};
+use busi::res::{IntoPageRes, PageRes};
+use common::id;
+use rbs::value;
+use rustls_pki_types::pem::PemObject;
+use rustls_pki_types::{CertificateDer, PrivateKeyDer};
+
+pub async fn add(req: CertificateAddReq, user: UserPrincipal) -> anyhow::Result<()> {
+    let certificate = CertificateBuilder::default()
+        .id(id::next().into())
+        .name(req.name.into())
+        .description(req.description)
+        .status(CertificateStatus::Disable.into())
+        .hosts(normalize_hosts(req.hosts).into())
+        .cert(req.cert.into())
+        .key(req.key.into())
+        .client_ca(req.client_ca)
+        .create_user_id(Some(user.id))
+        .create_time(Some(tools::now()))
+        .build()?;
+
+    let others = Certificate::select_all(Pool::get()?).await?;
+    if others.iter().any(|item| item.name == certificate.name) {
+        bail!(
+            "Certificate with name {} already exists",
+            certificate.name.unwrap()
+        )
+    }
+    check_hosts(certificate.hosts.as_ref(), &others)?;
+    check_pem(certificate.cert.as_ref(), certificate.key.as_ref())?;
+    check_client_ca(certificate.client_ca.as_ref())?;
+    Certificate::insert(Pool::get()?, &certificate).await?;
+    Ok(())
+}
+
+/// 域名转为小写并去除空白
+fn normalize_hosts(hosts: Vec<String>) -> Vec<String> {
+    hosts
+        .into_iter()
+        .map(|host| host.trim().to_ascii_lowercase())
+        .collect()
+}
+
+/// 校验域名格式，且不能与其他证书的域名重复
+fn check_hosts(hosts: Option<&Vec<String>>, others: &[Certificate]) -> anyhow::Result<()> {
+    let Some(hosts) = hosts else {
+        return Ok(());
+    };
+    if hosts.is_empty() {
+        bail!("证书配置错误：域名不能为空");
+    }
+    for host in hosts {
+        if !is_valid_host(host) {
+            bail!("证书配置错误：域名{}无效", host);
+        }
+        if let Some(other) = others
+            .iter()
+            .find(|other| other.hosts.as_ref().is_some_and(|h| h.contains(host)))
+        {
+            bail!(
+                "证书配置错误：域名{}已被证书{}使用",
+                host,
+                other.name.as_deref().unwrap_or_default()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// 域名由字母、数字、`-`组成，通配符仅支持`*.`开头，如：*.example.com
+fn is_valid_host(host: &str) -> bool {
+    let host = host.strip_prefix("*.").unwrap_or(host);
+    host.len() <= 253
+        && host.contains('.')
+        && host.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        })
+}
+
+/// 校验证书和私钥为有效的PEM格式，证书与私钥是否匹配由网关在加载时校验
+fn check_pem(cert: Option<&String>, key: Option<&String>) -> anyhow::Result<()> {
+    if let Some(cert) = cert {
+        let certs = CertificateDer::pem_slice_iter(cert.as_bytes()).collect::<Result<Vec<_>, _>>();
+        if !certs.is_ok_and(|certs| !certs.is_empty()) {
+            bail!("证书配置错误：证书不是有效的PEM格式");
+        }
+    }
+    if let Some(key) = key
+        && PrivateKeyDer::from_pem_slice(key.as_bytes()).is_err()
+    {
+        bail!("证书配置错误：私钥不是有效的PEM格式");
+    }
+    Ok(())
+}
+
+/// 校验客户端CA证书为有效的PEM格式，空字符串表示不请求客户端证书
+fn check_client_ca(client_ca: Option<&String>) -> anyhow::Result<()> {
+    if let Some(client_ca) = client_ca
+        && !client_ca.trim().is_empty()
+    {
+        let certs =
+            CertificateDer::pem_slice_iter(client_ca.as_bytes()).collect::<Result<Vec<_>, _>>();
+        if !certs.is_ok_and(|certs| !certs.is_empty()) {
+            bail!("证书配置错误：客户端CA证书不是有效的PEM格式");
+        }
+    }
+    Ok(())
+}
+
+pub async fn list(req: CertificateListReq) -> anyhow::Result<PageRes<CertificateListRes>> {
+    let page = certificate::list_page(Pool::get()?, &req.to_rb_page(), &req).await?;
+    let list = page.convert_to_page_res(|list| {
+        list.into_iter()
+            .map(|mut item| {
+                item.key = None;
+                CertificateListRes { inner: item }
+            })
+            .collect::<Vec<_>>()
+    });
+    Ok(list)
+}
+
+pub async fn update(req: CertificateUpdateReq, user: UserPrincipal) -> anyhow::Result<()> {
+    let old = Certificate::select_by_map(Pool::get()?, value! { "id": req.id}).await?;
+    if old.is_empty() {
+        bail!("Certificate not found");
+    }
+    if req.cert.is_some() != req.key.is_some() {
+        bail!("证书配置错误：更换证书时需同时传入证书和私钥");
+    }
+
+    let update = CertificateBuilder::default()
+        .id(req.id.into())
+        .description(req.description)
+        .hosts(req.hosts.map(normalize_hosts))
+        .cert(req.cert)
+        .key(req.key)
+        .client_ca(req.client_ca)
+        .update_user_id(Some(user.id))
+        .update_time(Some(tools::now()))
+        .build()?;
+    let mut others = Certificate::select_all(Pool::get()?).await?;
+    others.retain(|item| item.id != Some(req.id));
+    check_hosts(update.hosts.as_ref(), &others)?;
+    check_pem(update.cert.as_ref(), update.key.as_ref())?;
+    check_client_ca(update.client_ca.as_ref())?;
+
+    Certificate::update_by_map(Pool::get()?, &update, value! { "id":req.id}).await?;
+    Ok(())
+}
+
+pub async fn delete(req: IdsReq) -> anyhow::Result<()> {
+    Certificate::delete_by_map(Pool::get()?, value! { "id": req.ids}).await?;
+    Ok(())
+}
+
+pub(crate) async fn update_status(req: UpdateStatusReq, user: UserPrincipal) -> anyhow::Result<()> {
+    let old = Certificate::select_by_map(Pool::get()?, value! { "id": req.id}).await?;
+    if old.is_empty() {
+        bail!("Certificate not found")
+    }
+    Certificate::update_by_map(
+        Pool::get()?,
+        &CertificateBuilder::default()
+            .id(Some(req.id))
+            .status(Some(req.status))
+            .update_user_id(Some(user.id))
+            .update_time(Some(tools::now()))
+            .build()?,
+        value! { "id": req.id},
+    )
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_valid_host() {
+        assert!(is_valid_host("example.com"));
+        assert!(is_valid_host("*.example.com"));
+        assert!(is_valid_host("api-1.example.com"));
+        assert!(!is_valid_host("api.*.example.com"));
+        assert!(!is_valid_host("localhost"));
+        assert!(!is_valid_host("-api.example.com"));
+        assert!(!is_valid_host("api..example.com"));
+    }
+}
@@ -14,10 +14,12 @@ use strum_macros::EnumIter;
 use logging::log;
 
 mod v0_1_0;
+mod v0_1_7;
 
 #[derive(EnumIter, Debug, PartialEq)]
 pub enum AppVersion {
     V0_1_0,
+    V0_1_7,
 }
 
 impl FromStr for AppVersion {
@@ -25,6 +27,7 @@ impl FromStr for AppVersion {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "0.1.0" => Ok(AppVersion::V0_1_0),
+            "0.1.7" => Ok(AppVersion::V0_1_7),
             _ => bail!("Unknown version: {}", s),
         }
     }
@@ -34,6 +37,7 @@ impl Display for AppVersion {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let str = match self {
             AppVersion::V0_1_0 => "0.1.0".to_string(),
+            AppVersion::V0_1_7 => "0.1.7".to_string(),
         };
         write!(f, "{}", str)
     }
@@ -48,12 +52,14 @@ pub trait Migrator {
 
 pub enum MigratorHandler {
     V0_1_0(v0_1_0::Handler),
+    V0_1_7(v0_1_7::Handler),
 }
 
 impl MigratorHandler {
     pub async fn up(&self, conn: &mut RBatis) -> anyhow::Result<()> {
         match self {
             MigratorHandler::V0_1_0(handler) => handler.up(conn).await,
+            MigratorHandler::V0_1_7(handler) => handler.up(conn).await,
         }
     }
 }
@@ -62,6 +68,7 @@ impl AppVersion {
     fn get_migrator(&self) -> MigratorHandler {
         match self {
             AppVersion::V0_1_0 => MigratorHandler::V0_1_0(v0_1_0::Handler),
+            AppVersion::V0_1_7 => MigratorHandler::V0_1_7(v0_1_7::Handler),
         }
     }
 }
@@ -72,6 +79,8 @@ const INSERT_VERSION_SQL: &str =
     "INSERT INTO system_config (config_key, config_value) VALUES ('version', ?)";
 const UPDATE_VERSION_SQL: &str =
     "UPDATE system_config SET config_value = ? WHERE config_key = 'version'";
+/// 引入版本记录前的版本，库中没有版本信息时从该版本升级，升级脚本需要可以重复执行
+const BASE_VERSION: &str = "0.1.6";
 pub async fn run(rb: &mut RBatis) {
     // 获取系统已有的版本号
     let result = rb
@@ -87,14 +96,17 @@ pub async fn run(rb: &mut RBatis) {
     let current_version = match result {
         Some(value) => value,
         None => {
-            // 库中没有版本信息，设置已安装版本为当前程序版本
-            rb.exec(INSERT_VERSION_SQL, vec![Value::String(VERSION.to_string())])
-                .await
-                .unwrap_or_else(|e| {
-                    log::error!("Failed to set system version: {}", e);
-                    exit(1);
-                });
-            return;
+            // 库中没有版本信息，可能是新安装或从旧版本升级，从BASE_VERSION开始升级
+            rb.exec(
+                INSERT_VERSION_SQL,
+                vec![Value::String(BASE_VERSION.to_string())],
+            )
+            .await
+            .unwrap_or_else(|e| {
+                log::error!("Failed to set system version: {}", e);
+                exit(1);
+            });
+            BASE_VERSION.to_string()
         }
     };
 
@@ -0,0 +1,66 @@
+use crate::server::db::migrations::{AppVersion, Migrator};
+use rbatis::RBatis;
+
+pub(crate) struct Handler;
+
+impl Migrator for Handler {
+    fn version(&self) -> AppVersion {
+        AppVersion::V0_1_7
+    }
+
+    /// 执行`up.sql`中的语句，已存在的字段会跳过，可以重复执行
+    async fn up(&self, conn: &mut RBatis) -> anyhow::Result<()> {
+        for sql in statements(include_str!("up.sql")) {
+            if let Some((table, column)) = add_column(&sql)
+                && column_exists(conn, table, column).await
+            {
+                continue;
+            }
+            conn.exec(&sql, vec![]).await?;
+        }
+        Ok(())
+    }
+}
+
+/// 拆分SQL脚本为单条语句，忽略注释
+fn statements(script: &str) -> Vec<String> {
+    script
+        .lines()
+        .map(|line| line.split("--").next().unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join("\n")
+        .split(';')
+        .map(|sql| sql.trim().to_string())
+        .filter(|sql| !sql.is_empty())
+        .collect()
+}
+
+/// 解析`alter table {table} add column {column} ...`语句，返回表名和字段名
+fn add_column(sql: &str) -> Option<(&str, &str)> {
+    match sql.split_whitespace().collect::<Vec<_>>()[..] {
+        ["alter", "table", table, "add", "column", column, ..] => Some((table, column)),
+        _ => None,
+    }
+}
+
+async fn column_exists(conn: &RBatis, table: &str, column: &str) -> bool {
+    conn.query(&format!("select {} from {} limit 1", column, table), vec![])
+        .await
+        .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_statements() {
+        let sqls = statements(include_str!("up.sql"));
+        assert_eq!(add_column(&sqls[0]), Some(("service", "weights")));
+        assert!(sqls.iter().all(|sql| !sql.contains("--")));
+        assert!(
+            sqls.iter()
+                .any(|sql| sql.starts_with("create table if not exists api_key_usage"))
+        );
+    }
+}
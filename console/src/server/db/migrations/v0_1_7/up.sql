@@ -0,0 +1,85 @@
+-- 0.1.7：服务、路由、API Key新增的配置字段，证书、JWT签发方和API Key用量表
+-- 语句兼容SQLite和MySQL，集群模式下可直接执行该脚本升级
+
+alter table service add column weights varchar(5000);
+alter table service add column hash_key varchar(200);
+alter table service add column health_check varchar(1000);
+alter table service add column timeouts varchar(200);
+alter table service add column pool varchar(200);
+alter table service add column circuit_breaker varchar(2000);
+alter table service add column protocol varchar(20);
+alter table service add column tls text;
+
+alter table route add column path_type varchar(20);
+alter table route add column priority int not null default 0;
+alter table route add column action varchar(2000);
+alter table route add column maintenance tinyint(1) not null default 0;
+alter table route add column maintenance_page varchar(2000);
+alter table route add column backends varchar(2000);
+alter table route add column auth varchar(2000);
+alter table route add column rewrite varchar(2000);
+alter table route add column retry varchar(500);
+alter table route add column timeouts varchar(200);
+alter table route add column rate_limit varchar(500);
+alter table route add column proxy_headers varchar(2000);
+alter table route add column stream_body tinyint(1) not null default 0;
+alter table route add column websocket tinyint(1) not null default 0;
+alter table route add column client_cert tinyint(1) not null default 0;
+alter table route add column mirror varchar(500);
+
+alter table api_key add column scope varchar(5000);
+alter table api_key add column quota varchar(500);
+alter table api_key add column expire_notified tinyint(1) not null default 0;
+
+alter table gateway_node_state add column interval_request_ratelimit_count bigint not null default 0;
+alter table gateway_node_state add column request_ratelimit_count bigint not null default 0;
+alter table gateway_node_state add column websocket_connect_count bigint not null default 0;
+
+create table if not exists certificate
+(
+    id             bigint primary key,
+    name           varchar(100)  not null,
+    description    varchar(500),
+    status         varchar(20)   not null,
+    hosts          varchar(5000) not null,
+    cert           text          not null,
+    `key`          text          not null,
+    client_ca      text,
+    create_user_id bigint,
+    update_user_id bigint,
+    create_time    datetime,
+    update_time    datetime,
+    remark         varchar(500),
+    is_delete      tinyint(1)    not null default 0
+);
+
+create table if not exists jwt_provider
+(
+    id             bigint primary key,
+    name           varchar(100)  not null,
+    description    varchar(500),
+    status         varchar(20)   not null,
+    algorithm      varchar(20)   not null,
+    `key`          text,
+    jwks           text,
+    jwks_file      varchar(500),
+    issuer         varchar(500),
+    audiences      varchar(2000),
+    leeway         int           not null default 60,
+    create_user_id bigint,
+    update_user_id bigint,
+    create_time    datetime,
+    update_time    datetime,
+    remark         varchar(500),
+    is_delete      tinyint(1)    not null default 0
+);
+
+create table if not exists api_key_usage
+(
+    principal   varchar(500) not null,
+    usage_date  varchar(8)   not null,
+    requests    bigint       not null default 0,
+    tokens      bigint       not null default 0,
+    update_time datetime,
+    constraint uk_principal_usage_date unique (principal, usage_date)
+);
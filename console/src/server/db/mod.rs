@@ -37,10 +37,9 @@ pub async fn init(args: &Args) -> anyhow::Result<()> {
     };
 
     // 单机模式下执行版本升级
-    // 集群模式下需要提供升级脚本执行
-    // if AppConfig::mode() == &config::Mode::Standalone {
-    //     migrations::run(&mut Pool::get()?.clone()).await;
-    // }
+    // 集群模式下需要执行各版本的升级脚本，见migrations/*/up.sql
+    #[cfg(feature = "standalone")]
+    migrations::run(&mut Pool::get()?.clone()).await;
 
     Ok(())
 }
@@ -1,4 +1,5 @@
 use crate::server::key::ApiKeyListReq;
+use aiway_protocol::gateway::{ApiKeyQuota, ApiKeyScope};
 use derive_builder::Builder;
 use rbatis::rbdc::DateTime;
 use rbatis::{crud, htmlsql_select_page};
@@ -15,7 +16,7 @@ pub struct ApiKey {
     pub principal: Option<String>,
     /// 密钥
     pub secret: Option<String>,
-    /// 状态，0禁用 1启用
+    /// 状态：Disable | Ok | Expired
     pub status: Option<ApiKeyStatus>,
     /// 生效时间
     #[serde(serialize_with = "crate::server::common::serialize_datetime")]
@@ -25,6 +26,13 @@ pub struct ApiKey {
     pub exp_time: Option<DateTime>,
     /// 来源
     pub source: Option<ApiKeySource>,
+    /// 授权范围，JSON对象，为空时不限制
+    pub scope: Option<ApiKeyScope>,
+    /// 月度配额，JSON对象，为空时不限制
+    pub quota: Option<ApiKeyQuota>,
+    /// 是否已发送即将过期的提醒
+    #[serde(deserialize_with = "crate::server::common::deserialize_bool_from_int")]
+    pub expire_notified: Option<bool>,
     /// 创建人ID
     pub create_user_id: Option<i64>,
     /// 修改人ID
@@ -48,6 +56,8 @@ pub enum ApiKeyStatus {
     Disable,
     /// 启用
     Ok,
+    /// 已过期，由定时任务标记
+    Expired,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
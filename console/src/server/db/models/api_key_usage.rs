@@ -0,0 +1,59 @@
+use crate::server::key::ApiKeyUsageReq;
+use derive_builder::Builder;
+use rbatis::executor::Executor;
+use rbatis::rbdc::DateTime;
+use rbatis::{RBatis, crud, htmlsql};
+use rbs::value;
+use rocket::serde::{Deserialize, Serialize};
+
+/// API Key用量（按天），由定时任务每分钟从缓存同步一次
+#[derive(Debug, Clone, Serialize, Deserialize, Builder, Default)]
+#[builder(default)]
+pub struct ApiKeyUsage {
+    /// 主体标识
+    pub principal: Option<String>,
+    /// 日期，如20250101
+    pub usage_date: Option<String>,
+    /// 请求次数
+    pub requests: Option<i64>,
+    /// Token数
+    pub tokens: Option<i64>,
+    /// 更新时间
+    #[serde(serialize_with = "crate::server::common::serialize_datetime")]
+    pub update_time: Option<DateTime>,
+}
+
+crud!(ApiKeyUsage {});
+htmlsql!(usage_by_day(rb: &dyn Executor, param: &ApiKeyUsageReq) -> Vec<ApiKeyUsage> => "src/server/db/mapper/api_key_usage.html");
+
+impl ApiKeyUsage {
+    /// 新增或更新主体当天的用量，依赖`(principal, usage_date)`唯一索引
+    pub async fn upsert(rb: &RBatis, usage: &ApiKeyUsage) -> anyhow::Result<()> {
+        let sql = match rb.driver_type()? {
+            "mysql" => {
+                "insert into api_key_usage (principal, usage_date, requests, tokens, update_time) \
+                 values (?, ?, ?, ?, ?) \
+                 on duplicate key update requests = values(requests), tokens = values(tokens), \
+                 update_time = values(update_time)"
+            }
+            _ => {
+                "insert into api_key_usage (principal, usage_date, requests, tokens, update_time) \
+                 values (?, ?, ?, ?, ?) \
+                 on conflict (principal, usage_date) do update set requests = excluded.requests, \
+                 tokens = excluded.tokens, update_time = excluded.update_time"
+            }
+        };
+        rb.exec(
+            sql,
+            vec![
+                value!(&usage.principal),
+                value!(&usage.usage_date),
+                value!(&usage.requests),
+                value!(&usage.tokens),
+                value!(&usage.update_time),
+            ],
+        )
+        .await?;
+        Ok(())
+    }
+}
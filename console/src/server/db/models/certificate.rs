@@ -0,0 +1,52 @@
+use crate::server::certificate::CertificateListReq;
+use derive_builder::Builder;
+use rbatis::rbdc::DateTime;
+use rbatis::{crud, htmlsql_select_page};
+use rocket::serde::{Deserialize, Serialize};
+
+/// TLS证书
+#[derive(Debug, Clone, Serialize, Deserialize, Builder, Default)]
+#[builder(default)]
+pub struct Certificate {
+    pub id: Option<i64>,
+    /// 证书名称，全局唯一
+    pub name: Option<String>,
+    /// 描述
+    pub description: Option<String>,
+    /// 状态，停用的证书不会同步到网关
+    pub status: Option<CertificateStatus>,
+    /// 证书适用的域名，JSON数组，小写，支持通配符，如["example.com","*.example.com"]
+    pub hosts: Option<Vec<String>>,
+    /// 证书链，PEM格式，第一个为服务端证书
+    pub cert: Option<String>,
+    /// 私钥，PEM格式
+    pub key: Option<String>,
+    /// 验证客户端证书的CA证书，PEM格式，为空时不请求客户端证书
+    pub client_ca: Option<String>,
+    /// 创建人ID
+    pub create_user_id: Option<i64>,
+    /// 修改人ID
+    pub update_user_id: Option<i64>,
+    /// 创建时间
+    #[serde(serialize_with = "crate::server::common::serialize_datetime")]
+    pub create_time: Option<DateTime>,
+    /// 更新时间
+    #[serde(serialize_with = "crate::server::common::serialize_datetime")]
+    pub update_time: Option<DateTime>,
+    /// 备注
+    pub remark: Option<String>,
+    /// 是否删除
+    pub is_delete: Option<i8>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub enum CertificateStatus {
+    /// 停用
+    #[default]
+    Disable,
+    /// 启用
+    Ok,
+}
+
+crud!(Certificate {});
+htmlsql_select_page!(list_page(param: &CertificateListReq) -> Certificate => "src/server/db/mapper/certificate.html");
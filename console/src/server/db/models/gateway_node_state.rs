@@ -39,6 +39,8 @@ pub struct GatewayNodeState {
     pub http_connect_count: isize,
     /// SSE连接数
     pub sse_connect_count: isize,
+    /// WebSocket连接数
+    pub websocket_connect_count: isize,
     /// 平均QPS(统计周期内)
     pub avg_qps: usize,
     //////////////////////////// 区间内统计 ////////////////////////////
@@ -46,6 +48,9 @@ pub struct GatewayNodeState {
     pub interval_request_count: usize,
     /// 区间无效请求次数
     pub interval_request_invalid_count: usize,
+    /// 区间被限流请求次数
+    #[serde(default)]
+    pub interval_request_ratelimit_count: usize,
     /// 区间响应成功次数
     pub interval_response_2xx_count: usize,
     /// 区间3xx响应次数
@@ -61,6 +66,9 @@ pub struct GatewayNodeState {
     pub request_count: usize,
     /// 累计无效请求次数
     pub request_invalid_count: usize,
+    /// 累计被限流请求次数
+    #[serde(default)]
+    pub request_ratelimit_count: usize,
     /// 累计响应成功次数
     pub response_2xx_count: usize,
     /// 累计3xx响应次数
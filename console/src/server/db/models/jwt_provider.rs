@@ -0,0 +1,59 @@
+use crate::server::jwt::JwtProviderListReq;
+use aiway_protocol::gateway::jwt::JwtAlgorithm;
+use derive_builder::Builder;
+use rbatis::rbdc::DateTime;
+use rbatis::{crud, htmlsql_select_page};
+use rocket::serde::{Deserialize, Serialize};
+
+/// JWT签发方
+#[derive(Debug, Clone, Serialize, Deserialize, Builder, Default)]
+#[builder(default)]
+pub struct JwtProvider {
+    pub id: Option<i64>,
+    /// 签发方名称，全局唯一，路由通过名称引用
+    pub name: Option<String>,
+    /// 描述
+    pub description: Option<String>,
+    /// 状态，停用的签发方不会同步到网关
+    pub status: Option<JwtProviderStatus>,
+    /// 签名算法：HS256 | RS256 | ES256
+    pub algorithm: Option<JwtAlgorithm>,
+    /// 验证签名的密钥：HS256为共享密钥，RS256和ES256为PEM格式的公钥
+    pub key: Option<String>,
+    /// JWKS文档，JSON格式
+    pub jwks: Option<String>,
+    /// 网关节点上的JWKS文件路径，未配置密钥和JWKS时使用
+    pub jwks_file: Option<String>,
+    /// 签发者
+    pub issuer: Option<String>,
+    /// 受众，JSON数组
+    pub audiences: Option<Vec<String>>,
+    /// 允许的时钟偏差，单位：秒
+    pub leeway: Option<u64>,
+    /// 创建人ID
+    pub create_user_id: Option<i64>,
+    /// 修改人ID
+    pub update_user_id: Option<i64>,
+    /// 创建时间
+    #[serde(serialize_with = "crate::server::common::serialize_datetime")]
+    pub create_time: Option<DateTime>,
+    /// 更新时间
+    #[serde(serialize_with = "crate::server::common::serialize_datetime")]
+    pub update_time: Option<DateTime>,
+    /// 备注
+    pub remark: Option<String>,
+    /// 是否删除
+    pub is_delete: Option<i8>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub enum JwtProviderStatus {
+    /// 停用
+    #[default]
+    Disable,
+    /// 启用
+    Ok,
+}
+
+crud!(JwtProvider {});
+htmlsql_select_page!(list_page(param: &JwtProviderListReq) -> JwtProvider => "src/server/db/mapper/jwt_provider.html");
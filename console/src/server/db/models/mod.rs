@@ -1,6 +1,9 @@
 pub mod api_key;
+pub mod api_key_usage;
+pub mod certificate;
 pub mod gateway_node;
 pub mod gateway_node_state;
+pub mod jwt_provider;
 pub mod message;
 pub mod model;
 pub mod model_provider;
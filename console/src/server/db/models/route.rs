@@ -1,6 +1,12 @@
 use crate::server::route::RouteListReq;
 use derive_builder::Builder;
 use aiway_protocol::gateway::plugin::ConfiguredPlugin;
+use aiway_protocol::gateway::route::{
+    MaintenancePage, MatchCondition, MirrorPolicy, PathRewrite, PathType, ProxyHeaders,
+    RetryPolicy, RouteAction, RouteAuth, WeightedBackend,
+};
+use aiway_protocol::gateway::service::Timeouts;
+use aiway_protocol::gateway::RateLimit;
 use rbatis::rbdc::DateTime;
 use rbatis::{crud, htmlsql_select_page};
 use rocket::serde::{Deserialize, Serialize};
@@ -21,14 +27,27 @@ pub struct Route {
     pub host: Option<String>,
     /// 路由路径，非空
     pub path: Option<String>,
+    /// 路径的匹配方式：wildcard | regex
+    pub path_type: Option<PathType>,
+    /// 优先级，数值越大越优先匹配
+    pub priority: Option<i32>,
+    /// 处理方式，JSON对象，默认转发到服务：proxy | redirect | static | maintenance
+    pub action: Option<RouteAction>,
+    /// 是否维护中，开启后返回维护页面，不影响配置的处理方式
+    #[serde(deserialize_with = "crate::server::common::deserialize_bool_from_int")]
+    pub maintenance: Option<bool>,
+    /// 维护页面，JSON对象
+    pub maintenance_page: Option<MaintenancePage>,
     /// 目标服务名称，非空
     pub service: Option<String>,
+    /// 加权后端，JSON数组，配置后按权重和灰度条件转发到多个服务，目标服务不再生效
+    pub backends: Option<Vec<WeightedBackend>>,
     /// 请求方法：GET | POST | PUT | DELETE | HEAD | OPTIONS | PATCH | TRACE | CONNECT
     pub methods: Option<Vec<String>>,
-    /// 按请求头匹配
-    pub header: Option<BTreeMap<String, String>>,
-    /// 按请求参数匹配
-    pub query: Option<BTreeMap<String, String>>,
+    /// 按请求头匹配，JSON对象，值为字符串时精确匹配
+    pub header: Option<BTreeMap<String, MatchCondition>>,
+    /// 按请求参数匹配，JSON对象，值为字符串时精确匹配
+    pub query: Option<BTreeMap<String, MatchCondition>>,
     /// 请求阶段过滤器，JSON数组
     pub pre_filters: Option<Vec<ConfiguredPlugin>>,
     /// 响应阶段过滤器，JSON数组
@@ -36,9 +55,32 @@ pub struct Route {
     /// 是否开启鉴权
     #[serde(deserialize_with = "crate::server::common::deserialize_bool_from_int")]
     pub is_auth: Option<bool>,
+    /// 鉴权方式，JSON对象，默认为API Key
+    pub auth: Option<RouteAuth>,
     /// 鉴权白名单
     #[serde(deserialize_with = "crate::server::common::deserialize_to_string_vec")]
     pub auth_white_list: Option<Vec<String>>,
+    /// 路径重写配置，JSON对象
+    pub rewrite: Option<PathRewrite>,
+    /// 重试策略，JSON对象
+    pub retry: Option<RetryPolicy>,
+    /// 超时配置，JSON对象，优先于服务上配置的超时
+    pub timeouts: Option<Timeouts>,
+    /// 限流配置，JSON对象
+    pub rate_limit: Option<RateLimit>,
+    /// 转发请求头配置，JSON对象
+    pub proxy_headers: Option<ProxyHeaders>,
+    /// 是否流式转发请求体
+    #[serde(deserialize_with = "crate::server::common::deserialize_bool_from_int")]
+    pub stream_body: Option<bool>,
+    /// 是否允许WebSocket
+    #[serde(deserialize_with = "crate::server::common::deserialize_bool_from_int")]
+    pub websocket: Option<bool>,
+    /// 是否要求客户端证书
+    #[serde(deserialize_with = "crate::server::common::deserialize_bool_from_int")]
+    pub client_cert: Option<bool>,
+    /// 请求镜像配置，JSON对象
+    pub mirror: Option<MirrorPolicy>,
     /// 创建人ID
     pub create_user_id: Option<i64>,
     /// 修改人ID
@@ -1,9 +1,12 @@
 use crate::server::service::ServiceListReq;
 use derive_builder::Builder;
-use aiway_protocol::gateway::service::LbStrategy;
+use aiway_protocol::gateway::service::{
+    CircuitBreaker, HashKey, HealthCheck, LbStrategy, PoolConfig, Protocol, Timeouts, UpstreamTls,
+};
 use rbatis::rbdc::DateTime;
 use rbatis::{crud, htmlsql_select_page};
 use rocket::serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 /// 路由配置
 #[derive(Debug, Clone, Serialize, Deserialize, Builder, Default)]
@@ -20,10 +23,26 @@ pub struct Service {
     /// 这里仅保存节点地址即可，原因是：
     /// 1. 网关仅需要知道节点地址即可发起调用
     /// 2. console可能与节点之间的网络不通，因此不能确保能够在console检测节点状态，进而没必要保存节点状态
-    /// 3. 考虑在网关处或者其他方式实现节点故障处理
+    /// 3. 节点故障由网关的健康检查处理，网关上报的节点健康状态仅保存在内存中
     pub nodes: Option<Vec<String>>,
-    /// 负载均衡策略，可选值：random | round_robin
+    /// 负载均衡策略，可选值：random | round_robin | weighted_round_robin | least_active | consistent_hash
     pub lb: Option<LbStrategy>,
+    /// 节点权重，JSON对象，key为节点地址，如{"http://127.0.0.1:8080": 2}
+    pub weights: Option<HashMap<String, u32>>,
+    /// 一致性哈希的key来源，仅在负载均衡策略为consistent_hash时有效
+    pub hash_key: Option<HashKey>,
+    /// 健康检查配置，为空时不检查
+    pub health_check: Option<HealthCheck>,
+    /// 超时配置，JSON对象
+    pub timeouts: Option<Timeouts>,
+    /// 连接池配置，JSON对象
+    pub pool: Option<PoolConfig>,
+    /// 熔断配置，JSON对象，为空时不熔断
+    pub circuit_breaker: Option<CircuitBreaker>,
+    /// 服务节点的协议，可选值：http1 | h2c | h2 | grpc
+    pub protocol: Option<Protocol>,
+    /// 请求https节点时的TLS配置，JSON对象
+    pub tls: Option<UpstreamTls>,
     /// 创建人ID
     pub create_user_id: Option<i64>,
     /// 修改人ID
@@ -0,0 +1,319 @@
+create table if not exists system_config
+(
+    config_key   varchar(100) not null primary key,
+    config_value text         null
+);
+
+create table if not exists user
+(
+    id              bigint primary key,
+    nickname        varchar(500) not null,          -- 昵称
+    avatar          varchar(500),                   -- 头像
+    status          tinyint(1),                     -- 状态：0禁用 1正常
+    last_login_time datetime,                       -- 最后一次登录时间
+    create_time     datetime,                       -- 创建时间
+    update_time     datetime,                       -- 更新时间
+    remark          varchar(500),                   -- 备注
+    is_delete       tinyint(1)   not null default 0 -- 是否删除
+);
+
+create table if not exists user_auth
+(
+    id             bigint               not null primary key,
+    user_id        bigint               not null, -- 用户ID
+    type           tinyint(1)           not null, -- 认证类型: 1用户名密码 2邮箱
+    identity       varchar(500)         not null, -- 认证标识
+    secret         varchar(500)         null,     -- 认证密钥
+    create_user_id bigint               null,     -- 创建人ID
+    update_user_id bigint               null,     -- 修改人ID
+    create_time    datetime             null,     -- 创建时间
+    update_time    datetime             null,     -- 更新时间
+    remark         varchar(500)         null,     -- 备注
+    is_delete      tinyint(1) default 0 null      -- 是否删除
+);
+
+
+create table if not exists route
+(
+    id              bigint primary key,
+    name            varchar(100)  not null,           -- 路由名称
+    description     varchar(500),                     -- 路由描述
+    status          varchar(20)   not null,           -- 状态：Disable | Ok
+    host            varchar(100)  not null,           -- 需要匹配的域名
+    path            varchar(500)  not null,           -- 路由路径
+    path_type       varchar(20),                      -- 路径的匹配方式：wildcard | regex
+    priority        int           not null default 0, -- 优先级，数值越大越优先匹配
+    methods         varchar(1000) not null,           -- 请求方法，支持多个，JSON数组格式
+    action          varchar(2000),                    -- 处理方式，JSON对象，默认转发到服务
+    maintenance     tinyint(1)    not null default 0, -- 是否维护中
+    maintenance_page varchar(2000),                   -- 维护页面，JSON对象
+    service         varchar(100)  not null,           -- 目标服务名
+    backends        varchar(2000),                    -- 加权后端，JSON数组
+    header          varchar(1000) not null,           -- 按请求头匹配，JSON对象
+    query           varchar(1000) not null,           -- 按请求参数匹配，JSON对象
+    pre_filters     varchar(500)  not null,           -- 请求阶段过滤器，JSON数组
+    post_filters    varchar(500)  not null,           -- 响应阶段过滤器，JSON数组
+    is_auth         tinyint(1)    not null default 0, -- 是否需要认证
+    auth            varchar(2000),                    -- 鉴权方式，JSON对象，默认为API Key
+    auth_white_list varchar(1000),                    -- 认证白名单
+    rewrite         varchar(2000),                    -- 路径重写配置，JSON对象
+    retry           varchar(500),                     -- 重试策略，JSON对象
+    timeouts        varchar(200),                     -- 超时配置，JSON对象
+    rate_limit      varchar(500),                     -- 限流配置，JSON对象
+    proxy_headers   varchar(2000),                    -- 转发请求头配置，JSON对象
+    stream_body     tinyint(1)    not null default 0, -- 是否流式转发请求体
+    websocket       tinyint(1)    not null default 0, -- 是否允许WebSocket
+    client_cert     tinyint(1)    not null default 0, -- 是否要求客户端证书
+    mirror          varchar(500),                     -- 请求镜像配置，JSON对象
+    create_user_id  bigint,                           -- 创建人ID
+    update_user_id  bigint,                           -- 修改人ID
+    create_time     datetime,                         -- 创建时间
+    update_time     datetime,                         -- 更新时间
+    remark          varchar(500),                     -- 备注
+    is_delete       tinyint(1)    not null default 0  -- 是否删除
+);
+create table if not exists service
+(
+    id             bigint primary key,
+    name           varchar(100)  not null,          -- 服务名称，全局唯一
+    description    varchar(500)  not null,          -- 服务描述。注意这个描述要求非空，用于在控制台展示
+    status         varchar(20)   not null,          -- 状态：Disable | Ok
+    nodes          varchar(5000) not null,          -- 服务节点，JSON数组，支持IP和域名，如["http://127.0.0.1:8080"]
+    lb             varchar(30)   not null,          -- 负载均衡策略：random | round_robin | weighted_round_robin | least_active | consistent_hash
+    weights        varchar(5000),                   -- 节点权重，JSON对象，key为节点地址
+    hash_key       varchar(200),                    -- 一致性哈希的key来源，JSON对象
+    health_check   varchar(1000),                   -- 健康检查配置，JSON对象
+    timeouts       varchar(200),                    -- 超时配置，JSON对象
+    pool           varchar(200),                    -- 连接池配置，JSON对象
+    circuit_breaker varchar(2000),                  -- 熔断配置，JSON对象
+    protocol       varchar(20),                     -- 服务节点的协议：http1 | h2c | h2 | grpc
+    tls            text,                            -- 请求https节点时的TLS配置，JSON对象
+    create_user_id bigint,                          -- 创建人ID
+    update_user_id bigint,                          -- 修改人ID
+    create_time    datetime,                        -- 创建时间
+    update_time    datetime,                        -- 更新时间
+    remark         varchar(500),                    -- 备注
+    is_delete      tinyint(1)    not null default 0 -- 是否删除
+);
+
+create table if not exists plugin
+(
+    id             bigint primary key,
+    name           varchar(100) not null,          -- 插件名称
+    description    varchar(500),                   -- 插件描述
+    url            varchar(500) not null,          -- 下载地址，该地址用于gateway下载插件，需保证从gateway处可以访问。
+    version        varchar(50)  not null,          -- 插件版本，格式为0.1.0
+    default_config text,                           -- 插件默认配置，JSON字符串
+    document       text,                           -- 插件说明文档，Markdown格式
+    create_user_id bigint,                         -- 创建人ID
+    update_user_id bigint,                         -- 修改人ID
+    create_time    datetime,                       -- 创建时间
+    update_time    datetime,                       -- 更新时间
+    remark         varchar(500),                   -- 备注
+    is_delete      tinyint(1)   not null default 0 -- 是否删除
+);
+
+create table if not exists api_key
+(
+    id             bigint primary key,
+    name           varchar(100) not null,          -- 密钥名称
+    principal      varchar(500),                   -- 密钥所属的主体标识，可以为空
+    secret         varchar(100) not null,          -- 密钥
+    status         varchar(20)  not null,          -- 状态：Disable | Ok | Expired
+    eff_time       datetime     not null,          -- 生效时间，默认当前时间
+    exp_time       datetime,                       -- 失效时间，为空表示永久有效
+    source         varchar(20)  not null,          -- 密钥来源
+    scope          varchar(5000),                  -- 授权范围，JSON对象，为空时不限制
+    quota          varchar(500),                   -- 月度配额，JSON对象，为空时不限制
+    expire_notified tinyint(1)  not null default 0, -- 是否已发送即将过期的提醒
+    create_user_id bigint,                         -- 创建人ID
+    update_user_id bigint,                         -- 修改人ID
+    create_time    datetime,                       -- 创建时间
+    update_time    datetime,                       -- 更新时间
+    remark         varchar(500),                   -- 备注
+    is_delete      tinyint(1)   not null default 0 -- 是否删除
+);
+
+
+-- 网关节点
+create table if not exists gateway_node
+(
+    id                  bigint primary key,
+    node_id             varchar(100) not null,          -- 节点ID，md5(ip:port)后取前8位
+    node_name           varchar(100),                   -- 节点名称
+    ip                  varchar(100) not null,          -- IP
+    port                int          not null,          -- 端口
+    status              varchar(50)  not null,          -- 节点状态：Online | Offline | Unknown
+    status_msg          varchar(500),                   -- 节点状态信息
+    last_heartbeat_time datetime,                       -- 最后一次心跳时间
+    create_user_id      bigint,                         -- 创建人ID
+    update_user_id      bigint,                         -- 修改人ID
+    create_time         datetime,                       -- 创建时间
+    update_time         datetime,                       -- 更新时间
+    remark              varchar(500),                   -- 备注
+    is_delete           tinyint(1)   not null default 0 -- 是否删除
+);
+
+-- 网关节点状态
+create table if not exists gateway_node_state
+(
+    id                             bigint primary key,
+    node_id                        varchar(100) not null,           -- 节点ID
+    ts                             bigint       not null,           -- 毫秒时间戳
+    os                             varchar(50),                     -- 操作系统及版本，如: Ubuntu 22.04
+    host_name                      varchar(100),                    -- 主机名
+    cpu_usage                      float        not null default 0, -- cpu 使用率
+    mem_total                      bigint       not null default 0, -- 内存状态 - 总内存，单位：Bytes
+    mem_free                       bigint       not null default 0, -- 内存状态 - 空闲内存，单位：Bytes
+    mem_used                       bigint       not null default 0, -- 内存状态 - 使用内存，单位：Bytes
+    disk_total                     bigint       not null default 0, -- 磁盘状态 - 总空间，单位：Bytes
+    disk_free                      bigint       not null default 0, -- 磁盘状态 - 空闲空间，单位：Bytes
+    net_rx                         bigint       not null default 0, -- 网络状态 - 接收的字节数
+    net_tx                         bigint       not null default 0, -- 网络状态 - 发送的字节数
+    net_tcp_conn_count             bigint       not null default 0, -- 网络状态 - TCP连接数
+    avg_qps                        bigint       not null default 0, -- 平均QPS
+    interval_request_count         bigint       not null default 0, -- 区间内请求数
+    interval_request_invalid_count bigint       not null default 0, -- 区间内无效请求数
+    interval_request_ratelimit_count bigint     not null default 0, -- 区间内被限流请求数
+    interval_response_2xx_count    bigint       not null default 0, -- 区间内2xx响应数
+    interval_response_3xx_count    bigint       not null default 0, -- 区间内3xx响应数
+    interval_response_4xx_count    bigint       not null default 0, -- 区间内4xx响应数
+    interval_response_5xx_count    bigint       not null default 0, -- 区间内5xx响应数
+    interval_http_connect_count    bigint       not null default 0, -- 区间内http连接数
+    interval_avg_response_time     bigint       not null default 0, -- 区间内平均响应时间
+    request_count                  bigint       not null default 0, -- 累计请求数
+    request_invalid_count          bigint       not null default 0, -- 累计无效请求数
+    request_ratelimit_count        bigint       not null default 0, -- 累计被限流请求数
+    response_2xx_count             bigint       not null default 0, -- 累计2xx响应数
+    response_3xx_count             bigint       not null default 0, -- 累计3xx响应数
+    response_4xx_count             bigint       not null default 0, -- 累计4xx响应数
+    response_5xx_count             bigint       not null default 0, -- 累计5xx响应数
+    http_connect_count             bigint       not null default 0, -- http连接数
+    sse_connect_count              bigint       not null default 0, -- sse连接数
+    websocket_connect_count        bigint       not null default 0, -- websocket连接数
+    avg_response_time              bigint       not null default 0, -- 累计平均响应时间
+    create_time                    datetime,                        -- 创建时间
+    index idx_node_id (node_id),
+    index idx_ts (ts)
+);
+
+-- 消息（提醒/警告消息等）
+create table if not exists message
+(
+    id          bigint primary key,
+    -- 移除type字段，不需要类型标记，系统通知使用info级别即可
+    -- type        varchar(50) not null,           -- 消息类型：system | alert
+    level       varchar(50)  not null,          -- 消息级别：info | warn | error
+    title       varchar(500) not null,          -- 标题
+    content     text         not null,          -- 内容
+    read_status varchar(10)  not null,          -- Unread 未读 | Read 已读
+    create_time datetime     not null,
+    is_delete   tinyint(1)   not null default 0 -- 是否删除
+);
+
+-- 请求地区统计（小时级，保留近1年的）
+create table if not exists statistics_request_province
+(
+    province   varchar(50) not null,           -- 省份
+    count      bigint      not null default 0, -- 数量
+    start_time bigint      not null,           -- 起始时间戳（秒，0分0秒）
+    end_time   bigint      not null            -- 结束时间戳（秒，59分59秒）
+);
+
+-- 状态码统计（分钟级，保留近1年的）
+create table if not exists statistics_request_status_code
+(
+    status_code bigint not null,           -- 状态码
+    count       bigint not null default 0, -- 数量
+    state_time  bigint not null            -- 分钟起始时间戳（秒，0分0秒），范围为[state_time, state_time+59]
+);
+
+-- API Key用量（按天，每分钟从缓存同步一次）
+create table if not exists api_key_usage
+(
+    principal   varchar(500) not null,           -- 主体标识
+    usage_date  varchar(8)   not null,           -- 日期，如20250101
+    requests    bigint       not null default 0, -- 请求次数
+    tokens      bigint       not null default 0, -- Token数
+    update_time datetime,                        -- 更新时间
+    unique key uk_principal_usage_date (principal, usage_date)
+);
+
+-- 模型
+create table if not exists model
+(
+    id             bigint primary key,
+    name           varchar(500),                  -- 模型名称，全局唯一
+    status         varchar(20) not null,          -- 状态：Disable | Ok
+    lb_strategy    varchar(50) not null,          -- 负载均衡策略：RoundRobin | Random | WeightedRandom
+    create_user_id bigint,                        -- 创建人ID
+    update_user_id bigint,                        -- 修改人ID
+    create_time    datetime,                      -- 创建时间
+    update_time    datetime,                      -- 更新时间
+    remark         varchar(500),                  -- 备注
+    is_delete      tinyint(1)  not null default 0 -- 是否删除
+);
+
+-- 模型提供商
+create table if not exists model_provider
+(
+    id                 bigint primary key,
+    model_id           bigint       not null,           -- 模型ID
+    name               varchar(500),                    -- 模型提供商名称
+    api_url            varchar(500) not null,           -- 接口地址
+    api_key            varchar(500),                    -- 密钥
+    status             varchar(20)  not null,           -- 状态：Disable | Ok
+    weight             int          not null default 1, -- 权重
+    request_converter  text,                            -- 请求转换器
+    response_converter text,                            -- 响应转换器
+    target_model_name  varchar(500),                    -- 目标模型名称
+    create_user_id     bigint,                          -- 创建人ID
+    update_user_id     bigint,                          -- 修改人ID
+    create_time        datetime,                        -- 创建时间
+    update_time        datetime,                        -- 更新时间
+    remark             varchar(500),                    -- 备注
+    is_delete          tinyint(1)   not null default 0  -- 是否删除
+);
+create table if not exists certificate
+(
+    id             bigint primary key,
+    name           varchar(100)  not null,          -- 证书名称，全局唯一
+    description    varchar(500),                    -- 证书描述
+    status         varchar(20)   not null,          -- 状态：Disable | Ok
+    hosts          varchar(5000) not null,          -- 证书适用的域名，JSON数组，支持通配符，如["example.com","*.example.com"]
+    cert           text          not null,          -- 证书链，PEM格式
+    `key`          text          not null,          -- 私钥，PEM格式
+    client_ca      text,                            -- 验证客户端证书的CA证书，PEM格式
+    create_user_id bigint,                          -- 创建人ID
+    update_user_id bigint,                          -- 修改人ID
+    create_time    datetime,                        -- 创建时间
+    update_time    datetime,                        -- 更新时间
+    remark         varchar(500),                    -- 备注
+    is_delete      tinyint(1)    not null default 0 -- 是否删除
+);
+create table if not exists jwt_provider
+(
+    id             bigint primary key,
+    name           varchar(100)  not null,          -- 签发方名称，全局唯一
+    description    varchar(500),                    -- 描述
+    status         varchar(20)   not null,          -- 状态：Disable | Ok
+    algorithm      varchar(20)   not null,          -- 签名算法：HS256 | RS256 | ES256
+    `key`          text,                            -- 密钥：HS256为共享密钥，RS256和ES256为PEM格式的公钥
+    jwks           text,                            -- JWKS文档，JSON格式
+    jwks_file      varchar(500),                    -- 网关节点上的JWKS文件路径
+    issuer         varchar(500),                    -- 签发者
+    audiences      varchar(2000),                   -- 受众，JSON数组
+    leeway         int           not null default 60, -- 允许的时钟偏差，单位：秒
+    create_user_id bigint,                          -- 创建人ID
+    update_user_id bigint,                          -- 修改人ID
+    create_time    datetime,                        -- 创建时间
+    update_time    datetime,                        -- 更新时间
+    remark         varchar(500),                    -- 备注
+    is_delete      tinyint(1)    not null default 0 -- 是否删除
+);
+-- -------------------------------- 初始化用户 --------------------------------------
+insert ignore into user(id, nickname)
+values (1, 'admin');
+insert ignore into user_auth(id, user_id, type, identity, secret)
+values (1, 1, 1, 'admin', '$2b$12$uMYLbc5X3VIPkBxBKa7w9OrLwQEzyhCZe8.aGVxtQmpqCx4okFMoW');
+
@@ -41,14 +41,30 @@ create table if not exists route
     status          varchar(20)   not null,           -- 状态：Disable | Ok
     host            varchar(100)  not null,           -- 需要匹配的域名
     path            varchar(500)  not null,           -- 路由路径
+    path_type       varchar(20),                      -- 路径的匹配方式：wildcard | regex
+    priority        int           not null default 0, -- 优先级，数值越大越优先匹配
     methods         varchar(1000) not null,           -- 请求方法，支持多个，JSON数组格式
+    action          varchar(2000),                    -- 处理方式，JSON对象，默认转发到服务
+    maintenance     tinyint(1)    not null default 0, -- 是否维护中
+    maintenance_page varchar(2000),                   -- 维护页面，JSON对象
     service         varchar(100)  not null,           -- 目标服务名
-    header          varchar(1000) not null,           -- 按请求头匹配
-    query           varchar(1000) not null,           -- 按请求参数匹配
+    backends        varchar(2000),                    -- 加权后端，JSON数组
+    header          varchar(1000) not null,           -- 按请求头匹配，JSON对象
+    query           varchar(1000) not null,           -- 按请求参数匹配，JSON对象
     pre_filters     varchar(500)  not null,           -- 请求阶段过滤器，JSON数组
     post_filters    varchar(500)  not null,           -- 响应阶段过滤器，JSON数组
     is_auth         tinyint(1)    not null default 0, -- 是否需要认证
+    auth            varchar(2000),                    -- 鉴权方式，JSON对象，默认为API Key
     auth_white_list varchar(1000),                    -- 认证白名单
+    rewrite         varchar(2000),                    -- 路径重写配置，JSON对象
+    retry           varchar(500),                     -- 重试策略，JSON对象
+    timeouts        varchar(200),                     -- 超时配置，JSON对象
+    rate_limit      varchar(500),                     -- 限流配置，JSON对象
+    proxy_headers   varchar(2000),                    -- 转发请求头配置，JSON对象
+    stream_body     tinyint(1)    not null default 0, -- 是否流式转发请求体
+    websocket       tinyint(1)    not null default 0, -- 是否允许WebSocket
+    client_cert     tinyint(1)    not null default 0, -- 是否要求客户端证书
+    mirror          varchar(500),                     -- 请求镜像配置，JSON对象
     create_user_id  bigint,                           -- 创建人ID
     update_user_id  bigint,                           -- 修改人ID
     create_time     datetime,                         -- 创建时间
@@ -63,7 +79,15 @@ create table if not exists service
     description    varchar(500)  not null,          -- 服务描述。注意这个描述要求非空，用于在控制台展示
     status         varchar(20)   not null,          -- 状态：Disable | Ok
     nodes          varchar(5000) not null,          -- 服务节点，JSON数组，支持IP和域名，如["http://127.0.0.1:8080"]
-    lb             varchar(20)   not null,          -- 负载均衡策略：random | round_robin
+    lb             varchar(30)   not null,          -- 负载均衡策略：random | round_robin | weighted_round_robin | least_active | consistent_hash
+    weights        varchar(5000),                   -- 节点权重，JSON对象，key为节点地址
+    hash_key       varchar(200),                    -- 一致性哈希的key来源，JSON对象
+    health_check   varchar(1000),                   -- 健康检查配置，JSON对象
+    timeouts       varchar(200),                    -- 超时配置，JSON对象
+    pool           varchar(200),                    -- 连接池配置，JSON对象
+    circuit_breaker varchar(2000),                  -- 熔断配置，JSON对象
+    protocol       varchar(20),                     -- 服务节点的协议：http1 | h2c | h2 | grpc
+    tls            text,                            -- 请求https节点时的TLS配置，JSON对象
     create_user_id bigint,                          -- 创建人ID
     update_user_id bigint,                          -- 修改人ID
     create_time    datetime,                        -- 创建时间
@@ -95,10 +119,13 @@ create table if not exists api_key
     name           varchar(100) not null,          -- 密钥名称
     principal      varchar(500),                   -- 密钥所属的主体标识，可以为空
     secret         varchar(100) not null,          -- 密钥
-    status         varchar(20)  not null,          -- 状态：Disable | Ok
+    status         varchar(20)  not null,          -- 状态：Disable | Ok | Expired
     eff_time       datetime     not null,          -- 生效时间，默认当前时间
     exp_time       datetime,                       -- 失效时间，为空表示永久有效
     source         varchar(20)  not null,          -- 密钥来源
+    scope          varchar(5000),                  -- 授权范围，JSON对象，为空时不限制
+    quota          varchar(500),                   -- 月度配额，JSON对象，为空时不限制
+    expire_notified tinyint(1)  not null default 0, -- 是否已发送即将过期的提醒
     create_user_id bigint,                         -- 创建人ID
     update_user_id bigint,                         -- 修改人ID
     create_time    datetime,                       -- 创建时间
@@ -147,6 +174,7 @@ create table if not exists gateway_node_state
     avg_qps                        bigint       not null default 0, -- 平均QPS
     interval_request_count         bigint       not null default 0, -- 区间内请求数
     interval_request_invalid_count bigint       not null default 0, -- 区间内无效请求数
+    interval_request_ratelimit_count bigint     not null default 0, -- 区间内被限流请求数
     interval_response_2xx_count    bigint       not null default 0, -- 区间内2xx响应数
     interval_response_3xx_count    bigint       not null default 0, -- 区间内3xx响应数
     interval_response_4xx_count    bigint       not null default 0, -- 区间内4xx响应数
@@ -155,12 +183,14 @@ create table if not exists gateway_node_state
     interval_avg_response_time     bigint       not null default 0, -- 区间内平均响应时间
     request_count                  bigint       not null default 0, -- 累计请求数
     request_invalid_count          bigint       not null default 0, -- 累计无效请求数
+    request_ratelimit_count        bigint       not null default 0, -- 累计被限流请求数
     response_2xx_count             bigint       not null default 0, -- 累计2xx响应数
     response_3xx_count             bigint       not null default 0, -- 累计3xx响应数
     response_4xx_count             bigint       not null default 0, -- 累计4xx响应数
     response_5xx_count             bigint       not null default 0, -- 累计5xx响应数
     http_connect_count             bigint       not null default 0, -- http连接数
     sse_connect_count              bigint       not null default 0, -- sse连接数
+    websocket_connect_count        bigint       not null default 0, -- websocket连接数
     avg_response_time              bigint       not null default 0, -- 累计平均响应时间
     create_time                    datetime                         -- 创建时间
 );
@@ -198,6 +228,17 @@ create table if not exists statistics_request_status_code
     state_time  bigint not null            -- 分钟起始时间戳（秒，0分0秒），范围为[state_time, state_time+59]
 );
 
+-- API Key用量（按天，每分钟从缓存同步一次）
+create table if not exists api_key_usage
+(
+    principal   varchar(500) not null,           -- 主体标识
+    usage_date  varchar(8)   not null,           -- 日期，如20250101
+    requests    bigint       not null default 0, -- 请求次数
+    tokens      bigint       not null default 0, -- Token数
+    update_time datetime                         -- 更新时间
+);
+create unique index if not exists uk_principal_usage_date on api_key_usage (principal, usage_date);
+
 -- 模型
 create table if not exists model
 (
@@ -233,6 +274,43 @@ create table if not exists model_provider
     remark             varchar(500),                    -- 备注
     is_delete          tinyint(1)   not null default 0  -- 是否删除
 );
+create table if not exists certificate
+(
+    id             bigint primary key,
+    name           varchar(100)  not null,          -- 证书名称，全局唯一
+    description    varchar(500),                    -- 证书描述
+    status         varchar(20)   not null,          -- 状态：Disable | Ok
+    hosts          varchar(5000) not null,          -- 证书适用的域名，JSON数组，支持通配符，如["example.com","*.example.com"]
+    cert           text          not null,          -- 证书链，PEM格式
+    key            text          not null,          -- 私钥，PEM格式
+    client_ca      text,                            -- 验证客户端证书的CA证书，PEM格式
+    create_user_id bigint,                          -- 创建人ID
+    update_user_id bigint,                          -- 修改人ID
+    create_time    datetime,                        -- 创建时间
+    update_time    datetime,                        -- 更新时间
+    remark         varchar(500),                    -- 备注
+    is_delete      tinyint(1)    not null default 0 -- 是否删除
+);
+create table if not exists jwt_provider
+(
+    id             bigint primary key,
+    name           varchar(100)  not null,          -- 签发方名称，全局唯一
+    description    varchar(500),                    -- 描述
+    status         varchar(20)   not null,          -- 状态：Disable | Ok
+    algorithm      varchar(20)   not null,          -- 签名算法：HS256 | RS256 | ES256
+    key            text,                            -- 密钥：HS256为共享密钥，RS256和ES256为PEM格式的公钥
+    jwks           text,                            -- JWKS文档，JSON格式
+    jwks_file      varchar(500),                    -- 网关节点上的JWKS文件路径
+    issuer         varchar(500),                    -- 签发者
+    audiences      varchar(2000),                   -- 受众，JSON数组
+    leeway         int           not null default 60, -- 允许的时钟偏差，单位：秒
+    create_user_id bigint,                          -- 创建人ID
+    update_user_id bigint,                          -- 修改人ID
+    create_time    datetime,                        -- 创建时间
+    update_time    datetime,                        -- 更新时间
+    remark         varchar(500),                    -- 备注
+    is_delete      tinyint(1)    not null default 0 -- 是否删除
+);
 -- -------------------------------- 初始化用户 --------------------------------------
 insert or ignore into user(id, nickname)
 values (1, 'admin');
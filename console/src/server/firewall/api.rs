@@ -1,5 +1,5 @@
 use crate::server::auth::UserPrincipal;
-use crate::server::firewall::request::FirewallUpdateReq;
+use crate::server::firewall::request::{FirewallUpdateReq, RotateKeyReq};
 use crate::server::firewall::service;
 use busi::res::Res;
 use aiway_protocol::gateway::Firewall;
@@ -7,7 +7,7 @@ use rocket::serde::json::Json;
 use rocket::{get, post, routes};
 
 pub fn routes() -> Vec<rocket::Route> {
-    routes![update, detail]
+    routes![update, detail, rotate_key]
 }
 
 /// 更新防火墙配置
@@ -27,3 +27,12 @@ pub async fn detail(_user: UserPrincipal) -> Res<Firewall> {
         Err(e) => Res::error(&e.to_string()),
     }
 }
+
+/// 轮换API Key的加密密钥
+#[post("/rotate_key", data = "<req>")]
+pub async fn rotate_key(req: Json<RotateKeyReq>, user: UserPrincipal) -> Res<()> {
+    match service::rotate_key(req.0, user).await {
+        Ok(_) => Res::success(()),
+        Err(e) => Res::error(&e.to_string()),
+    }
+}
@@ -6,3 +6,14 @@ pub struct FirewallUpdateReq {
     #[serde(flatten)]
     pub inner: Firewall,
 }
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RotateKeyReq {
+    /// 新的加密密钥，长度固定为32位
+    pub key: String,
+    /// 旧密钥的宽限期，单位：天，宽限期内用旧密钥签发的API Key依然有效，为0时立即失效
+    pub grace_days: u32,
+    /// 是否使用新密钥重新签发全部API Key
+    #[serde(default)]
+    pub reissue: bool,
+}
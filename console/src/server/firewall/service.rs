@@ -1,11 +1,102 @@
+use crate::server::auth::UserPrincipal;
 use crate::server::db::models::system_config::{ConfigKey, SystemConfig};
-use crate::server::firewall::request::FirewallUpdateReq;
-use aiway_protocol::gateway::Firewall;
+use crate::server::firewall::request::{FirewallUpdateReq, RotateKeyReq};
+use crate::server::key;
+use crate::server::route::check_rate_limit;
+use aiway_protocol::common::constants::ENCRYPT_KEY;
+use aiway_protocol::gateway::{Firewall, RetiredSecretKey, parse_ip_range, parse_max_connections};
+use anyhow::bail;
 
-pub async fn update(req: FirewallUpdateReq) -> anyhow::Result<()> {
+pub async fn update(mut req: FirewallUpdateReq) -> anyhow::Result<()> {
+    if let Some(rate_limit) = &req.inner.rate_limit {
+        check_rate_limit(rate_limit)?;
+    }
+    let inner = &req.inner;
+    let ips = inner
+        .ip_policy
+        .iter()
+        .chain(&inner.trust_ips)
+        .chain(&inner.trusted_proxies);
+    for ip in ips {
+        if parse_ip_range(ip).is_err() {
+            bail!(
+                "IP配置错误：{}，应为IP或网段，如192.168.1.1、192.168.0.0/16、2001:db8::/32",
+                ip
+            );
+        }
+    }
+    for rule in &req.inner.max_connections {
+        match parse_max_connections(rule) {
+            Ok((_, 0)) => bail!("最大连接数配置错误：{}，连接数必须大于0", rule),
+            Ok(_) => {}
+            Err(_) => bail!(
+                "最大连接数配置错误：{}，格式应为ip:port/连接数或*/连接数",
+                rule
+            ),
+        }
+    }
+    // 加密密钥只能通过轮换修改，避免已签发的API Key全部失效
+    let old = SystemConfig::get::<Firewall>(ConfigKey::Firewall).await?;
+    req.inner.api_secret_encrypt_key = old.api_secret_encrypt_key;
+    req.inner.api_secret_key_id = old.api_secret_key_id;
+    req.inner.retired_api_secret_keys = old.retired_api_secret_keys;
     SystemConfig::upsert(ConfigKey::Firewall, &req.inner).await
 }
 
+/// 轮换API Key的加密密钥
+///
+/// 当前密钥移入旧密钥列表，宽限期结束后不再用于验证，同时清理已过宽限期的旧密钥。
+/// 网关同步到新配置后，新签发的API Key才能通过验证。
+pub async fn rotate_key(req: RotateKeyReq, user: UserPrincipal) -> anyhow::Result<()> {
+    let Ok(key) = <[u8; 32]>::try_from(req.key.as_bytes()) else {
+        bail!("加密密钥配置错误：长度必须为32位");
+    };
+    if key == *ENCRYPT_KEY {
+        bail!("加密密钥配置错误：不能使用默认密钥");
+    }
+    let mut firewall = SystemConfig::get::<Firewall>(ConfigKey::Firewall).await?;
+    if key == firewall.api_secret_encrypt_key
+        || firewall
+            .retired_api_secret_keys
+            .iter()
+            .any(|retired| retired.key == key)
+    {
+        bail!("加密密钥配置错误：不能与当前密钥或旧密钥相同");
+    }
+
+    let now = chrono::Local::now().timestamp();
+    firewall
+        .retired_api_secret_keys
+        .retain(|retired| retired.expire_time > now);
+    if req.grace_days > 0 {
+        firewall.retired_api_secret_keys.push(RetiredSecretKey {
+            id: firewall.api_secret_key_id,
+            key: firewall.api_secret_encrypt_key,
+            expire_time: now + req.grace_days as i64 * 24 * 3600,
+        });
+    }
+    // 新的密钥ID不能与宽限期内的旧密钥重复
+    let Some(key_id) = (1..=u8::MAX)
+        .map(|i| firewall.api_secret_key_id.wrapping_add(i))
+        .find(|id| {
+            !firewall
+                .retired_api_secret_keys
+                .iter()
+                .any(|retired| retired.id == *id)
+        })
+    else {
+        bail!("宽限期内的旧密钥过多，请稍后再试");
+    };
+    firewall.api_secret_encrypt_key = key;
+    firewall.api_secret_key_id = key_id;
+    SystemConfig::upsert(ConfigKey::Firewall, &firewall).await?;
+
+    if req.reissue {
+        key::service::reissue_all(&user).await?;
+    }
+    Ok(())
+}
+
 pub async fn detail() -> anyhow::Result<Firewall> {
     SystemConfig::get(ConfigKey::Firewall).await
 }
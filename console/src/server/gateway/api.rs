@@ -7,7 +7,9 @@
 
 use crate::server;
 use crate::server::gateway;
-use crate::server::gateway::{alerter, ip_region, plugin, reporter, route, service};
+use crate::server::gateway::{
+    alerter, certificate, ip_region, jwt, plugin, reporter, route, service,
+};
 use busi::res::Res;
 use aiway_protocol::gateway::Config;
 use aiway_protocol::gateway::alert::AlertMessage;
@@ -21,6 +23,8 @@ pub fn routes() -> Vec<rocket::Route> {
         all_plugins,
         configuration,
         firewall,
+        all_certificates,
+        all_jwt_providers,
         report,
         alert,
         download_ip_region_file,
@@ -73,6 +77,24 @@ async fn firewall() -> Res<aiway_protocol::gateway::Firewall> {
     }
 }
 
+/// 查询TLS证书
+#[get("/gateway/certificates")]
+async fn all_certificates() -> Res<Vec<aiway_protocol::gateway::Certificate>> {
+    match certificate::certificates().await {
+        Ok(res) => Res::success(res),
+        Err(e) => Res::error(&e.to_string()),
+    }
+}
+
+/// 查询JWT签发方
+#[get("/gateway/jwt-providers")]
+async fn all_jwt_providers() -> Res<Vec<aiway_protocol::gateway::JwtProvider>> {
+    match jwt::jwt_providers().await {
+        Ok(res) => Res::success(res),
+        Err(e) => Res::error(&e.to_string()),
+    }
+}
+
 /// 接收状态上报
 #[post("/gateway/report", data = "<req>")]
 async fn report(req: Json<aiway_protocol::gateway::state::State>) -> Res<()> {
@@ -0,0 +1,19 @@
+use crate::server::db::Pool;
+use crate::server::db::models::certificate::{Certificate, CertificateStatus};
+use rbs::value;
+
+pub(crate) async fn certificates() -> anyhow::Result<Vec<aiway_protocol::gateway::Certificate>> {
+    let certificates =
+        Certificate::select_by_map(Pool::get()?, value! {"status": CertificateStatus::Ok}).await?;
+    let list = certificates
+        .into_iter()
+        .map(|certificate| aiway_protocol::gateway::Certificate {
+            name: certificate.name.unwrap(),
+            hosts: certificate.hosts.unwrap_or_default(),
+            cert: certificate.cert.unwrap_or_default(),
+            key: certificate.key.unwrap_or_default(),
+            client_ca: certificate.client_ca.filter(|ca| !ca.trim().is_empty()),
+        })
+        .collect();
+    Ok(list)
+}
@@ -0,0 +1,24 @@
+use crate::server::db::Pool;
+use crate::server::db::models::jwt_provider::{JwtProvider, JwtProviderStatus};
+use rbs::value;
+
+pub(crate) async fn jwt_providers() -> anyhow::Result<Vec<aiway_protocol::gateway::JwtProvider>> {
+    let providers =
+        JwtProvider::select_by_map(Pool::get()?, value! {"status": JwtProviderStatus::Ok}).await?;
+    // 清除后的字段为空字符串
+    let non_empty = |value: Option<String>| value.filter(|s| !s.trim().is_empty());
+    let list = providers
+        .into_iter()
+        .map(|provider| aiway_protocol::gateway::JwtProvider {
+            name: provider.name.unwrap(),
+            algorithm: provider.algorithm.unwrap_or_default(),
+            key: non_empty(provider.key),
+            jwks: non_empty(provider.jwks),
+            jwks_file: non_empty(provider.jwks_file),
+            issuer: non_empty(provider.issuer),
+            audiences: provider.audiences.unwrap_or_default(),
+            leeway: provider.leeway.unwrap_or(60),
+        })
+        .collect();
+    Ok(list)
+}
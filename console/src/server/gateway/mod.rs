@@ -1,5 +1,7 @@
 pub mod api;
 mod service;
+mod certificate;
+mod jwt;
 mod route;
 mod plugin;
 mod global_filter;
@@ -1,6 +1,7 @@
 use crate::server::db::models::gateway_node::{GatewayNode, GatewayNodeBuilder, GatewayNodeStatus};
 use crate::server::db::models::gateway_node_state::{GatewayNodeState, GatewayNodeStateBuilder};
 use crate::server::db::{Pool, tools};
+use crate::server::service;
 use aiway_protocol::common::constants;
 use aiway_protocol::gateway::state::State;
 use alert::Alert;
@@ -16,6 +17,9 @@ pub async fn report(req: State) -> anyhow::Result<()> {
 
     log::debug!("node_id:{}, state: {:?}", node_id, req);
 
+    // 服务节点健康状态仅保存在内存中
+    service::update_node_health(node_id, req.node_health).await;
+
     let tx = Pool::get()?;
     let gateway_node = GatewayNode::select_by_map(tx, value! {"node_id": node_id}).await?;
     if gateway_node.is_empty() {
@@ -72,6 +76,7 @@ pub async fn report(req: State) -> anyhow::Result<()> {
         .net_tcp_conn_count(req.system_state.net_state.tcp_conn_count)
         .http_connect_count(req.moment_counter.http_connect_count)
         .sse_connect_count(req.moment_counter.sse_connect_count)
+        .websocket_connect_count(req.moment_counter.websocket_connect_count)
         .avg_qps(if req.counter.response_time_since_last > 0 {
             req.counter.request_count / constants::REPORT_STATE_INTERVAL as usize
         } else {
@@ -80,6 +85,7 @@ pub async fn report(req: State) -> anyhow::Result<()> {
         // 上报区间内的统计
         .interval_request_count(req.counter.request_count)
         .interval_request_invalid_count(req.counter.request_invalid_count)
+        .interval_request_ratelimit_count(req.counter.request_ratelimit_count)
         .interval_response_2xx_count(req.counter.response_2xx_count)
         .interval_response_3xx_count(req.counter.response_3xx_count)
         .interval_response_4xx_count(req.counter.response_4xx_count)
@@ -92,6 +98,9 @@ pub async fn report(req: State) -> anyhow::Result<()> {
         // 累计统计
         .request_count(req.counter.request_count + last.request_count)
         .request_invalid_count(req.counter.request_invalid_count + last.request_invalid_count)
+        .request_ratelimit_count(
+            req.counter.request_ratelimit_count + last.request_ratelimit_count,
+        )
         .response_2xx_count(req.counter.response_2xx_count + last.response_2xx_count)
         .response_3xx_count(req.counter.response_3xx_count + last.response_3xx_count)
         .response_4xx_count(req.counter.response_4xx_count + last.response_4xx_count)
@@ -1,27 +1,48 @@
 use crate::server::db::Pool;
 use crate::server::db::models::route::{Route, RouteStatus};
 use crate::server::route::PathPattern;
+use aiway_protocol::gateway::route::{PathType, RouteAction};
 use rbs::value;
 
 pub(crate) async fn routes() -> anyhow::Result<Vec<aiway_protocol::gateway::Route>> {
     let routes = Route::select_by_map(Pool::get()?, value! {"status": RouteStatus::Ok}).await?;
     let mut list = Vec::with_capacity(routes.len());
     for route in routes {
+        let path_type = route.path_type.unwrap_or_default();
         list.push(aiway_protocol::gateway::route::Route {
             name: route.name.unwrap(),
             host: route.host.unwrap(),
             path: route.path.clone().unwrap(),
-            // 由路径重写插件实现
-            //strip_prefix: route.strip_prefix.unwrap() == 1,
-            match_path: PathPattern::new(route.path.unwrap()).to_pattern(),
+            match_path: match path_type {
+                PathType::Wildcard => PathPattern::new(route.path.unwrap()).to_pattern(),
+                PathType::Regex => route.path.unwrap(),
+            },
+            path_type,
+            priority: route.priority.unwrap_or_default(),
+            // 维护中的路由返回维护页面，关闭后恢复配置的处理方式
+            action: match route.maintenance {
+                Some(true) => RouteAction::Maintenance(route.maintenance_page.unwrap_or_default()),
+                _ => route.action.unwrap_or_default(),
+            },
             service: route.service.unwrap(),
+            backends: route.backends.unwrap_or_default(),
             methods: route.methods.unwrap_or_default(),
             header: route.header.unwrap_or_default(),
             query: route.query.unwrap_or_default(),
             pre_filters: route.pre_filters.unwrap_or_default(),
             post_filters: route.post_filters.unwrap_or_default(),
             is_auth: route.is_auth.unwrap_or_default(),
+            auth: route.auth.unwrap_or_default(),
             auth_white_list: route.auth_white_list.unwrap_or_default(),
+            rewrite: route.rewrite,
+            retry: route.retry,
+            timeouts: route.timeouts,
+            rate_limit: route.rate_limit,
+            proxy_headers: route.proxy_headers,
+            stream_body: route.stream_body.unwrap_or_default(),
+            websocket: route.websocket.unwrap_or_default(),
+            client_cert: route.client_cert.unwrap_or_default(),
+            mirror: route.mirror,
         });
     }
 
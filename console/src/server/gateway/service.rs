@@ -11,6 +11,14 @@ pub(crate) async fn services() -> anyhow::Result<Vec<aiway_protocol::gateway::Se
             name: service.name.unwrap(),
             nodes: service.nodes.unwrap(),
             lb: service.lb.unwrap(),
+            weights: service.weights.unwrap_or_default(),
+            hash_key: service.hash_key,
+            health_check: service.health_check,
+            timeouts: service.timeouts,
+            pool: service.pool,
+            circuit_breaker: service.circuit_breaker,
+            protocol: service.protocol.unwrap_or_default(),
+            tls: service.tls,
         });
     }
     Ok(list)
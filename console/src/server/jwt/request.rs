@@ -0,0 +1,61 @@
+use crate::server::db::models::jwt_provider::JwtProviderStatus;
+use aiway_protocol::gateway::jwt::JwtAlgorithm;
+use busi::impl_pagination;
+use busi::req::PageReq;
+use rocket::serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JwtProviderAddReq {
+    /// 签发方名称，全局唯一
+    pub name: String,
+    /// 描述
+    pub description: Option<String>,
+    /// 签名算法：HS256 | RS256 | ES256
+    pub algorithm: JwtAlgorithm,
+    /// 验证签名的密钥：HS256为共享密钥，RS256和ES256为PEM格式的公钥
+    pub key: Option<String>,
+    /// JWKS文档，JSON格式
+    pub jwks: Option<String>,
+    /// 网关节点上的JWKS文件路径，未配置密钥和JWKS时使用
+    pub jwks_file: Option<String>,
+    /// 签发者，配置后校验iss
+    pub issuer: Option<String>,
+    /// 受众，配置后校验aud
+    pub audiences: Option<Vec<String>>,
+    /// 允许的时钟偏差，单位：秒，默认60
+    pub leeway: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JwtProviderUpdateReq {
+    pub id: i64,
+    /// 描述
+    pub description: Option<String>,
+    /// 签名算法
+    pub algorithm: Option<JwtAlgorithm>,
+    /// 密钥、JWKS、JWKS文件路径和签发者，传入空字符串时清除
+    pub key: Option<String>,
+    pub jwks: Option<String>,
+    pub jwks_file: Option<String>,
+    pub issuer: Option<String>,
+    /// 受众
+    pub audiences: Option<Vec<String>>,
+    /// 允许的时钟偏差，单位：秒
+    pub leeway: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JwtProviderListReq {
+    pub page: PageReq,
+    /// 模糊搜索：名称/描述/签发者
+    pub filter_text: Option<String>,
+    /// 状态
+    pub status: Option<JwtProviderStatus>,
+}
+impl_pagination!(JwtProviderListReq);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateStatusReq {
+    pub id: i64,
+    pub status: JwtProviderStatus,
+}
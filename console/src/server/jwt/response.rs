@@ -0,0 +1,9 @@
+use crate::server::db::models::jwt_provider::JwtProvider;
+use rocket::serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JwtProviderListRes {
+    /// 签发方信息，HS256不返回共享密钥
+    #[serde(flatten)]
+    pub inner: JwtProvider,
+}
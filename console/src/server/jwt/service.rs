@@ -0,0 +1,218 @@
+use crate::server::auth::UserPrincipal;
+use crate::server::db::models::jwt_provider;
+use crate::server::db::models::jwt_provider::{JwtProvider, JwtProviderBuilder, JwtProviderStatus};
+use crate::server::db::models::route::Route;
+use crate::server::db::{Pool, tools};
+use crate::server::jwt::request::{
+    JwtProviderAddReq, JwtProviderListReq, JwtProviderUpdateReq, UpdateStatusReq,
+};
+use crate::server::jwt::response::JwtProviderListRes;
+use aiway_protocol::gateway::jwt::JwtAlgorithm;
+use aiway_protocol::gateway::route::RouteAuth;
+use anyhow::bail;
+use busi::req::{IdsReq, Pagination};
+use busi::res::{IntoPageRes, PageRes};
+use common::id;
+use jsonwebtoken::DecodingKey;
+use jsonwebtoken::jwk::JwkSet;
+use rbs::value;
+
+/// HS256共享密钥的最小长度，与SHA-256的输出长度一致
+const MIN_SECRET_LEN: usize = 32;
+
+pub async fn add(req: JwtProviderAddReq, user: UserPrincipal) -> anyhow::Result<()> {
+    let provider = JwtProviderBuilder::default()
+        .id(id::next().into())
+        .name(req.name.trim().to_string().into())
+        .description(req.description)
+        .status(JwtProviderStatus::Disable.into())
+        .algorithm(req.algorithm.into())
+        .key(req.key)
+        .jwks(req.jwks)
+        .jwks_file(req.jwks_file)
+        .issuer(req.issuer)
+        .audiences(req.audiences.unwrap_or_default().into())
+        .leeway(req.leeway.unwrap_or(60).into())
+        .create_user_id(Some(user.id))
+        .create_time(Some(tools::now()))
+        .build()?;
+
+    if provider.name.as_deref().unwrap_or_default().is_empty() {
+        bail!("JWT签发方配置错误：名称不能为空");
+    }
+    let others = JwtProvider::select_all(Pool::get()?).await?;
+    if others.iter().any(|item| item.name == provider.name) {
+        bail!(
+            "JWT provider with name {} already exists",
+            provider.name.unwrap()
+        )
+    }
+    check_keys(&provider)?;
+    JwtProvider::insert(Pool::get()?, &provider).await?;
+    Ok(())
+}
+
+/// 空字符串视为未配置
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|s| !s.is_empty())
+}
+
+/// 校验密钥和JWKS，密钥、JWKS和JWKS文件路径至少配置一个
+///
+/// JWKS文件在网关节点上，由网关在加载时校验。
+fn check_keys(provider: &JwtProvider) -> anyhow::Result<()> {
+    let algorithm = provider.algorithm.unwrap_or_default();
+    let key = non_empty(&provider.key);
+    let jwks = non_empty(&provider.jwks);
+    if key.is_none() && jwks.is_none() && non_empty(&provider.jwks_file).is_none() {
+        bail!("JWT签发方配置错误：密钥、JWKS和JWKS文件路径至少配置一个");
+    }
+    if let Some(key) = key {
+        match algorithm {
+            JwtAlgorithm::Hs256 if key.len() < MIN_SECRET_LEN => {
+                bail!(
+                    "JWT签发方配置错误：HS256的密钥长度不能少于{}字节",
+                    MIN_SECRET_LEN
+                )
+            }
+            JwtAlgorithm::Hs256 => {}
+            JwtAlgorithm::Rs256 if DecodingKey::from_rsa_pem(key.as_bytes()).is_err() => {
+                bail!("JWT签发方配置错误：密钥不是有效的PEM格式的RSA公钥")
+            }
+            JwtAlgorithm::Es256 if DecodingKey::from_ec_pem(key.as_bytes()).is_err() => {
+                bail!("JWT签发方配置错误：密钥不是有效的PEM格式的EC公钥")
+            }
+            _ => {}
+        }
+    }
+    if let Some(jwks) = jwks {
+        let Ok(jwks) = serde_json::from_str::<JwkSet>(jwks) else {
+            bail!("JWT签发方配置错误：JWKS不是有效的JSON格式");
+        };
+        if jwks.keys.is_empty() {
+            bail!("JWT签发方配置错误：JWKS中没有密钥");
+        }
+        if let Some(jwk) = jwks
+            .keys
+            .iter()
+            .find(|jwk| DecodingKey::from_jwk(jwk).is_err())
+        {
+            bail!(
+                "JWT签发方配置错误：JWKS中的密钥{}无法解析",
+                jwk.common.key_id.as_deref().unwrap_or_default()
+            );
+        }
+    }
+    Ok(())
+}
+
+pub async fn list(req: JwtProviderListReq) -> anyhow::Result<PageRes<JwtProviderListRes>> {
+    let page = jwt_provider::list_page(Pool::get()?, &req.to_rb_page(), &req).await?;
+    let list = page.convert_to_page_res(|list| {
+        list.into_iter()
+            .map(|mut item| {
+                // 公钥可以返回，共享密钥不返回
+                if matches!(item.algorithm, Some(JwtAlgorithm::Hs256)) {
+                    item.key = None;
+                }
+                JwtProviderListRes { inner: item }
+            })
+            .collect::<Vec<_>>()
+    });
+    Ok(list)
+}
+
+pub async fn update(req: JwtProviderUpdateReq, user: UserPrincipal) -> anyhow::Result<()> {
+    let old = JwtProvider::select_by_map(Pool::get()?, value! { "id": req.id}).await?;
+    let Some(old) = old.into_iter().next() else {
+        bail!("JWT provider not found");
+    };
+
+    let update = JwtProviderBuilder::default()
+        .id(req.id.into())
+        .description(req.description)
+        .algorithm(req.algorithm)
+        .key(req.key)
+        .jwks(req.jwks)
+        .jwks_file(req.jwks_file)
+        .issuer(req.issuer)
+        .audiences(req.audiences)
+        .leeway(req.leeway)
+        .update_user_id(Some(user.id))
+        .update_time(Some(tools::now()))
+        .build()?;
+    // 未传入的字段保持不变，按修改后的配置校验
+    check_keys(&JwtProvider {
+        algorithm: update.algorithm.or(old.algorithm),
+        key: update.key.clone().or(old.key),
+        jwks: update.jwks.clone().or(old.jwks),
+        jwks_file: update.jwks_file.clone().or(old.jwks_file),
+        ..Default::default()
+    })?;
+
+    JwtProvider::update_by_map(Pool::get()?, &update, value! { "id":req.id}).await?;
+    Ok(())
+}
+
+pub async fn delete(req: IdsReq) -> anyhow::Result<()> {
+    let providers = JwtProvider::select_by_map(Pool::get()?, value! { "id": &req.ids}).await?;
+    let routes = Route::select_all(Pool::get()?).await?;
+    for provider in providers {
+        let name = provider.name.unwrap_or_default();
+        if let Some(route) = routes.iter().find(
+            |route| matches!(&route.auth, Some(RouteAuth::Jwt(auth)) if auth.provider == name),
+        ) {
+            bail!(
+                "JWT签发方{}正在被路由{}使用，无法删除",
+                name,
+                route.name.as_deref().unwrap_or_default()
+            );
+        }
+    }
+    JwtProvider::delete_by_map(Pool::get()?, value! { "id": req.ids}).await?;
+    Ok(())
+}
+
+pub(crate) async fn update_status(req: UpdateStatusReq, user: UserPrincipal) -> anyhow::Result<()> {
+    let old = JwtProvider::select_by_map(Pool::get()?, value! { "id": req.id}).await?;
+    if old.is_empty() {
+        bail!("JWT provider not found")
+    }
+    JwtProvider::update_by_map(
+        Pool::get()?,
+        &JwtProviderBuilder::default()
+            .id(Some(req.id))
+            .status(Some(req.status))
+            .update_user_id(Some(user.id))
+            .update_time(Some(tools::now()))
+            .build()?,
+        value! { "id": req.id},
+    )
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_keys() {
+        let provider = |algorithm, key: &str, jwks: &str| JwtProvider {
+            algorithm: Some(algorithm),
+            key: Some(key.to_string()),
+            jwks: Some(jwks.to_string()),
+            ..Default::default()
+        };
+        assert!(check_keys(&provider(JwtAlgorithm::Hs256, "", "")).is_err());
+        assert!(check_keys(&provider(JwtAlgorithm::Hs256, "short", "")).is_err());
+        let secret = "0123456789abcdef0123456789abcdef";
+        assert!(check_keys(&provider(JwtAlgorithm::Hs256, secret, "")).is_ok());
+        assert!(check_keys(&provider(JwtAlgorithm::Rs256, "not a pem", "")).is_err());
+
+        let jwks = r#"{"keys":[{"kty":"oct","kid":"k1","k":"c2VjcmV0"}]}"#;
+        assert!(check_keys(&provider(JwtAlgorithm::Hs256, "", jwks)).is_ok());
+        assert!(check_keys(&provider(JwtAlgorithm::Hs256, "", r#"{"keys":[]}"#)).is_err());
+        assert!(check_keys(&provider(JwtAlgorithm::Hs256, "", "{")).is_err());
+    }
+}
@@ -1,14 +1,25 @@
 use crate::server::auth::UserPrincipal;
-use crate::server::key::request::ApiKeyAddOrUpdateReq;
-use crate::server::key::response::ApiKeyListRes;
-use crate::server::key::{ApiKeyListReq, service};
-use busi::req::IdsReq;
+use crate::server::key::request::{
+    ApiKeyAddOrUpdateReq, UpdateQuotaReq, UpdateScopeReq, UpdateStatusReq,
+};
+use crate::server::key::response::{ApiKeyListRes, ApiKeyUsageRes};
+use crate::server::key::{ApiKeyListReq, ApiKeyUsageReq, service};
+use busi::req::{IdReq, IdsReq};
 use busi::res::{PageRes, Res};
 use rocket::serde::json::Json;
 use rocket::{post, routes};
 
 pub fn routes() -> Vec<rocket::Route> {
-    routes![add, delete, list]
+    routes![
+        add,
+        delete,
+        list,
+        update_scope,
+        update_status,
+        reissue,
+        update_quota,
+        usage
+    ]
 }
 
 /// 新增密钥
@@ -36,3 +47,48 @@ pub async fn list(req: Json<ApiKeyListReq>, _user: UserPrincipal) -> Res<PageRes
         Err(e) => Res::error(&e.to_string()),
     }
 }
+
+/// 修改授权范围
+#[post("/update_scope", data = "<req>")]
+pub async fn update_scope(req: Json<UpdateScopeReq>, user: UserPrincipal) -> Res<()> {
+    match service::update_scope(req.0, user).await {
+        Ok(_) => Res::success(()),
+        Err(e) => Res::error(&e.to_string()),
+    }
+}
+
+/// 启用或禁用密钥
+#[post("/update_status", data = "<req>")]
+pub async fn update_status(req: Json<UpdateStatusReq>, user: UserPrincipal) -> Res<()> {
+    match service::update_status(req.0, user).await {
+        Ok(_) => Res::success(()),
+        Err(e) => Res::error(&e.to_string()),
+    }
+}
+
+/// 使用当前的加密密钥重新签发密钥
+#[post("/reissue", data = "<req>")]
+pub async fn reissue(req: Json<IdReq>, user: UserPrincipal) -> Res<()> {
+    match service::reissue(req.0, user).await {
+        Ok(_) => Res::success(()),
+        Err(e) => Res::error(&e.to_string()),
+    }
+}
+
+/// 修改月度配额
+#[post("/update_quota", data = "<req>")]
+pub async fn update_quota(req: Json<UpdateQuotaReq>, user: UserPrincipal) -> Res<()> {
+    match service::update_quota(req.0, user).await {
+        Ok(_) => Res::success(()),
+        Err(e) => Res::error(&e.to_string()),
+    }
+}
+
+/// 按天查询用量
+#[post("/usage", data = "<req>")]
+pub async fn usage(req: Json<ApiKeyUsageReq>, _user: UserPrincipal) -> Res<Vec<ApiKeyUsageRes>> {
+    match service::usage(req.0).await {
+        Ok(res) => Res::success(res),
+        Err(e) => Res::error(&e.to_string()),
+    }
+}
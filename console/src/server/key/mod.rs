@@ -1,6 +1,7 @@
 pub mod api;
 mod request;
 mod response;
-mod service;
+pub(crate) mod service;
 
 pub use request::ApiKeyListReq;
+pub use request::ApiKeyUsageReq;
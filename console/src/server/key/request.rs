@@ -1,3 +1,5 @@
+use crate::server::db::models::api_key::ApiKeyStatus;
+use aiway_protocol::gateway::{ApiKeyQuota, ApiKeyScope};
 use busi::req::PageReq;
 use busi::impl_pagination;
 use rbatis::rbdc::DateTime;
@@ -7,8 +9,49 @@ use rocket::serde::{Deserialize, Serialize};
 pub struct ApiKeyAddOrUpdateReq {
     pub name: String,
     pub principal: Option<String>,
+    /// 生效时间，为空时立即生效
+    pub eff_time: Option<DateTime>,
+    /// 失效时间，为空时永久有效
     pub exp_time: Option<DateTime>,
+    /// 授权范围，为空时不限制
+    pub scope: Option<ApiKeyScope>,
+    /// 月度配额，为空时不限制，配置时必须设置主体标识
+    pub quota: Option<ApiKeyQuota>,
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateScopeReq {
+    pub id: i64,
+    /// 授权范围，为空时不限制
+    pub scope: Option<ApiKeyScope>,
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateStatusReq {
+    pub id: i64,
+    /// 状态，只能修改为启用或禁用
+    pub status: ApiKeyStatus,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateQuotaReq {
+    pub id: i64,
+    /// 月度配额，为空时不限制
+    pub quota: Option<ApiKeyQuota>,
+}
+
+/// 查询用量，按API Key或主体标识查询，同一主体的多个API Key共享用量
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKeyUsageReq {
+    /// API Key的ID，按其主体标识查询
+    pub id: Option<i64>,
+    /// 主体标识，传入id时忽略
+    pub principal: Option<String>,
+    /// 起始日期（包含），如20250101
+    pub start_date: Option<String>,
+    /// 结束日期（包含），如20250131
+    pub end_date: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ApiKeyListReq {
     page: PageReq,
@@ -1,4 +1,5 @@
 use crate::server::db::models::api_key::ApiKey;
+use crate::server::db::models::api_key_usage::ApiKeyUsage;
 use rocket::serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -6,3 +7,9 @@ pub struct ApiKeyListRes {
     #[serde(flatten)]
     pub inner: ApiKey,
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKeyUsageRes {
+    #[serde(flatten)]
+    pub inner: ApiKeyUsage,
+}
@@ -1,28 +1,34 @@
 use crate::server::auth::UserPrincipal;
 use crate::server::db::models::api_key::{ApiKeySource, ApiKeyStatus};
+use crate::server::db::models::api_key_usage;
 use crate::server::db::models::system_config::{ConfigKey, SystemConfig};
 use crate::server::db::{Pool, models, tools};
-use crate::server::key::ApiKeyListReq;
-use crate::server::key::request::ApiKeyAddOrUpdateReq;
-use crate::server::key::response::ApiKeyListRes;
-use busi::req::{IdsReq, Pagination};
+use crate::server::key::request::{
+    ApiKeyAddOrUpdateReq, UpdateQuotaReq, UpdateScopeReq, UpdateStatusReq,
+};
+use crate::server::key::response::{ApiKeyListRes, ApiKeyUsageRes};
+use crate::server::key::{ApiKeyListReq, ApiKeyUsageReq};
+use busi::req::{IdReq, IdsReq, Pagination};
 use busi::res::{IntoPageRes, PageRes};
-use aiway_protocol::gateway::{ApiKey, Firewall};
+use aiway_protocol::gateway::{ApiKey, ApiKeyEntry, ApiKeyQuota, ApiKeyScope, Firewall};
 use anyhow::bail;
 use cache::caches::CacheKey;
 use common::id;
+use logging::log;
+use rbatis::executor::Executor;
 use rbs::value;
-use serde_json::Value;
 
 pub async fn add(req: ApiKeyAddOrUpdateReq, user: UserPrincipal) -> anyhow::Result<()> {
+    let scope = check_scope(req.scope).await?;
+    check_quota(req.quota.as_ref(), req.principal.as_deref())?;
     let firewall = SystemConfig::get::<Firewall>(ConfigKey::Firewall).await?;
-
-    let api_secret_encrypt_key = &firewall.api_secret_encrypt_key;
-    let ak = match &req.principal {
-        None => ApiKey::new().encrypt(api_secret_encrypt_key),
-        Some(principal) => ApiKey::new_with_principal(principal).encrypt(api_secret_encrypt_key),
-    };
-    cache::set(CacheKey::ApiKey(ak.clone()).to_string(), &Value::Null, None).await?;
+    let ak = issue(req.principal.as_deref(), &firewall);
+    let eff_time = req.eff_time.unwrap_or_else(tools::now);
+    if let Some(exp_time) = &req.exp_time
+        && exp_time <= &eff_time
+    {
+        bail!("API Key配置错误：失效时间必须晚于生效时间");
+    }
 
     let api_key = models::api_key::ApiKeyBuilder::default()
         .id(Some(id::next()))
@@ -30,12 +36,21 @@ pub async fn add(req: ApiKeyAddOrUpdateReq, user: UserPrincipal) -> anyhow::Resu
         .principal(req.principal)
         .secret(Some(ak.clone()))
         .status(Some(ApiKeyStatus::Ok))
-        .eff_time(Some(tools::now()))
+        .eff_time(Some(eff_time))
         .exp_time(req.exp_time)
         .source(Some(ApiKeySource::Console))
+        .scope(Some(scope.clone()).filter(|scope| !scope.is_unrestricted()))
+        .quota(req.quota.filter(|quota| !quota.is_unlimited()))
         .create_user_id(Some(user.id))
         .create_time(Some(tools::now()))
         .build()?;
+    // 缓存的值为状态、有效期和授权范围，网关鉴权时校验
+    cache::set(
+        CacheKey::ApiKey(ak.clone()).to_string(),
+        &cache_entry(&api_key),
+        None,
+    )
+    .await?;
 
     if let Err(e) = models::api_key::ApiKey::insert(Pool::get()?, &api_key).await {
         cache::remove(&CacheKey::ApiKey(ak).to_string()).await?;
@@ -45,6 +60,268 @@ pub async fn add(req: ApiKeyAddOrUpdateReq, user: UserPrincipal) -> anyhow::Resu
     Ok(())
 }
 
+/// 使用当前的加密密钥签发API Key
+fn issue(principal: Option<&str>, firewall: &Firewall) -> String {
+    let api_key = match principal {
+        None => ApiKey::new(),
+        Some(principal) => ApiKey::new_with_principal(principal),
+    };
+    api_key
+        .with_key_id(firewall.api_secret_key_id)
+        .encrypt(&firewall.api_secret_encrypt_key)
+}
+
+/// 缓存中API Key对应的值，网关按此校验状态、有效期和授权范围
+pub(crate) fn cache_entry(api_key: &models::api_key::ApiKey) -> ApiKeyEntry {
+    ApiKeyEntry {
+        enabled: matches!(api_key.status, Some(ApiKeyStatus::Ok)),
+        eff_time: api_key.eff_time.as_ref().map(|t| t.unix_timestamp()),
+        exp_time: api_key.exp_time.as_ref().map(|t| t.unix_timestamp()),
+        scope: api_key.scope.clone().unwrap_or_default(),
+        quota: api_key.quota.clone().unwrap_or_default(),
+    }
+}
+
+/// 请求方法
+const METHODS: [&str; 9] = [
+    "GET", "POST", "PUT", "DELETE", "HEAD", "OPTIONS", "PATCH", "TRACE", "CONNECT",
+];
+
+/// 校验授权范围，路由和服务必须存在，请求方法转为大写
+async fn check_scope(scope: Option<ApiKeyScope>) -> anyhow::Result<ApiKeyScope> {
+    let mut scope = scope.unwrap_or_default();
+    if !scope.routes.is_empty() {
+        let routes = models::route::Route::select_all(Pool::get()?).await?;
+        for name in scope.routes.iter() {
+            let Some(route) = routes.iter().find(|r| r.name.as_ref() == Some(name)) else {
+                bail!("授权范围配置错误：路由{}不存在", name);
+            };
+            // 流式转发请求体的路由无法获取模型，限制模型后所有请求都会被拒绝
+            if !scope.models.is_empty() && route.stream_body == Some(true) {
+                bail!("授权范围配置错误：路由{}流式转发请求体，无法限制模型", name);
+            }
+        }
+    }
+    if !scope.services.is_empty() {
+        let services = models::service::Service::select_all(Pool::get()?).await?;
+        if let Some(name) = scope
+            .services
+            .iter()
+            .find(|name| !services.iter().any(|s| s.name.as_ref() == Some(*name)))
+        {
+            bail!("授权范围配置错误：服务{}不存在", name);
+        }
+    }
+    for method in scope.methods.iter_mut() {
+        *method = method.trim().to_ascii_uppercase();
+        if !METHODS.contains(&method.as_str()) {
+            bail!("授权范围配置错误：{}，不是合法的请求方法", method);
+        }
+    }
+    if scope.models.iter().any(|model| model.trim().is_empty()) {
+        bail!("授权范围配置错误：模型名称不能为空");
+    }
+    Ok(scope)
+}
+
+/// 修改授权范围，同时更新缓存，网关的下一次请求即生效
+pub async fn update_scope(req: UpdateScopeReq, user: UserPrincipal) -> anyhow::Result<()> {
+    let api_key =
+        models::api_key::ApiKey::select_by_map(Pool::get()?, value! {"id": req.id}).await?;
+    let Some(api_key) = api_key.into_iter().next() else {
+        bail!("API Key not found");
+    };
+    let scope = check_scope(req.scope).await?;
+
+    let update = models::api_key::ApiKeyBuilder::default()
+        .scope(Some(scope.clone()))
+        .update_user_id(Some(user.id))
+        .update_time(Some(tools::now()))
+        .build()?;
+    models::api_key::ApiKey::update_by_map(Pool::get()?, &update, value! {"id": req.id}).await?;
+    let secret = api_key.secret.clone().unwrap_or_default();
+    let api_key = models::api_key::ApiKey {
+        scope: Some(scope),
+        ..api_key
+    };
+    cache::set(
+        CacheKey::ApiKey(secret).to_string(),
+        &cache_entry(&api_key),
+        None,
+    )
+    .await?;
+    Ok(())
+}
+
+/// 校验配额，用量按主体标识统计，配置了配额时必须设置主体标识
+fn check_quota(quota: Option<&ApiKeyQuota>, principal: Option<&str>) -> anyhow::Result<()> {
+    let Some(quota) = quota.filter(|quota| !quota.is_unlimited()) else {
+        return Ok(());
+    };
+    if principal.unwrap_or_default().trim().is_empty() {
+        bail!("配额配置错误：未设置主体标识的API Key无法计量用量");
+    }
+    if quota.requests == Some(0) || quota.tokens == Some(0) {
+        bail!("配额配置错误：配额必须大于0");
+    }
+    Ok(())
+}
+
+/// 修改月度配额，同时更新缓存，网关的下一次请求即生效
+pub async fn update_quota(req: UpdateQuotaReq, user: UserPrincipal) -> anyhow::Result<()> {
+    let api_key =
+        models::api_key::ApiKey::select_by_map(Pool::get()?, value! {"id": req.id}).await?;
+    let Some(api_key) = api_key.into_iter().next() else {
+        bail!("API Key not found");
+    };
+    check_quota(req.quota.as_ref(), api_key.principal.as_deref())?;
+    let quota = req.quota.unwrap_or_default();
+
+    let update = models::api_key::ApiKeyBuilder::default()
+        .quota(Some(quota.clone()))
+        .update_user_id(Some(user.id))
+        .update_time(Some(tools::now()))
+        .build()?;
+    models::api_key::ApiKey::update_by_map(Pool::get()?, &update, value! {"id": req.id}).await?;
+    let secret = api_key.secret.clone().unwrap_or_default();
+    let api_key = models::api_key::ApiKey {
+        quota: Some(quota),
+        ..api_key
+    };
+    cache::set(
+        CacheKey::ApiKey(secret).to_string(),
+        &cache_entry(&api_key),
+        None,
+    )
+    .await?;
+    Ok(())
+}
+
+/// 按天查询用量，今天的用量每分钟同步一次
+pub async fn usage(mut req: ApiKeyUsageReq) -> anyhow::Result<Vec<ApiKeyUsageRes>> {
+    if let Some(id) = req.id {
+        let api_key =
+            models::api_key::ApiKey::select_by_map(Pool::get()?, value! {"id": id}).await?;
+        let Some(api_key) = api_key.into_iter().next() else {
+            bail!("API Key not found");
+        };
+        req.principal = api_key.principal;
+    }
+    if req.principal.as_deref().unwrap_or_default().is_empty() {
+        bail!("未设置主体标识的API Key没有用量");
+    }
+    let list = api_key_usage::usage_by_day(Pool::get()?, &req).await?;
+    Ok(list
+        .into_iter()
+        .map(|item| ApiKeyUsageRes { inner: item })
+        .collect())
+}
+
+/// 启用或禁用，同时更新缓存，网关的下一次请求即生效
+pub async fn update_status(req: UpdateStatusReq, user: UserPrincipal) -> anyhow::Result<()> {
+    let api_key =
+        models::api_key::ApiKey::select_by_map(Pool::get()?, value! {"id": req.id}).await?;
+    let Some(api_key) = api_key.into_iter().next() else {
+        bail!("API Key not found");
+    };
+    match req.status {
+        ApiKeyStatus::Expired => bail!("不能将API Key的状态修改为已过期"),
+        ApiKeyStatus::Ok if matches!(api_key.status, Some(ApiKeyStatus::Expired)) => {
+            bail!("API Key已过期，无法启用")
+        }
+        _ => {}
+    }
+
+    let update = models::api_key::ApiKeyBuilder::default()
+        .status(Some(req.status.clone()))
+        .update_user_id(Some(user.id))
+        .update_time(Some(tools::now()))
+        .build()?;
+    models::api_key::ApiKey::update_by_map(Pool::get()?, &update, value! {"id": req.id}).await?;
+    let secret = api_key.secret.clone().unwrap_or_default();
+    let api_key = models::api_key::ApiKey {
+        status: Some(req.status),
+        ..api_key
+    };
+    cache::set(
+        CacheKey::ApiKey(secret).to_string(),
+        &cache_entry(&api_key),
+        None,
+    )
+    .await?;
+    Ok(())
+}
+
+/// 使用当前的加密密钥重新签发，其他配置不变，旧的API Key立即失效
+pub async fn reissue(req: IdReq, user: UserPrincipal) -> anyhow::Result<()> {
+    let api_key =
+        models::api_key::ApiKey::select_by_map(Pool::get()?, value! {"id": req.id}).await?;
+    let Some(api_key) = api_key.into_iter().next() else {
+        bail!("API Key not found");
+    };
+    let firewall = SystemConfig::get::<Firewall>(ConfigKey::Firewall).await?;
+    let ak = update_secret(Pool::get()?, &api_key, &firewall, user.id).await?;
+    refresh_cache(&api_key, ak).await
+}
+
+/// 重新签发全部API Key，轮换加密密钥后调用
+///
+/// 在同一个事务中更新全部API Key，提交后再更新缓存，任一更新失败时全部回滚，缓存保持不变。
+pub(crate) async fn reissue_all(user: &UserPrincipal) -> anyhow::Result<()> {
+    let firewall = SystemConfig::get::<Firewall>(ConfigKey::Firewall).await?;
+    let api_keys = models::api_key::ApiKey::select_all(Pool::get()?).await?;
+    let tx = Pool::get()?.acquire_begin().await?;
+    let mut reissued = Vec::with_capacity(api_keys.len());
+    for api_key in api_keys {
+        match update_secret(&tx, &api_key, &firewall, user.id).await {
+            Ok(ak) => reissued.push((api_key, ak)),
+            Err(e) => {
+                if let Err(e) = tx.rollback().await {
+                    log::error!("重新签发API Key时事务回滚失败：{}", e);
+                }
+                return Err(e);
+            }
+        }
+    }
+    tx.commit().await?;
+
+    for (api_key, ak) in reissued {
+        refresh_cache(&api_key, ak).await?;
+    }
+    Ok(())
+}
+
+/// 使用当前的加密密钥生成新的API Key并保存，返回新的API Key
+async fn update_secret(
+    executor: &dyn Executor,
+    api_key: &models::api_key::ApiKey,
+    firewall: &Firewall,
+    user_id: i64,
+) -> anyhow::Result<String> {
+    let ak = issue(api_key.principal.as_deref(), firewall);
+    let update = models::api_key::ApiKeyBuilder::default()
+        .secret(Some(ak.clone()))
+        .update_user_id(Some(user_id))
+        .update_time(Some(tools::now()))
+        .build()?;
+    models::api_key::ApiKey::update_by_map(executor, &update, value! {"id": api_key.id}).await?;
+    Ok(ak)
+}
+
+/// 移除旧API Key的缓存，缓存新的API Key
+async fn refresh_cache(api_key: &models::api_key::ApiKey, ak: String) -> anyhow::Result<()> {
+    if let Some(secret) = &api_key.secret {
+        cache::remove(&CacheKey::ApiKey(secret.clone()).to_string()).await?;
+    }
+    cache::set(
+        CacheKey::ApiKey(ak).to_string(),
+        &cache_entry(api_key),
+        None,
+    )
+    .await?;
+    Ok(())
+}
+
 pub async fn delete(req: IdsReq) -> anyhow::Result<()> {
     for id in req.ids.iter() {
         let api_key =
@@ -25,6 +25,8 @@ pub struct GatewayState {
     pub request_count: usize,
     /// 累计无效请求次数
     pub request_invalid_count: usize,
+    /// 累计被限流请求次数
+    pub request_ratelimit_count: usize,
     /// 累计响应成功次数
     pub response_2xx_count: usize,
     /// 累计3xx响应次数
@@ -39,6 +41,9 @@ pub struct GatewayState {
     /// 当前sse连接总数
     /// 取值：gateway_node表的所有节点的sse连接总数
     pub sse_connect_count: usize,
+    /// 当前WebSocket连接总数
+    /// 取值：gateway_node表的所有节点的WebSocket连接总数
+    pub websocket_connect_count: usize,
     /// 网关当前网络接收字节数
     pub net_rx: usize,
     /// 网关当前网络发送字节数
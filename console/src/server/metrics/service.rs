@@ -92,6 +92,10 @@ pub async fn gateway_state() -> anyhow::Result<GatewayState> {
         .iter()
         .map(|s| s.request_invalid_count)
         .sum::<usize>();
+    state.request_ratelimit_count = node_states
+        .iter()
+        .map(|s| s.request_ratelimit_count)
+        .sum::<usize>();
     state.response_2xx_count = node_states
         .iter()
         .map(|s| s.response_2xx_count)
@@ -116,6 +120,10 @@ pub async fn gateway_state() -> anyhow::Result<GatewayState> {
         .iter()
         .map(|s| s.sse_connect_count as usize)
         .sum::<usize>();
+    state.websocket_connect_count = node_states
+        .iter()
+        .map(|s| s.websocket_connect_count as usize)
+        .sum::<usize>();
     state.net_rx = node_states.iter().map(|s| s.net_rx as usize).sum::<usize>();
     state.net_tx = node_states.iter().map(|s| s.net_tx as usize).sum::<usize>();
 
@@ -41,6 +41,15 @@
 //!
 //! 插件（这里指全局插件）变更后，推送到conreg，网关在监听到插件列表变化时重新加载。
 //!
+//! ### 证书管理
+//! TLS证书增删改查，网关按SNI选择证书。
+//!
+//! 主要配置项：
+//! - 证书适用的域名，支持通配符
+//! - 证书链及私钥，PEM格式
+//!
+//! 证书变更后，网关定时拉取并重新加载，无需重启。
+//!
 //! ### 日志
 //! 日志查询、分析。
 //!
@@ -53,6 +62,9 @@
 //!
 //! 控制台变更了密钥后，同步到Redis，网关从Redis中读取。
 //!
+//! API Key的加密密钥支持轮换，密钥ID记录在API Key中，网关按密钥环验证，旧密钥在宽限期内依然可用。
+//! 生产模式（`--production`）下使用默认密钥时，控制台拒绝启动。
+//!
 //! ### 安全监控
 //! 主要监控网络请求以及分析日志，发现异常流量或者日志里发现异常发送警告通知。
 //!
@@ -82,11 +94,13 @@ use std::net::IpAddr;
 use std::str::FromStr;
 
 mod auth;
+mod certificate;
 mod common;
 pub mod db;
 mod file;
 mod firewall;
 mod gateway;
+mod jwt;
 mod key;
 mod log;
 mod message;
@@ -128,6 +142,8 @@ pub async fn start_http_server(args: &Args) -> anyhow::Result<()> {
     builder = builder.mount("/api/metrics", metrics::api::routes());
     builder = builder.mount("/api/log", log::api::routes());
     builder = builder.mount("/api/firewall", firewall::api::routes());
+    builder = builder.mount("/api/certificate", certificate::api::routes());
+    builder = builder.mount("/api/jwt", jwt::api::routes());
     builder = builder.mount("/api/system", system::routes());
     builder = builder.mount("/api/message", message::api::routes());
     builder = builder.mount("/api/node", node::api::routes());
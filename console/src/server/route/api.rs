@@ -1,6 +1,7 @@
 use crate::server::auth::UserPrincipal;
 use crate::server::route::request::{
-    RouteAddOrUpdateReq, RouteListReq, UpdateGlobalFilterConfigReq, UpdateStatusReq,
+    RouteAddOrUpdateReq, RouteListReq, UpdateBackendsReq, UpdateGlobalFilterConfigReq,
+    UpdateMaintenanceReq, UpdateStatusReq,
 };
 use crate::server::route::response::RouteListRes;
 use crate::server::route::service;
@@ -17,6 +18,8 @@ pub fn routes() -> Vec<rocket::Route> {
         update,
         delete,
         update_status,
+        update_backends,
+        update_maintenance,
         update_global_filter_config,
         get_global_filter_config
     ]
@@ -65,6 +68,24 @@ pub async fn update_status(req: Json<UpdateStatusReq>, user: UserPrincipal) -> R
     }
 }
 
+/// 调整加权后端
+#[post("/update_backends", data = "<req>")]
+pub async fn update_backends(req: Json<UpdateBackendsReq>, user: UserPrincipal) -> Res<()> {
+    match service::update_backends(req.0, user).await {
+        Ok(_) => Res::success(()),
+        Err(e) => Res::error(&e.to_string()),
+    }
+}
+
+/// 批量开启或关闭维护模式
+#[post("/update_maintenance", data = "<req>")]
+pub async fn update_maintenance(req: Json<UpdateMaintenanceReq>, user: UserPrincipal) -> Res<()> {
+    match service::update_maintenance(req.0, user).await {
+        Ok(_) => Res::success(()),
+        Err(e) => Res::error(&e.to_string()),
+    }
+}
+
 #[post("/global_filter/update", data = "<req>")]
 pub async fn update_global_filter_config(
     req: Json<UpdateGlobalFilterConfigReq>,
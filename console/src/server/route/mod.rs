@@ -5,6 +5,7 @@ mod service;
 
 use matchit::InsertError;
 pub use request::RouteListReq;
+pub(crate) use service::check_rate_limit;
 
 /// 路由路径匹配模式
 ///
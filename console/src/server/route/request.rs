@@ -1,7 +1,13 @@
 use crate::server::db::models::route::{Route, RouteStatus};
 use busi::req::PageReq;
 use aiway_protocol::gateway::GlobalFilter;
+use aiway_protocol::gateway::RateLimit;
 use aiway_protocol::gateway::plugin::ConfiguredPlugin;
+use aiway_protocol::gateway::route::{
+    MaintenancePage, MatchCondition, MirrorPolicy, PathRewrite, PathType, ProxyHeaders,
+    RetryPolicy, RouteAction, RouteAuth, WeightedBackend,
+};
+use aiway_protocol::gateway::service::Timeouts;
 use busi::impl_pagination;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
@@ -18,16 +24,26 @@ pub struct RouteAddOrUpdateReq {
     pub host: String,
     /// 路径匹配
     pub path: String,
+    /// 路径的匹配方式：wildcard | regex，默认为wildcard
+    pub path_type: Option<PathType>,
+    /// 优先级，数值越大越优先匹配，默认为0
+    pub priority: Option<i32>,
     #[serde(default = "Default::default")]
     pub methods: Vec<String>,
-    /// 目标服务
+    /// 处理方式，默认转发到服务，如：{"type": "redirect", "url": "https://new.example.com/{p}"}
+    pub action: Option<RouteAction>,
+    /// 目标服务，处理方式为转发时必填
+    #[serde(default = "Default::default")]
     pub service: String,
-    /// header匹配
+    /// 加权后端，如：[{"service": "orders-v1", "weight": 95}, {"service": "orders-v2", "weight": 5}]，
+    /// 可配置灰度条件：{"header": {"x-canary": "1"}, "cookie": {"canary": "1"}}
+    pub backends: Option<Vec<WeightedBackend>>,
+    /// header匹配，值为字符串时精确匹配，或使用对象指定匹配方式，如：{"op": "regex", "value": "^v\\d+$"}
     #[serde(default = "Default::default")]
-    pub header: BTreeMap<String, String>,
-    /// query匹配
+    pub header: BTreeMap<String, MatchCondition>,
+    /// query匹配，格式同header
     #[serde(default = "Default::default")]
-    pub query: BTreeMap<String, String>,
+    pub query: BTreeMap<String, MatchCondition>,
     /// 前置过滤器
     #[serde(default = "Default::default")]
     pub pre_filters: Vec<ConfiguredPlugin>,
@@ -36,8 +52,28 @@ pub struct RouteAddOrUpdateReq {
     pub post_filters: Vec<ConfiguredPlugin>,
     /// 是否需要认证
     pub is_auth: Option<bool>,
+    /// 鉴权方式，如：{"type": "jwt", "provider": "keycloak", "headers": {"X-User-Id": "sub"}}
+    pub auth: Option<RouteAuth>,
     /// 认证白名单
     pub auth_white_list: Option<Vec<String>>,
+    /// 路径重写
+    pub rewrite: Option<PathRewrite>,
+    /// 重试策略
+    pub retry: Option<RetryPolicy>,
+    /// 超时配置
+    pub timeouts: Option<Timeouts>,
+    /// 限流配置
+    pub rate_limit: Option<RateLimit>,
+    /// 转发请求头配置
+    pub proxy_headers: Option<ProxyHeaders>,
+    /// 是否流式转发请求体
+    pub stream_body: Option<bool>,
+    /// 是否允许WebSocket
+    pub websocket: Option<bool>,
+    /// 是否要求客户端证书
+    pub client_cert: Option<bool>,
+    /// 请求镜像，如：{"service": "orders-v2", "percent": 10}
+    pub mirror: Option<MirrorPolicy>,
 }
 
 fn default_host() -> String {
@@ -53,14 +89,30 @@ impl From<RouteAddOrUpdateReq> for Route {
             status: Some(Default::default()),
             host: req.host.into(),
             path: req.path.into(),
+            path_type: req.path_type,
+            priority: req.priority,
+            action: req.action,
+            maintenance: None,
+            maintenance_page: None,
             service: req.service.into(),
+            backends: req.backends,
             methods: req.methods.into(),
             header: req.header.into(),
             query: req.query.into(),
             pre_filters: req.pre_filters.into(),
             post_filters: req.post_filters.into(),
             is_auth: req.is_auth,
+            auth: req.auth,
             auth_white_list: req.auth_white_list,
+            rewrite: req.rewrite,
+            retry: req.retry,
+            timeouts: req.timeouts,
+            rate_limit: req.rate_limit,
+            proxy_headers: req.proxy_headers,
+            stream_body: req.stream_body,
+            websocket: req.websocket,
+            client_cert: req.client_cert,
+            mirror: req.mirror,
             create_user_id: None,
             update_user_id: None,
             create_time: None,
@@ -86,6 +138,25 @@ pub struct UpdateStatusReq {
     pub id: i64,
     pub status: RouteStatus,
 }
+/// 调整加权后端，用于逐步切换流量，不影响路由的其他配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateBackendsReq {
+    pub id: i64,
+    /// 加权后端，为空时转发到路由的目标服务
+    #[serde(default = "Default::default")]
+    pub backends: Vec<WeightedBackend>,
+}
+
+/// 批量开启或关闭维护模式
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateMaintenanceReq {
+    pub ids: Vec<i64>,
+    /// 是否维护中
+    pub maintenance: bool,
+    /// 维护页面，开启时设置，为空时使用默认的维护页面
+    pub page: Option<MaintenancePage>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UpdateGlobalFilterConfigReq {
     #[serde(flatten)]
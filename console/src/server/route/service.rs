@@ -1,11 +1,13 @@
 use crate::server::auth::UserPrincipal;
+use crate::server::db::models::jwt_provider::JwtProvider;
 use crate::server::db::models::route;
 use crate::server::db::models::route::{Route, RouteBuilder, RouteStatus};
 use crate::server::db::models::system_config::{ConfigKey, SystemConfig};
 use crate::server::db::{Pool, tools};
 use crate::server::route::PathPatterns;
 use crate::server::route::request::{
-    RouteAddOrUpdateReq, RouteListReq, UpdateGlobalFilterConfigReq, UpdateStatusReq,
+    RouteAddOrUpdateReq, RouteListReq, UpdateBackendsReq, UpdateGlobalFilterConfigReq,
+    UpdateMaintenanceReq, UpdateStatusReq,
 };
 use crate::server::route::response::RouteListRes;
 use anyhow::{Context, bail};
@@ -13,7 +15,14 @@ use common::id;
 use busi::req::{IdsReq, Pagination};
 use busi::res::{IntoPageRes, PageRes};
 use aiway_protocol::gateway::GlobalFilter;
+use aiway_protocol::gateway::RateLimit;
+use aiway_protocol::gateway::RequestContext;
+use aiway_protocol::gateway::rate_limit::RateLimitKey;
+use aiway_protocol::gateway::route::{
+    PathType, RedirectAction, RouteAction, RouteAuth, WeightedBackend,
+};
 use rbs::value;
+use std::collections::{BTreeMap, HashSet};
 
 pub async fn add(req: RouteAddOrUpdateReq, user: UserPrincipal) -> anyhow::Result<()> {
     let route = Route {
@@ -25,6 +34,16 @@ pub async fn add(req: RouteAddOrUpdateReq, user: UserPrincipal) -> anyhow::Resul
     };
 
     check_exists(&route, None).await?;
+    check_action(&route)?;
+    check_rewrite(&route)?;
+    check_retry(&route)?;
+    if let Some(rate_limit) = &route.rate_limit {
+        check_rate_limit(rate_limit)?;
+    }
+    check_proxy_headers(&route)?;
+    check_backends(route.backends.as_deref().unwrap_or_default())?;
+    check_mirror(&route)?;
+    check_auth(&route).await?;
 
     Route::insert(Pool::get()?, &route).await?;
     Ok(())
@@ -32,11 +51,13 @@ pub async fn add(req: RouteAddOrUpdateReq, user: UserPrincipal) -> anyhow::Resul
 
 /// 检查路由是否存在
 ///
-/// 唯一路由：Host + Path 唯一，且同一 Host 下的 Path 不能冲突。
+/// 同一 Host 下可以有多个路径相同的路由，按优先级和匹配条件区分，
+/// 但路径、请求方法、请求头和请求参数条件不能完全相同，否则后匹配的路由永远不会生效。
 /// 由于 Host 不是必须的，所以在检查是否冲突时需要处理 None 的情况
 async fn check_exists(route: &Route, exclude_id: Option<i64>) -> anyhow::Result<()> {
+    check_path(route)?;
+
     let host = route.host.as_deref();
-    let path = route.path.as_deref();
     let tx = Pool::get()?;
     let mut list: Vec<Route> = if host.is_some() {
         tx.query_decode("select * from route where host = ?", vec![value!(host)])
@@ -49,19 +70,263 @@ async fn check_exists(route: &Route, exclude_id: Option<i64>) -> anyhow::Result<
     // 移除排除的 ID，一般用来忽略自身
     list.retain(|item| item.id != exclude_id);
 
-    let mut paths = vec![path.map(|s| s.to_string()).context("Route path required")?];
-    paths.extend(
-        list.into_iter()
-            .map(|item| item.path.unwrap())
-            .collect::<Vec<_>>(),
-    );
-    if let Err(e) = matchit::Router::try_from(PathPatterns::new(paths)) {
-        bail!("路径验证失败：{}", e);
+    let conditions = match_conditions(route)?;
+    for item in list {
+        if match_conditions(&item)? == conditions {
+            bail!(
+                "路由匹配条件重复：与路由{}的路径及匹配条件完全相同",
+                item.name.unwrap_or_default()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// 检查路径及请求头、请求参数条件是否合法
+fn check_path(route: &Route) -> anyhow::Result<()> {
+    let path = route.path.clone().context("Route path required")?;
+    match route.path_type.unwrap_or_default() {
+        PathType::Wildcard => {
+            if let Err(e) = matchit::Router::try_from(PathPatterns::new([path])) {
+                bail!("路径验证失败：{}", e);
+            }
+        }
+        PathType::Regex => {
+            if let Err(e) = aiway_protocol::gateway::route::path_regex(&path) {
+                bail!("路径验证失败：正则表达式无效，{}", e);
+            }
+        }
+    }
+    let conditions = route
+        .header
+        .iter()
+        .flatten()
+        .chain(route.query.iter().flatten());
+    for (name, condition) in conditions {
+        if let Err(e) = condition.validate() {
+            bail!("路由匹配条件配置错误：{}，{}", name, e);
+        }
+    }
+    Ok(())
+}
+
+/// 路由的匹配条件，用于判断两个路由是否完全相同
+fn match_conditions(route: &Route) -> anyhow::Result<String> {
+    let mut methods = route.methods.clone().unwrap_or_default();
+    methods.sort();
+    Ok(serde_json::to_string(&(
+        &route.path,
+        route.path_type.unwrap_or_default(),
+        methods,
+        route.header.clone().unwrap_or_default(),
+        route.query.clone().unwrap_or_default(),
+    ))?)
+}
+
+/// 检查路径重写配置是否合法
+fn check_rewrite(route: &Route) -> anyhow::Result<()> {
+    if let Some(rewrite) = &route.rewrite
+        && let Err(e) = rewrite.validate()
+    {
+        bail!("路径重写配置错误：{}", e);
+    }
+    Ok(())
+}
+
+/// 检查处理方式是否合法
+fn check_action(route: &Route) -> anyhow::Result<()> {
+    match route.action.as_ref().unwrap_or(&RouteAction::Proxy) {
+        RouteAction::Proxy => {
+            let service = route.service.as_deref().unwrap_or_default();
+            let backends = route.backends.as_deref().unwrap_or_default();
+            if service.trim().is_empty() && backends.is_empty() {
+                bail!("处理方式配置错误：转发到服务时目标服务不能为空");
+            }
+        }
+        RouteAction::Redirect(redirect) => {
+            if redirect.url.trim().is_empty() {
+                bail!("处理方式配置错误：重定向地址不能为空");
+            }
+            if !RedirectAction::STATUSES.contains(&redirect.status) {
+                bail!("处理方式配置错误：重定向状态码必须为301、302、303、307或308");
+            }
+        }
+        RouteAction::Static(response) => {
+            if !(100..=599).contains(&response.status) {
+                bail!("处理方式配置错误：状态码{}无效", response.status);
+            }
+            check_response_headers(&response.headers)?;
+        }
+        RouteAction::Maintenance(page) => check_response_headers(&page.headers)?,
+    }
+    Ok(())
+}
+
+/// 检查固定响应和维护页面的响应头名称是否合法
+fn check_response_headers(headers: &BTreeMap<String, String>) -> anyhow::Result<()> {
+    for name in headers.keys() {
+        if !is_header_name(name) {
+            bail!("处理方式配置错误：{}，不是合法的响应头名称", name);
+        }
+    }
+    Ok(())
+}
+
+/// 检查重试策略是否合法
+fn check_retry(route: &Route) -> anyhow::Result<()> {
+    if let Some(retry) = &route.retry {
+        if retry.max_attempts == 0 || retry.max_attempts > 10 {
+            bail!("重试策略配置错误：最大尝试次数必须在1到10之间");
+        }
+        if retry.budget_percent > 100 {
+            bail!("重试策略配置错误：重试预算不能超过100%");
+        }
+        if retry.backoff > retry.max_backoff {
+            bail!("重试策略配置错误：退避时间不能大于最大退避时间");
+        }
+    }
+    Ok(())
+}
+
+/// 检查限流配置是否合法，路由和防火墙中的全局限流共用
+pub(crate) fn check_rate_limit(rate_limit: &RateLimit) -> anyhow::Result<()> {
+    if rate_limit.limit == 0 || rate_limit.window_secs == 0 {
+        bail!("限流配置错误：请求数和周期必须大于0");
+    }
+    if let Some(burst) = rate_limit.burst
+        && burst == 0
+    {
+        bail!("限流配置错误：令牌桶容量必须大于0");
+    }
+    if let RateLimitKey::Header(name) = &rate_limit.key
+        && name.trim().is_empty()
+    {
+        bail!("限流配置错误：请求头名称不能为空");
+    }
+    Ok(())
+}
+
+/// 检查转发请求头配置是否合法
+fn check_proxy_headers(route: &Route) -> anyhow::Result<()> {
+    let Some(proxy_headers) = &route.proxy_headers else {
+        return Ok(());
+    };
+    let names = proxy_headers
+        .remove
+        .iter()
+        .chain(proxy_headers.set.keys())
+        .chain(proxy_headers.add.keys());
+    for name in names {
+        if !is_header_name(name) {
+            bail!("转发请求头配置错误：{}，不是合法的请求头名称", name);
+        }
+    }
+    Ok(())
+}
+
+/// 检查加权后端配置是否合法
+fn check_backends(backends: &[WeightedBackend]) -> anyhow::Result<()> {
+    if backends.is_empty() {
+        return Ok(());
+    }
+    let mut services = HashSet::new();
+    for backend in backends {
+        if backend.service.trim().is_empty() {
+            bail!("加权后端配置错误：服务不能为空");
+        }
+        if !services.insert(backend.service.as_str()) {
+            bail!("加权后端配置错误：服务{}重复", backend.service);
+        }
+        let conditions = backend.header.iter().chain(backend.cookie.iter());
+        for (name, condition) in conditions {
+            if let Err(e) = condition.validate() {
+                bail!("加权后端配置错误：{}，{}", name, e);
+            }
+        }
+    }
+    if backends.iter().all(|backend| backend.weight == 0) {
+        bail!("加权后端配置错误：至少需要一个权重大于0的服务");
+    }
+    Ok(())
+}
+
+/// 检查请求镜像配置是否合法
+fn check_mirror(route: &Route) -> anyhow::Result<()> {
+    let Some(mirror) = &route.mirror else {
+        return Ok(());
+    };
+    if mirror.service.trim().is_empty() {
+        bail!("请求镜像配置错误：镜像服务不能为空");
     }
+    if route.service.as_ref() == Some(&mirror.service) {
+        bail!("请求镜像配置错误：镜像服务不能与目标服务相同");
+    }
+    let backends = route.backends.as_deref().unwrap_or_default();
+    if backends
+        .iter()
+        .any(|backend| backend.service == mirror.service)
+    {
+        bail!("请求镜像配置错误：镜像服务不能与加权后端的服务相同");
+    }
+    if mirror.percent > 100 {
+        bail!("请求镜像配置错误：采样百分比必须在0到100之间");
+    }
+    if mirror.timeout == 0 {
+        bail!("请求镜像配置错误：超时时间必须大于0");
+    }
+    Ok(())
+}
 
+/// 检查鉴权方式是否合法，JWT鉴权引用的签发方必须存在
+async fn check_auth(route: &Route) -> anyhow::Result<()> {
+    let Some(RouteAuth::Jwt(auth)) = &route.auth else {
+        return Ok(());
+    };
+    if auth.provider.trim().is_empty() {
+        bail!("鉴权配置错误：JWT签发方不能为空");
+    }
+    let providers =
+        JwtProvider::select_by_map(Pool::get()?, value! {"name": &auth.provider}).await?;
+    if providers.is_empty() {
+        bail!("鉴权配置错误：JWT签发方{}不存在", auth.provider);
+    }
+    if auth.principal_claim.trim().is_empty() {
+        bail!("鉴权配置错误：主体标识的声明不能为空");
+    }
+    for (name, claim) in &auth.headers {
+        if !is_header_name(name) {
+            bail!("鉴权配置错误：{}，不是合法的请求头名称", name);
+        }
+        if claim.trim().is_empty() {
+            bail!("鉴权配置错误：请求头{}对应的声明不能为空", name);
+        }
+    }
+    // 不能覆盖网关写入的state
+    const RESERVED: [&str; 3] = [
+        RequestContext::STATE_PRINCIPAL,
+        RequestContext::STATE_CLIENT_CERT_SUBJECT,
+        RequestContext::STATE_JWT_CLAIMS,
+    ];
+    for (key, claim) in &auth.state {
+        if key.trim().is_empty() || RESERVED.contains(&key.as_str()) {
+            bail!("鉴权配置错误：{}，不能作为state名称", key);
+        }
+        if claim.trim().is_empty() {
+            bail!("鉴权配置错误：state {}对应的声明不能为空", key);
+        }
+    }
     Ok(())
 }
 
+/// 请求头名称是否合法，即RFC 7230中的token
+fn is_header_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+}
+
 pub async fn list(
     req: RouteListReq,
     _user: UserPrincipal,
@@ -91,6 +356,16 @@ pub async fn update(req: RouteAddOrUpdateReq, user: UserPrincipal) -> anyhow::Re
     };
 
     check_exists(&update, Some(id)).await?;
+    check_action(&update)?;
+    check_rewrite(&update)?;
+    check_retry(&update)?;
+    if let Some(rate_limit) = &update.rate_limit {
+        check_rate_limit(rate_limit)?;
+    }
+    check_proxy_headers(&update)?;
+    check_backends(update.backends.as_deref().unwrap_or_default())?;
+    check_mirror(&update)?;
+    check_auth(&update).await?;
 
     Route::update_by_map(Pool::get()?, &update, value! { "id":id}).await?;
     Ok(())
@@ -120,6 +395,58 @@ pub async fn update_status(req: UpdateStatusReq, user: UserPrincipal) -> anyhow:
     Ok(())
 }
 
+/// 调整加权后端的权重，可以逐步将流量从旧服务切换到新服务，无需删除重建路由
+pub async fn update_backends(req: UpdateBackendsReq, user: UserPrincipal) -> anyhow::Result<()> {
+    let old = Route::select_by_map(Pool::get()?, value! { "id": req.id}).await?;
+    if old.is_empty() {
+        bail!("Route not found")
+    }
+    check_backends(&req.backends)?;
+    check_mirror(&Route {
+        backends: Some(req.backends.clone()),
+        ..old[0].clone()
+    })?;
+    Route::update_by_map(
+        Pool::get()?,
+        &RouteBuilder::default()
+            .id(Some(req.id))
+            .backends(Some(req.backends))
+            .update_user_id(Some(user.id))
+            .update_time(Some(tools::now()))
+            .build()?,
+        value! { "id": req.id},
+    )
+    .await?;
+    Ok(())
+}
+
+/// 批量开启或关闭维护模式，关闭后恢复路由配置的处理方式
+pub async fn update_maintenance(
+    req: UpdateMaintenanceReq,
+    user: UserPrincipal,
+) -> anyhow::Result<()> {
+    if req.ids.is_empty() {
+        return Ok(());
+    }
+    if let Some(page) = &req.page {
+        check_response_headers(&page.headers)?;
+    }
+    // 未指定维护页面时保留原有的维护页面，都未配置时使用默认的维护页面
+    let page = req.page.filter(|_| req.maintenance);
+    Route::update_by_map(
+        Pool::get()?,
+        &RouteBuilder::default()
+            .maintenance(Some(req.maintenance))
+            .maintenance_page(page)
+            .update_user_id(Some(user.id))
+            .update_time(Some(tools::now()))
+            .build()?,
+        value! { "id": req.ids},
+    )
+    .await?;
+    Ok(())
+}
+
 pub async fn update_global_filter_config(
     req: UpdateGlobalFilterConfigReq,
     _user: UserPrincipal,
@@ -131,3 +458,43 @@ pub async fn update_global_filter_config(
 pub(crate) async fn get_global_filter_config(_user: UserPrincipal) -> anyhow::Result<GlobalFilter> {
     SystemConfig::get(ConfigKey::GlobalFilter).await
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aiway_protocol::gateway::route::MirrorPolicy;
+
+    #[test]
+    fn test_check_mirror() {
+        let mirror = |service: &str| MirrorPolicy {
+            service: service.to_string(),
+            percent: 100,
+            timeout: 1000,
+        };
+        let route = Route {
+            service: Some("user".to_string()),
+            backends: Some(vec![WeightedBackend {
+                service: "user-v2".to_string(),
+                weight: 10,
+                ..Default::default()
+            }]),
+            mirror: Some(mirror("user-shadow")),
+            ..Default::default()
+        };
+        assert!(check_mirror(&route).is_ok());
+        assert!(
+            check_mirror(&Route {
+                mirror: Some(mirror("user")),
+                ..route.clone()
+            })
+            .is_err()
+        );
+        assert!(
+            check_mirror(&Route {
+                mirror: Some(mirror("user-v2")),
+                ..route.clone()
+            })
+            .is_err()
+        );
+    }
+}
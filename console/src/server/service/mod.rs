@@ -5,3 +5,4 @@ mod response;
 mod service;
 
 pub use request::ServiceListReq;
+pub(crate) use service::update_node_health;
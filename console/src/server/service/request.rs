@@ -1,8 +1,11 @@
 use crate::server::db::models::service::ServiceStatus;
 use busi::req::PageReq;
-use aiway_protocol::gateway::service::LbStrategy;
+use aiway_protocol::gateway::service::{
+    CircuitBreaker, HashKey, HealthCheck, LbStrategy, PoolConfig, Protocol, Timeouts, UpstreamTls,
+};
 use busi::impl_pagination;
 use rocket::serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServiceAddReq {
@@ -13,9 +16,27 @@ pub struct ServiceAddReq {
     /// 服务节点
     #[serde(default = "Vec::default")]
     pub nodes: Vec<String>,
-    /// 负载均衡策略，可选值：random | round_robin
+    /// 负载均衡策略，可选值：random | round_robin | weighted_round_robin | least_active | consistent_hash
     #[serde(default = "LbStrategy::default")]
     pub lb: LbStrategy,
+    /// 节点权重，key为节点地址，未配置的节点权重为1
+    #[serde(default)]
+    pub weights: HashMap<String, u32>,
+    /// 一致性哈希的key来源
+    pub hash_key: Option<HashKey>,
+    /// 健康检查配置，为空时不检查
+    pub health_check: Option<HealthCheck>,
+    /// 超时配置
+    pub timeouts: Option<Timeouts>,
+    /// 连接池配置
+    pub pool: Option<PoolConfig>,
+    /// 熔断配置，为空时不熔断
+    pub circuit_breaker: Option<CircuitBreaker>,
+    /// 服务节点的协议，可选值：http1 | h2c | h2 | grpc
+    #[serde(default)]
+    pub protocol: Protocol,
+    /// 请求https节点时的TLS配置
+    pub tls: Option<UpstreamTls>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,8 +46,24 @@ pub struct ServiceUpdateReq {
     pub description: Option<String>,
     /// 服务节点
     pub nodes: Option<Vec<String>>,
-    /// 负载均衡策略，可选值：random | round_robin
+    /// 负载均衡策略，可选值：random | round_robin | weighted_round_robin | least_active | consistent_hash
     pub lb: Option<LbStrategy>,
+    /// 节点权重
+    pub weights: Option<HashMap<String, u32>>,
+    /// 一致性哈希的key来源
+    pub hash_key: Option<HashKey>,
+    /// 健康检查配置
+    pub health_check: Option<HealthCheck>,
+    /// 超时配置
+    pub timeouts: Option<Timeouts>,
+    /// 连接池配置
+    pub pool: Option<PoolConfig>,
+    /// 熔断配置，为空时不熔断
+    pub circuit_breaker: Option<CircuitBreaker>,
+    /// 服务节点的协议，可选值：http1 | h2c | h2 | grpc
+    pub protocol: Option<Protocol>,
+    /// 请求https节点时的TLS配置
+    pub tls: Option<UpstreamTls>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -5,4 +5,16 @@ use rocket::serde::{Deserialize, Serialize};
 pub struct ServiceListRes {
     #[serde(flatten)]
     pub inner: Service,
+    /// 节点健康状态，由网关节点上报，未开启健康检查时为空
+    pub node_health: Vec<ServiceNodeHealth>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ServiceNodeHealth {
+    /// 节点地址
+    pub node: String,
+    /// 是否健康，任一网关节点判定为不健康时为false
+    pub healthy: bool,
+    /// 判定该节点不健康的网关节点ID
+    pub unhealthy_gateways: Vec<String>,
 }
@@ -5,12 +5,28 @@ use crate::server::db::{Pool, tools};
 use crate::server::service::request::{
     ServiceAddReq, ServiceListReq, ServiceUpdateReq, UpdateStatusReq,
 };
-use crate::server::service::response::ServiceListRes;
+use crate::server::service::response::{ServiceListRes, ServiceNodeHealth};
+use aiway_protocol::common::constants;
+use aiway_protocol::gateway::service::{
+    CircuitBreaker, HealthCheck, PoolConfig, Protocol, UpstreamTls,
+};
+use aiway_protocol::gateway::state::NodeHealthState;
 use anyhow::bail;
 use common::id;
 use busi::req::{IdsReq, Pagination};
 use busi::res::{IntoPageRes, PageRes};
 use rbs::value;
+use rocket::tokio::sync::RwLock;
+use rustls_pki_types::pem::PemObject;
+use rustls_pki_types::{CertificateDer, PrivateKeyDer};
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock};
+
+/// 网关节点上报的服务节点健康状态
+/// - key: 网关节点ID
+/// - value: (上报时间戳, 节点健康状态)
+static NODE_HEALTH: LazyLock<Arc<RwLock<HashMap<String, (i64, Vec<NodeHealthState>)>>>> =
+    LazyLock::new(Default::default);
 
 pub async fn add(req: ServiceAddReq, user: UserPrincipal) -> anyhow::Result<()> {
     let service = ServiceBuilder::default()
@@ -20,6 +36,14 @@ pub async fn add(req: ServiceAddReq, user: UserPrincipal) -> anyhow::Result<()>
         .status(ServiceStatus::Disable.into())
         .nodes(req.nodes.into())
         .lb(req.lb.into())
+        .weights(req.weights.into())
+        .hash_key(req.hash_key)
+        .health_check(req.health_check)
+        .timeouts(req.timeouts)
+        .pool(req.pool)
+        .circuit_breaker(req.circuit_breaker)
+        .protocol(req.protocol.into())
+        .tls(req.tls)
         .create_user_id(Some(user.id))
         .create_time(Some(tools::now()))
         .build()?;
@@ -27,6 +51,12 @@ pub async fn add(req: ServiceAddReq, user: UserPrincipal) -> anyhow::Result<()>
     if check_exists(&service, None).await? {
         bail!("Service with name {} already exists", service.name.unwrap())
     }
+    check_weights(&service.weights, service.nodes.as_ref())?;
+    check_health_check(&service.health_check)?;
+    check_pool(&service.pool)?;
+    check_circuit_breaker(&service.circuit_breaker)?;
+    check_protocol(service.protocol.as_ref(), service.nodes.as_ref())?;
+    check_tls(service.tls.as_ref(), service.nodes.as_ref())?;
     Service::insert(Pool::get()?, &service).await?;
     Ok(())
 }
@@ -44,16 +74,197 @@ async fn check_exists(service: &Service, exclude_id: Option<i64>) -> anyhow::Res
     Ok(!list.is_empty())
 }
 
+fn check_weights(
+    weights: &Option<HashMap<String, u32>>,
+    nodes: Option<&Vec<String>>,
+) -> anyhow::Result<()> {
+    let (Some(weights), Some(nodes)) = (weights, nodes) else {
+        return Ok(());
+    };
+    for (node, weight) in weights {
+        if !nodes.contains(node) {
+            bail!("节点权重配置错误：节点{}不存在", node);
+        }
+        if *weight == 0 {
+            bail!("节点权重配置错误：节点{}的权重必须大于0", node);
+        }
+    }
+    Ok(())
+}
+
+fn check_health_check(health_check: &Option<HealthCheck>) -> anyhow::Result<()> {
+    let Some(health_check) = health_check else {
+        return Ok(());
+    };
+    if let Some(active) = &health_check.active {
+        if !active.path.starts_with('/') {
+            bail!("健康检查路径必须以/开头");
+        }
+        if active.interval == 0
+            || active.timeout == 0
+            || active.healthy_threshold == 0
+            || active.unhealthy_threshold == 0
+        {
+            bail!("健康检查间隔、超时时间和阈值必须大于0");
+        }
+    }
+    if let Some(passive) = &health_check.passive
+        && passive.max_failures == 0
+    {
+        bail!("被动检查的失败次数阈值必须大于0");
+    }
+    Ok(())
+}
+
+fn check_pool(pool: &Option<PoolConfig>) -> anyhow::Result<()> {
+    if let Some(pool) = pool
+        && pool.max_connections_per_host == Some(0)
+    {
+        bail!("连接池配置错误：每个节点的最大连接数必须大于0");
+    }
+    Ok(())
+}
+
+fn check_circuit_breaker(circuit_breaker: &Option<CircuitBreaker>) -> anyhow::Result<()> {
+    let Some(circuit_breaker) = circuit_breaker else {
+        return Ok(());
+    };
+    if circuit_breaker.window_secs == 0
+        || circuit_breaker.open_secs == 0
+        || circuit_breaker.half_open_calls == 0
+    {
+        bail!("熔断配置错误：窗口时长、打开时长和半开请求数必须大于0");
+    }
+    if !(1..=100).contains(&circuit_breaker.failure_rate)
+        || !(1..=100).contains(&circuit_breaker.slow_call_rate)
+    {
+        bail!("熔断配置错误：失败率和慢调用率阈值必须在1~100之间");
+    }
+    if let Some(fallback) = &circuit_breaker.fallback
+        && !(100..=599).contains(&fallback.status)
+    {
+        bail!("熔断配置错误：降级响应的状态码{}无效", fallback.status);
+    }
+    Ok(())
+}
+
+fn check_protocol(protocol: Option<&Protocol>, nodes: Option<&Vec<String>>) -> anyhow::Result<()> {
+    let (Some(Protocol::H2), Some(nodes)) = (protocol, nodes) else {
+        return Ok(());
+    };
+    if let Some(node) = nodes.iter().find(|node| !node.starts_with("https://")) {
+        bail!(
+            "服务协议配置错误：h2需要使用https节点，节点{}不是https地址",
+            node
+        );
+    }
+    Ok(())
+}
+
+/// 校验TLS配置，节点必须为https地址，证书和私钥必须为有效的PEM格式且同时配置
+fn check_tls(tls: Option<&UpstreamTls>, nodes: Option<&Vec<String>>) -> anyhow::Result<()> {
+    let Some(tls) = tls else {
+        return Ok(());
+    };
+    if let Some(node) = nodes
+        .into_iter()
+        .flatten()
+        .find(|node| !node.starts_with("https://"))
+    {
+        bail!("服务TLS配置错误：节点{}不是https地址", node);
+    }
+    if let Some(ca) = &tls.ca {
+        let certs = CertificateDer::pem_slice_iter(ca.as_bytes()).collect::<Result<Vec<_>, _>>();
+        if !certs.is_ok_and(|certs| !certs.is_empty()) {
+            bail!("服务TLS配置错误：CA证书不是有效的PEM格式");
+        }
+    }
+    match (&tls.cert, &tls.key) {
+        (Some(cert), Some(key)) => {
+            let certs =
+                CertificateDer::pem_slice_iter(cert.as_bytes()).collect::<Result<Vec<_>, _>>();
+            if !certs.is_ok_and(|certs| !certs.is_empty()) {
+                bail!("服务TLS配置错误：客户端证书不是有效的PEM格式");
+            }
+            if PrivateKeyDer::from_pem_slice(key.as_bytes()).is_err() {
+                bail!("服务TLS配置错误：客户端私钥不是有效的PEM格式");
+            }
+        }
+        (None, None) => {}
+        _ => bail!("服务TLS配置错误：客户端证书和私钥需同时配置"),
+    }
+    if let Some(server_name) = &tls.server_name
+        && server_name.trim().is_empty()
+    {
+        bail!("服务TLS配置错误：SNI不能为空");
+    }
+    Ok(())
+}
+
 pub async fn list(req: ServiceListReq) -> anyhow::Result<PageRes<ServiceListRes>> {
     let page = service::list_page(Pool::get()?, &req.to_rb_page(), &req).await?;
+    let node_health = NODE_HEALTH.read().await;
     let list = page.convert_to_page_res(|list| {
         list.into_iter()
-            .map(|item| ServiceListRes { inner: item })
+            .map(|mut item| {
+                // 不返回客户端私钥
+                if let Some(tls) = &mut item.tls {
+                    tls.key = None;
+                }
+                ServiceListRes {
+                    node_health: build_node_health(&item, &node_health),
+                    inner: item,
+                }
+            })
             .collect::<Vec<_>>()
     });
     Ok(list)
 }
 
+/// 汇总各网关节点上报的服务节点健康状态，忽略超过2个上报周期未上报的网关节点
+fn build_node_health(
+    service: &Service,
+    reports: &HashMap<String, (i64, Vec<NodeHealthState>)>,
+) -> Vec<ServiceNodeHealth> {
+    let (Some(name), Some(nodes)) = (&service.name, &service.nodes) else {
+        return vec![];
+    };
+    if service.health_check.is_none() {
+        return vec![];
+    }
+    let expire = chrono::Local::now().timestamp_millis()
+        - (constants::REPORT_STATE_INTERVAL * 2 * 1000) as i64;
+
+    nodes
+        .iter()
+        .map(|node| {
+            let unhealthy_gateways = reports
+                .iter()
+                .filter(|(_, (ts, _))| *ts >= expire)
+                .filter(|(_, (_, states))| {
+                    states
+                        .iter()
+                        .any(|s| &s.service == name && &s.node == node && !s.healthy)
+                })
+                .map(|(gateway, _)| gateway.clone())
+                .collect::<Vec<_>>();
+            ServiceNodeHealth {
+                node: node.clone(),
+                healthy: unhealthy_gateways.is_empty(),
+                unhealthy_gateways,
+            }
+        })
+        .collect()
+}
+
+/// 更新网关节点上报的服务节点健康状态
+pub(crate) async fn update_node_health(node_id: &str, states: Vec<NodeHealthState>) {
+    NODE_HEALTH.write().await.insert(
+        node_id.to_string(),
+        (chrono::Local::now().timestamp_millis(), states),
+    );
+}
+
 pub async fn update(req: ServiceUpdateReq, user: UserPrincipal) -> anyhow::Result<()> {
     let old = Service::select_by_map(Pool::get()?, value! { "id": req.id}).await?;
     if old.is_empty() {
@@ -65,9 +276,29 @@ pub async fn update(req: ServiceUpdateReq, user: UserPrincipal) -> anyhow::Resul
         .description(req.description)
         .nodes(req.nodes)
         .lb(req.lb)
+        .weights(req.weights)
+        .hash_key(req.hash_key)
+        .health_check(req.health_check)
+        .timeouts(req.timeouts)
+        .pool(req.pool)
+        .circuit_breaker(req.circuit_breaker)
+        .protocol(req.protocol)
+        .tls(req.tls)
         .update_user_id(Some(user.id))
         .update_time(Some(tools::now()))
         .build()?;
+    check_weights(&update.weights, update.nodes.as_ref().or(old[0].nodes.as_ref()))?;
+    check_health_check(&update.health_check)?;
+    check_pool(&update.pool)?;
+    check_circuit_breaker(&update.circuit_breaker)?;
+    check_protocol(
+        update.protocol.as_ref().or(old[0].protocol.as_ref()),
+        update.nodes.as_ref().or(old[0].nodes.as_ref()),
+    )?;
+    check_tls(
+        update.tls.as_ref(),
+        update.nodes.as_ref().or(old[0].nodes.as_ref()),
+    )?;
 
     Service::update_by_map(Pool::get()?, &update, value! { "id":req.id}).await?;
     Ok(())
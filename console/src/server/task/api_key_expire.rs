@@ -0,0 +1,100 @@
+use crate::server::db::models::api_key::{ApiKey, ApiKeyBuilder, ApiKeyStatus};
+use crate::server::db::models::system_config::{ConfigKey, SystemConfig};
+use crate::server::db::{Pool, tools};
+use crate::server::key::service::cache_entry;
+use aiway_protocol::gateway::alert::AlertConfig;
+use alert::Alert;
+use cache::caches::CacheKey;
+use logging::log;
+use rbs::value;
+
+/// 未配置时，到期前7天发送提醒
+const DEFAULT_EXPIRE_DAYS: u32 = 7;
+
+pub(crate) async fn expire() {
+    if let Err(e) = expire_().await {
+        log::error!("{}", e);
+        Alert::error("定时任务【API Key过期检查】执行异常", &e.to_string());
+    }
+}
+
+/// 将已到期的API Key标记为已过期，并更新缓存
+///
+/// 网关鉴权时会按缓存中的失效时间校验，这里仅同步状态，便于在控制台查看。
+async fn expire_() -> anyhow::Result<()> {
+    let now = chrono::Local::now().timestamp();
+    let keys = ApiKey::select_by_map(Pool::get()?, value! {"status": "Ok"}).await?;
+    for key in keys {
+        let Some(exp_time) = key.exp_time.as_ref().map(|t| t.unix_timestamp()) else {
+            continue;
+        };
+        if exp_time > now {
+            continue;
+        }
+        let id = key.id.unwrap_or_default();
+        let update = ApiKeyBuilder::default()
+            .status(Some(ApiKeyStatus::Expired))
+            .update_time(Some(tools::now()))
+            .build()?;
+        ApiKey::update_by_map(Pool::get()?, &update, value! {"id": id}).await?;
+        let key = ApiKey {
+            status: Some(ApiKeyStatus::Expired),
+            ..key
+        };
+        cache::set(
+            CacheKey::ApiKey(key.secret.clone().unwrap_or_default()).to_string(),
+            &cache_entry(&key),
+            None,
+        )
+        .await?;
+        log::info!(
+            "[api_key_expire] API Key已过期：{}",
+            key.name.unwrap_or_default()
+        );
+    }
+    Ok(())
+}
+
+pub(crate) async fn remind() {
+    if let Err(e) = remind_().await {
+        log::error!("{}", e);
+        Alert::error("定时任务【API Key过期提醒】执行异常", &e.to_string());
+    }
+}
+
+/// 在到期前发送提醒，每个API Key仅提醒一次
+async fn remind_() -> anyhow::Result<()> {
+    let config = SystemConfig::get::<AlertConfig>(ConfigKey::Alert).await?;
+    let days = config.api_key_expire_days.unwrap_or(DEFAULT_EXPIRE_DAYS);
+    if days == 0 {
+        return Ok(());
+    }
+    let now = chrono::Local::now().timestamp();
+    let deadline = now + days as i64 * 24 * 3600;
+    let keys = ApiKey::select_by_map(Pool::get()?, value! {"status": "Ok"}).await?;
+    for key in keys {
+        if key.expire_notified == Some(true) {
+            continue;
+        }
+        let Some(exp_time) = key.exp_time.as_ref() else {
+            continue;
+        };
+        if exp_time.unix_timestamp() > deadline {
+            continue;
+        }
+        Alert::warn(
+            "API Key即将过期",
+            &format!(
+                "名称: {}\n主体: {}\n到期时间: {}",
+                key.name.as_deref().unwrap_or_default(),
+                key.principal.as_deref().unwrap_or_default(),
+                exp_time.format("YYYY-MM-DD hh:mm:ss")
+            ),
+        );
+        let update = ApiKeyBuilder::default()
+            .expire_notified(Some(true))
+            .build()?;
+        ApiKey::update_by_map(Pool::get()?, &update, value! {"id": key.id}).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,64 @@
+use crate::server::db::models::api_key::ApiKey;
+use crate::server::db::models::api_key_usage::{ApiKeyUsage, ApiKeyUsageBuilder};
+use crate::server::db::{Pool, tools};
+use aiway_protocol::gateway::ApiKeyQuota;
+use alert::Alert;
+use cache::caches::CacheKey;
+use chrono::{Duration, Local};
+use logging::log;
+use std::collections::HashSet;
+
+pub(crate) async fn flush() {
+    if let Err(e) = flush_().await {
+        log::error!("{}", e);
+        Alert::error("定时任务【API Key用量同步】执行异常", &e.to_string());
+    }
+}
+
+/// 将缓存中按天的用量同步到数据库
+///
+/// 同时同步前一天的用量，避免跨天时最后一分钟的用量丢失。
+async fn flush_() -> anyhow::Result<()> {
+    let principals = ApiKey::select_all(Pool::get()?)
+        .await?
+        .into_iter()
+        .filter_map(|key| key.principal)
+        .filter(|principal| !principal.is_empty())
+        .collect::<HashSet<_>>();
+    let today = Local::now();
+    let days = [today, today - Duration::days(1)]
+        .map(|day| day.format(ApiKeyQuota::DAY_FORMAT).to_string());
+    for principal in principals.iter() {
+        for day in days.iter() {
+            let requests = get(principal, day, ApiKeyQuota::REQUESTS).await?;
+            let tokens = get(principal, day, ApiKeyQuota::TOKENS).await?;
+            if requests.is_none() && tokens.is_none() {
+                continue;
+            }
+            save(
+                principal,
+                day,
+                requests.unwrap_or_default(),
+                tokens.unwrap_or_default(),
+            )
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+async fn get(principal: &str, day: &str, metric: &str) -> anyhow::Result<Option<i64>> {
+    let key = CacheKey::Usage(principal.to_string(), day.to_string(), metric.to_string());
+    cache::get::<i64>(&key.to_string()).await
+}
+
+async fn save(principal: &str, day: &str, requests: i64, tokens: i64) -> anyhow::Result<()> {
+    let usage = ApiKeyUsageBuilder::default()
+        .principal(Some(principal.to_string()))
+        .usage_date(Some(day.to_string()))
+        .requests(Some(requests))
+        .tokens(Some(tokens))
+        .update_time(Some(tools::now()))
+        .build()?;
+    ApiKeyUsage::upsert(Pool::get()?, &usage).await
+}
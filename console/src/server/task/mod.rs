@@ -1,3 +1,5 @@
+mod api_key_expire;
+mod api_key_usage;
 mod ip_region_count;
 mod request_status_count;
 mod state;
@@ -45,6 +47,24 @@ pub async fn start() -> anyhow::Result<()> {
     })?;
     sched.add(request_status_count_clean).await?;
 
+    // API Key过期检查
+    let api_key_expire = Job::new_async("every 1 minutes", move |_, _| {
+        Box::pin(api_key_expire::expire())
+    })?;
+    sched.add(api_key_expire).await?;
+
+    // API Key过期提醒
+    let api_key_remind = Job::new_async("every 1 hours", move |_, _| {
+        Box::pin(api_key_expire::remind())
+    })?;
+    sched.add(api_key_remind).await?;
+
+    // API Key用量同步
+    let api_key_usage = Job::new_async("every 1 minutes", move |_, _| {
+        Box::pin(api_key_usage::flush())
+    })?;
+    sched.add(api_key_usage).await?;
+
     sched.start().await?;
 
     Ok(())
@@ -0,0 +1,307 @@
+//! # 服务节点熔断
+//! 在转发请求前检查节点的熔断状态，熔断中的节点不参与负载均衡。
+//!
+//! 每个节点按秒划分桶，统计滑动窗口内的请求数、失败数和慢调用数，
+//! 配置详见[`CircuitBreaker`]。
+//!
+//! 熔断器打开时发送告警。半开状态下再次打开仅记录日志，避免持续故障时重复告警。
+//!
+use aiway_protocol::gateway::service::{CircuitBreaker, FallbackResponse};
+use alert::Alert;
+use dashmap::DashMap;
+use std::sync::{Arc, Mutex};
+
+/// 熔断状态
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BreakerState {
+    /// 关闭，正常转发
+    Closed,
+    /// 打开，直到指定的时间戳（毫秒）
+    Open(i64),
+    /// 半开
+    HalfOpen {
+        /// 已放行的请求数
+        permitted: u32,
+        /// 已成功的请求数
+        successes: u32,
+    },
+}
+
+/// 每秒的统计桶
+#[derive(Debug, Default, Clone, Copy)]
+struct Bucket {
+    /// 所属的秒级时间戳
+    second: i64,
+    total: u32,
+    failures: u32,
+    slow: u32,
+}
+
+#[derive(Debug)]
+struct NodeBreaker {
+    state: BreakerState,
+    buckets: Vec<Bucket>,
+}
+
+impl NodeBreaker {
+    fn new(config: &CircuitBreaker) -> Self {
+        Self {
+            state: BreakerState::Closed,
+            buckets: vec![Bucket::default(); config.window_secs.max(1) as usize],
+        }
+    }
+
+    /// 是否可以转发，打开状态到期后转为半开
+    fn is_available(&mut self, config: &CircuitBreaker, now: i64) -> bool {
+        match self.state {
+            BreakerState::Closed => true,
+            BreakerState::Open(until) if now >= until => {
+                self.state = BreakerState::HalfOpen {
+                    permitted: 0,
+                    successes: 0,
+                };
+                true
+            }
+            BreakerState::Open(_) => false,
+            BreakerState::HalfOpen { permitted, .. } => permitted < config.half_open_calls,
+        }
+    }
+
+    /// 获取转发许可，半开状态下占用一个名额
+    fn acquire(&mut self, config: &CircuitBreaker, now: i64) -> bool {
+        if !self.is_available(config, now) {
+            return false;
+        }
+        if let BreakerState::HalfOpen { permitted, .. } = &mut self.state {
+            *permitted += 1;
+        }
+        true
+    }
+
+    /// 归还未记录结果的转发许可，半开状态下释放占用的名额
+    fn release(&mut self) {
+        if let BreakerState::HalfOpen { permitted, .. } = &mut self.state {
+            *permitted = permitted.saturating_sub(1);
+        }
+    }
+
+    /// 记录请求结果，返回是否由关闭转为打开
+    fn record(&mut self, config: &CircuitBreaker, now: i64, success: bool, slow: bool) -> bool {
+        match &mut self.state {
+            BreakerState::Closed => {
+                let second = now / 1000;
+                let len = self.buckets.len();
+                let bucket = &mut self.buckets[second as usize % len];
+                if bucket.second != second {
+                    *bucket = Bucket {
+                        second,
+                        ..Default::default()
+                    };
+                }
+                bucket.total += 1;
+                bucket.failures += !success as u32;
+                bucket.slow += slow as u32;
+
+                let (total, failures, slow) = self
+                    .buckets
+                    .iter()
+                    .filter(|b| second - b.second < len as i64)
+                    .fold((0, 0, 0), |acc, b| {
+                        (acc.0 + b.total, acc.1 + b.failures, acc.2 + b.slow)
+                    });
+                if total < config.min_calls.max(1) {
+                    return false;
+                }
+                if failures * 100 >= config.failure_rate * total
+                    || slow * 100 >= config.slow_call_rate * total
+                {
+                    self.open(config, now);
+                    return true;
+                }
+                false
+            }
+            BreakerState::HalfOpen { successes, .. } => {
+                if !success || slow {
+                    self.open(config, now);
+                    return false;
+                }
+                *successes += 1;
+                if *successes >= config.half_open_calls {
+                    self.state = BreakerState::Closed;
+                }
+                false
+            }
+            // 打开前放行的请求，忽略
+            BreakerState::Open(_) => false,
+        }
+    }
+
+    fn open(&mut self, config: &CircuitBreaker, now: i64) {
+        self.state = BreakerState::Open(now + (config.open_secs * 1000) as i64);
+        self.buckets.fill(Bucket::default());
+    }
+}
+
+/// 服务的熔断状态
+pub struct ServiceBreaker {
+    /// 服务名
+    service: String,
+    /// 熔断配置
+    config: Option<CircuitBreaker>,
+    /// 节点熔断状态，key为节点地址
+    nodes: DashMap<String, Arc<Mutex<NodeBreaker>>>,
+}
+
+impl ServiceBreaker {
+    /// 创建服务熔断状态
+    ///
+    /// 如果提供了旧的熔断状态且配置未变更，则保留仍然存在的节点的状态。
+    pub fn new(
+        service: &str,
+        config: Option<CircuitBreaker>,
+        nodes: &[String],
+        old: Option<&ServiceBreaker>,
+    ) -> Self {
+        let breakers = DashMap::new();
+        if let Some(config) = &config {
+            let old = old.filter(|old| old.config.as_ref() == Some(config));
+            for node in nodes {
+                let breaker = old
+                    .and_then(|old| old.nodes.get(node).map(|b| b.value().clone()))
+                    .unwrap_or_else(|| Arc::new(Mutex::new(NodeBreaker::new(config))));
+                breakers.insert(node.clone(), breaker);
+            }
+        }
+        Self {
+            service: service.to_string(),
+            config,
+            nodes: breakers,
+        }
+    }
+
+    /// 是否开启了熔断
+    pub fn is_enabled(&self) -> bool {
+        self.config.is_some()
+    }
+
+    /// 节点是否可用，用于负载均衡前过滤节点
+    pub fn is_available(&self, node: &str) -> bool {
+        let (Some(config), Some(breaker)) = (&self.config, self.nodes.get(node)) else {
+            return true;
+        };
+        let now = chrono::Local::now().timestamp_millis();
+        breaker.lock().unwrap().is_available(config, now)
+    }
+
+    /// 获取转发许可，熔断中返回false
+    pub fn acquire(&self, node: &str) -> bool {
+        let (Some(config), Some(breaker)) = (&self.config, self.nodes.get(node)) else {
+            return true;
+        };
+        let now = chrono::Local::now().timestamp_millis();
+        breaker.lock().unwrap().acquire(config, now)
+    }
+
+    /// 归还转发许可，请求未完成（如客户端断开）或结果不计入熔断统计时调用
+    pub fn release(&self, node: &str) {
+        if let (Some(_), Some(breaker)) = (&self.config, self.nodes.get(node)) {
+            breaker.lock().unwrap().release();
+        }
+    }
+
+    /// 记录请求结果
+    ///
+    /// - success: 连接错误、超时或5xx响应为false
+    /// - elapsed: 收到响应头的耗时，单位：毫秒
+    pub fn record(&self, node: &str, success: bool, elapsed: u64) {
+        let (Some(config), Some(breaker)) = (&self.config, self.nodes.get(node)) else {
+            return;
+        };
+        let now = chrono::Local::now().timestamp_millis();
+        let slow = elapsed >= config.slow_call_ms;
+        let opened = breaker.lock().unwrap().record(config, now, success, slow);
+        if opened {
+            log::warn!(
+                "circuit breaker of service {} node {} opened",
+                self.service,
+                node
+            );
+            Alert::warn(
+                "服务节点熔断",
+                &format!(
+                    "服务: {}\n节点: {}\n熔断时长: {}秒",
+                    self.service, node, config.open_secs
+                ),
+            );
+        }
+    }
+
+    /// 降级响应
+    pub fn fallback(&self) -> Option<&FallbackResponse> {
+        self.config.as_ref().and_then(|c| c.fallback.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_node_breaker() {
+        let config = CircuitBreaker {
+            min_calls: 4,
+            half_open_calls: 2,
+            ..Default::default()
+        };
+        let mut breaker = NodeBreaker::new(&config);
+        let now = 1_000_000;
+
+        // 失败率达到50%后打开
+        assert!(!breaker.record(&config, now, true, false));
+        assert!(!breaker.record(&config, now, true, false));
+        assert!(!breaker.record(&config, now, false, false));
+        assert!(breaker.record(&config, now, false, false));
+        assert!(!breaker.acquire(&config, now));
+
+        // 到期后半开，仅放行2个请求
+        let now = now + (config.open_secs * 1000) as i64;
+        assert!(breaker.acquire(&config, now));
+        assert!(breaker.acquire(&config, now));
+        assert!(!breaker.acquire(&config, now));
+
+        // 全部成功后关闭
+        breaker.record(&config, now, true, false);
+        breaker.record(&config, now, true, false);
+        assert_eq!(breaker.state, BreakerState::Closed);
+    }
+
+    #[test]
+    fn test_release_half_open_permit() {
+        let config = CircuitBreaker {
+            min_calls: 1,
+            half_open_calls: 2,
+            ..Default::default()
+        };
+        let mut breaker = NodeBreaker::new(&config);
+        let now = 1_000_000;
+        assert!(breaker.record(&config, now, false, false));
+
+        // 半开状态下放行的请求未记录结果（如客户端断开），归还名额后可以继续放行
+        let now = now + (config.open_secs * 1000) as i64;
+        assert!(breaker.acquire(&config, now));
+        assert!(breaker.acquire(&config, now));
+        breaker.release();
+        breaker.release();
+        assert!(breaker.acquire(&config, now));
+        assert!(breaker.acquire(&config, now));
+        assert!(!breaker.acquire(&config, now));
+
+        breaker.record(&config, now, true, false);
+        breaker.record(&config, now, true, false);
+        assert_eq!(breaker.state, BreakerState::Closed);
+
+        // 关闭状态下归还许可不影响统计
+        breaker.release();
+        assert_eq!(breaker.state, BreakerState::Closed);
+    }
+}
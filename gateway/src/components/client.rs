@@ -2,7 +2,9 @@
 //!
 use crate::Args;
 use busi::res::Res;
-use aiway_protocol::gateway::{Config, Firewall, GlobalFilter, Plugin, Route, Service};
+use aiway_protocol::gateway::{
+    Certificate, Config, Firewall, GlobalFilter, JwtProvider, Plugin, Route, Service,
+};
 use anyhow::bail;
 use clap::Parser;
 use reqwest::{Client, ClientBuilder};
@@ -96,6 +98,20 @@ impl InnerHttpClient {
         Ok(firewall)
     }
 
+    pub async fn fetch_certificates(&self) -> anyhow::Result<Vec<Certificate>> {
+        let endpoint = format!("http://{}/api/v1/gateway/certificates", self.args.console);
+        let mut certificates = self.fetch_resource::<Vec<Certificate>>(endpoint).await?;
+        certificates.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(certificates)
+    }
+
+    pub async fn fetch_jwt_providers(&self) -> anyhow::Result<Vec<JwtProvider>> {
+        let endpoint = format!("http://{}/api/v1/gateway/jwt-providers", self.args.console);
+        let mut providers = self.fetch_resource::<Vec<JwtProvider>>(endpoint).await?;
+        providers.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(providers)
+    }
+
     pub async fn fetch_ip_region_file(&self) -> anyhow::Result<PathBuf> {
         let endpoint = format!(
             "http://{}/api/v1/gateway/download-ip-region-file",
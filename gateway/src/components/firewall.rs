@@ -1,6 +1,9 @@
 use crate::components::client::INNER_HTTP_CLIENT;
-use aiway_protocol::gateway::{AllowDenyPolicy, Firewall};
+use crate::components::ip_trie::IpTrie;
+use crate::components::real_ip;
+use aiway_protocol::gateway::{AllowDenyPolicy, Firewall, RateLimit};
 use anyhow::Context;
+use std::net::IpAddr;
 use std::process::exit;
 use std::sync::{Arc, OnceLock};
 use std::time::Duration;
@@ -8,9 +11,27 @@ use tokio::sync::RwLock;
 
 pub struct Firewalld {
     pub config: Arc<RwLock<Firewall>>,
+    /// 由`ip_policy`、`trust_ips`和`trusted_proxies`构建的前缀树，随配置一起更新
+    ip_rules: Arc<RwLock<IpRules>>,
     hash: Arc<RwLock<String>>,
 }
 
+struct IpRules {
+    ip_policy: IpTrie,
+    trust_ips: IpTrie,
+    trusted_proxies: IpTrie,
+}
+
+impl IpRules {
+    fn new(firewall: &Firewall) -> Self {
+        Self {
+            ip_policy: IpTrie::new(&firewall.ip_policy),
+            trust_ips: IpTrie::new(&firewall.trust_ips),
+            trusted_proxies: IpTrie::new(&firewall.trusted_proxies),
+        }
+    }
+}
+
 pub static FIREWALLD: OnceLock<Firewalld> = OnceLock::new();
 impl Firewalld {
     pub async fn init() {
@@ -28,6 +49,7 @@ impl Firewalld {
         let hash = format!("{:x}", hash);
 
         FIREWALLD.get_or_init(|| Self {
+            ip_rules: Arc::new(RwLock::new(IpRules::new(&firewall))),
             config: Arc::new(RwLock::new(firewall)),
             hash: Arc::new(RwLock::new(hash)),
         });
@@ -73,39 +95,71 @@ impl Firewalld {
                 log::info!("loaded gateway firewall: {:?}", config);
 
                 {
+                    *old_config.ip_rules.write().await = IpRules::new(&config);
                     *old_config.config.write().await = config;
                     *old_config.hash.write().await = hash;
                 }
             }
         });
     }
-    pub async fn check(ip: &str, referer: &str) -> Result<(), String> {
-        let firewall = FIREWALLD.get().unwrap().config.read().await;
+    pub async fn check(ip: IpAddr, referer: &str) -> Result<(), String> {
+        let firewalld = FIREWALLD.get().unwrap();
+        let firewall = firewalld.config.read().await;
+        let ip_rules = firewalld.ip_rules.read().await;
 
         // 受信IP直接通过
-        if firewall.trust_ips.contains(ip) {
+        if ip_rules.trust_ips.contains(ip) {
             return Ok(());
         }
 
         // 检查IP策略
-        Self::check_ip_policy(&firewall, ip)?;
+        Self::check_ip_policy(&firewall, &ip_rules, ip)?;
         // 检查Referer策略
         Self::check_referer_policy(&firewall, referer)?;
 
-        // 检查最大连接数
+        Ok(())
+    }
+
+    /// 对端是否为受信代理
+    pub async fn is_trusted_proxy(ip: IpAddr) -> bool {
+        let firewalld = FIREWALLD.get().unwrap();
+        firewalld.ip_rules.read().await.trusted_proxies.contains(ip)
+    }
 
+    /// 解析真实的客户端IP，规则见[`real_ip`]
+    pub async fn resolve_client_ip<'a, I: IntoIterator<Item = &'a str>>(
+        peer: IpAddr,
+        forwarded_for: I,
+        real_ip: Option<&str>,
+    ) -> IpAddr {
+        let firewalld = FIREWALLD.get().unwrap();
+        let ip_rules = firewalld.ip_rules.read().await;
+        real_ip::resolve_client_ip(&ip_rules.trusted_proxies, peer, forwarded_for, real_ip)
+    }
+
+    /// 检查网关节点的连接数是否超过限制，对受信IP同样生效
+    ///
+    /// - node: 当前网关节点，格式为ip:port
+    /// - connections: 当前连接数，包含本次请求
+    pub async fn check_connections(node: &str, connections: isize) -> Result<(), String> {
+        let firewall = FIREWALLD.get().unwrap().config.read().await;
+        if let Some(max_connections) = firewall.max_connections_of(node)
+            && connections > max_connections as isize
+        {
+            return Err("Too many connections, please try again later".to_string());
+        }
         Ok(())
     }
 
-    fn check_ip_policy(firewall: &Firewall, ip: &str) -> Result<(), String> {
+    fn check_ip_policy(firewall: &Firewall, ip_rules: &IpRules, ip: IpAddr) -> Result<(), String> {
         match firewall.ip_policy_mode {
             AllowDenyPolicy::Allow => {
-                if !firewall.ip_policy.contains(ip) {
+                if !ip_rules.ip_policy.contains(ip) {
                     return Err(format!("Your IP ({}) is not allowed", ip));
                 }
             }
             AllowDenyPolicy::Deny => {
-                if firewall.ip_policy.contains(ip) {
+                if ip_rules.ip_policy.contains(ip) {
                     return Err(format!("Your IP ({}) is not allowed", ip));
                 }
             }
@@ -137,13 +191,26 @@ impl Firewalld {
         Ok(())
     }
 
-    pub async fn get_api_secret_encrypt_key() -> [u8; 32] {
+    /// 全局限流配置
+    pub async fn get_rate_limit() -> Option<RateLimit> {
+        FIREWALLD
+            .get()
+            .unwrap()
+            .config
+            .read()
+            .await
+            .rate_limit
+            .clone()
+    }
+
+    /// 可用于验证API Key的加密密钥，包含宽限期内的旧密钥，见[`Firewall::api_secret_keys`]
+    pub async fn get_api_secret_keys() -> Vec<(u8, [u8; 32])> {
         FIREWALLD
             .get()
             .unwrap()
             .config
             .read()
             .await
-            .api_secret_encrypt_key
+            .api_secret_keys(chrono::Local::now().timestamp())
     }
 }
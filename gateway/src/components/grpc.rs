@@ -0,0 +1,435 @@
+//! # gRPC
+//! 支持gRPC客户端通过网关调用gRPC服务（服务协议为[`Protocol::Grpc`](aiway_protocol::gateway::service::Protocol::Grpc)）。
+//!
+//! ## 实现方式
+//! gRPC的状态码通常在trailers中返回，而Rocket不支持发送trailers，因此在独立的端口上启动gRPC监听器（h2c）：
+//! - 每个gRPC请求通过本机回环地址以HTTP/1.1转发到Rocket，与HTTP请求经过相同的Fairing，
+//!   包括防火墙、鉴权、路由、限流、负载均衡等。转发连接的真实对端地址通过
+//!   [`proxy_protocol::bind_peer`]记录，用于解析客户端IP。
+//! - 转发时携带调用标识[`Headers::GRPC_CALL`]，Rocket转发到服务后，将服务返回的trailers通过[`send_trailers`]
+//!   发送到监听器，监听器在响应体结束后以trailers返回给客户端。
+//! - 请求体流式转发到服务，客户端流和双向流调用的消息随到随转，不等待客户端结束发送。
+//!   路由或全局过滤器中有需要读取请求体的插件时，请求体先完整读取到上下文中，
+//!   此时客户端流调用在客户端结束发送后才转发到服务，双向流调用不可用。
+//! - 网关自身返回的非gRPC响应，如鉴权失败、限流等，转换为仅包含头部的gRPC错误响应。
+//!
+//! ## 状态码
+//! gRPC的状态码映射为HTTP状态码后，记录到请求日志的`status_code`和状态统计中。
+//! 状态码在trailers中时，请求日志和状态统计在响应结束后，通过[`defer_log`]和[`complete`]记录。
+//! 仅转发到gRPC服务时通过[`expect`]登记的请求会延迟记录，其他状态码在trailers中的响应按HTTP状态码立即记录，
+//! 见[`response_status`]。
+//!
+use crate::components::proxy_protocol;
+use crate::report::STATE;
+use aiway_protocol::gateway::request_log::RequestLog;
+use context::Headers;
+use dashmap::DashMap;
+use dashmap::mapref::entry::Entry;
+use http_body_util::combinators::UnsyncBoxBody;
+use http_body_util::{BodyExt, Empty};
+use hyper::body::{Bytes, Incoming};
+use hyper::header::{CONNECTION, CONTENT_TYPE, HOST, HeaderValue, TRANSFER_ENCODING};
+use hyper::server::conn::http2;
+use hyper::service::service_fn;
+use hyper::{HeaderMap, Request, Response, StatusCode, Uri, Version};
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::LazyLock;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::oneshot;
+
+/// gRPC状态码：调用被取消
+pub const CANCELLED: u16 = 1;
+/// gRPC状态码：未知错误
+pub const UNKNOWN: u16 = 2;
+/// gRPC状态码：服务不可用
+const UNAVAILABLE: u16 = 14;
+
+type GrpcBody = UnsyncBoxBody<Bytes, hyper::Error>;
+
+/// 调用标识 -> trailers的发送端，由gRPC监听器注册
+static CALLS: LazyLock<DashMap<String, oneshot::Sender<HeaderMap>>> = LazyLock::new(DashMap::new);
+
+/// 状态码在trailers中的请求：请求ID -> 先到达的请求日志或状态码
+static PENDING: LazyLock<DashMap<String, Pending>> = LazyLock::new(DashMap::new);
+
+enum Pending {
+    /// 已转发到服务，等待响应阶段的请求日志和响应结束
+    Expected,
+    /// 响应阶段已生成的请求日志
+    Log(Box<RequestLog>),
+    /// 响应结束后得到的HTTP状态码
+    Status(u16),
+}
+
+/// 是否为gRPC的Content-Type，如`application/grpc`、`application/grpc+proto`
+pub fn is_grpc(content_type: Option<&str>) -> bool {
+    content_type.is_some_and(|value| {
+        value == "application/grpc"
+            || value.starts_with("application/grpc+")
+            || value.starts_with("application/grpc;")
+    })
+}
+
+/// 响应的状态码，用于请求日志和状态统计
+///
+/// gRPC响应的状态码在响应头中时，映射为HTTP状态码；在trailers中时返回None，需要在响应结束后记录。
+pub fn status_code(
+    status: u16,
+    content_type: Option<&str>,
+    grpc_status: Option<&str>,
+) -> Option<u16> {
+    if status != 200 || !is_grpc(content_type) {
+        return Some(status);
+    }
+    grpc_status.map(|code| http_status(parse_status(code)))
+}
+
+/// 请求日志和状态统计使用的状态码
+///
+/// 仅当请求已通过[`expect`]登记，且状态码在trailers中时返回None，由响应结束后的[`complete`]记录；
+/// 未登记的请求（如非gRPC服务返回了gRPC响应）无法得到trailers中的状态码，使用HTTP状态码。
+pub fn response_status(
+    request_id: &str,
+    status: u16,
+    content_type: Option<&str>,
+    grpc_status: Option<&str>,
+) -> Option<u16> {
+    match status_code(status, content_type, grpc_status) {
+        None if !PENDING.contains_key(request_id) => Some(status),
+        code => code,
+    }
+}
+
+/// gRPC状态码映射为HTTP状态码
+pub fn http_status(code: u16) -> u16 {
+    match code {
+        0 => 200,
+        // CANCELLED
+        1 => 499,
+        // INVALID_ARGUMENT | FAILED_PRECONDITION | OUT_OF_RANGE
+        3 | 9 | 11 => 400,
+        // DEADLINE_EXCEEDED
+        4 => 504,
+        // NOT_FOUND
+        5 => 404,
+        // ALREADY_EXISTS | ABORTED
+        6 | 10 => 409,
+        // PERMISSION_DENIED
+        7 => 403,
+        // RESOURCE_EXHAUSTED
+        8 => 429,
+        // UNIMPLEMENTED
+        12 => 501,
+        // UNAVAILABLE
+        14 => 503,
+        // UNAUTHENTICATED
+        16 => 401,
+        // UNKNOWN | INTERNAL | DATA_LOSS
+        _ => 500,
+    }
+}
+
+/// HTTP状态码映射为gRPC状态码，参考gRPC的[HTTP状态码映射](https://github.com/grpc/grpc/blob/master/doc/http-grpc-status-mapping.md)
+fn grpc_status(status: u16) -> u16 {
+    match status {
+        // INTERNAL
+        400 => 13,
+        // UNAUTHENTICATED
+        401 => 16,
+        // PERMISSION_DENIED
+        403 => 7,
+        // UNIMPLEMENTED
+        404 => 12,
+        429 | 502..=504 => UNAVAILABLE,
+        _ => UNKNOWN,
+    }
+}
+
+/// 解析`grpc-status`，无法解析时为UNKNOWN
+pub fn parse_status(value: &str) -> u16 {
+    value.trim().parse().unwrap_or(UNKNOWN)
+}
+
+/// 将服务返回的trailers发送到gRPC监听器，请求不是来自gRPC监听器时忽略
+pub fn send_trailers(call: &str, trailers: HeaderMap) {
+    if let Some((_, tx)) = CALLS.remove(call) {
+        let _ = tx.send(trailers);
+    }
+}
+
+/// 登记状态码在trailers中的gRPC响应，由转发到服务时的响应处理调用，响应结束后必须调用[`complete`]
+pub fn expect(request_id: &str) {
+    PENDING.insert(request_id.to_string(), Pending::Expected);
+}
+
+/// 延迟记录请求日志，状态码由[`complete`]提供，未登记的请求立即记录
+pub fn defer_log(log: RequestLog) {
+    match PENDING.entry(log.request_id.clone()) {
+        Entry::Occupied(mut entry) => match entry.get() {
+            Pending::Expected => {
+                entry.insert(Pending::Log(Box::new(log)));
+            }
+            Pending::Status(status) => {
+                let status = *status;
+                entry.remove();
+                write_log(log, status);
+            }
+            Pending::Log(_) => log::warn!("duplicate grpc request log: {}", log.request_id),
+        },
+        // 未登记的请求，状态码和状态统计已在响应阶段记录
+        Entry::Vacant(_) => write(&log),
+    }
+}
+
+/// gRPC响应结束，记录状态码在trailers中的请求日志和状态统计
+pub fn complete(request_id: &str, code: u16) {
+    let status = http_status(code);
+    if let Entry::Occupied(mut entry) = PENDING.entry(request_id.to_string()) {
+        match entry.get() {
+            Pending::Expected => {
+                entry.insert(Pending::Status(status));
+            }
+            Pending::Log(_) => {
+                if let Pending::Log(log) = entry.remove() {
+                    write_log(*log, status);
+                }
+            }
+            Pending::Status(_) => {}
+        }
+    }
+}
+
+fn write_log(mut log: RequestLog, status: u16) {
+    log.status_code = status;
+    STATE.inc_status_request_count(status, 1);
+    write(&log);
+}
+
+fn write(log: &RequestLog) {
+    match serde_json::to_vec(log) {
+        Ok(value) => logging::log_request(value),
+        Err(e) => log::error!("Failed to serialize RequestLog to JSON: {}", e),
+    }
+}
+
+/// 在`listen`上启动gRPC监听器，请求转发到`upstream`上的Rocket服务
+pub async fn serve(listen: SocketAddr, upstream: SocketAddr) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(listen).await?;
+
+    tokio::spawn(async move {
+        loop {
+            let (stream, peer) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    log::error!("grpc accept error: {}", e);
+                    continue;
+                }
+            };
+            tokio::spawn(async move {
+                let service = service_fn(move |req| forward(req, peer, upstream));
+                if let Err(e) = http2::Builder::new(TokioExecutor::new())
+                    .serve_connection(TokioIo::new(stream), service)
+                    .await
+                {
+                    log::debug!("grpc connection from {} closed: {}", peer, e);
+                }
+            });
+        }
+    });
+
+    log::info!(
+        "grpc listener started on {}, forward to {}",
+        listen,
+        upstream
+    );
+    Ok(())
+}
+
+/// 转发gRPC请求，所有错误都转换为gRPC错误响应
+async fn forward(
+    req: Request<Incoming>,
+    peer: SocketAddr,
+    upstream: SocketAddr,
+) -> Result<Response<GrpcBody>, Infallible> {
+    match call(req, peer, upstream).await {
+        Ok(response) => Ok(response),
+        Err(e) => {
+            log::error!("grpc forward error: {}", e);
+            Ok(error_response(UNAVAILABLE, "gateway unavailable"))
+        }
+    }
+}
+
+async fn call(
+    req: Request<Incoming>,
+    peer: SocketAddr,
+    upstream: SocketAddr,
+) -> anyhow::Result<Response<GrpcBody>> {
+    let call = CallGuard::new();
+    let (tx, rx) = oneshot::channel();
+    CALLS.insert(call.0.clone(), tx);
+
+    let stream = TcpStream::connect(upstream).await?;
+    stream.set_nodelay(true)?;
+    let peer = proxy_protocol::bind_peer(stream.local_addr()?, peer);
+    let (mut sender, conn) = hyper::client::conn::http1::handshake(TokioIo::new(stream)).await?;
+    tokio::spawn(async move {
+        // 连接关闭后移除对端地址
+        let _peer = peer;
+        if let Err(e) = conn.await {
+            log::debug!("grpc upstream connection closed: {}", e);
+        }
+    });
+
+    // HTTP/2请求转为HTTP/1.1请求，authority转为Host请求头
+    let (mut parts, body) = req.into_parts();
+    if let Some(authority) = parts.uri.authority()
+        && !parts.headers.contains_key(HOST)
+    {
+        parts
+            .headers
+            .insert(HOST, HeaderValue::from_str(authority.as_str())?);
+    }
+    parts.uri = parts
+        .uri
+        .path_and_query()
+        .map(|path| path.as_str())
+        .unwrap_or("/")
+        .parse::<Uri>()?;
+    parts.version = Version::HTTP_11;
+    parts
+        .headers
+        .insert(Headers::GRPC_CALL, HeaderValue::from_str(&call.0)?);
+
+    let response = sender
+        .send_request(Request::from_parts(parts, body))
+        .await?;
+    let (mut parts, body) = response.into_parts();
+
+    // 网关自身返回的错误响应，如鉴权失败、限流、熔断等
+    let content_type = parts
+        .headers
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok());
+    if parts.status != StatusCode::OK || !is_grpc(content_type) {
+        return Ok(error_response(
+            grpc_status(parts.status.as_u16()),
+            parts.status.canonical_reason().unwrap_or_default(),
+        ));
+    }
+
+    parts.version = Version::HTTP_2;
+    parts.headers.remove(CONNECTION);
+    parts.headers.remove(TRANSFER_ENCODING);
+    let trailers_only = parts.headers.contains_key(Headers::GRPC_STATUS);
+    let body = body.with_trailers(async move {
+        let _call = call;
+        match rx.await {
+            Ok(trailers) => Some(Ok(trailers)),
+            // 状态码已在响应头中返回
+            Err(_) if trailers_only => None,
+            Err(_) => Some(Ok(status_trailers(UNKNOWN, "missing trailers"))),
+        }
+    });
+    Ok(Response::from_parts(parts, body.boxed_unsync()))
+}
+
+/// 仅包含头部的gRPC错误响应
+fn error_response(code: u16, message: &str) -> Response<GrpcBody> {
+    let body = Empty::new().map_err(|never| match never {}).boxed_unsync();
+    let mut response = Response::new(body);
+    let headers = response.headers_mut();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/grpc"));
+    headers.extend(status_trailers(code, message));
+    response
+}
+
+fn status_trailers(code: u16, message: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(Headers::GRPC_STATUS, HeaderValue::from(code));
+    if let Ok(message) = HeaderValue::from_str(message) {
+        headers.insert(Headers::GRPC_MESSAGE, message);
+    }
+    headers
+}
+
+/// 调用标识，被丢弃时从[`CALLS`]中移除
+struct CallGuard(String);
+
+impl CallGuard {
+    fn new() -> Self {
+        Self(uuid::Uuid::new_v4().to_string())
+    }
+}
+
+impl Drop for CallGuard {
+    fn drop(&mut self) {
+        CALLS.remove(&self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_code() {
+        let grpc = Some("application/grpc");
+        assert_eq!(status_code(200, grpc, Some("0")), Some(200));
+        assert_eq!(status_code(200, grpc, Some("16")), Some(401));
+        assert_eq!(status_code(200, grpc, Some("x")), Some(500));
+        assert_eq!(status_code(200, grpc, None), None);
+        assert_eq!(status_code(200, Some("application/grpc+proto"), None), None);
+        assert_eq!(
+            status_code(200, Some("application/grpc-web"), None),
+            Some(200)
+        );
+        assert_eq!(status_code(401, Some("application/json"), None), Some(401));
+    }
+
+    #[test]
+    fn test_pending() {
+        let grpc = Some("application/grpc");
+        let log = |request_id: &str| RequestLog {
+            request_id: request_id.to_string(),
+            ..Default::default()
+        };
+
+        // 未登记的请求按HTTP状态码立即记录，不会暂存
+        assert_eq!(response_status("g1", 200, grpc, None), Some(200));
+        defer_log(log("g1"));
+        assert!(!PENDING.contains_key("g1"));
+
+        // 登记的请求，请求日志先到达
+        expect("g2");
+        assert_eq!(response_status("g2", 200, grpc, None), None);
+        assert_eq!(response_status("g2", 200, grpc, Some("0")), Some(200));
+        defer_log(log("g2"));
+        assert!(matches!(
+            PENDING.get("g2").as_deref(),
+            Some(Pending::Log(_))
+        ));
+        complete("g2", 0);
+        assert!(!PENDING.contains_key("g2"));
+
+        // 登记的请求，响应先结束
+        expect("g3");
+        complete("g3", CANCELLED);
+        assert!(matches!(
+            PENDING.get("g3").as_deref(),
+            Some(Pending::Status(499))
+        ));
+        defer_log(log("g3"));
+        assert!(!PENDING.contains_key("g3"));
+    }
+
+    #[test]
+    fn test_grpc_status() {
+        assert_eq!(grpc_status(401), 16);
+        assert_eq!(grpc_status(429), UNAVAILABLE);
+        assert_eq!(grpc_status(500), UNKNOWN);
+        assert_eq!(http_status(grpc_status(403)), 403);
+    }
+}
@@ -0,0 +1,280 @@
+//! # 服务节点健康检查
+//! 维护每个服务节点的健康状态，不健康的节点不参与负载均衡。
+//!
+//! - 主动检查：由[`Servicer`](crate::components::Servicer)定时触发，请求节点的检查地址。
+//! - 被动检查：在转发请求后上报结果，连续的连接错误或5xx响应达到阈值后摘除节点。
+//!
+//! 主动检查https节点时使用服务的TLS配置，见[`upstream_tls`](super::upstream_tls)。
+//!
+//! 健康状态仅保存在当前网关节点的内存中，各网关节点独立检查，互不影响。
+//! 服务配置变更后，已存在节点的健康状态会被保留。
+//!
+use crate::components::upstream_tls;
+use aiway_protocol::gateway::service::{HealthCheck, UpstreamTls};
+use aiway_protocol::gateway::state::NodeHealthState;
+use dashmap::DashMap;
+use reqwest::{Client, ClientBuilder, Url};
+use rocket::futures::future::join_all;
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU32, Ordering};
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+
+/// 主动检查使用的HTTP客户端
+static PROBE_CLIENT: LazyLock<Client> = LazyLock::new(|| probe_builder().build().unwrap());
+
+fn probe_builder() -> ClientBuilder {
+    Client::builder()
+        .connect_timeout(Duration::from_secs(1))
+        .pool_max_idle_per_host(1)
+}
+
+/// 单个节点的健康状态
+#[derive(Debug, Default)]
+struct NodeHealth {
+    /// 是否已被摘除
+    ejected: AtomicBool,
+    /// 被摘除的时间戳，毫秒
+    ejected_at: AtomicI64,
+    /// 被动检查的连续失败次数
+    failures: AtomicU32,
+    /// 主动检查的连续失败次数
+    probe_failures: AtomicU32,
+    /// 主动检查的连续成功次数
+    probe_successes: AtomicU32,
+}
+
+impl NodeHealth {
+    fn eject(&self) -> bool {
+        let ejected = !self.ejected.swap(true, Ordering::Relaxed);
+        if ejected {
+            self.ejected_at
+                .store(chrono::Local::now().timestamp_millis(), Ordering::Relaxed);
+        }
+        ejected
+    }
+
+    fn recover(&self) -> bool {
+        self.failures.store(0, Ordering::Relaxed);
+        self.probe_failures.store(0, Ordering::Relaxed);
+        self.ejected.swap(false, Ordering::Relaxed)
+    }
+}
+
+/// 服务的健康状态
+pub struct ServiceHealth {
+    /// 服务名
+    service: String,
+    /// 健康检查配置
+    config: Option<HealthCheck>,
+    /// 节点健康状态，key为节点地址
+    nodes: DashMap<String, Arc<NodeHealth>>,
+    /// 上次主动检查的时间戳，毫秒
+    last_probe: AtomicI64,
+    /// 服务的TLS配置
+    tls: Option<UpstreamTls>,
+    /// 配置了TLS时，每个节点的检查客户端，key为节点地址
+    clients: DashMap<String, Client>,
+}
+
+impl ServiceHealth {
+    /// 创建服务健康状态
+    ///
+    /// 如果提供了旧的健康状态，则保留仍然存在的节点的状态。
+    pub fn new(
+        service: &str,
+        config: Option<HealthCheck>,
+        nodes: &[String],
+        tls: Option<UpstreamTls>,
+        old: Option<&ServiceHealth>,
+    ) -> Self {
+        let health = DashMap::new();
+        for node in nodes {
+            let state = old
+                .and_then(|old| old.nodes.get(node).map(|s| s.value().clone()))
+                .unwrap_or_default();
+            health.insert(node.clone(), state);
+        }
+        Self {
+            service: service.to_string(),
+            config,
+            nodes: health,
+            last_probe: AtomicI64::new(0),
+            tls,
+            clients: DashMap::new(),
+        }
+    }
+
+    /// 是否开启了健康检查
+    pub fn is_enabled(&self) -> bool {
+        self.config
+            .as_ref()
+            .map(|c| c.active.is_some() || c.passive.is_some())
+            .unwrap_or(false)
+    }
+
+    /// 节点是否可用
+    pub fn is_available(&self, node: &str) -> bool {
+        let health = match self.nodes.get(node) {
+            Some(health) => health,
+            None => return true,
+        };
+        if !health.ejected.load(Ordering::Relaxed) {
+            return true;
+        }
+
+        // 未开启主动检查时，被动摘除的节点在摘除时长后自动恢复
+        if let Some(config) = &self.config
+            && config.active.is_none()
+            && let Some(passive) = &config.passive
+        {
+            let elapsed =
+                chrono::Local::now().timestamp_millis() - health.ejected_at.load(Ordering::Relaxed);
+            if elapsed >= (passive.eject_secs * 1000) as i64 && health.recover() {
+                log::info!("service {} node {} recovered", self.service, node);
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// 上报请求结果，用于被动检查
+    ///
+    /// - success: 连接错误或5xx响应为false，否则为true
+    pub fn report(&self, node: &str, success: bool) {
+        let passive = match self.config.as_ref().and_then(|c| c.passive.as_ref()) {
+            Some(passive) => passive,
+            None => return,
+        };
+        let health = match self.nodes.get(node) {
+            Some(health) => health,
+            None => return,
+        };
+        if success {
+            health.failures.store(0, Ordering::Relaxed);
+            return;
+        }
+        let failures = health.failures.fetch_add(1, Ordering::Relaxed) + 1;
+        if failures >= passive.max_failures && health.eject() {
+            log::warn!(
+                "service {} node {} ejected after {} consecutive failures",
+                self.service,
+                node,
+                failures
+            );
+        }
+    }
+
+    /// 是否需要执行主动检查，如果需要，则同时更新检查时间
+    pub fn should_probe(&self) -> bool {
+        let active = match self.config.as_ref().and_then(|c| c.active.as_ref()) {
+            Some(active) => active,
+            None => return false,
+        };
+        let now = chrono::Local::now().timestamp_millis();
+        let last = self.last_probe.load(Ordering::Relaxed);
+        if now - last < (active.interval * 1000) as i64 {
+            return false;
+        }
+        self.last_probe
+            .compare_exchange(last, now, Ordering::Relaxed, Ordering::Relaxed)
+            .is_ok()
+    }
+
+    /// 对所有节点执行一次主动检查
+    pub async fn probe(&self) {
+        let active = match self.config.as_ref().and_then(|c| c.active.as_ref()) {
+            Some(active) => active,
+            None => return,
+        };
+        let nodes = self
+            .nodes
+            .iter()
+            .map(|item| (item.key().clone(), item.value().clone()))
+            .collect::<Vec<_>>();
+
+        let probes = nodes.into_iter().map(|(node, health)| async move {
+            let url = format!(
+                "{}/{}",
+                node.trim_end_matches('/'),
+                active.path.trim_start_matches('/')
+            );
+            let ok = match self.probe_request(&node, &url) {
+                Ok((client, probe_url)) => match client
+                    .get(probe_url)
+                    .timeout(Duration::from_millis(active.timeout))
+                    .send()
+                    .await
+                {
+                    Ok(response) => {
+                        response.status().is_success() || response.status().is_redirection()
+                    }
+                    Err(e) => {
+                        log::debug!("health check {} failed: {}", url, e);
+                        false
+                    }
+                },
+                Err(e) => {
+                    log::warn!("health check {} failed: {}", url, e);
+                    false
+                }
+            };
+
+            if ok {
+                health.probe_failures.store(0, Ordering::Relaxed);
+                let successes = health.probe_successes.fetch_add(1, Ordering::Relaxed) + 1;
+                if successes >= active.healthy_threshold && health.recover() {
+                    log::info!("service {} node {} recovered", self.service, node);
+                }
+            } else {
+                health.probe_successes.store(0, Ordering::Relaxed);
+                let failures = health.probe_failures.fetch_add(1, Ordering::Relaxed) + 1;
+                if failures >= active.unhealthy_threshold && health.eject() {
+                    log::warn!(
+                        "service {} node {} ejected, health check failed {} times",
+                        self.service,
+                        node,
+                        failures
+                    );
+                }
+            }
+        });
+
+        join_all(probes).await;
+    }
+
+    /// 获取节点的检查客户端和检查地址
+    ///
+    /// 配置了TLS的服务，为每个节点构建一个客户端，覆盖了SNI时检查地址中的域名会被替换。
+    fn probe_request(&self, node: &str, url: &str) -> anyhow::Result<(Client, Url)> {
+        let mut url = Url::parse(url)?;
+        let tls = match &self.tls {
+            Some(tls) => tls,
+            None => return Ok((PROBE_CLIENT.clone(), url)),
+        };
+        let origin = upstream_tls::override_server_name(&mut url, tls)?;
+        if let Some(client) = self.clients.get(node) {
+            return Ok((client.clone(), url));
+        }
+        let client = upstream_tls::configure(probe_builder(), tls, origin.as_deref())?.build()?;
+        self.clients.insert(node.to_string(), client.clone());
+        Ok((client, url))
+    }
+
+    /// 获取所有节点的健康状态，用于上报到控制台
+    pub fn states(&self) -> Vec<NodeHealthState> {
+        self.nodes
+            .iter()
+            .map(|item| NodeHealthState {
+                service: self.service.clone(),
+                node: item.key().clone(),
+                healthy: !item.value().ejected.load(Ordering::Relaxed),
+                failures: item
+                    .value()
+                    .failures
+                    .load(Ordering::Relaxed)
+                    .max(item.value().probe_failures.load(Ordering::Relaxed)),
+            })
+            .collect()
+    }
+}
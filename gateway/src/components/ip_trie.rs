@@ -0,0 +1,131 @@
+//! # IP前缀树
+//! 按二进制位构建的前缀树，用于判断IP是否在配置的IP或网段内。
+//!
+//! 查找时间与配置的条目数无关，IPv4最多比较32位，IPv6最多比较128位。
+//! IPv4映射的IPv6地址（如`::ffff:192.168.1.1`）按IPv4匹配。
+//!
+use aiway_protocol::gateway::parse_ip_range;
+use std::net::IpAddr;
+
+/// IP前缀树
+#[derive(Debug, Default)]
+pub struct IpTrie {
+    v4: PrefixTrie,
+    v6: PrefixTrie,
+}
+
+impl IpTrie {
+    /// 从IP或网段列表构建，无效的条目会被忽略，由控制台保证配置的有效性
+    pub fn new<'a, I: IntoIterator<Item = &'a String>>(values: I) -> Self {
+        let mut trie = Self::default();
+        for value in values {
+            match parse_ip_range(value) {
+                Ok((ip, prefix)) => trie.insert(ip, prefix),
+                Err(e) => log::warn!("ignore invalid ip range: {}", e),
+            }
+        }
+        trie
+    }
+
+    fn insert(&mut self, ip: IpAddr, prefix: u8) {
+        match ip {
+            IpAddr::V4(ip) => self.v4.insert(u32::from(ip) as u128, 32, prefix),
+            IpAddr::V6(ip) => self.v6.insert(u128::from(ip), 128, prefix),
+        }
+    }
+
+    /// IP是否在任一IP或网段内
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match ip.to_canonical() {
+            IpAddr::V4(ip) => self.v4.contains(u32::from(ip) as u128, 32),
+            IpAddr::V6(ip) => self.v6.contains(u128::from(ip), 128),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct PrefixTrie {
+    /// 节点列表，第一个为根节点
+    nodes: Vec<TrieNode>,
+}
+
+#[derive(Debug, Default, Clone)]
+struct TrieNode {
+    /// 子节点的下标，0表示没有子节点
+    children: [usize; 2],
+    /// 是否为某个网段的结尾
+    terminal: bool,
+}
+
+impl Default for PrefixTrie {
+    fn default() -> Self {
+        Self {
+            nodes: vec![TrieNode::default()],
+        }
+    }
+}
+
+impl PrefixTrie {
+    /// 插入网段
+    ///
+    /// - bits: 地址，低`width`位有效
+    /// - width: 地址位数
+    /// - prefix: 前缀长度
+    fn insert(&mut self, bits: u128, width: u8, prefix: u8) {
+        let mut node = 0;
+        for i in 0..prefix {
+            if self.nodes[node].terminal {
+                // 已包含更大的网段
+                return;
+            }
+            let bit = ((bits >> (width - 1 - i)) & 1) as usize;
+            if self.nodes[node].children[bit] == 0 {
+                self.nodes.push(TrieNode::default());
+                self.nodes[node].children[bit] = self.nodes.len() - 1;
+            }
+            node = self.nodes[node].children[bit];
+        }
+        self.nodes[node].terminal = true;
+    }
+
+    fn contains(&self, bits: u128, width: u8) -> bool {
+        let mut node = 0;
+        for i in 0..width {
+            if self.nodes[node].terminal {
+                return true;
+            }
+            let bit = ((bits >> (width - 1 - i)) & 1) as usize;
+            node = self.nodes[node].children[bit];
+            if node == 0 {
+                return false;
+            }
+        }
+        self.nodes[node].terminal
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ip_trie() {
+        let values = [
+            "192.168.0.0/16".to_string(),
+            "10.0.0.1".to_string(),
+            "2001:db8::/32".to_string(),
+        ];
+        let trie = IpTrie::new(&values);
+        assert!(trie.contains("192.168.10.1".parse().unwrap()));
+        assert!(trie.contains("10.0.0.1".parse().unwrap()));
+        assert!(!trie.contains("10.0.0.2".parse().unwrap()));
+        assert!(trie.contains("::ffff:192.168.1.1".parse().unwrap()));
+        assert!(trie.contains("2001:db8:1::1".parse().unwrap()));
+        assert!(!trie.contains("2001:db9::1".parse().unwrap()));
+
+        // 0.0.0.0/0匹配所有IPv4
+        let trie = IpTrie::new(&["0.0.0.0/0".to_string()]);
+        assert!(trie.contains("1.2.3.4".parse().unwrap()));
+        assert!(!trie.contains("::1".parse().unwrap()));
+    }
+}
@@ -0,0 +1,296 @@
+//! # JWT验证
+//! 路由的鉴权方式为JWT时，按路由引用的[`JwtProvider`]验证JWT。
+//!
+//! 实现流程：
+//! - 启动时从控制台的`GET /api/v1/gateway/jwt-providers`端点获取签发方，如果获取失败则无法启动。
+//! - 每5秒从控制台拉取签发方，连同引用的本地JWKS文件内容校验hash值，如果不一致则重新加载。
+//! - 无法加载的签发方（如公钥格式错误、JWKS文件不存在）会被跳过，并输出错误日志，引用它的路由验证失败。
+//!
+//! 验证签名、`alg`、`exp`、`nbf`、`iss`和`aud`，其中`exp`必须存在，`nbf`存在时校验。
+//!
+use crate::components::client::INNER_HTTP_CLIENT;
+use aiway_protocol::gateway::JwtProvider;
+use aiway_protocol::gateway::jwt::JwtAlgorithm;
+use anyhow::{Context, bail};
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::process::exit;
+use std::sync::{Arc, LazyLock, RwLock};
+use std::time::Duration;
+
+/// 已加载的签发方
+static PROVIDERS: LazyLock<RwLock<Providers>> = LazyLock::new(Default::default);
+
+const INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Default)]
+struct Providers {
+    hash: String,
+    /// 签发方名称 -> 验证器
+    verifiers: HashMap<String, Arc<Verifier>>,
+}
+
+/// 签发方的验证器
+struct Verifier {
+    /// 验证签名的密钥，kid为None时匹配任意JWT
+    keys: Vec<(Option<String>, DecodingKey)>,
+    validation: Validation,
+}
+
+impl Verifier {
+    fn new(provider: &JwtProvider, jwks_file: Option<&str>) -> anyhow::Result<Self> {
+        let algorithm = algorithm(provider.algorithm);
+        let keys = match (&provider.key, &provider.jwks, jwks_file) {
+            (Some(key), _, _) => vec![(None, decoding_key(provider.algorithm, key)?)],
+            (None, Some(jwks), _) => jwks_keys(jwks)?,
+            (None, None, Some(jwks)) => jwks_keys(jwks)?,
+            (None, None, None) => bail!("no key or jwks configured"),
+        };
+        if keys.is_empty() {
+            bail!("no valid key in jwks");
+        }
+
+        let mut validation = Validation::new(algorithm);
+        validation.leeway = provider.leeway;
+        validation.validate_nbf = true;
+        if let Some(issuer) = &provider.issuer {
+            validation.set_issuer(&[issuer]);
+        }
+        match provider.audiences.is_empty() {
+            true => validation.validate_aud = false,
+            false => validation.set_audience(&provider.audiences),
+        }
+        Ok(Self { keys, validation })
+    }
+
+    /// 验证JWT，返回全部声明
+    fn verify(&self, token: &str) -> anyhow::Result<Value> {
+        let header = jsonwebtoken::decode_header(token)?;
+        let key = self
+            .keys
+            .iter()
+            .find(|(kid, _)| kid.is_none() || header.kid.is_none() || *kid == header.kid)
+            .map(|(_, key)| key)
+            .with_context(|| format!("unknown kid: {:?}", header.kid))?;
+        let data = jsonwebtoken::decode::<Value>(token, key, &self.validation)?;
+        if !data.claims.is_object() {
+            bail!("claims is not a json object");
+        }
+        Ok(data.claims)
+    }
+}
+
+fn algorithm(algorithm: JwtAlgorithm) -> Algorithm {
+    match algorithm {
+        JwtAlgorithm::Hs256 => Algorithm::HS256,
+        JwtAlgorithm::Rs256 => Algorithm::RS256,
+        JwtAlgorithm::Es256 => Algorithm::ES256,
+    }
+}
+
+/// 解析密钥：HS256为共享密钥，RS256和ES256为PEM格式的公钥
+fn decoding_key(algorithm: JwtAlgorithm, key: &str) -> anyhow::Result<DecodingKey> {
+    let key = match algorithm {
+        JwtAlgorithm::Hs256 => DecodingKey::from_secret(key.as_bytes()),
+        JwtAlgorithm::Rs256 => DecodingKey::from_rsa_pem(key.as_bytes())?,
+        JwtAlgorithm::Es256 => DecodingKey::from_ec_pem(key.as_bytes())?,
+    };
+    Ok(key)
+}
+
+/// 解析JWKS文档，跳过无法解析的密钥
+fn jwks_keys(jwks: &str) -> anyhow::Result<Vec<(Option<String>, DecodingKey)>> {
+    let jwks = serde_json::from_str::<JwkSet>(jwks).context("invalid jwks")?;
+    let keys = jwks
+        .keys
+        .iter()
+        .filter_map(|jwk| match DecodingKey::from_jwk(jwk) {
+            Ok(key) => Some((jwk.common.key_id.clone(), key)),
+            Err(e) => {
+                log::warn!("skip jwk {:?}: {}", jwk.common.key_id, e);
+                None
+            }
+        })
+        .collect();
+    Ok(keys)
+}
+
+/// 按签发方验证JWT，返回全部声明
+pub fn verify(provider: &str, token: &str) -> anyhow::Result<Value> {
+    let verifier = PROVIDERS
+        .read()
+        .unwrap()
+        .verifiers
+        .get(provider)
+        .cloned()
+        .with_context(|| format!("jwt provider {} not found", provider))?;
+    verifier.verify(token)
+}
+
+pub async fn init() {
+    let providers = match INNER_HTTP_CLIENT.fetch_jwt_providers().await {
+        Ok(providers) => providers,
+        Err(e) => {
+            log::error!("{}", e);
+            exit(1)
+        }
+    };
+    if let Err(e) = reload(&providers) {
+        log::error!("load jwt providers error: {}", e);
+        exit(1)
+    }
+    watch();
+}
+
+/// 读取签发方引用的本地JWKS文件，仅在控制台未配置密钥和JWKS时读取
+fn read_jwks_files(providers: &[JwtProvider]) -> HashMap<String, String> {
+    providers
+        .iter()
+        .filter(|provider| provider.key.is_none() && provider.jwks.is_none())
+        .filter_map(|provider| {
+            let path = provider.jwks_file.as_ref()?;
+            match std::fs::read_to_string(path) {
+                Ok(jwks) => Some((provider.name.clone(), jwks)),
+                Err(e) => {
+                    log::error!("read jwks file {} error: {}", path, e);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// 签发方或本地JWKS文件变化时重新加载，返回是否有变化
+fn reload(providers: &[JwtProvider]) -> anyhow::Result<bool> {
+    let files = read_jwks_files(providers);
+    let hash = format!(
+        "{:x}",
+        md5::compute(serde_json::to_string(&(providers, &files))?)
+    );
+    if PROVIDERS.read().unwrap().hash == hash {
+        return Ok(false);
+    }
+
+    let mut verifiers = HashMap::new();
+    for provider in providers {
+        let jwks_file = files.get(&provider.name).map(|jwks| jwks.as_str());
+        match Verifier::new(provider, jwks_file) {
+            Ok(verifier) => {
+                verifiers.insert(provider.name.clone(), Arc::new(verifier));
+            }
+            Err(e) => log::error!("load jwt provider {} error: {}", provider.name, e),
+        }
+    }
+    log::info!("loaded {} jwt providers", verifiers.len());
+    *PROVIDERS.write().unwrap() = Providers { hash, verifiers };
+    Ok(true)
+}
+
+fn watch() {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(INTERVAL);
+        loop {
+            interval.tick().await;
+            let providers = match INNER_HTTP_CLIENT.fetch_jwt_providers().await {
+                Ok(providers) => providers,
+                Err(e) => {
+                    log::error!("{}", e);
+                    continue;
+                }
+            };
+            match reload(&providers) {
+                Ok(true) => {}
+                Ok(false) => log::debug!("jwt providers not changed, wait next interval"),
+                Err(e) => log::error!("reload jwt providers error: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{EncodingKey, Header};
+    use serde_json::json;
+
+    fn provider() -> JwtProvider {
+        serde_json::from_value(json!({
+            "name": "idp",
+            "algorithm": "HS256",
+            "key": "secret",
+            "issuer": "https://idp.example.com",
+            "audiences": ["aiway"],
+        }))
+        .unwrap()
+    }
+
+    fn token(claims: Value, key: &str) -> String {
+        let key = EncodingKey::from_secret(key.as_bytes());
+        jsonwebtoken::encode(&Header::new(Algorithm::HS256), &claims, &key).unwrap()
+    }
+
+    #[test]
+    fn test_verify() {
+        let verifier = Verifier::new(&provider(), None).unwrap();
+        let now = jsonwebtoken::get_current_timestamp();
+        let claims = json!({
+            "sub": "u1",
+            "iss": "https://idp.example.com",
+            "aud": "aiway",
+            "exp": now + 60,
+        });
+        let verified = verifier.verify(&token(claims.clone(), "secret")).unwrap();
+        assert_eq!(verified["sub"], "u1");
+
+        // 签名错误
+        assert!(verifier.verify(&token(claims.clone(), "other")).is_err());
+
+        // 已过期
+        let mut expired = claims.clone();
+        expired["exp"] = json!(now - 120);
+        assert!(verifier.verify(&token(expired, "secret")).is_err());
+
+        // 未生效
+        let mut not_before = claims.clone();
+        not_before["nbf"] = json!(now + 120);
+        assert!(verifier.verify(&token(not_before, "secret")).is_err());
+
+        // 签发者或受众不一致
+        let mut issuer = claims.clone();
+        issuer["iss"] = json!("https://other.example.com");
+        assert!(verifier.verify(&token(issuer, "secret")).is_err());
+        let mut audience = claims.clone();
+        audience["aud"] = json!(["other"]);
+        assert!(verifier.verify(&token(audience, "secret")).is_err());
+
+        // 缺少exp
+        let mut no_exp = claims;
+        no_exp.as_object_mut().unwrap().remove("exp");
+        assert!(verifier.verify(&token(no_exp, "secret")).is_err());
+    }
+
+    #[test]
+    fn test_jwks() {
+        // oct类型的JWK，k为base64url编码的"secret"
+        let jwks = r#"{"keys":[{"kty":"oct","kid":"k1","alg":"HS256","k":"c2VjcmV0"}]}"#;
+        let mut provider = provider();
+        provider.key = None;
+        provider.issuer = None;
+        provider.audiences = vec![];
+        assert!(Verifier::new(&provider, None).is_err());
+
+        let verifier = Verifier::new(&provider, Some(jwks)).unwrap();
+        let claims = json!({"sub": "u1", "exp": jsonwebtoken::get_current_timestamp() + 60});
+        let key = EncodingKey::from_secret(b"secret");
+        let mut header = Header::new(Algorithm::HS256);
+        header.kid = Some("k1".to_string());
+        let token = jsonwebtoken::encode(&header, &claims, &key).unwrap();
+        assert!(verifier.verify(&token).is_ok());
+
+        header.kid = Some("k2".to_string());
+        let token = jsonwebtoken::encode(&header, &claims, &key).unwrap();
+        assert!(verifier.verify(&token).is_err());
+    }
+}
@@ -0,0 +1,151 @@
+//! # 请求镜像的结果记录
+//! 镜像请求的状态码和耗时记录在原请求的日志中，便于与原请求对比。
+//!
+//! 镜像请求在后台发送，完成时间与原请求无关：
+//! - 发送镜像请求时通过[`start`]登记。
+//! - 原请求的日志通过[`defer_log`]提交，镜像请求未完成时暂存，未发送镜像请求时直接记录。
+//! - 镜像请求完成后通过[`complete`]提交结果，合并到日志后记录。
+//!
+use aiway_protocol::gateway::request_log::RequestLog;
+use dashmap::DashMap;
+use dashmap::mapref::entry::Entry;
+use std::sync::LazyLock;
+
+/// 等待合并的镜像请求，key为请求ID
+static PENDING: LazyLock<DashMap<String, Pending>> = LazyLock::new(DashMap::new);
+
+enum Pending {
+    /// 镜像请求已发送，未完成，请求日志未提交
+    Sent,
+    /// 镜像请求已完成，请求日志未提交
+    Done(MirrorResult),
+    /// 请求日志已提交，镜像请求未完成
+    Log(Box<RequestLog>, fn(RequestLog)),
+}
+
+/// 镜像请求的结果
+#[derive(Debug)]
+pub struct MirrorResult {
+    /// 镜像服务
+    pub service: String,
+    /// HTTP状态码，请求失败时为None
+    pub status_code: Option<u16>,
+    /// 耗时，单位：毫秒
+    pub elapsed: i64,
+    /// 请求失败的原因
+    pub error_reason: Option<String>,
+}
+
+impl MirrorResult {
+    fn apply(self, log: &mut RequestLog) {
+        log.mirror_service = Some(self.service);
+        log.mirror_status_code = self.status_code;
+        log.mirror_elapsed = Some(self.elapsed);
+        log.mirror_error_reason = self.error_reason;
+    }
+}
+
+/// 登记已发送的镜像请求
+pub fn start(request_id: &str) {
+    PENDING.insert(request_id.to_string(), Pending::Sent);
+}
+
+/// 提交请求日志，未发送镜像请求或镜像请求已完成时，立即通过`write`记录
+pub fn defer_log(mut log: RequestLog, write: fn(RequestLog)) {
+    match PENDING.entry(log.request_id.clone()) {
+        Entry::Occupied(mut entry) => match entry.get() {
+            Pending::Sent => {
+                entry.insert(Pending::Log(Box::new(log), write));
+            }
+            Pending::Done(_) => {
+                if let Pending::Done(result) = entry.remove() {
+                    result.apply(&mut log);
+                }
+                write(log);
+            }
+            Pending::Log(..) => log::warn!("duplicate mirror request log: {}", log.request_id),
+        },
+        Entry::Vacant(_) => write(log),
+    }
+}
+
+/// 镜像请求完成，合并结果到已提交的请求日志并记录
+pub fn complete(request_id: &str, result: MirrorResult) {
+    match PENDING.entry(request_id.to_string()) {
+        Entry::Occupied(mut entry) => match entry.get() {
+            Pending::Sent => {
+                entry.insert(Pending::Done(result));
+            }
+            Pending::Log(..) => {
+                if let Pending::Log(mut log, write) = entry.remove() {
+                    result.apply(&mut log);
+                    write(*log);
+                }
+            }
+            Pending::Done(_) => log::warn!("duplicate mirror result: {}", request_id),
+        },
+        // 未登记的镜像请求，理论上不会执行到这里
+        Entry::Vacant(_) => log::warn!("unknown mirror request: {}", request_id),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    static WRITTEN: Mutex<Vec<RequestLog>> = Mutex::new(Vec::new());
+
+    fn write(log: RequestLog) {
+        WRITTEN.lock().unwrap().push(log);
+    }
+
+    fn log(request_id: &str) -> RequestLog {
+        RequestLog {
+            request_id: request_id.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn result(status_code: u16) -> MirrorResult {
+        MirrorResult {
+            service: "orders-v2".to_string(),
+            status_code: Some(status_code),
+            elapsed: 12,
+            error_reason: None,
+        }
+    }
+
+    fn written(request_id: &str) -> Option<RequestLog> {
+        let written = WRITTEN.lock().unwrap();
+        written
+            .iter()
+            .find(|log| log.request_id == request_id)
+            .cloned()
+    }
+
+    #[test]
+    fn test_defer_log() {
+        // 未发送镜像请求，直接记录
+        defer_log(log("r1"), write);
+        assert!(written("r1").unwrap().mirror_service.is_none());
+
+        // 镜像请求先完成
+        start("r2");
+        complete("r2", result(200));
+        assert!(written("r2").is_none());
+        defer_log(log("r2"), write);
+        assert_eq!(written("r2").unwrap().mirror_status_code, Some(200));
+
+        // 请求日志先提交
+        start("r3");
+        defer_log(log("r3"), write);
+        assert!(written("r3").is_none());
+        complete("r3", result(500));
+        let log = written("r3").unwrap();
+        assert_eq!(log.mirror_service.as_deref(), Some("orders-v2"));
+        assert_eq!(log.mirror_status_code, Some(500));
+        assert_eq!(log.mirror_elapsed, Some(12));
+        assert!(PENDING.is_empty());
+    }
+}
@@ -1,11 +1,22 @@
+mod breaker;
 mod client;
 mod config;
 mod firewall;
 mod global_filter;
+pub mod grpc;
+mod health;
 mod ip_region;
+mod ip_trie;
+pub mod jwt;
+pub mod mirror;
 mod plugins;
+pub mod proxy_protocol;
+mod real_ip;
 mod router;
 mod servicer;
+pub mod tls;
+pub mod upstream_tls;
+pub mod usage;
 
 pub use config::ConfigFactory;
 pub use firewall::Firewalld;
@@ -16,4 +27,5 @@ pub use plugins::PLUGINS;
 pub use plugins::PluginFactory;
 pub use router::ROUTER;
 pub use router::Router;
+pub use servicer::ActiveInstance;
 pub use servicer::Servicer;
@@ -0,0 +1,249 @@
+//! # PROXY协议
+//! 支持[PROXY协议](https://www.haproxy.org/download/2.9/doc/proxy-protocol.txt)的v1和v2版本，
+//! 用于在L4负载均衡器之后获取真实的客户端地址。
+//!
+//! ## 实现方式
+//! Rocket不支持自定义监听器，因此在网关的监听地址上启动一个TCP前置监听器：
+//! - 读取并移除连接开头的PROXY协议头，再将连接转发到本机回环地址上的Rocket服务。
+//! - 转发连接的本地地址与真实对端地址的映射保存在[`PEERS`]中，连接关闭时移除。
+//! - Rocket中通过[`peer_of`]获取真实的对端地址。
+//!
+//! [gRPC监听器](super::grpc)转发到Rocket的连接同样通过[`bind_peer`]记录真实的对端地址。
+//!
+//! 仅当直连的对端为受信代理时，PROXY协议头中的源地址才有效，否则使用直连的对端地址。
+//! 没有PROXY协议头的连接按普通连接处理。
+//!
+use crate::components::Firewalld;
+use anyhow::bail;
+use dashmap::DashMap;
+use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+use std::sync::LazyLock;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// v1协议头前缀
+const V1_PREFIX: &[u8] = b"PROXY ";
+/// v1协议头的最大长度，含结尾的CRLF
+const V1_MAX_LEN: usize = 107;
+/// v2协议头签名
+const V2_SIGNATURE: &[u8] = b"\r\n\r\n\0\r\nQUIT\n";
+/// 读取协议头的超时时间
+const HEADER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 转发到Rocket的连接的本地地址 -> 真实的对端地址
+static PEERS: LazyLock<DashMap<SocketAddr, SocketAddr>> = LazyLock::new(DashMap::new);
+
+/// 获取真实的对端地址，未启用PROXY协议时返回原地址
+pub fn peer_of(addr: SocketAddr) -> SocketAddr {
+    PEERS.get(&addr).map(|peer| *peer).unwrap_or(addr)
+}
+
+/// 记录转发到Rocket的连接的真实对端地址，返回值被丢弃时移除
+pub fn bind_peer(local: SocketAddr, peer: SocketAddr) -> PeerGuard {
+    PEERS.insert(local, peer);
+    PeerGuard(local)
+}
+
+/// 转发连接的对端地址记录，被丢弃时从[`PEERS`]中移除
+pub struct PeerGuard(SocketAddr);
+
+impl Drop for PeerGuard {
+    fn drop(&mut self) {
+        PEERS.remove(&self.0);
+    }
+}
+
+/// 在`listen`上启动前置监听器，连接转发到`upstream`，即Rocket监听的本机回环地址
+///
+/// Rocket监听由系统分配的端口，完成监听后再启动前置监听器，避免端口被其他进程占用。
+pub async fn serve(listen: SocketAddr, upstream: SocketAddr) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(listen).await?;
+
+    tokio::spawn(async move {
+        loop {
+            let (stream, peer) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    log::error!("proxy protocol accept error: {}", e);
+                    continue;
+                }
+            };
+            tokio::spawn(async move {
+                if let Err(e) = handle(stream, peer, upstream).await {
+                    log::debug!("proxy protocol connection from {} closed: {}", peer, e);
+                }
+            });
+        }
+    });
+
+    log::info!("proxy protocol enabled, forward {} to {}", listen, upstream);
+    Ok(())
+}
+
+async fn handle(
+    mut client: TcpStream,
+    peer: SocketAddr,
+    upstream: SocketAddr,
+) -> anyhow::Result<()> {
+    let (source, rest) = tokio::time::timeout(HEADER_TIMEOUT, read_header(&mut client)).await??;
+    let peer = match source {
+        Some(source) if Firewalld::is_trusted_proxy(peer.ip()).await => source,
+        _ => peer,
+    };
+
+    let mut server = TcpStream::connect(upstream).await?;
+    server.set_nodelay(true)?;
+    let _peer = bind_peer(server.local_addr()?, peer);
+
+    server.write_all(&rest).await?;
+    tokio::io::copy_bidirectional(&mut client, &mut server).await?;
+    Ok(())
+}
+
+/// 读取并移除PROXY协议头，返回(源地址, 协议头之后已读取的数据)
+async fn read_header(stream: &mut TcpStream) -> anyhow::Result<(Option<SocketAddr>, Vec<u8>)> {
+    let mut buf = Vec::with_capacity(256);
+    loop {
+        match parse(&buf)? {
+            Parsed::Incomplete => {
+                if stream.read_buf(&mut buf).await? == 0 {
+                    bail!("connection closed before proxy protocol header");
+                }
+            }
+            Parsed::None => return Ok((None, buf)),
+            Parsed::Header { source, len } => return Ok((source, buf.split_off(len))),
+        }
+    }
+}
+
+/// PROXY协议头的解析结果
+#[derive(Debug, PartialEq)]
+enum Parsed {
+    /// 数据不完整，需要继续读取
+    Incomplete,
+    /// 不是PROXY协议，按普通连接处理
+    None,
+    /// PROXY协议头，source为源地址，LOCAL或UNKNOWN时为空，len为协议头长度
+    Header {
+        source: Option<SocketAddr>,
+        len: usize,
+    },
+}
+
+fn parse(buf: &[u8]) -> anyhow::Result<Parsed> {
+    if buf.starts_with(V2_SIGNATURE) {
+        parse_v2(buf)
+    } else if buf.starts_with(V1_PREFIX) {
+        parse_v1(buf)
+    } else if V2_SIGNATURE.starts_with(buf) || V1_PREFIX.starts_with(buf) {
+        Ok(Parsed::Incomplete)
+    } else {
+        Ok(Parsed::None)
+    }
+}
+
+/// v1：`PROXY TCP4 192.168.0.1 192.168.0.11 56324 443\r\n`
+fn parse_v1(buf: &[u8]) -> anyhow::Result<Parsed> {
+    let Some(end) = buf
+        .windows(2)
+        .take(V1_MAX_LEN - 1)
+        .position(|w| w == b"\r\n")
+    else {
+        if buf.len() < V1_MAX_LEN {
+            return Ok(Parsed::Incomplete);
+        }
+        bail!("proxy protocol v1 header too long");
+    };
+    let line = std::str::from_utf8(&buf[..end])?;
+    let parts = line.split(' ').collect::<Vec<_>>();
+    let source = match parts.as_slice() {
+        ["PROXY", "UNKNOWN", ..] => None,
+        ["PROXY", "TCP4" | "TCP6", src, _, sport, _] => {
+            Some(SocketAddr::new(src.parse()?, sport.parse()?))
+        }
+        _ => bail!("invalid proxy protocol v1 header: {}", line),
+    };
+    Ok(Parsed::Header {
+        source,
+        len: end + 2,
+    })
+}
+
+/// v2：12字节签名 + 版本和命令 + 地址族和协议 + 2字节地址长度 + 地址
+fn parse_v2(buf: &[u8]) -> anyhow::Result<Parsed> {
+    if buf.len() < 16 {
+        return Ok(Parsed::Incomplete);
+    }
+    if buf[12] >> 4 != 2 {
+        bail!("invalid proxy protocol version: {}", buf[12] >> 4);
+    }
+    let len = 16 + u16::from_be_bytes([buf[14], buf[15]]) as usize;
+    if buf.len() < len {
+        return Ok(Parsed::Incomplete);
+    }
+    let address = &buf[16..len];
+    let source = match (buf[12] & 0x0F, buf[13] >> 4) {
+        // LOCAL命令，如负载均衡器的健康检查，使用直连的对端地址
+        (0, _) => None,
+        (1, 1) if address.len() >= 12 => {
+            let ip = <[u8; 4]>::try_from(&address[..4])?;
+            let port = u16::from_be_bytes([address[8], address[9]]);
+            Some(SocketAddr::new(IpAddr::from(ip), port))
+        }
+        (1, 2) if address.len() >= 36 => {
+            let ip = <[u8; 16]>::try_from(&address[..16])?;
+            let port = u16::from_be_bytes([address[32], address[33]]);
+            Some(SocketAddr::new(IpAddr::from(Ipv6Addr::from(ip)), port))
+        }
+        (1, _) => None,
+        (cmd, _) => bail!("invalid proxy protocol command: {}", cmd),
+    };
+    Ok(Parsed::Header { source, len })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let buf = b"PROXY TCP4 1.2.3.4 10.0.0.1 56324 80\r\nGET / HTTP/1.1\r\n";
+        assert_eq!(
+            parse(buf).unwrap(),
+            Parsed::Header {
+                source: Some("1.2.3.4:56324".parse().unwrap()),
+                len: 38,
+            }
+        );
+        assert_eq!(parse(b"PROXY TCP4 1.2.3.4").unwrap(), Parsed::Incomplete);
+        assert_eq!(parse(b"PRO").unwrap(), Parsed::Incomplete);
+        assert_eq!(parse(b"POST / HTTP/1.1").unwrap(), Parsed::None);
+        assert!(parse(b"PROXY TCP4 x\r\n").is_err());
+
+        let mut buf = V2_SIGNATURE.to_vec();
+        buf.extend_from_slice(&[
+            0x21, 0x11, 0, 12, 1, 2, 3, 4, 10, 0, 0, 1, 0x1F, 0x90, 0, 80,
+        ]);
+        assert_eq!(parse(&buf[..20]).unwrap(), Parsed::Incomplete);
+        buf.extend_from_slice(b"GET");
+        assert_eq!(
+            parse(&buf).unwrap(),
+            Parsed::Header {
+                source: Some("1.2.3.4:8080".parse().unwrap()),
+                len: 28,
+            }
+        );
+
+        // LOCAL命令
+        let mut buf = V2_SIGNATURE.to_vec();
+        buf.extend_from_slice(&[0x20, 0x00, 0, 0]);
+        assert_eq!(
+            parse(&buf).unwrap(),
+            Parsed::Header {
+                source: None,
+                len: 16
+            }
+        );
+    }
+}
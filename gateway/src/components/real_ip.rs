@@ -0,0 +1,101 @@
+//! # 真实客户端IP
+//! 网关部署在负载均衡器之后时，直连网关的对端为负载均衡器，需要从请求中提取真实的客户端IP。
+//!
+//! ## 解析规则
+//! - 对端不是受信代理时，直接使用对端IP，忽略客户端传入的`X-Forwarded-For`和`X-Real-IP`。
+//! - 对端是受信代理时，从右向左遍历`X-Forwarded-For`，跳过受信代理，第一个非受信代理的IP即为客户端IP。
+//!   如果全部为受信代理，则取最左侧的IP。
+//! - 没有`X-Forwarded-For`时，使用`X-Real-IP`。
+//! - 启用PROXY协议时，对端为PROXY协议头中的源地址，见[`proxy_protocol`](super::proxy_protocol)。
+//!
+use crate::components::ip_trie::IpTrie;
+use std::net::{IpAddr, SocketAddr};
+
+/// 解析客户端IP
+///
+/// - trusted_proxies: 受信代理
+/// - peer: 对端IP
+/// - forwarded_for: `X-Forwarded-For`的值，可能有多个
+/// - real_ip: `X-Real-IP`的值
+pub fn resolve_client_ip<'a, I: IntoIterator<Item = &'a str>>(
+    trusted_proxies: &IpTrie,
+    peer: IpAddr,
+    forwarded_for: I,
+    real_ip: Option<&str>,
+) -> IpAddr {
+    let peer = peer.to_canonical();
+    if !trusted_proxies.contains(peer) {
+        return peer;
+    }
+
+    let forwarded_for = forwarded_for
+        .into_iter()
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .collect::<Vec<_>>();
+    if forwarded_for.is_empty() {
+        return real_ip.and_then(parse_ip).unwrap_or(peer);
+    }
+
+    let mut client = peer;
+    for value in forwarded_for.into_iter().rev() {
+        // 无法解析的IP，不再继续向左查找，避免使用伪造的值
+        let Some(ip) = parse_ip(value) else {
+            break;
+        };
+        client = ip;
+        if !trusted_proxies.contains(ip) {
+            break;
+        }
+    }
+    client
+}
+
+/// 解析IP，兼容带端口的格式，如`1.2.3.4:5678`、`[::1]:8080`
+fn parse_ip(value: &str) -> Option<IpAddr> {
+    let value = value.trim();
+    value
+        .parse::<IpAddr>()
+        .or_else(|_| value.parse::<SocketAddr>().map(|addr| addr.ip()))
+        .ok()
+        .map(|ip| ip.to_canonical())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_client_ip() {
+        let trusted = IpTrie::new(&["10.0.0.0/8".to_string()]);
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+
+        // 对端不受信，忽略Header
+        let client = resolve_client_ip(&trusted, ip("1.1.1.1"), ["2.2.2.2"], Some("3.3.3.3"));
+        assert_eq!(client, ip("1.1.1.1"));
+
+        // 跳过受信代理，取第一个非受信IP
+        let client = resolve_client_ip(
+            &trusted,
+            ip("10.0.0.1"),
+            ["9.9.9.9, 2.2.2.2", "10.0.0.2"],
+            None,
+        );
+        assert_eq!(client, ip("2.2.2.2"));
+
+        // 全部为受信代理，取最左侧的IP
+        let client = resolve_client_ip(&trusted, ip("10.0.0.1"), ["10.0.0.3, 10.0.0.2"], None);
+        assert_eq!(client, ip("10.0.0.3"));
+
+        // 无法解析的IP
+        let client = resolve_client_ip(&trusted, ip("10.0.0.1"), ["2.2.2.2, unknown"], None);
+        assert_eq!(client, ip("10.0.0.1"));
+
+        // 带端口及X-Real-IP
+        let client = resolve_client_ip(&trusted, ip("10.0.0.1"), ["[2001:db8::1]:80"], None);
+        assert_eq!(client, ip("2001:db8::1"));
+        let client = resolve_client_ip(&trusted, ip("10.0.0.1"), [], Some("3.3.3.3"));
+        assert_eq!(client, ip("3.3.3.3"));
+    }
+}
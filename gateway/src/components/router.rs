@@ -8,12 +8,23 @@
 //! - 缓存路由表到内存以及本地。
 //! - 启动定时任务，每5秒从控制台拉取路由表，校验hash值，如果不一致则更新本地路由表。
 //!
+//! 路由匹配：
+//! - 路由表按优先级降序、Host精确程度、路径精确程度、条件个数降序排列，见[`RouteMatcher`]。
+//! - 按顺序依次匹配，全部条件都满足的第一个路由生效，条件不满足时继续匹配后续的路由。
+//! - 为避免每次请求遍历全部路由，按路径的第一级建立索引，只匹配可能命中的路由，匹配顺序不变。
+//!   匹配开销取决于同一第一级路径下的路由数和无法建立索引的路由数（如`/**`、正则路径），而不是路由总数。
+//!   早期版本将全部路由放在一个`matchit`路由树中，不支持优先级、正则路径和按条件兜底。
+//! - 同一路径可以有多个路由，如按请求头区分转发到不同的服务，没有条件的路由作为兜底。
+//!
 //! 路由定义：[`Route`]
 //!
 
 use crate::components::client::INNER_HTTP_CLIENT;
-use dashmap::DashMap;
-use aiway_protocol::gateway::{HttpContext, Route};
+use aiway_protocol::gateway::route::{self, PathType};
+use aiway_protocol::gateway::{HttpContext, RequestContext, Route};
+use regex::Regex;
+use std::cmp::{Ordering, Reverse};
+use std::collections::HashMap;
 use std::process::exit;
 use std::sync::{Arc, OnceLock, RwLock};
 use std::time::Duration;
@@ -22,11 +33,14 @@ pub struct Router {
     /// 路由表
     routes: Arc<RwLock<Vec<Arc<Route>>>>,
     /// 路由匹配器
-    matcher: Arc<RwLock<matchit::Router<Arc<Route>>>>,
+    matcher: Arc<RwLock<RouteMatcher>>,
 }
 
 pub static ROUTER: OnceLock<Router> = OnceLock::new();
 
+/// 匹配到的路由及路径中的参数
+type Matched = (Arc<Route>, Vec<(String, String)>);
+
 impl Router {
     pub async fn init() {
         if let Err(e) = Self::load().await {
@@ -57,30 +71,8 @@ impl Router {
         Ok(())
     }
 
-    fn build_matcher(routes: &[Arc<Route>]) -> matchit::Router<Arc<Route>> {
-        // 对routes排序，按照host长度降序、path长度降序、header个数降序、query个数降序的顺序排序
-        // 这样保证更具体的优先匹配
-        let mut routes = routes.iter().collect::<Vec<_>>();
-        routes.sort_unstable_by(|a, b| {
-            b.host
-                .len()
-                .cmp(&a.host.len())
-                .then_with(|| b.path.len().cmp(&a.path.len()))
-                .then_with(|| b.header.len().cmp(&a.header.len()))
-                .then_with(|| b.query.len().cmp(&a.query.len()))
-        });
-        // 匹配器，key为路径
-        let mut matcher = matchit::Router::new();
-
-        for route in routes {
-            // 这里理论上不会发生路径冲突，因为在控制台保存的时候已经验证了
-            // 但为了避免错误，这里这里还是输出一下日志
-            // 如果这里输出错误日志了，应该检查控制台的验证逻辑是否正确
-            if let Err(e) = matcher.insert(route.match_path.clone(), route.clone()) {
-                log::error!("build route matcher error: {}", e);
-            }
-        }
-        matcher
+    fn build_matcher(routes: &[Arc<Route>]) -> RouteMatcher {
+        RouteMatcher::new(routes)
     }
 
     async fn fetch_routes() -> anyhow::Result<Vec<Route>> {
@@ -135,23 +127,212 @@ impl Router {
         });
     }
 
-    pub fn matches(&self, context: Arc<HttpContext>) -> Option<Arc<Route>> {
-        if let Ok(router) = self.matcher.read()
-            && let Ok(result) = router.at(&context.request.get_path())
-        {
-            let route = result.value;
-            // 再依次匹配 Host/Method/Header/Query
-            if Self::match_host(route, context.request.get_host())
-                && Self::match_method(route, context.request.get_method())
-                && Self::match_host(route, &context.request.host)
-                && Self::match_header(route, &context)
-                && Self::match_query(route, &context.request.query)
-            {
-                return Some(route.clone());
+    /// 匹配路由
+    ///
+    /// 匹配成功时返回路由及路径中的通配符参数
+    pub fn matches(&self, context: Arc<HttpContext>) -> Option<Matched> {
+        self.matcher.read().ok()?.find(&context.request)
+    }
+}
+
+/// 按匹配顺序排列的路由表
+///
+/// 通配符路径的第一级为静态路径时，按第一级路径建立索引；第一级含通配符的路径和正则路径无法建立索引。
+/// 匹配时只检查与请求路径第一级相同的路由和无法建立索引的路由，两者按匹配顺序合并，
+/// 结果与按顺序遍历全部路由一致。
+struct RouteMatcher {
+    entries: Vec<MatchEntry>,
+    /// 第一级静态路径 -> 路由在`entries`中的下标，升序
+    index: HashMap<String, Vec<usize>>,
+    /// 无法建立索引的路由在`entries`中的下标，升序
+    dynamic: Vec<usize>,
+}
+
+struct MatchEntry {
+    route: Arc<Route>,
+    path: PathMatcher,
+}
+
+/// 路径匹配器
+enum PathMatcher {
+    /// 通配符路径，仅包含一个路径的`matchit`路由
+    Wildcard(matchit::Router<()>),
+    /// 正则路径
+    Regex(Regex),
+}
+
+impl PathMatcher {
+    fn new(route: &Route) -> anyhow::Result<Self> {
+        match route.path_type {
+            PathType::Wildcard => {
+                let mut router = matchit::Router::new();
+                router.insert(route.match_path.clone(), ())?;
+                Ok(PathMatcher::Wildcard(router))
+            }
+            PathType::Regex => Ok(PathMatcher::Regex(route::path_regex(&route.path)?)),
+        }
+    }
+
+    /// 匹配路径，成功时返回路径参数
+    fn matches(&self, path: &str) -> Option<Vec<(String, String)>> {
+        match self {
+            PathMatcher::Wildcard(router) => {
+                let result = router.at(path).ok()?;
+                Some(
+                    result
+                        .params
+                        .iter()
+                        .map(|(k, v)| (k.to_string(), v.to_string()))
+                        .collect(),
+                )
             }
+            PathMatcher::Regex(regex) => {
+                let captures = regex.captures(path)?;
+                Some(
+                    regex
+                        .capture_names()
+                        .enumerate()
+                        .skip(1)
+                        .filter_map(|(i, name)| {
+                            let value = captures.get(i)?.as_str().to_string();
+                            let name = name.map(str::to_string).unwrap_or(format!("p{}", i));
+                            Some((name, value))
+                        })
+                        .collect(),
+                )
+            }
+        }
+    }
+}
+
+impl RouteMatcher {
+    /// 构建路由表，无法构建路径匹配器的路由会被跳过
+    fn new(routes: &[Arc<Route>]) -> Self {
+        let mut entries = routes
+            .iter()
+            .filter_map(|route| match PathMatcher::new(route) {
+                Ok(path) => Some(MatchEntry {
+                    route: route.clone(),
+                    path,
+                }),
+                // 路径在控制台保存时已经验证，如果这里输出错误日志了，应该检查控制台的验证逻辑是否正确
+                Err(e) => {
+                    log::error!("build route {} matcher error: {}", route.name, e);
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        entries.sort_by(|a, b| Self::compare(&a.route, &b.route));
+
+        let mut index: HashMap<String, Vec<usize>> = HashMap::new();
+        let mut dynamic = vec![];
+        for (i, entry) in entries.iter().enumerate() {
+            match Self::index_key(&entry.route) {
+                Some(key) => index.entry(key.to_string()).or_default().push(i),
+                None => dynamic.push(i),
+            }
+        }
+        RouteMatcher {
+            entries,
+            index,
+            dynamic,
+        }
+    }
+
+    /// 路由的索引，即通配符路径的第一级静态路径，如`/api/*`为`api`
+    fn index_key(route: &Route) -> Option<&str> {
+        if route.path_type != PathType::Wildcard {
+            return None;
+        }
+        let segment = Self::first_segment(&route.match_path)?;
+        if segment.contains(['*', '{', '}']) {
+            return None;
+        }
+        Some(segment)
+    }
+
+    /// 路径的第一级，如`/api/users`为`api`
+    fn first_segment(path: &str) -> Option<&str> {
+        path.strip_prefix('/')?.split('/').next()
+    }
+
+    /// 路由的匹配顺序，更具体的路由排在前面
+    ///
+    /// 优先级降序 -> Host精确程度 -> 路径精确程度 -> 条件个数降序 -> 名称
+    fn compare(a: &Route, b: &Route) -> Ordering {
+        Reverse(a.priority)
+            .cmp(&Reverse(b.priority))
+            .then_with(|| Self::host_rank(&a.host).cmp(&Self::host_rank(&b.host)))
+            .then_with(|| Self::path_rank(a).cmp(&Self::path_rank(b)))
+            .then_with(|| Self::condition_rank(b).cmp(&Self::condition_rank(a)))
+            .then_with(|| a.name.cmp(&b.name))
+    }
+
+    /// Host的精确程度，越小越精确：精确域名 -> 泛域名 -> 任意域名，同类型时长度越长越精确
+    fn host_rank(host: &str) -> (u8, Reverse<usize>) {
+        let kind = if host == "*" {
+            2
+        } else if host.starts_with('*') {
+            1
+        } else {
+            0
+        };
+        (kind, Reverse(host.len()))
+    }
+
+    /// 路径的精确程度，越小越精确
+    ///
+    /// 不含`**`的通配符路径 -> 正则路径 -> 含`**`的通配符路径，正则路径之间按名称排序。
+    /// 通配符路径逐级比较，静态路径 -> `*` -> `**`，与`matchit`的匹配顺序一致，
+    /// 如`/api/users`优先于`/api/*`，`/api/*/users`优先于`/api/**`。
+    fn path_rank(route: &Route) -> (u8, Vec<u8>, Reverse<usize>) {
+        if route.path_type == PathType::Regex {
+            return (1, vec![], Reverse(0));
         }
+        let segments = route
+            .path
+            .split('/')
+            .map(|segment| match segment {
+                "**" => 2,
+                _ if segment.contains('*') => 1,
+                _ => 0,
+            })
+            .collect::<Vec<_>>();
+        let kind = if segments.contains(&2) { 2 } else { 0 };
+        let literal = route.path.chars().filter(|c| *c != '*').count();
+        (kind, segments, Reverse(literal))
+    }
 
-        None
+    /// 条件个数，请求头和请求参数条件的总数，相同时限制了请求方法的优先
+    fn condition_rank(route: &Route) -> (usize, bool) {
+        (
+            route.header.len() + route.query.len(),
+            !route.methods.is_empty(),
+        )
+    }
+
+    /// 按顺序匹配路由，返回第一个全部条件都满足的路由及路径参数
+    fn find(&self, request: &RequestContext) -> Option<Matched> {
+        let path = request.get_path();
+        let indexed = Self::first_segment(&path)
+            .and_then(|segment| self.index.get(segment))
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        merge(indexed, &self.dynamic).find_map(|i| {
+            let entry = &self.entries[i];
+            let route = &entry.route;
+            if !Self::match_host(route, request.get_host())
+                || !Self::match_method(route, request.get_method())
+            {
+                return None;
+            }
+            let params = entry.path.matches(&path)?;
+            if Self::match_header(route, request) && Self::match_query(route, request) {
+                Some((route.clone(), params))
+            } else {
+                None
+            }
+        })
     }
 
     fn match_host(route: &Route, host: &str) -> bool {
@@ -198,55 +379,178 @@ impl Router {
                 .any(|route_method| Some(route_method.as_str()) == method)
     }
 
-    fn match_header(route: &Route, context: &HttpContext) -> bool {
+    fn match_header(route: &Route, request: &RequestContext) -> bool {
         route
             .header
             .iter()
-            .all(|(key, value)| context.request.get_header(key).as_ref() == Some(value))
+            .all(|(key, condition)| condition.matches(request.get_header(key).as_deref()))
     }
 
-    fn match_query(route: &Route, query: &DashMap<String, String>) -> bool {
-        route
-            .query
-            .iter()
-            .all(|(key, value)| query.get(key).map(|v| v.value() == value).unwrap_or(false))
+    fn match_query(route: &Route, request: &RequestContext) -> bool {
+        route.query.iter().all(|(key, condition)| {
+            condition.matches(request.query.get(key).as_ref().map(|v| v.value().as_str()))
+        })
     }
 }
 
+/// 按升序合并两个升序的下标列表
+fn merge<'a>(a: &'a [usize], b: &'a [usize]) -> impl Iterator<Item = usize> + 'a {
+    let mut a = a.iter().copied().peekable();
+    let mut b = b.iter().copied().peekable();
+    std::iter::from_fn(move || match (a.peek(), b.peek()) {
+        (Some(x), Some(y)) if x <= y => a.next(),
+        (Some(_), None) => a.next(),
+        _ => b.next(),
+    })
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use aiway_protocol::gateway::route::{MatchCondition, MatchRule};
+
+    fn route(name: &str, path: &str) -> Route {
+        Route {
+            name: name.to_string(),
+            host: "*".to_string(),
+            path: path.to_string(),
+            match_path: path.replace("**", "{*p}").replace("/*", "/{p1}"),
+            ..Default::default()
+        }
+    }
+
+    fn request(path: &str, headers: &[(&str, &str)]) -> RequestContext {
+        let request = RequestContext {
+            host: "api.example.com".to_string(),
+            method: "GET".into(),
+            ..Default::default()
+        };
+        request.set_path(path);
+        for (key, value) in headers {
+            request.headers.insert(key.to_string(), value.to_string());
+        }
+        request
+    }
+
+    fn find(matcher: &RouteMatcher, path: &str, headers: &[(&str, &str)]) -> Option<String> {
+        matcher
+            .find(&request(path, headers))
+            .map(|(route, _)| route.name.clone())
+    }
+
     #[test]
     fn test_matches() {
-        let mut router = matchit::Router::new();
-        router.insert(convert("/api/b/"), 1).unwrap();
-        router.insert(convert("/api/**"), 2).unwrap();
-
-        let matched = router.at("/api/b/").unwrap();
-        assert_eq!(matched.value, &1);
-    }
-
-    fn convert(pattern: &str) -> String {
-        let mut result = String::new();
-        let mut param_count = 1;
-        let mut chars = pattern.chars().peekable();
-
-        while let Some(ch) = chars.next() {
-            if ch == '*' {
-                // Check if it's a tailing "**" capturing all remaining path
-                if chars.peek() == Some(&'*') {
-                    chars.next(); // consume the second '*'
-                    result.push_str("{*p}");
-                } else {
-                    // Single '*' - named parameter
-                    result.push_str(&format!("{{p{}}}", param_count));
-                    param_count += 1;
-                }
-            } else {
-                result.push(ch);
-            }
-        }
+        let mut gray = route("gray", "/api/users");
+        gray.header.insert(
+            "x-env".to_string(),
+            MatchCondition::Rule(MatchRule::Prefix {
+                value: "gray".to_string(),
+            }),
+        );
+        let mut regex = route("regex", "/api/v(\\d+)/(?<name>[a-z]+)");
+        regex.path_type = PathType::Regex;
+        let mut priority = route("priority", "/api/**");
+        priority.priority = 1;
+        priority.header.insert(
+            "x-debug".to_string(),
+            MatchCondition::Rule(MatchRule::Present),
+        );
+        let routes = [
+            route("all", "/api/**"),
+            route("users", "/api/users"),
+            gray,
+            route("one", "/api/*"),
+            regex,
+            priority,
+        ]
+        .into_iter()
+        .map(Arc::new)
+        .collect::<Vec<_>>();
+        let matcher = RouteMatcher::new(&routes);
+
+        // 同一路径按条件区分
+        assert_eq!(find(&matcher, "/api/users", &[]).as_deref(), Some("users"));
+        assert_eq!(
+            find(&matcher, "/api/users", &[("x-env", "gray-1")]).as_deref(),
+            Some("gray")
+        );
+        // 静态路径 -> `*` -> 正则路径 -> `**`
+        assert_eq!(find(&matcher, "/api/v1", &[]).as_deref(), Some("one"));
+        assert_eq!(find(&matcher, "/api/v1/a", &[]).as_deref(), Some("regex"));
+        assert_eq!(find(&matcher, "/api/v1/a/b", &[]).as_deref(), Some("all"));
+        // 优先级
+        assert_eq!(
+            find(&matcher, "/api/users", &[("x-debug", "1")]).as_deref(),
+            Some("priority")
+        );
+        assert_eq!(find(&matcher, "/other", &[]), None);
+
+        // 正则路径的捕获组作为路径参数
+        let (_, params) = matcher.find(&request("/api/v2/chat", &[])).unwrap();
+        assert_eq!(
+            params,
+            vec![
+                ("p1".to_string(), "2".to_string()),
+                ("name".to_string(), "chat".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_index() {
+        let mut priority = route("priority", "/*/users");
+        priority.priority = 1;
+        let mut regex = route("regex", "/v(\\d+)/chat");
+        regex.path_type = PathType::Regex;
+        let routes = [
+            route("users", "/api/users"),
+            route("orders", "/orders/*"),
+            route("all", "/**"),
+            priority,
+            regex,
+        ]
+        .into_iter()
+        .map(Arc::new)
+        .collect::<Vec<_>>();
+        let matcher = RouteMatcher::new(&routes);
+        assert_eq!(matcher.index.len(), 2);
+        assert_eq!(matcher.dynamic.len(), 3);
+
+        // 索引中的路由与无法建立索引的路由按匹配顺序合并
+        assert_eq!(
+            find(&matcher, "/api/users", &[]).as_deref(),
+            Some("priority")
+        );
+        assert_eq!(find(&matcher, "/orders/1", &[]).as_deref(), Some("orders"));
+        assert_eq!(find(&matcher, "/v1/chat", &[]).as_deref(), Some("regex"));
+        assert_eq!(find(&matcher, "/api/other", &[]).as_deref(), Some("all"));
+        assert_eq!(find(&matcher, "/unknown/a", &[]).as_deref(), Some("all"));
+
+        assert_eq!(
+            merge(&[0, 3, 4], &[1, 2, 5]).collect::<Vec<_>>(),
+            [0, 1, 2, 3, 4, 5]
+        );
+        assert_eq!(merge(&[], &[1]).collect::<Vec<_>>(), [1]);
+    }
 
-        println!("{}", result);
-        result
+    #[test]
+    fn test_fallback() {
+        let mut users = route("users", "/api/users/*");
+        users.query.insert(
+            "version".to_string(),
+            MatchCondition::Exact("2".to_string()),
+        );
+        let routes = [route("all", "/api/**"), users]
+            .into_iter()
+            .map(Arc::new)
+            .collect::<Vec<_>>();
+        let matcher = RouteMatcher::new(&routes);
+
+        let request = request("/api/users/1", &[]);
+        assert_eq!(matcher.find(&request).unwrap().0.name, "all");
+        request.query.insert("version".to_string(), "2".to_string());
+        let (route, params) = matcher.find(&request).unwrap();
+        assert_eq!(route.name, "users");
+        assert_eq!(params, vec![("p1".to_string(), "1".to_string())]);
     }
 }
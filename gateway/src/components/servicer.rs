@@ -7,19 +7,29 @@
 //! - 反序列化响应结果为[`Vec<Servicer>`]
 //! - 缓存服务列表到内存以及本地。
 //! - 启动定时任务，每5秒从控制台拉取服务列表，校验hash值，如果不一致则更新本地服务列表。
+//! - 启动健康检查任务，按服务配置的间隔主动检查节点，不健康的节点不参与负载均衡，详见[`health`](super::health)。
 //!
 //! 服务定义：[`Servicer`]
 //!
 
+use crate::components::breaker::ServiceBreaker;
 use crate::components::client::INNER_HTTP_CLIENT;
+use crate::components::health::ServiceHealth;
+use aiway_protocol::gateway;
+use aiway_protocol::gateway::service::{
+    FallbackResponse, HashKey, LbStrategy, PoolConfig, Protocol, Timeouts, UpstreamTls,
+};
+use aiway_protocol::gateway::state::NodeHealthState;
+use anyhow::bail;
 use dashmap::DashMap;
 use loadbalance::LoadBalance;
-use aiway_protocol::gateway;
-use aiway_protocol::gateway::service::LbStrategy;
+use rocket::Request;
+use rocket::request::{FromRequest, Outcome};
+use std::collections::HashMap;
 use std::process::exit;
-use std::sync::{Arc, OnceLock};
+use std::sync::{Arc, Mutex, OnceLock};
 use std::time::Duration;
-use tokio::sync::RwLock;
+use tokio::sync::{OwnedSemaphorePermit, RwLock, Semaphore};
 
 pub struct Servicer {
     services: DashMap<String, Arc<LbService>>,
@@ -43,22 +53,48 @@ impl Servicer {
         let hash = format!("{:x}", hash);
 
         SERVICES.get_or_init(|| Self {
-            services: Self::process_services(list),
+            services: Self::process_services(list, None),
             hash: Arc::new(RwLock::new(hash)),
         });
 
         Self::watch();
+        Self::watch_health();
 
         Ok(())
     }
 
-    fn process_services(list: Vec<gateway::Service>) -> DashMap<String, Arc<LbService>> {
+    /// 构建服务列表，如果提供了旧的服务列表，则保留节点的健康状态和熔断状态
+    fn process_services(
+        list: Vec<gateway::Service>,
+        old: Option<&DashMap<String, Arc<LbService>>>,
+    ) -> DashMap<String, Arc<LbService>> {
         let services = DashMap::new();
         for service in list.into_iter() {
             let lb_strategy = service.lb.clone();
+            let old_service = old.and_then(|old| old.get(&service.name).map(|s| s.value().clone()));
+            let health = ServiceHealth::new(
+                &service.name,
+                service.health_check.clone(),
+                &service.nodes,
+                service.tls.clone(),
+                old_service.as_ref().map(|s| &s.health),
+            );
+            let breaker = ServiceBreaker::new(
+                &service.name,
+                service.circuit_breaker.clone(),
+                &service.nodes,
+                old_service.as_ref().map(|s| &s.breaker),
+            );
+            let connections = connection_limits(&service, old_service.as_deref());
             services.insert(
                 service.name.clone(),
-                Arc::new(LbService::new(service, lb_strategy)),
+                Arc::new(LbService::new(
+                    service,
+                    lb_strategy,
+                    health,
+                    breaker,
+                    connections,
+                )),
             );
         }
         services
@@ -96,7 +132,7 @@ impl Servicer {
 
                 log::info!("loaded {} services", list.len());
 
-                let new_services = Self::process_services(list);
+                let new_services = Self::process_services(list, Some(&old_services.services));
                 {
                     old_services
                         .services
@@ -111,26 +147,359 @@ impl Servicer {
         });
     }
 
-    pub fn get_instance(service_id: &str) -> Option<String> {
+    const HEALTH_CHECK_TICK: Duration = Duration::from_secs(1);
+    /// 健康检查任务，每秒检查一次各服务是否到达主动检查间隔
+    fn watch_health() {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Self::HEALTH_CHECK_TICK);
+            loop {
+                interval.tick().await;
+
+                let services = SERVICES
+                    .get()
+                    .unwrap()
+                    .services
+                    .iter()
+                    .filter(|item| item.value().health.should_probe())
+                    .map(|item| item.value().clone())
+                    .collect::<Vec<_>>();
+
+                for service in services {
+                    tokio::spawn(async move {
+                        service.health.probe().await;
+                    });
+                }
+            }
+        });
+    }
+
+    /// 按负载均衡策略获取服务实例
+    ///
+    /// - hash_key: 根据服务配置的key来源获取一致性哈希的key，仅在策略为一致性哈希时调用
+    pub fn get_instance<F>(service_id: &str, hash_key: F) -> Option<String>
+    where
+        F: FnOnce(&HashKey) -> Option<String>,
+    {
         let service = SERVICES.get().unwrap().services.get(service_id);
         if let Some(service) = service {
-            return service.lb.select(&service.service.nodes);
+            let key = match service.service.lb {
+                LbStrategy::ConsistentHash => hash_key(
+                    service
+                        .service
+                        .hash_key
+                        .as_ref()
+                        .unwrap_or(&HashKey::ClientIp),
+                ),
+                _ => None,
+            };
+            return service.select(key.as_deref());
         }
         None
     }
+
+    /// 获取重试的服务实例，优先选择未尝试过的节点，所有节点都已尝试过时从全部节点中选择
+    pub fn get_retry_instance(service_id: &str, tried: &[String]) -> Option<String> {
+        let service = SERVICES.get().unwrap().services.get(service_id)?;
+        service.select_excluding(tried)
+    }
+
+    /// 获取服务的超时、连接池配置、节点协议和TLS配置
+    pub fn get_connection_config(service_id: &str) -> ConnectionConfig {
+        match SERVICES.get().unwrap().services.get(service_id) {
+            Some(service) => ConnectionConfig {
+                timeouts: service.service.timeouts.clone(),
+                pool: service.service.pool.clone(),
+                protocol: service.service.protocol,
+                tls: service.tls.clone(),
+            },
+            None => ConnectionConfig::default(),
+        }
+    }
+
+    /// 释放实例，在请求完成后调用，转发的请求由[`ActiveInstance`]释放
+    pub fn release(service_id: &str, node: &str) {
+        if let Some(service) = SERVICES.get().unwrap().services.get(service_id) {
+            service.lb.release(&node.to_string());
+        }
+    }
+
+    /// 获取节点的转发许可，节点熔断中时返回None
+    ///
+    /// 许可需通过[`BreakerPermit::report`]上报请求结果，未上报就被丢弃时归还许可。
+    pub fn acquire(service_id: &str, node: &str) -> Option<BreakerPermit> {
+        let permitted = match SERVICES.get().unwrap().services.get(service_id) {
+            Some(service) => service.breaker.acquire(node),
+            None => true,
+        };
+        permitted.then(|| BreakerPermit {
+            service: service_id.to_string(),
+            node: node.to_string(),
+            reported: false,
+        })
+    }
+
+    /// 获取节点的连接许可，未配置最大连接数时返回None
+    ///
+    /// 连接数已达上限时等待，超过`timeout`仍未获取到时返回错误。
+    pub async fn acquire_connection(
+        service_id: &str,
+        node: &str,
+        timeout: Duration,
+    ) -> anyhow::Result<Option<OwnedSemaphorePermit>> {
+        let semaphore = match SERVICES.get().unwrap().services.get(service_id) {
+            Some(service) => match service.connections.get(node) {
+                Some(semaphore) => semaphore.clone(),
+                None => return Ok(None),
+            },
+            None => return Ok(None),
+        };
+        match tokio::time::timeout(timeout, semaphore.acquire_owned()).await {
+            Ok(permit) => Ok(Some(permit?)),
+            Err(_) => bail!(
+                "connection limit of service {} node {} reached",
+                service_id,
+                node
+            ),
+        }
+    }
+
+    /// 获取服务的降级响应，所有节点都熔断时使用
+    pub fn get_fallback(service_id: &str) -> Option<FallbackResponse> {
+        let service = SERVICES.get().unwrap().services.get(service_id)?;
+        service.breaker.fallback().cloned()
+    }
+
+    /// 上报节点的请求结果，用于被动健康检查和熔断
+    ///
+    /// - success: 连接错误、超时或5xx响应为false
+    /// - elapsed: 收到响应头的耗时，单位：毫秒
+    pub fn report(service_id: &str, node: &str, success: bool, elapsed: u64) {
+        if let Some(service) = SERVICES.get().unwrap().services.get(service_id) {
+            service.health.report(node, success);
+            service.breaker.record(node, success, elapsed);
+        }
+    }
+
+    /// 获取所有服务节点的健康状态，仅包含开启了健康检查的服务
+    pub fn health_states() -> Vec<NodeHealthState> {
+        match SERVICES.get() {
+            Some(servicer) => servicer
+                .services
+                .iter()
+                .filter(|item| item.value().health.is_enabled())
+                .flat_map(|item| item.value().health.states())
+                .collect(),
+            None => vec![],
+        }
+    }
+}
+
+/// 请求正在使用的服务实例，用于最少活跃请求负载均衡
+///
+/// 保存在Rocket的请求本地缓存中，请求结束时（响应体发送完成、客户端断开或请求阶段出错）被丢弃，
+/// 此时释放实例及其连接许可。流式响应在整个响应期间都计为活跃请求。
+#[derive(Default)]
+pub struct ActiveInstance(Mutex<Option<Active>>);
+
+struct Active {
+    service_id: String,
+    node: String,
+    /// 节点的连接许可，被丢弃时释放
+    connection: Option<OwnedSemaphorePermit>,
+}
+
+impl ActiveInstance {
+    /// 获取请求的活跃实例
+    pub fn of<'r>(req: &'r Request<'_>) -> &'r ActiveInstance {
+        req.local_cache(ActiveInstance::default)
+    }
+
+    /// 设置请求使用的实例，并释放之前使用的实例，用于负载均衡选中实例后或重试切换实例时
+    pub fn set(&self, service_id: &str, node: &str) {
+        let old = self.0.lock().unwrap().replace(Active {
+            service_id: service_id.to_string(),
+            node: node.to_string(),
+            connection: None,
+        });
+        if let Some(old) = old {
+            Servicer::release(&old.service_id, &old.node);
+        }
+    }
+
+    /// 持有当前实例的连接许可，直到请求结束或切换实例
+    pub fn hold(&self, connection: OwnedSemaphorePermit) {
+        if let Some(active) = self.0.lock().unwrap().as_mut() {
+            active.connection = Some(connection);
+        }
+    }
+}
+
+impl Drop for ActiveInstance {
+    fn drop(&mut self) {
+        if let Some(active) = self.0.lock().unwrap().take() {
+            Servicer::release(&active.service_id, &active.node);
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for &'r ActiveInstance {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(ActiveInstance::of(req))
+    }
+}
+
+/// 节点的转发许可，半开状态下占用熔断器的一个名额
+///
+/// 请求体超出限制、客户端断开等情况下不会上报请求结果，丢弃许可时归还名额，
+/// 避免节点一直处于半开状态而不再参与负载均衡。
+pub struct BreakerPermit {
+    service: String,
+    node: String,
+    reported: bool,
+}
+
+impl BreakerPermit {
+    /// 上报请求结果，见[`Servicer::report`]
+    pub fn report(mut self, success: bool, elapsed: u64) {
+        self.reported = true;
+        Servicer::report(&self.service, &self.node, success, elapsed);
+    }
+}
+
+impl Drop for BreakerPermit {
+    fn drop(&mut self) {
+        if self.reported {
+            return;
+        }
+        if let Some(service) = SERVICES.get().unwrap().services.get(&self.service) {
+            service.breaker.release(&self.node);
+        }
+    }
+}
+
+/// 请求服务节点时的连接配置
+#[derive(Debug, Default)]
+pub struct ConnectionConfig {
+    pub timeouts: Option<Timeouts>,
+    pub pool: Option<PoolConfig>,
+    pub protocol: Protocol,
+    pub tls: Option<Arc<UpstreamTls>>,
+}
+
+/// 按服务的最大连接数为每个节点创建信号量，配置未变更时保留旧的信号量，避免重新计数
+fn connection_limits(
+    service: &gateway::Service,
+    old: Option<&LbService>,
+) -> HashMap<String, Arc<Semaphore>> {
+    let Some(max) = service
+        .pool
+        .as_ref()
+        .and_then(|p| p.max_connections_per_host)
+    else {
+        return HashMap::new();
+    };
+    let old = old.filter(|old| {
+        old.service
+            .pool
+            .as_ref()
+            .and_then(|p| p.max_connections_per_host)
+            == Some(max)
+    });
+    service
+        .nodes
+        .iter()
+        .map(|node| {
+            let semaphore = old
+                .and_then(|old| old.connections.get(node).cloned())
+                .unwrap_or_else(|| Arc::new(Semaphore::new(max)));
+            (node.clone(), semaphore)
+        })
+        .collect()
 }
 
 struct LbService {
     service: gateway::Service,
     lb: Box<dyn LoadBalance<String>>,
+    health: ServiceHealth,
+    breaker: ServiceBreaker,
+    /// 节点的连接数限制，key为节点地址，未配置最大连接数时为空
+    connections: HashMap<String, Arc<Semaphore>>,
+    /// 服务的TLS配置，作为构建Client的参数，避免每次请求时克隆
+    tls: Option<Arc<UpstreamTls>>,
 }
 
 impl LbService {
-    pub fn new(service: gateway::Service, strategy: LbStrategy) -> Self {
+    pub fn new(
+        service: gateway::Service,
+        strategy: LbStrategy,
+        health: ServiceHealth,
+        breaker: ServiceBreaker,
+        connections: HashMap<String, Arc<Semaphore>>,
+    ) -> Self {
+        let weights = service.weights.clone();
         let lb: Box<dyn LoadBalance<String>> = match strategy {
             LbStrategy::Random => Box::new(loadbalance::RandomLoadBalance::new()),
             LbStrategy::RoundRobin => Box::new(loadbalance::RoundRobinLoadBalance::new()),
+            LbStrategy::WeightedRoundRobin => {
+                Box::new(loadbalance::WeightedRoundRobinLoadBalance::new(weights))
+            }
+            LbStrategy::LeastActive => Box::new(loadbalance::LeastActiveLoadBalance::new(weights)),
+            LbStrategy::ConsistentHash => {
+                Box::new(loadbalance::ConsistentHashLoadBalance::new(weights))
+            }
         };
-        Self { service, lb }
+        let tls = service.tls.clone().map(Arc::new);
+        Self {
+            service,
+            lb,
+            health,
+            breaker,
+            connections,
+            tls,
+        }
+    }
+
+    /// 可用的节点，排除健康检查摘除的节点和熔断中的节点
+    fn available_nodes(&self) -> Vec<String> {
+        self.service
+            .nodes
+            .iter()
+            .filter(|node| self.health.is_available(node) && self.breaker.is_available(node))
+            .cloned()
+            .collect()
+    }
+
+    /// 选择一个节点，仅从可用的节点中选择。如果所有节点都不可用，则从全部节点中选择。
+    ///
+    /// 全部节点都熔断时，选出的节点在转发前会被熔断器拦截。
+    fn select(&self, key: Option<&str>) -> Option<String> {
+        if !self.health.is_enabled() && !self.breaker.is_enabled() {
+            return self.lb.select_with_key(&self.service.nodes, key);
+        }
+        let nodes = self.available_nodes();
+        if nodes.is_empty() {
+            log::warn!(
+                "all nodes of service {} are unavailable, fallback to all nodes",
+                self.service.name
+            );
+            return self.lb.select_with_key(&self.service.nodes, key);
+        }
+        self.lb.select_with_key(&nodes, key)
+    }
+
+    /// 选择一个未尝试过的节点，用于重试
+    fn select_excluding(&self, tried: &[String]) -> Option<String> {
+        let nodes = self
+            .available_nodes()
+            .into_iter()
+            .filter(|node| !tried.contains(node))
+            .collect::<Vec<_>>();
+        if nodes.is_empty() {
+            return self.select(None);
+        }
+        self.lb.select(&nodes)
     }
 }
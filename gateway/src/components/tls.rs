@@ -0,0 +1,372 @@
+//! # TLS
+//! 在`--tls-port`上启动TLS监听器，按客户端的SNI选择证书，终止TLS后将连接转发到本机回环地址上的Rocket服务。
+//!
+//! 实现流程：
+//! - 启动时从控制台的`GET /api/v1/gateway/certificates`端点获取证书，如果获取失败则无法启动。
+//! - 每5秒从控制台拉取证书，校验hash值，如果不一致则重新加载，之后的握手使用新证书，无需重启。
+//! - 无法加载的证书（如证书与私钥不匹配）会被跳过，并输出错误日志。
+//! - 转发连接的真实对端地址通过[`bind_peer`](super::proxy_protocol::bind_peer)记录，
+//!   Rocket中通过[`is_tls`]判断请求是否经TLS监听器收到。
+//! - 证书配置了客户端CA时，握手时请求客户端证书，验证通过的证书主题通过[`client_cert_subject`]获取，
+//!   格式为RFC 4514，如：`CN=client,O=example`。未提供证书的连接也允许建立，由路由决定是否必须。
+//!
+//! 证书的选择规则见[`Certificate`]，路由仍按请求的Host匹配。TLS监听器不解析PROXY协议头。
+//!
+use crate::components::client::INNER_HTTP_CLIENT;
+use crate::components::proxy_protocol;
+use aiway_protocol::gateway::Certificate;
+use anyhow::bail;
+use dashmap::DashMap;
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::{Acceptor, WebPkiClientVerifier};
+use rustls::sign::{CertifiedKey, SingleCertAndKey};
+use rustls::{RootCertStore, ServerConfig};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, LazyLock, RwLock};
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::LazyConfigAcceptor;
+use x509_parser::der_parser::asn1_rs::{Any, Oid, Tag, ToDer};
+use x509_parser::prelude::{FromDer, X509Certificate, X509Name};
+
+/// 已加载的证书
+static CERTS: LazyLock<RwLock<Certs>> = LazyLock::new(Default::default);
+/// 经TLS监听器转发到Rocket的连接的本地地址 -> 客户端证书的主题
+static CONNECTIONS: LazyLock<DashMap<SocketAddr, Option<String>>> = LazyLock::new(DashMap::new);
+static PROVIDER: LazyLock<Arc<CryptoProvider>> =
+    LazyLock::new(|| Arc::new(rustls::crypto::aws_lc_rs::default_provider()));
+
+const INTERVAL: Duration = Duration::from_secs(5);
+/// TLS握手的超时时间
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Default)]
+struct Certs {
+    hash: String,
+    /// 域名 -> 使用该域名证书的TLS配置，通配符域名以`*.`开头
+    configs: HashMap<String, Arc<ServerConfig>>,
+}
+
+impl Certs {
+    fn new(certificates: &[Certificate], hash: String) -> Self {
+        let mut configs = HashMap::new();
+        for certificate in certificates {
+            let config = match server_config(certificate) {
+                Ok(config) => Arc::new(config),
+                Err(e) => {
+                    log::error!("load certificate {} error: {}", certificate.name, e);
+                    continue;
+                }
+            };
+            for host in &certificate.hosts {
+                configs.insert(host.to_ascii_lowercase(), config.clone());
+            }
+        }
+        Self { hash, configs }
+    }
+
+    /// 按SNI查找证书，优先精确匹配，再匹配通配符域名
+    fn find(&self, server_name: &str) -> Option<Arc<ServerConfig>> {
+        let server_name = server_name.to_ascii_lowercase();
+        self.configs
+            .get(&server_name)
+            .or_else(|| {
+                let (_, parent) = server_name.split_once('.')?;
+                self.configs.get(&format!("*.{}", parent))
+            })
+            .cloned()
+    }
+}
+
+fn certified_key(certificate: &Certificate) -> anyhow::Result<CertifiedKey> {
+    let certs = CertificateDer::pem_slice_iter(certificate.cert.as_bytes())
+        .collect::<Result<Vec<_>, _>>()?;
+    if certs.is_empty() {
+        bail!("no certificate found");
+    }
+    let key = PrivateKeyDer::from_pem_slice(certificate.key.as_bytes())?;
+    Ok(CertifiedKey::from_der(certs, key, &PROVIDER)?)
+}
+
+/// 构建证书的TLS配置，配置了客户端CA时请求客户端证书
+fn server_config(certificate: &Certificate) -> anyhow::Result<ServerConfig> {
+    let key = Arc::new(certified_key(certificate)?);
+    let builder = ServerConfig::builder_with_provider(PROVIDER.clone())
+        .with_safe_default_protocol_versions()?;
+    let builder = match &certificate.client_ca {
+        Some(ca) => {
+            let mut roots = RootCertStore::empty();
+            for cert in CertificateDer::pem_slice_iter(ca.as_bytes()) {
+                roots.add(cert?)?;
+            }
+            // 允许不提供客户端证书，由路由决定是否必须
+            let verifier =
+                WebPkiClientVerifier::builder_with_provider(Arc::new(roots), PROVIDER.clone())
+                    .allow_unauthenticated()
+                    .build()?;
+            builder.with_client_cert_verifier(verifier)
+        }
+        None => builder.with_no_client_auth(),
+    };
+    let mut config = builder.with_cert_resolver(Arc::new(SingleCertAndKey::from(key)));
+    // 转发到Rocket的连接使用HTTP/1.1
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(config)
+}
+
+/// 请求是否经TLS监听器收到，`remote`为Rocket中请求的对端地址
+pub fn is_tls(remote: SocketAddr) -> bool {
+    CONNECTIONS.contains_key(&remote)
+}
+
+/// 请求的客户端证书主题，`remote`为Rocket中请求的对端地址，未提供客户端证书时返回None
+pub fn client_cert_subject(remote: SocketAddr) -> Option<String> {
+    CONNECTIONS.get(&remote).and_then(|subject| subject.clone())
+}
+
+/// 在`listen`上启动TLS监听器，将终止TLS后的连接转发到`upstream`
+pub async fn serve(listen: SocketAddr, upstream: SocketAddr) -> anyhow::Result<()> {
+    let certificates = INNER_HTTP_CLIENT.fetch_certificates().await?;
+    reload(&certificates)?;
+    watch();
+
+    let listener = TcpListener::bind(listen).await?;
+    tokio::spawn(async move {
+        loop {
+            let (stream, peer) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    log::error!("tls accept error: {}", e);
+                    continue;
+                }
+            };
+            tokio::spawn(async move {
+                if let Err(e) = handle(stream, peer, upstream).await {
+                    log::debug!("tls connection from {} closed: {}", peer, e);
+                }
+            });
+        }
+    });
+
+    log::info!("tls enabled, forward {} to {}", listen, upstream);
+    Ok(())
+}
+
+async fn handle(stream: TcpStream, peer: SocketAddr, upstream: SocketAddr) -> anyhow::Result<()> {
+    let mut client = tokio::time::timeout(HANDSHAKE_TIMEOUT, accept(stream)).await??;
+    let subject = client
+        .get_ref()
+        .1
+        .peer_certificates()
+        .and_then(|certs| certs.first())
+        .and_then(subject_name);
+
+    let mut server = TcpStream::connect(upstream).await?;
+    server.set_nodelay(true)?;
+    let local = server.local_addr()?;
+    let _peer = proxy_protocol::bind_peer(local, peer);
+    CONNECTIONS.insert(local, subject);
+    let result = tokio::io::copy_bidirectional(&mut client, &mut server).await;
+    CONNECTIONS.remove(&local);
+    result?;
+    Ok(())
+}
+
+/// 按SNI选择证书后完成握手
+async fn accept(stream: TcpStream) -> anyhow::Result<tokio_rustls::server::TlsStream<TcpStream>> {
+    let start = LazyConfigAcceptor::new(Acceptor::default(), stream).await?;
+    let config = match start.client_hello().server_name() {
+        Some(server_name) => match CERTS.read().unwrap().find(server_name) {
+            Some(config) => config,
+            None => bail!("no certificate for server name: {}", server_name),
+        },
+        None => bail!("tls handshake without server name"),
+    };
+    Ok(start.into_stream(config).await?)
+}
+
+/// 解析证书的主题，格式为RFC 4514
+fn subject_name(cert: &CertificateDer<'_>) -> Option<String> {
+    let (_, cert) = X509Certificate::from_der(cert.as_ref()).ok()?;
+    format_name(cert.subject())
+}
+
+/// 将Name格式化为RFC 4514字符串，RDN按逆序输出，多值RDN的属性以`+`连接
+///
+/// `X509Name`的`Display`按证书中的顺序输出，且以`, `分隔，不符合RFC 4514，因此逐个属性格式化。
+fn format_name(name: &X509Name<'_>) -> Option<String> {
+    let mut rdns = name
+        .iter_rdn()
+        .map(|rdn| {
+            rdn.iter()
+                .map(|attribute| {
+                    let name = attribute_type(attribute.attr_type());
+                    let value = attribute_value(attribute.attr_value())?;
+                    Some(format!("{}={}", name, value))
+                })
+                .collect::<Option<Vec<_>>>()
+                .map(|attributes| attributes.join("+"))
+        })
+        .collect::<Option<Vec<_>>>()?;
+    rdns.reverse();
+    Some(rdns.join(","))
+}
+
+/// 属性类型的名称，未知的类型输出为点分格式的OID
+fn attribute_type(oid: &Oid<'_>) -> String {
+    let name = match oid.as_bytes() {
+        [0x55, 0x04, 0x03] => "CN",
+        [0x55, 0x04, 0x06] => "C",
+        [0x55, 0x04, 0x07] => "L",
+        [0x55, 0x04, 0x08] => "ST",
+        [0x55, 0x04, 0x09] => "STREET",
+        [0x55, 0x04, 0x0a] => "O",
+        [0x55, 0x04, 0x0b] => "OU",
+        [0x09, 0x92, 0x26, 0x89, 0x93, 0xf2, 0x2c, 0x64, 0x01, 0x01] => "UID",
+        [0x09, 0x92, 0x26, 0x89, 0x93, 0xf2, 0x2c, 0x64, 0x01, 0x19] => "DC",
+        [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x01] => "emailAddress",
+        _ => return oid.to_id_string(),
+    };
+    name.to_string()
+}
+
+/// 属性值，字符串类型转义后输出，其他类型按RFC 4514输出为`#`加十六进制的DER编码
+fn attribute_value(value: &Any<'_>) -> Option<String> {
+    match value.tag() {
+        Tag::Utf8String | Tag::PrintableString | Tag::Ia5String => {
+            Some(escape_value(std::str::from_utf8(value.as_bytes()).ok()?))
+        }
+        Tag::BmpString => {
+            let units = value
+                .as_bytes()
+                .chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .collect::<Vec<_>>();
+            Some(escape_value(&String::from_utf16(&units).ok()?))
+        }
+        _ => Some(format!("#{}", hex(&value.to_der_vec().ok()?))),
+    }
+}
+
+/// 按RFC 4514转义属性值
+fn escape_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    let last = value.chars().count().saturating_sub(1);
+    for (i, c) in value.chars().enumerate() {
+        match c {
+            '"' | '+' | ',' | ';' | '<' | '>' | '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '#' if i == 0 => escaped.push_str("\\#"),
+            ' ' if i == 0 || i == last => escaped.push_str("\\ "),
+            '\0' => escaped.push_str("\\00"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 证书变化时重新加载，返回是否有变化
+fn reload(certificates: &[Certificate]) -> anyhow::Result<bool> {
+    let hash = format!("{:x}", md5::compute(serde_json::to_string(certificates)?));
+    if CERTS.read().unwrap().hash == hash {
+        return Ok(false);
+    }
+    let certs = Certs::new(certificates, hash);
+    log::info!(
+        "loaded {} certificates for {} hosts",
+        certificates.len(),
+        certs.configs.len()
+    );
+    *CERTS.write().unwrap() = certs;
+    Ok(true)
+}
+
+fn watch() {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(INTERVAL);
+        loop {
+            interval.tick().await;
+            let certificates = match INNER_HTTP_CLIENT.fetch_certificates().await {
+                Ok(certificates) => certificates,
+                Err(e) => {
+                    log::error!("{}", e);
+                    continue;
+                }
+            };
+            match reload(&certificates) {
+                Ok(true) => {}
+                Ok(false) => log::debug!("certificates not changed, wait next interval"),
+                Err(e) => log::error!("reload certificates error: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `openssl req -x509 -multivalue-rdn -subj "/C=CN/O=example/OU=dev+UID=42/CN=client"`
+    const CLIENT_CERT: &str = r"-----BEGIN CERTIFICATE-----
+MIIB9jCCAZ2gAwIBAgIUSMcdYxUKg9do8RbqSNEQaFmtsR8wCgYIKoZIzj0EAwIw
+UDELMAkGA1UEBhMCQ04xEDAOBgNVBAoMB2V4YW1wbGUxHjAKBgNVBAsMA2RldjAQ
+BgoJkiaJk/IsZAEBDAI0MjEPMA0GA1UEAwwGY2xpZW50MCAXDTI2MTAxODA5MTUx
+NFoYDzIxMjYwOTI0MDkxNTE0WjBQMQswCQYDVQQGEwJDTjEQMA4GA1UECgwHZXhh
+bXBsZTEeMAoGA1UECwwDZGV2MBAGCgmSJomT8ixkAQEMAjQyMQ8wDQYDVQQDDAZj
+bGllbnQwWTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAASpmPfyPqyYQc7ZB3SKzSlI
+Agw9k02f1kMwHGhDOdKLTN93eNZQ7x1x47rTz0P8sG894YgqDCm694Y0UZ0FC1vI
+o1MwUTAdBgNVHQ4EFgQUEchVV1ZxfDB68PwQ+MufXAHzcEQwHwYDVR0jBBgwFoAU
+EchVV1ZxfDB68PwQ+MufXAHzcEQwDwYDVR0TAQH/BAUwAwEB/zAKBggqhkjOPQQD
+AgNHADBEAiAfs8vXqd0VjI0sM1bQhyUk8VM7M0Njp+R3zQl9QvpEiAIgVfwFUQ5g
+fCi0Ge8wIrZ9y+SdrLt2Wk1WQM/6Cn9PIN4=
+-----END CERTIFICATE-----
+";
+
+    /// `openssl req -x509 -utf8 -subj "/CN=#a\\,b /serialNumber=123/O=测试"`
+    const ESCAPE_CERT: &str = r"-----BEGIN CERTIFICATE-----
+MIIBtDCCAVugAwIBAgIUbfW1t0lgmnFKynLse9eWdHOqmT4wCgYIKoZIzj0EAwIw
+LzEOMAwGA1UEAwwFI2EsYiAxDDAKBgNVBAUTAzEyMzEPMA0GA1UECgwG5rWL6K+V
+MCAXDTI2MTAxODA5MTUxNFoYDzIxMjYwOTI0MDkxNTE0WjAvMQ4wDAYDVQQDDAUj
+YSxiIDEMMAoGA1UEBRMDMTIzMQ8wDQYDVQQKDAbmtYvor5UwWTATBgcqhkjOPQIB
+BggqhkjOPQMBBwNCAATv1q9SadclJs4IMR97AufjipFs9vlaQ+zTFDRzBRGjqQHI
+KArUH/bEhlgfRNx0f4ymw1+ZF/UT3MN3tKULJCCho1MwUTAdBgNVHQ4EFgQU7RDq
+fWuMdWjPV6+nCmzaHi5IEwEwHwYDVR0jBBgwFoAU7RDqfWuMdWjPV6+nCmzaHi5I
+EwEwDwYDVR0TAQH/BAUwAwEB/zAKBggqhkjOPQQDAgNHADBEAiA979uXZ7+sBrq7
+dI5scfyQfpfE3P3EPK/w9FD+3z0R1AIgHZhooGEYJgUxuTavTPFf+kn/w1nMOZl+
+Qhh+Vbe/BAI=
+-----END CERTIFICATE-----
+";
+
+    fn subject(pem: &str) -> Option<String> {
+        subject_name(&CertificateDer::from_pem_slice(pem.as_bytes()).unwrap())
+    }
+
+    #[test]
+    fn test_subject_name() {
+        assert_eq!(
+            subject(CLIENT_CERT).as_deref(),
+            Some("CN=client,OU=dev+UID=42,O=example,C=CN")
+        );
+        // 需要转义的字符、未知的属性类型和非ASCII字符
+        assert_eq!(
+            subject(ESCAPE_CERT).as_deref(),
+            Some("O=测试,2.5.4.5=123,CN=\\#a\\,b\\ ")
+        );
+        assert_eq!(subject_name(&CertificateDer::from(vec![0x30, 0x03])), None);
+    }
+
+    #[test]
+    fn test_escape_value() {
+        assert_eq!(escape_value("#a b "), "\\#a b\\ ");
+        assert_eq!(escape_value("a+b;c"), "a\\+b\\;c");
+    }
+}
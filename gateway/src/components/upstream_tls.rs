@@ -0,0 +1,102 @@
+//! # 服务节点的TLS配置
+//! 请求https节点时应用服务的[`UpstreamTls`]配置，转发请求和主动健康检查共用。
+//!
+//! - CA证书：配置后仅信任这些CA验证节点证书。
+//! - 客户端证书：双向TLS时网关向节点提供的证书。
+//! - SNI：reqwest不支持单独指定SNI，因此将请求地址中的域名替换为配置的域名，
+//!   再通过自定义的DNS解析将该域名解析到原节点，SNI及证书验证均使用配置的域名。
+//!   由于解析结果与节点相关，覆盖了SNI的服务需要为每个节点单独构建Client。
+//! - 跳过验证：不验证节点证书，仅用于开发测试。
+//!
+use aiway_protocol::gateway::service::UpstreamTls;
+use anyhow::Context;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::{Certificate, ClientBuilder, Identity, Url};
+use std::sync::Arc;
+
+/// 按TLS配置设置ClientBuilder
+///
+/// - origin: 覆盖了SNI时，原节点的域名或IP，见[`override_server_name`]
+pub fn configure(
+    mut builder: ClientBuilder,
+    tls: &UpstreamTls,
+    origin: Option<&str>,
+) -> anyhow::Result<ClientBuilder> {
+    if let Some(ca) = &tls.ca {
+        let certs = Certificate::from_pem_bundle(ca.as_bytes()).context("invalid tls ca")?;
+        builder = builder.tls_certs_only(certs);
+    }
+    if let (Some(cert), Some(key)) = (&tls.cert, &tls.key) {
+        let pem = format!("{}\n{}", cert, key);
+        let identity = Identity::from_pem(pem.as_bytes()).context("invalid tls cert or key")?;
+        builder = builder.identity(identity);
+    }
+    if tls.insecure_skip_verify {
+        builder = builder.tls_danger_accept_invalid_certs(true);
+    }
+    if let Some(origin) = origin {
+        builder = builder.dns_resolver(Arc::new(OriginResolver(origin.to_string())));
+    }
+    Ok(builder)
+}
+
+/// 配置了SNI时，将https请求地址中的域名替换为配置的域名，返回原节点的域名或IP
+pub fn override_server_name(url: &mut Url, tls: &UpstreamTls) -> anyhow::Result<Option<String>> {
+    let Some(server_name) = &tls.server_name else {
+        return Ok(None);
+    };
+    if url.scheme() != "https" {
+        return Ok(None);
+    }
+    let origin = match url.host_str() {
+        Some(host) if host != server_name => host.to_string(),
+        _ => return Ok(None),
+    };
+    url.set_host(Some(server_name))
+        .with_context(|| format!("invalid tls server name: {}", server_name))?;
+    Ok(Some(origin))
+}
+
+/// 将任意域名解析到原节点
+struct OriginResolver(String);
+
+impl Resolve for OriginResolver {
+    fn resolve(&self, _name: Name) -> Resolving {
+        // IPv6地址在URL中带有方括号
+        let host = self
+            .0
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_string();
+        Box::pin(async move {
+            // 端口使用请求地址中的端口
+            let addrs = tokio::net::lookup_host((host.as_str(), 0)).await?;
+            Ok(Box::new(addrs.collect::<Vec<_>>().into_iter()) as Addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_override_server_name() {
+        let tls = UpstreamTls {
+            server_name: Some("api.internal".to_string()),
+            ..Default::default()
+        };
+        let mut url = Url::parse("https://10.0.0.1:8443/v1/chat?a=1").unwrap();
+        let origin = override_server_name(&mut url, &tls).unwrap();
+        assert_eq!(origin.as_deref(), Some("10.0.0.1"));
+        assert_eq!(url.as_str(), "https://api.internal:8443/v1/chat?a=1");
+
+        // 明文节点不覆盖
+        let mut url = Url::parse("http://10.0.0.1:8080/").unwrap();
+        assert_eq!(override_server_name(&mut url, &tls).unwrap(), None);
+
+        let mut url = Url::parse("https://[::1]:8443/").unwrap();
+        let origin = override_server_name(&mut url, &tls).unwrap();
+        assert_eq!(origin.as_deref(), Some("[::1]"));
+    }
+}
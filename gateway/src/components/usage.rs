@@ -0,0 +1,181 @@
+//! # API Key用量计量
+//! 按API Key的主体标识统计请求次数和Token数，用于月度配额和计费，见[`ApiKeyQuota`]。
+//!
+//! - 通过缓存的`increment`按天和按月分别计数，多个网关节点的用量在缓存中汇总。
+//! - 按天的计数由控制台定时同步到数据库，按月的计数用于校验配额。
+//! - 请求次数在响应时记录，被网关拒绝的请求（如鉴权失败、限流）不计量。
+//! - Token数按响应中的`usage.total_tokens`统计，SSE响应取最后一个包含该字段的事件，在流结束或客户端断开时记录。
+//! - 缓存不可用时放行请求，用量可能少记；并发请求时配额可能被少量超出。
+//!
+use aiway_protocol::gateway::ApiKeyQuota;
+use cache::caches::CacheKey;
+use chrono::Local;
+use rocket::futures::{Stream, StreamExt};
+use serde_json::Value;
+
+/// 按天计数的保留时间，单位：秒，控制台每分钟同步一次
+const DAY_TTL: i64 = 3 * 24 * 3600;
+/// 按月计数的保留时间，单位：秒
+const MONTH_TTL: i64 = 32 * 24 * 3600;
+/// SSE单行的最大长度，超出时丢弃该行
+const MAX_LINE_LEN: usize = 64 * 1024;
+const TOTAL_TOKENS: &[u8] = b"\"total_tokens\"";
+
+/// 校验月度配额，用尽时返回原因
+pub async fn check_quota(principal: &str, quota: &ApiKeyQuota) -> Result<(), String> {
+    if quota.is_unlimited() {
+        return Ok(());
+    }
+    let month = Local::now().format(ApiKeyQuota::MONTH_FORMAT).to_string();
+    let requests = match quota.requests {
+        Some(_) => get(principal, &month, ApiKeyQuota::REQUESTS).await,
+        None => 0,
+    };
+    let tokens = match quota.tokens {
+        Some(_) => get(principal, &month, ApiKeyQuota::TOKENS).await,
+        None => 0,
+    };
+    quota.check(requests, tokens)
+}
+
+async fn get(principal: &str, period: &str, metric: &str) -> u64 {
+    let key = CacheKey::Usage(
+        principal.to_string(),
+        period.to_string(),
+        metric.to_string(),
+    );
+    match cache::get::<i64>(&key.to_string()).await {
+        Ok(value) => value.unwrap_or_default().max(0) as u64,
+        Err(e) => {
+            log::warn!("get usage {} error: {}", key, e);
+            0
+        }
+    }
+}
+
+/// 记录用量，在后台执行
+pub fn record(principal: &str, requests: u64, tokens: u64) {
+    if requests == 0 && tokens == 0 {
+        return;
+    }
+    let principal = principal.to_string();
+    tokio::spawn(async move {
+        let now = Local::now();
+        let day = now.format(ApiKeyQuota::DAY_FORMAT).to_string();
+        let month = now.format(ApiKeyQuota::MONTH_FORMAT).to_string();
+        for (metric, value) in [
+            (ApiKeyQuota::REQUESTS, requests),
+            (ApiKeyQuota::TOKENS, tokens),
+        ] {
+            if value == 0 {
+                continue;
+            }
+            for (period, ttl) in [(&day, DAY_TTL), (&month, MONTH_TTL)] {
+                let key = CacheKey::Usage(principal.clone(), period.clone(), metric.to_string());
+                if let Err(e) = increment(&key.to_string(), value as i64, ttl).await {
+                    log::warn!("record usage {} error: {}", key, e);
+                }
+            }
+        }
+    });
+}
+
+async fn increment(key: &str, value: i64, ttl: i64) -> anyhow::Result<()> {
+    // 首次计数时设置过期时间
+    if cache::increment(key, value).await? == value {
+        cache::expire(key, ttl).await?;
+    }
+    Ok(())
+}
+
+/// 从JSON响应体中提取`usage.total_tokens`
+pub fn total_tokens(body: &[u8]) -> Option<u64> {
+    if !contains(body, TOTAL_TOKENS) {
+        return None;
+    }
+    let value: Value = serde_json::from_slice(body).ok()?;
+    value.get("usage")?.get("total_tokens")?.as_u64()
+}
+
+fn contains(data: &[u8], pattern: &[u8]) -> bool {
+    data.windows(pattern.len()).any(|window| window == pattern)
+}
+
+/// 计量SSE响应的Token数，流结束或被丢弃（客户端断开）时记录
+pub fn meter_stream<S, T, E>(stream: S, principal: String) -> impl Stream<Item = Result<T, E>>
+where
+    S: Stream<Item = Result<T, E>>,
+    T: AsRef<[u8]>,
+{
+    let mut meter = StreamMeter {
+        principal,
+        counter: SseTokenCounter::default(),
+    };
+    stream.map(move |item| {
+        if let Ok(chunk) = &item {
+            meter.counter.feed(chunk.as_ref());
+        }
+        item
+    })
+}
+
+struct StreamMeter {
+    principal: String,
+    counter: SseTokenCounter,
+}
+
+impl Drop for StreamMeter {
+    fn drop(&mut self) {
+        record(&self.principal, 0, self.counter.total);
+    }
+}
+
+/// 从SSE流中提取Token数，取最后一个包含`usage.total_tokens`的`data`事件
+#[derive(Default)]
+struct SseTokenCounter {
+    /// 未读取完的行
+    line: Vec<u8>,
+    total: u64,
+}
+
+impl SseTokenCounter {
+    fn feed(&mut self, chunk: &[u8]) {
+        self.line.extend_from_slice(chunk);
+        while let Some(pos) = self.line.iter().position(|b| *b == b'\n') {
+            let line = self.line.drain(..=pos).collect::<Vec<_>>();
+            if let Some(data) = line.trim_ascii().strip_prefix(b"data:")
+                && let Some(total) = total_tokens(data.trim_ascii())
+            {
+                self.total = total;
+            }
+        }
+        if self.line.len() > MAX_LINE_LEN {
+            self.line.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_total_tokens() {
+        let body =
+            br#"{"id":"1","usage":{"prompt_tokens":5,"completion_tokens":7,"total_tokens":12}}"#;
+        assert_eq!(total_tokens(body), Some(12));
+        assert_eq!(total_tokens(br#"{"id":"1"}"#), None);
+        assert_eq!(total_tokens(b"\"total_tokens\" not json"), None);
+    }
+
+    #[test]
+    fn test_sse_token_counter() {
+        let mut counter = SseTokenCounter::default();
+        counter.feed(b"data: {\"choices\":[{\"delta\":{\"content\":\"hi\"}}],\"usage\":null}\n\n");
+        assert_eq!(counter.total, 0);
+        // 事件被拆分到多个数据块中
+        counter.feed(b"data: {\"choices\":[],\"usage\":{\"total_");
+        counter.feed(b"tokens\":42}}\n\ndata: [DONE]\n\n");
+        assert_eq!(counter.total, 42);
+    }
+}
@@ -1,15 +1,28 @@
 //! # 鉴权
 //! ## 主要功能
-//! 从请求中提取ApiKey并验证，验证不通过则返回403。
+//! 从请求中提取ApiKey并验证，验证不通过则返回401。
+//! API Key按密钥环解密，密钥轮换后旧密钥在宽限期内依然可用。
+//! API Key的状态和有效期随缓存中的值一起校验，禁用、未生效或已过期的API Key验证失败，见[`ApiKeyEntry`]。
+//! 请求超出API Key的授权范围（路由、服务、请求方法和模型）时返回403，见[`ApiKeyScope`]。
+//! API Key的月度配额用尽时返回402，见[`usage`]。
+//!
+//! 路由的鉴权方式为JWT时，从`Authorization: Bearer <JWT>`中提取JWT，按路由引用的签发方验证，
+//! 验证不通过则返回401。验证通过后按路由配置将声明映射到请求头和state，见[`JwtAuth`]。
+//!
+//! 路由要求客户端证书时，请求必须经TLS监听器收到且提供了验证通过的客户端证书，否则返回401。
+//! 证书主体保存到请求上下文的state中，同时作为主体标识，开启API Key鉴权时被API Key的principal覆盖。
 //!
 //! 考虑是调用另外的服务验证，还是对API Key解密验证?
 //!
-use crate::components::Firewalld;
-use aiway_protocol::gateway::ApiKey;
+use crate::components::{Firewalld, jwt, tls, usage};
+use aiway_protocol::gateway::jwt::{claim_to_string, find_claim};
+use aiway_protocol::gateway::route::{JwtAuth, RouteAuth};
+use aiway_protocol::gateway::{ApiKey, ApiKeyEntry, ApiKeyScope, RequestContext, Route};
 use cache::caches::CacheKey;
 use context::{HCM, Headers, set_error, skip_if_error};
 use rocket::fairing::Fairing;
 use rocket::{Data, Request};
+use serde_json::Value;
 
 pub struct Authentication {}
 impl Authentication {
@@ -37,6 +50,22 @@ impl Fairing for Authentication {
         // SAFE: 此时路由一定存在
         let route = ctx.request.get_route().unwrap();
 
+        // 客户端证书
+        if route.client_cert {
+            let subject = match req.remote().and_then(tls::client_cert_subject) {
+                Some(subject) => subject,
+                None => {
+                    log::debug!("路由 {} 要求客户端证书，请求未提供", route.name);
+                    set_error!(req, 401, "Unauthorized");
+                    return;
+                }
+            };
+            ctx.request
+                .insert_state(RequestContext::STATE_CLIENT_CERT_SUBJECT, subject.clone());
+            ctx.request
+                .insert_state(RequestContext::STATE_PRINCIPAL, subject);
+        }
+
         // 未开启权限验证的不用校验
         if !route.is_auth {
             log::debug!("路由 {} 未开启权限验证，无需鉴权", route.name);
@@ -54,9 +83,9 @@ impl Fairing for Authentication {
 
         let bearer_token = req.headers().get_one(Headers::AUTHORIZATION);
 
-        let api_key = match bearer_token {
-            Some(api_key) => match api_key.strip_prefix(BEARER_PREFIX) {
-                Some(api_key) => api_key,
+        let token = match bearer_token {
+            Some(token) => match token.strip_prefix(BEARER_PREFIX) {
+                Some(token) => token,
                 None => {
                     set_error!(req, 401, "Unauthorized");
                     return;
@@ -68,18 +97,123 @@ impl Fairing for Authentication {
             }
         };
 
-        let decrypt_key = &Firewalld::get_api_secret_encrypt_key().await;
-        if ApiKey::decrypt(decrypt_key, api_key).is_err() {
+        let principal = match &route.auth {
+            RouteAuth::ApiKey => {
+                let Some((principal, entry)) = Self::verify_api_key(token).await else {
+                    set_error!(req, 401, "Unauthorized");
+                    return;
+                };
+                if let Err(e) = Self::check_scope(&ctx.request, route, &entry.scope) {
+                    log::debug!("API Key超出授权范围：{}", e);
+                    set_error!(req, 403, "Forbidden");
+                    return;
+                }
+                // 未设置主体标识的API Key不计量
+                if !principal.is_empty() {
+                    if let Err(e) = usage::check_quota(&principal, &entry.quota).await {
+                        log::debug!("API Key配额已用尽：{}，principal：{}", e, principal);
+                        set_error!(req, 402, "Payment Required");
+                        return;
+                    }
+                    ctx.request
+                        .insert_state(RequestContext::STATE_USAGE_PRINCIPAL, principal.clone());
+                }
+                Some(principal)
+            }
+            RouteAuth::Jwt(auth) => Self::verify_jwt(&ctx.request, auth, token),
+        };
+        let Some(principal) = principal else {
             set_error!(req, 401, "Unauthorized");
             return;
+        };
+
+        // 保存主体标识，用于限流等
+        ctx.request
+            .insert_state(RequestContext::STATE_PRINCIPAL, principal);
+    }
+}
+
+impl Authentication {
+    /// 验证API Key，返回API Key的principal和授权范围
+    async fn verify_api_key(api_key: &str) -> Option<(String, ApiKeyEntry)> {
+        // 按密钥环解密，密钥轮换后，旧密钥签发的API Key在宽限期内依然有效
+        let keys = Firewalld::get_api_secret_keys().await;
+        let principal =
+            ApiKey::decrypt_with_keyring(keys.iter().map(|(id, key)| (*id, key)), api_key)
+                .ok()?
+                .principal;
+
+        // 缓存中不存在时验证失败，值为空时永久有效且不限制授权范围
+        let entry =
+            cache::get::<Option<ApiKeyEntry>>(&CacheKey::ApiKey(api_key.to_string()).to_string())
+                .await
+                .ok()??
+                .unwrap_or_default();
+        if let Err(e) = entry.check_valid(chrono::Local::now().timestamp()) {
+            log::debug!("API Key不可用：{}，principal：{}", e, principal);
+            return None;
         }
+        Some((principal, entry))
+    }
 
-        let exists = cache::exists(&CacheKey::ApiKey(api_key.to_string()).to_string())
-            .await
-            .unwrap_or(false);
-        if !exists {
-            set_error!(req, 401, "Unauthorized");
-            return;
+    /// 校验请求是否在API Key的授权范围内
+    fn check_scope(
+        request: &RequestContext,
+        route: &Route,
+        scope: &ApiKeyScope,
+    ) -> Result<(), String> {
+        if scope.is_unrestricted() {
+            return Ok(());
+        }
+        // 网关直接响应的路由不转发到服务
+        let services = match (route.action.is_proxy(), route.backends.is_empty()) {
+            (false, _) => vec![],
+            (true, true) => vec![route.service.as_str()],
+            (true, false) => route.backends.iter().map(|b| b.service.as_str()).collect(),
+        };
+        // 限制了模型时才解析请求体，流式转发请求体时没有请求体，按无法确定模型拒绝
+        let model = match scope.models.is_empty() {
+            true => None,
+            false => request
+                .get_body()
+                .and_then(|body| serde_json::from_slice::<Value>(body).ok())
+                .and_then(|body| body.get("model")?.as_str().map(String::from)),
+        };
+        scope.check(
+            &route.name,
+            services,
+            request.get_method().unwrap_or_default(),
+            model.as_deref(),
+        )
+    }
+
+    /// 验证JWT，将声明映射到请求头和state，返回主体标识
+    fn verify_jwt(request: &RequestContext, auth: &JwtAuth, token: &str) -> Option<String> {
+        let claims = match jwt::verify(&auth.provider, token) {
+            Ok(claims) => claims,
+            Err(e) => {
+                log::debug!("JWT验证失败，签发方：{}，原因：{}", auth.provider, e);
+                return None;
+            }
+        };
+
+        for (header, claim) in &auth.headers {
+            let header = header.to_lowercase();
+            match find_claim(&claims, claim) {
+                Some(value) => request.insert_header(&header, &claim_to_string(value)),
+                // 不存在的声明移除对应的请求头，避免客户端伪造
+                None => request.remove_header(&header),
+            }
+        }
+        for (key, claim) in &auth.state {
+            if let Some(value) = find_claim(&claims, claim) {
+                request.insert_state(key, value);
+            }
         }
+        let principal = find_claim(&claims, &auth.principal_claim).map(claim_to_string);
+        request.insert_state(RequestContext::STATE_JWT_CLAIMS, claims);
+
+        // JWT中没有主体标识时鉴权失败
+        principal.filter(|principal| !principal.is_empty())
     }
 }
@@ -0,0 +1,47 @@
+//! # 提取请求体
+//! ## 主要功能
+//! 按路由配置将请求体读取到上下文中，供插件和转发使用。
+//!
+//! ## 基本准则
+//! - 在路由匹配后、全局过滤器前执行，因为是否读取请求体取决于路由及插件配置。
+//! - 流式转发请求体时不读取，由转发端点直接从客户端连接读取并发送到服务，见[`Route::is_stream_body`]。
+//! - gRPC调用可能是客户端流或双向流，没有需要读取请求体的插件时总是流式转发，见[`grpc`](crate::components::grpc)。
+//!
+//! [`Route::is_stream_body`]: aiway_protocol::gateway::Route::is_stream_body
+//!
+use crate::components::GLOBAL_FILTER;
+use context::{HCM, Headers, skip_if_error};
+use rocket::fairing::Fairing;
+use rocket::{Data, Request};
+
+pub struct RequestBody {}
+impl RequestBody {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[rocket::async_trait]
+impl Fairing for RequestBody {
+    fn info(&self) -> rocket::fairing::Info {
+        rocket::fairing::Info {
+            name: "RequestBody",
+            kind: rocket::fairing::Kind::Request,
+        }
+    }
+
+    async fn on_request(&self, req: &mut Request<'_>, data: &mut Data<'_>) {
+        skip_if_error!(req);
+        let context = HCM.get_from_request(req);
+        // SAFE: 此时路由一定存在
+        let route = context.request.get_route().unwrap();
+        let global_filter = GLOBAL_FILTER.get().unwrap().config.read().await;
+        if route.is_stream_body(req.method().as_str(), &global_filter) {
+            return;
+        }
+        if req.headers().contains(Headers::GRPC_CALL) && !route.inspects_body(&global_filter) {
+            return;
+        }
+        context::take_body(&context, data).await;
+    }
+}
@@ -2,8 +2,10 @@
 //! - 在response fairing之前执行
 //! - 只会处理网关内部异常，下游服务的异常不会被处理，而是透传
 
+use crate::fairing::security::RetryAfter;
+use context::{HCM, Headers, extract_error};
 use rocket::Request;
-use context::extract_error;
+use rocket::response::Responder;
 
 #[rocket::catch(401)]
 pub fn catch_401(req: &Request) -> String {
@@ -13,6 +15,14 @@ pub fn catch_401(req: &Request) -> String {
     "401 Unauthorized".to_string()
 }
 
+#[rocket::catch(402)]
+pub fn catch_402(req: &Request) -> String {
+    if let Some((_, message)) = extract_error!(req) {
+        return message.to_string();
+    }
+    "402 Payment Required".to_string()
+}
+
 #[rocket::catch(403)]
 pub fn catch_403(req: &Request) -> String {
     if let Some((_, message)) = extract_error!(req) {
@@ -28,3 +38,58 @@ pub fn catch_404(req: &Request) -> String {
     }
     "404 NotFound".to_string()
 }
+
+#[rocket::catch(429)]
+pub fn catch_429(req: &Request) -> HeaderResponse {
+    let message = match extract_error!(req) {
+        Some((_, message)) => message.to_string(),
+        None => "429 Too Many Requests".to_string(),
+    };
+    // 限流fairing将限流相关的响应头设置在响应上下文中
+    let headers = match req.headers().get_one(Headers::REQUEST_ID) {
+        Some(_) => {
+            let response = &HCM.get_from_request(req).response;
+            [
+                Headers::RATELIMIT_LIMIT,
+                Headers::RATELIMIT_REMAINING,
+                Headers::RATELIMIT_RESET,
+                Headers::RETRY_AFTER,
+            ]
+            .into_iter()
+            .filter_map(|name| response.get_header(name).map(|value| (name, value)))
+            .collect()
+        }
+        None => vec![],
+    };
+    HeaderResponse { message, headers }
+}
+
+#[rocket::catch(503)]
+pub fn catch_503(req: &Request) -> HeaderResponse {
+    let message = match extract_error!(req) {
+        Some((_, message)) => message.to_string(),
+        None => "503 Service Unavailable".to_string(),
+    };
+    // 仅网关节点连接数已满时，安全校验fairing在请求的本地缓存中设置重试时间
+    let headers = match req.local_cache(RetryAfter::default).0 {
+        Some(seconds) => vec![(Headers::RETRY_AFTER, seconds.to_string())],
+        None => vec![],
+    };
+    HeaderResponse { message, headers }
+}
+
+/// 附带响应头的错误响应，状态码由catcher设置
+pub struct HeaderResponse {
+    message: String,
+    headers: Vec<(&'static str, String)>,
+}
+
+impl<'r> Responder<'r, 'static> for HeaderResponse {
+    fn respond_to(self, req: &'r Request<'_>) -> rocket::response::Result<'static> {
+        let mut response = rocket::response::Response::build_from(self.message.respond_to(req)?);
+        for (name, value) in self.headers {
+            response.raw_header(name, value);
+        }
+        response.ok()
+    }
+}
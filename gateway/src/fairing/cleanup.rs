@@ -31,6 +31,7 @@ impl Fairing for Cleaner {
         // 移除掉仅网关内部使用的Header
         res.remove_header(Headers::ERROR_CODE);
         res.remove_header(Headers::ERROR_MESSAGE);
+        res.remove_header(Headers::ERROR_REASON);
 
         // 连接数减1
         STATE.inc_http_connect_count(-1);
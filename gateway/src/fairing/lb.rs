@@ -1,6 +1,7 @@
 //! # 负载均衡
 //!
-use crate::components::Servicer;
+use crate::components::{ActiveInstance, Servicer};
+use aiway_protocol::gateway::service::HashKey;
 use rocket::fairing::Fairing;
 use rocket::{Data, Request};
 use context::{set_error, skip_if_error, HCM};
@@ -30,21 +31,45 @@ impl Fairing for LoadBalance {
         }
 
         let route = route.unwrap();
-        let service = route.get_service();
-        if service.is_empty() {
-            // 没有匹配到service或service为空，修改uri，转发到502端点
-            log::warn!("No valid service matched for route path: {}", route.path);
-        } else {
-            match Servicer::get_instance(service) {
-                Some(instance) if !instance.is_empty() => {
-                    // 设置最终需要转发的URL
-                    context.request.set_routing_url(instance);
-                    return;
-                }
-                _ => {
-                    log::warn!("No available instance for service: {}", service);
+        // 非转发的路由由网关直接响应，不需要选择服务
+        if !route.action.is_proxy() {
+            return;
+        }
+
+        // 路由配置了加权后端时，按灰度条件和权重选择服务
+        let service = route.select_backend(
+            |name| context.request.get_header(name),
+            |name| context.request.get_cookie(name),
+            fastrand::u64(..),
+        );
+        match service {
+            Some((service, _)) if service.is_empty() => {
+                // 没有匹配到service或service为空，修改uri，转发到502端点
+                log::warn!("No valid service matched for route path: {}", route.path);
+            }
+            Some((service, split)) => {
+                context.request.set_service(service.to_string(), split);
+                let hash_key = |key: &HashKey| match key {
+                    HashKey::ClientIp => Some(context.request.get_client_ip().to_string()),
+                    HashKey::Header(name) => context.request.get_header(&name.to_lowercase()),
+                    HashKey::Query(name) => context.request.get_query(name),
+                };
+                match Servicer::get_instance(service, hash_key) {
+                    Some(instance) if !instance.is_empty() => {
+                        // 请求结束时释放实例，包括后续的fairing中出错的情况
+                        ActiveInstance::of(req).set(service, &instance);
+                        // 设置最终需要转发的URL
+                        context.request.set_routing_url(instance);
+                        return;
+                    }
+                    _ => {
+                        log::warn!("No available instance for service: {}", service);
+                    }
                 }
             }
+            None => {
+                log::warn!("No backend with weight for route: {}", route.name);
+            }
         }
 
         set_error!(req, 502, "BadGateway");
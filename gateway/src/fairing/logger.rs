@@ -11,11 +11,13 @@
 
 use crate::Args;
 use crate::components::IpRegion;
+use crate::components::grpc;
+use crate::components::mirror;
 use clap::Parser;
 use aiway_protocol::gateway::request_log::RequestLog;
 use rocket::Request;
 use rocket::fairing::Fairing;
-use context::Headers;
+use context::{HCM, Headers};
 
 pub struct Logger {
     args: Args,
@@ -47,7 +49,7 @@ impl Fairing for Logger {
         // let req_cxt = &context.request;
         // let res_cxt = &context.response;
 
-        let client_ip = req.client_ip().unwrap().to_string();
+        let client_ip = Headers::get_client_ip(req).unwrap_or_default();
 
         // 请求ID
         let request_id = req.headers().get_one(Headers::REQUEST_ID).unwrap();
@@ -66,6 +68,18 @@ impl Fairing for Logger {
         // 地理位置
         let region = IpRegion::search(&client_ip);
 
+        // gRPC响应的状态码映射为HTTP状态码，状态码在trailers中时，在响应结束后记录日志
+        let status_code = grpc::response_status(
+            request_id,
+            res.status().code,
+            res.headers().get_one(Headers::CONTENT_TYPE),
+            res.headers().get_one(Headers::GRPC_STATUS),
+        );
+
+        // 转发的服务及加权后端的选择方式，被安全校验拒绝的请求没有上下文
+        let context = HCM.find(request_id);
+        let request = context.as_ref().map(|context| &context.request);
+
         let request_log = RequestLog {
             request_id: request_id.to_string(),
             client_ip: client_ip.to_string(),
@@ -77,7 +91,7 @@ impl Fairing for Logger {
             request_time,
             response_time,
             elapsed: response_time - request_time,
-            status_code: res.status().code,
+            status_code: status_code.unwrap_or(res.status().code),
             response_size: res.body().preset_size(),
             user_agent: req
                 .headers()
@@ -88,12 +102,30 @@ impl Fairing for Logger {
                 .get_one(Headers::REFERER)
                 .map(|s| s.to_string()),
             node_address: format!("{}:{}", self.args.address, self.args.port),
+            error_reason: res
+                .headers()
+                .get_one(Headers::ERROR_REASON)
+                .map(|s| s.to_string()),
+            service: request.and_then(|r| r.get_service()).cloned(),
+            split: request
+                .and_then(|r| r.get_split())
+                .map(|split| split.as_str().to_string()),
         };
 
-        match serde_json::to_vec(&request_log) {
-            Ok(value) => logging::log_request(value),
-            Err(e) => log::error!("Failed to serialize RequestLog to JSON: {}", e),
-        }
+        // 转发到gRPC服务且状态码在trailers中时，在响应结束后记录
+        let write: fn(RequestLog) = match status_code {
+            Some(_) => write_log,
+            None => grpc::defer_log,
+        };
+        // 发送了镜像请求时，在镜像请求完成后合并镜像结果再记录
+        mirror::defer_log(request_log, write);
+    }
+}
+
+fn write_log(request_log: RequestLog) {
+    match serde_json::to_vec(&request_log) {
+        Ok(value) => logging::log_request(value),
+        Err(e) => log::error!("Failed to serialize RequestLog to JSON: {}", e),
     }
 }
 
@@ -21,6 +21,7 @@
 //! 则执行顺序为：A(Req) -> B(Req) -> A(Res) -> C(Res)
 //!
 pub mod auth;
+pub mod body;
 pub mod catchers;
 pub mod cleanup;
 pub mod filter;
@@ -28,6 +29,7 @@ pub mod global_filter;
 pub mod lb;
 pub mod logger;
 pub mod pre;
+pub mod ratelimit;
 pub mod request;
 pub mod response;
 pub mod routing;
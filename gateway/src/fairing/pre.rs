@@ -1,5 +1,6 @@
 //! # 预处理
 //!
+use crate::components::{Firewalld, proxy_protocol, tls};
 use crate::report::STATE;
 use rocket::fairing::Fairing;
 use rocket::http::Header;
@@ -38,6 +39,30 @@ impl Fairing for Pre {
             Headers::REQUEST_TIME,
             chrono::Local::now().timestamp_millis().to_string(),
         ));
+
+        // 解析真实的客户端IP，覆盖客户端传入的同名Header，后续统一通过该Header获取
+        if let Some(remote) = req.remote() {
+            let peer = proxy_protocol::peer_of(remote).ip().to_canonical();
+            let headers = req.headers();
+            let client_ip = Firewalld::resolve_client_ip(
+                peer,
+                headers.get(Headers::X_FORWARDED_FOR),
+                headers.get_one(Headers::X_REAL_IP),
+            )
+            .await;
+            req.replace_header(Header::new(Headers::CLIENT_IP, client_ip.to_string()));
+            req.replace_header(Header::new(Headers::PEER_IP, peer.to_string()));
+        } else {
+            req.remove_header(Headers::CLIENT_IP);
+            req.remove_header(Headers::PEER_IP);
+        }
+
+        // 请求协议，覆盖客户端传入的同名Header
+        let scheme = match req.remote() {
+            Some(remote) if tls::is_tls(remote) => "https",
+            _ => "http",
+        };
+        req.replace_header(Header::new(Headers::SCHEME, scheme));
     }
 
     async fn on_response<'r>(&self, _req: &'r Request<'_>, res: &mut rocket::Response<'r>) {
@@ -0,0 +1,143 @@
+//! # 限流
+//! ## 主要功能
+//! 按防火墙中的全局限流和路由上的限流配置限制请求频率，超出限制时返回429。
+//!
+//! ## 基本准则
+//! - 在鉴权之后执行，以便按API Key的主体标识限流。
+//! - 先检查全局限流，再检查路由限流，任一超出即拒绝。
+//! - 限流key取不到时不限流，如未开启鉴权的路由按API Key限流。
+//! - 缓存不可用时放行请求，避免缓存故障导致网关不可用。
+//!
+//! ## 响应头
+//! - `X-RateLimit-Limit`：周期内允许的请求数
+//! - `X-RateLimit-Remaining`：剩余可用请求数
+//! - `X-RateLimit-Reset`：配额重置的剩余时间，单位：秒
+//! - `Retry-After`：仅在返回429时设置
+//!
+//! 同时配置了全局和路由限流时，响应头取剩余请求数较少的一个。
+//!
+use crate::components::Firewalld;
+use crate::report::STATE;
+use aiway_protocol::gateway::RequestContext;
+use aiway_protocol::gateway::rate_limit::{RateLimit, RateLimitAlgorithm, RateLimitKey};
+use cache::RateLimitResult;
+use cache::caches::CacheKey;
+use context::{HCM, Headers, set_error, skip_if_error};
+use rocket::fairing::Fairing;
+use rocket::{Data, Request};
+
+pub struct RateLimiter {}
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[rocket::async_trait]
+impl Fairing for RateLimiter {
+    fn info(&self) -> rocket::fairing::Info {
+        rocket::fairing::Info {
+            name: "RateLimiter",
+            kind: rocket::fairing::Kind::Request,
+        }
+    }
+
+    async fn on_request(&self, req: &mut Request<'_>, _data: &mut Data<'_>) {
+        skip_if_error!(req);
+
+        let ctx = HCM.get_from_request(req);
+        // SAFE: 此时路由一定存在
+        let route = ctx.request.get_route().unwrap().clone();
+        let global = Firewalld::get_rate_limit().await;
+
+        let limits = [
+            ("global".to_string(), global.as_ref()),
+            (
+                format!("route:{}{}", route.host, route.path),
+                route.rate_limit.as_ref(),
+            ),
+        ];
+
+        // 剩余请求数最少的限流结果，用于设置响应头
+        let mut current: Option<(u64, RateLimitResult)> = None;
+        for (scope, rate_limit) in limits {
+            let Some(rate_limit) = rate_limit else {
+                continue;
+            };
+            let Some(key) = extract_key(req, &ctx.request, &rate_limit.key) else {
+                continue;
+            };
+            let key = CacheKey::RateLimit(scope, key).to_string();
+            let Some(result) = check(rate_limit, &key).await else {
+                continue;
+            };
+            if current.is_none_or(|(_, c)| !result.allowed || result.remaining < c.remaining) {
+                current = Some((limit_of(rate_limit), result));
+            }
+            if !result.allowed {
+                break;
+            }
+        }
+
+        let Some((limit, result)) = current else {
+            return;
+        };
+        let response = &ctx.response;
+        response.insert_header(Headers::RATELIMIT_LIMIT, &limit.to_string());
+        response.insert_header(Headers::RATELIMIT_REMAINING, &result.remaining.to_string());
+        response.insert_header(Headers::RATELIMIT_RESET, &result.reset.to_string());
+
+        if !result.allowed {
+            response.insert_header(Headers::RETRY_AFTER, &result.retry_after.max(1).to_string());
+            STATE.inc_request_ratelimit_count(1);
+            set_error!(req, 429, "Too Many Requests");
+        }
+    }
+}
+
+/// 提取限流key
+fn extract_key(req: &Request<'_>, ctx: &RequestContext, key: &RateLimitKey) -> Option<String> {
+    match key {
+        RateLimitKey::ApiKey => ctx
+            .get_state::<String>(RequestContext::STATE_PRINCIPAL)
+            .ok()
+            .flatten(),
+        RateLimitKey::ClientIp => Some(ctx.get_client_ip().to_string()),
+        RateLimitKey::Header(name) => req
+            .headers()
+            .get_one(&name.to_lowercase())
+            .map(|s| s.to_string()),
+    }
+}
+
+/// 周期内允许的请求数，令牌桶为桶容量
+fn limit_of(rate_limit: &RateLimit) -> u64 {
+    match rate_limit.algorithm {
+        RateLimitAlgorithm::FixedWindow => rate_limit.limit,
+        RateLimitAlgorithm::TokenBucket => rate_limit.burst.unwrap_or(rate_limit.limit),
+    }
+}
+
+async fn check(rate_limit: &RateLimit, key: &str) -> Option<RateLimitResult> {
+    let result = match rate_limit.algorithm {
+        RateLimitAlgorithm::FixedWindow => {
+            cache::fixed_window(key, rate_limit.limit, rate_limit.window_secs).await
+        }
+        RateLimitAlgorithm::TokenBucket => {
+            cache::token_bucket(
+                key,
+                limit_of(rate_limit),
+                rate_limit.limit,
+                rate_limit.window_secs,
+            )
+            .await
+        }
+    };
+    match result {
+        Ok(result) => Some(result),
+        Err(e) => {
+            log::warn!("rate limit {} failed: {}", key, e);
+            None
+        }
+    }
+}
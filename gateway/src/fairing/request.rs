@@ -1,6 +1,7 @@
 //! # 提取请求上下文
 //! ## 主要功能
-//! 从请求中提出可序列化的请求上下文，包括请求基本信息、body等数据。
+//! 从请求中提出可序列化的请求上下文，包括请求基本信息等数据。
+//! 请求体在路由匹配后提取，见[`RequestBody`](super::body::RequestBody)。
 //!
 //! ## 基本准则
 //! - 在鉴权通过后执行。
@@ -5,8 +5,11 @@
 //! ## 基本准则
 //! - 该fairing必须执行
 //! - 使用覆盖模式，即上下文中的响应数据优先覆盖原始响应中的数据。这是因为，上下文中的数据可能是由插件修改而来，应该优先被设置。
+//! - API Key需要计量时，在此记录请求次数和Token数，见[`usage`]。
 //!
+use crate::components::{grpc, usage};
 use crate::report::STATE;
+use aiway_protocol::gateway::RequestContext;
 use context::{HCM, Headers, skip_if_error};
 use rocket::Request;
 use rocket::fairing::Fairing;
@@ -46,6 +49,19 @@ impl Fairing for ResponseData {
             res.set_header(Header::new(header.key().clone(), header.value().clone()));
         });
 
+        let usage_principal = request_context
+            .get_state::<String>(RequestContext::STATE_USAGE_PRINCIPAL)
+            .ok()
+            .flatten();
+        if let Some(principal) = &usage_principal {
+            let tokens = response_context
+                .body
+                .get()
+                .and_then(|body| usage::total_tokens(body))
+                .unwrap_or_default();
+            usage::record(principal, 1, tokens);
+        }
+
         if let Some(body) = response_context.body.get()
             && !body.is_empty()
         {
@@ -54,6 +70,10 @@ impl Fairing for ResponseData {
 
         if let Some(body) = response_context.take_stream_body() {
             use rocket::futures::StreamExt;
+            let body = match usage_principal {
+                Some(principal) => usage::meter_stream(body, principal).boxed(),
+                None => body,
+            };
             let async_read = tokio_util::io::StreamReader::new(body.map(|result| match result {
                 Ok(bytes) => Ok(Bytes::from(bytes)),
                 Err(e) => Err(std::io::Error::other(e)),
@@ -67,7 +87,15 @@ impl Fairing for ResponseData {
             Headers::get_request_id(req),
         ));
 
-        STATE.inc_status_request_count(res.status().code, 1);
+        // gRPC响应的状态码在trailers中时，在响应结束后统计
+        if let Some(status) = grpc::response_status(
+            &request_context.request_id,
+            res.status().code,
+            res.headers().get_one(Headers::CONTENT_TYPE),
+            res.headers().get_one(Headers::GRPC_STATUS),
+        ) {
+            STATE.inc_status_request_count(status, 1);
+        }
         STATE.inc_response_time(
             (response_context.get_response_ts() - request_context.get_request_ts()) as usize,
         );
@@ -25,7 +25,7 @@ impl Fairing for Routing {
     async fn on_request(&self, req: &mut Request<'_>, _data: &mut Data<'_>) {
         skip_if_error!(req);
         let context = HCM.get_from_request(req);
-        let route = match ROUTER.get().unwrap().matches(context.clone()) {
+        let (route, params) = match ROUTER.get().unwrap().matches(context.clone()) {
             Some(r) => r,
             None => {
                 // 没有匹配到路由，返回404
@@ -34,5 +34,6 @@ impl Fairing for Routing {
             }
         };
         context.request.set_route(route);
+        context.request.set_path_params(params);
     }
 }
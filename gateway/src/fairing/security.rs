@@ -12,21 +12,40 @@
 //! ## 校验规则
 //! - IP访问策略：allow:127.0.0.1, deny:1.1.1.1, allow:192.168.0.0/16
 //! - Referer策略：allow:https://aaa.com, deny:https://bbb.com
-//! - QPS策略：127.0.0.1:8080/1000, */2000
+//! - 最大连接数：127.0.0.1:8080/1000, */2000，超出时返回503
+//!
+//! 客户端IP为预处理中解析出的真实IP，见[`Firewalld::resolve_client_ip`]。
 //!
 //! ## 获取规则
 //! 从控制台定时拉取网Firewall配置
 //!
+use crate::Args;
 use crate::components::Firewalld;
 use crate::report::STATE;
+use clap::Parser;
 use rocket::fairing::Fairing;
 use rocket::{Data, Request};
-use context::set_error;
+use context::{Headers, set_error};
+
+/// 网关节点连接数已满时，建议客户端重试的时间，单位：秒
+const CONNECTIONS_RETRY_AFTER: u64 = 1;
 
-pub struct PreSecurity {}
+/// 拒绝请求时建议客户端重试的时间，单位：秒
+///
+/// 拒绝时请求上下文尚未创建，因此保存在请求的本地缓存中，由catcher写入`Retry-After`响应头。
+#[derive(Default)]
+pub struct RetryAfter(pub Option<u64>);
+
+pub struct PreSecurity {
+    /// 当前网关节点，格式为ip:port，用于匹配最大连接数配置
+    node: String,
+}
 impl PreSecurity {
     pub fn new() -> Self {
-        Self {}
+        let args = Args::parse();
+        Self {
+            node: format!("{}:{}", args.address, args.port),
+        }
     }
 }
 
@@ -40,22 +59,31 @@ impl Fairing for PreSecurity {
     }
 
     async fn on_request(&self, req: &mut Request<'_>, _data: &mut Data<'_>) {
-        let ip = req.client_ip().unwrap();
-        let referer = req
-            .headers()
-            .get_one(context::Headers::REFERER)
-            .unwrap_or_default();
+        // http连接计数，包含被拦截的请求
+        // 该计数会在cleaner以及panic hook中-1
+        let connections = STATE.inc_http_connect_count(1);
+
+        // 检查当前节点的连接数，超出时返回503
+        if let Err(e) = Firewalld::check_connections(&self.node, connections).await {
+            STATE.inc_request_invalid_count(1);
+            req.local_cache(|| RetryAfter(Some(CONNECTIONS_RETRY_AFTER)));
+            set_error!(req, 503, e);
+            return;
+        }
+
+        // 客户端IP在预处理中设置，无法获取对端地址时为空，无法校验IP访问策略，拒绝请求
+        let Some(ip) = Headers::get_client_ip(req).and_then(|ip| ip.parse().ok()) else {
+            STATE.inc_request_invalid_count(1);
+            set_error!(req, 403, "Unable to determine client IP");
+            return;
+        };
+        let referer = req.headers().get_one(Headers::REFERER).unwrap_or_default();
         // 调用防火墙校验请求
-        if let Err(e) = Firewalld::check(&ip.to_string(), referer).await {
+        if let Err(e) = Firewalld::check(ip, referer).await {
             // 拦截请求后，无效请求数+1
             STATE.inc_request_invalid_count(1);
             // 跳过后续的fairing处理
             set_error!(req, 403, e.to_string());
-            return;
         }
-
-        // http连接计数
-        // 该计数会在cleaner以及panic hook中-1
-        STATE.inc_http_connect_count(1);
     }
 }
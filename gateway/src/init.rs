@@ -1,5 +1,5 @@
 use crate::components::{
-    ConfigFactory, Firewalld, GlobalFilterConfig, IpRegion, PluginFactory, Router, Servicer,
+    ConfigFactory, Firewalld, GlobalFilterConfig, IpRegion, PluginFactory, Router, Servicer, jwt,
 };
 use crate::report::STATE;
 use crate::{Args, report};
@@ -43,6 +43,9 @@ pub async fn init(args: &Args) {
     // 初始化防火墙
     Firewalld::init().await;
 
+    // 初始化JWT签发方
+    jwt::init().await;
+
     // 初始化IpRegion
     IpRegion::init().await;
 
@@ -39,6 +39,18 @@ struct Args {
     /// Cache password
     #[arg(long, default_value = "")]
     pub cache_password: String,
+
+    /// Enable PROXY protocol (v1/v2) on the listener, for use behind an L4 load balancer
+    #[arg(long, default_value_t = false)]
+    pub proxy_protocol: bool,
+
+    /// gRPC listen port (h2c), gRPC requests go through the same filters as HTTP requests
+    #[arg(long)]
+    pub grpc_port: Option<u16>,
+
+    /// TLS listen port, certificates are managed in the console and selected by SNI
+    #[arg(long)]
+    pub tls_port: Option<u16>,
 }
 
 impl Args {
@@ -0,0 +1,98 @@
+//! # 网关直接响应的路由
+//!
+//! 路由的[`RouteAction`]不是转发时，由网关直接响应，不经过负载均衡：
+//! - 重定向：返回3xx状态码和`Location`响应头，可将请求参数追加到重定向地址。
+//! - 固定响应：返回配置的状态码、响应头和响应体，适用于模拟数据等场景。
+//! - 维护中：返回503和维护页面，请求日志中的错误原因为`maintenance`。
+//!
+//! 鉴权、限流和过滤器插件与转发的路由相同，依然会执行。
+//!
+use crate::openapi::error::GatewayError;
+use crate::openapi::response::GatewayResponse;
+use aiway_protocol::gateway::route::RouteAction;
+use aiway_protocol::gateway::{RequestContext, ResponseContext};
+use context::Headers;
+use dashmap::DashMap;
+use std::collections::BTreeMap;
+use tokio_util::bytes::Bytes;
+
+/// 未配置`Content-Type`时的默认值
+const DEFAULT_CONTENT_TYPE: &str = "application/json";
+
+/// 按路由的处理方式直接响应
+pub fn respond(
+    action: &RouteAction,
+    request_context: &RequestContext,
+    response_context: &ResponseContext,
+) -> GatewayResponse {
+    match action {
+        RouteAction::Redirect(redirect) => {
+            let mut location = redirect.location(&request_context.path_params);
+            if redirect.keep_query {
+                location = append_query(&location, &request_context.query);
+            }
+            response_context.set_status(redirect.status);
+            response_context.insert_header(Headers::LOCATION, &location);
+        }
+        RouteAction::Static(response) => {
+            response_context.set_status(response.status);
+            set_content(response_context, &response.headers, &response.body);
+        }
+        RouteAction::Maintenance(page) => {
+            response_context.set_status(503);
+            set_content(response_context, &page.headers, &page.body);
+            if let Some(retry_after) = page.retry_after {
+                response_context.insert_header(Headers::RETRY_AFTER, &retry_after.to_string());
+            }
+            // 记录错误原因，由Logger写入请求日志
+            response_context.insert_header(Headers::ERROR_REASON, "maintenance");
+        }
+        // 理论上不会执行到这里
+        RouteAction::Proxy => {
+            response_context.set_status(502);
+            return GatewayResponse::Error(GatewayError::BadGateway);
+        }
+    }
+    GatewayResponse::Success
+}
+
+/// 设置响应头和响应体，未配置`Content-Type`时使用默认值
+fn set_content(context: &ResponseContext, headers: &BTreeMap<String, String>, body: &str) {
+    if !headers
+        .keys()
+        .any(|name| name.eq_ignore_ascii_case(Headers::CONTENT_TYPE))
+    {
+        context.insert_header(Headers::CONTENT_TYPE, DEFAULT_CONTENT_TYPE);
+    }
+    context.set_headers(headers.clone());
+    context.set_body(Bytes::from(body.to_string()));
+}
+
+/// 将请求参数追加到重定向地址
+fn append_query(location: &str, query: &DashMap<String, String>) -> String {
+    if query.is_empty() {
+        return location.to_string();
+    }
+    let query = url::form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(query.iter().map(|q| (q.key().clone(), q.value().clone())))
+        .finish();
+    let separator = if location.contains('?') { '&' } else { '?' };
+    format!("{}{}{}", location, separator, query)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_append_query() {
+        let query = DashMap::new();
+        assert_eq!(append_query("/new", &query), "/new");
+        query.insert("q".to_string(), "a b&c".to_string());
+        assert_eq!(append_query("/new", &query), "/new?q=a+b%26c");
+        assert_eq!(
+            append_query("https://example.com/new?from=old", &query),
+            "https://example.com/new?from=old&q=a+b%26c"
+        );
+    }
+}
@@ -0,0 +1,83 @@
+//! # 转发的请求体
+//!
+//! - 已读取到上下文中的请求体，可重复发送，支持重试。
+//! - 流式请求体直接从客户端连接读取并发送到服务，只能发送一次，不支持重试。
+//!   大小不受Rocket的请求体限制，由[`STREAM_LIMIT`]限制，未配置时为[`DEFAULT_STREAM_LIMIT`]。
+//!
+use crate::openapi::client::{HTTP_CLIENT, RequestOptions, UpstreamError};
+use aiway_protocol::gateway::RequestContext;
+use anyhow::bail;
+use dashmap::DashMap;
+use reqwest::Url;
+use rocket::Data;
+use rocket::data::{ByteUnit, DataStream, Limits};
+use tokio_util::bytes::Bytes;
+
+/// 流式请求体的大小限制名称，在Rocket的`limits`中配置
+pub const STREAM_LIMIT: &str = "stream";
+
+/// 流式请求体的默认大小限制
+pub const DEFAULT_STREAM_LIMIT: ByteUnit = ByteUnit::Gibibyte(10);
+
+pub enum UpstreamBody<'r> {
+    /// 已读取到上下文中的请求体
+    Buffered(Bytes),
+    /// 流式请求体及其大小限制，发送后为空
+    Stream(Option<(DataStream<'r>, u64)>),
+}
+
+impl<'r> UpstreamBody<'r> {
+    /// 不支持流式请求体的请求方法，如GET，请求体总是在上下文中
+    pub fn buffered(context: &RequestContext) -> Self {
+        Self::Buffered(context.get_body().cloned().unwrap_or_default())
+    }
+
+    /// 支持流式请求体的请求方法，上下文中没有请求体时，从客户端连接读取
+    pub fn new(context: &RequestContext, data: Data<'r>, limits: &Limits) -> Self {
+        if !context.is_stream_body() {
+            return Self::buffered(context);
+        }
+        let limit = limits.get(STREAM_LIMIT).unwrap_or(DEFAULT_STREAM_LIMIT);
+        // 多读取一个字节，用于判断是否超出限制
+        let stream = data.open(limit + 1);
+        Self::Stream(Some((stream, limit.as_u64())))
+    }
+
+    pub fn is_stream(&self) -> bool {
+        matches!(self, Self::Stream(_))
+    }
+
+    /// 已读取到上下文中的请求体，流式请求体为None
+    pub fn as_buffered(&self) -> Option<&Bytes> {
+        match self {
+            Self::Buffered(body) => Some(body),
+            Self::Stream(_) => None,
+        }
+    }
+
+    /// 发送请求
+    pub async fn send(
+        &mut self,
+        method: &str,
+        url: Url,
+        headers: DashMap<String, String>,
+        options: &RequestOptions,
+    ) -> anyhow::Result<Result<reqwest::Response, UpstreamError>> {
+        match self {
+            Self::Buffered(body) => {
+                HTTP_CLIENT
+                    .request(method, url, headers, body.clone(), options)
+                    .await
+            }
+            Self::Stream(stream) => match stream.take() {
+                Some((stream, limit)) => {
+                    HTTP_CLIENT
+                        .request_stream(method, url, headers, stream, limit, options)
+                        .await
+                }
+                // 流式请求体不会重试，理论上不会执行到这里
+                None => bail!("stream body has already been sent"),
+            },
+        }
+    }
+}
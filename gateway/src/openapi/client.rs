@@ -1,20 +1,202 @@
+use crate::components::upstream_tls;
+use aiway_protocol::gateway::service::{PoolConfig, Protocol, Timeouts, UpstreamTls};
 use dashmap::DashMap;
 use reqwest::header::{HeaderMap, HeaderName};
 use reqwest::{Client, ClientBuilder, Url};
+use rocket::futures::{StreamExt, stream};
 use std::str::FromStr;
-use std::sync::LazyLock;
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+use tokio::io::AsyncRead;
+use tokio::sync::mpsc;
+use tokio_util::bytes::Bytes;
+use tokio_util::io::ReaderStream;
+
+/// 默认连接超时，单位：毫秒
+const DEFAULT_CONNECT_TIMEOUT: u64 = 10_000;
+/// 默认请求超时，单位：毫秒。模型接口的非流式响应可能较慢，这里设置得比较宽松
+const DEFAULT_REQUEST_TIMEOUT: u64 = 300_000;
+/// 默认空闲超时，单位：毫秒
+const DEFAULT_IDLE_TIMEOUT: u64 = 300_000;
+/// 默认每个节点的最大空闲连接数
+const DEFAULT_POOL_MAX_IDLE_PER_HOST: usize = 64;
+/// 默认空闲连接的保持时间，单位：毫秒
+const DEFAULT_POOL_IDLE_TIMEOUT: u64 = 90_000;
+/// 流式请求体每次读取的大小
+const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+/// 流式请求体缓冲的最大块数，服务读取慢时，会暂停读取客户端的请求体
+const STREAM_CHANNEL_SIZE: usize = 8;
 
 /// 对LoadBalanceClient的封装
+///
+/// 连接超时、空闲超时、连接池配置、协议和TLS配置需要在构建Client时指定，
+/// 因此按配置缓存Client，配置相同的服务共用一个Client。
+/// 覆盖了SNI的服务，每个节点使用单独的Client。
+/// 连接池只限制空闲连接，节点的最大并发连接数见[`Servicer::acquire_connection`](crate::components::Servicer::acquire_connection)。
 pub struct HttpClient {
-    client: Client,
+    clients: DashMap<ClientOptions, Client>,
 }
 
 pub static HTTP_CLIENT: LazyLock<HttpClient> = LazyLock::new(HttpClient::new);
 
+/// Client的构建参数
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ClientOptions {
+    connect_timeout: u64,
+    idle_timeout: u64,
+    pool_max_idle_per_host: usize,
+    pool_idle_timeout: u64,
+    protocol: Protocol,
+    tls: Option<Arc<UpstreamTls>>,
+    /// 覆盖了SNI时，原节点的域名或IP
+    origin: Option<String>,
+}
+
+/// 请求服务的选项
+#[derive(Debug, Clone)]
+pub struct RequestOptions {
+    client: ClientOptions,
+    /// 请求超时，单位：毫秒
+    request_timeout: u64,
+}
+
+impl RequestOptions {
+    /// 构建请求选项
+    ///
+    /// - timeouts: 路由和服务合并后的超时配置
+    /// - pool: 服务的连接池配置
+    /// - protocol: 服务节点的协议
+    /// - tls: 服务的TLS配置
+    pub fn new(
+        timeouts: &Timeouts,
+        pool: Option<&PoolConfig>,
+        protocol: Protocol,
+        tls: Option<Arc<UpstreamTls>>,
+    ) -> Self {
+        Self {
+            client: ClientOptions {
+                connect_timeout: timeouts.connect.unwrap_or(DEFAULT_CONNECT_TIMEOUT),
+                idle_timeout: timeouts.idle.unwrap_or(DEFAULT_IDLE_TIMEOUT),
+                pool_max_idle_per_host: pool
+                    .and_then(|p| p.max_idle_per_host)
+                    .unwrap_or(DEFAULT_POOL_MAX_IDLE_PER_HOST),
+                pool_idle_timeout: pool
+                    .and_then(|p| p.idle_timeout)
+                    .unwrap_or(DEFAULT_POOL_IDLE_TIMEOUT),
+                protocol,
+                tls,
+                origin: None,
+            },
+            request_timeout: timeouts.request.unwrap_or(DEFAULT_REQUEST_TIMEOUT),
+        }
+    }
+
+    /// 连接超时
+    pub fn connect_timeout(&self) -> Duration {
+        Duration::from_millis(self.client.connect_timeout)
+    }
+}
+
+/// 请求服务时的错误
+#[derive(Debug)]
+pub enum UpstreamError {
+    /// 请求超时，在超时时间内未收到响应头
+    Timeout,
+    /// 请求错误，如连接失败、连接超时等
+    Request(reqwest::Error),
+    /// 节点熔断中，未发送请求
+    CircuitOpen,
+    /// 流式请求体超过大小限制
+    PayloadTooLarge,
+    /// 节点的连接数已达上限，未发送请求
+    ConnectionLimit,
+    /// 读取客户端的流式请求体失败，如客户端断开，不是服务的问题
+    ClientAbort,
+}
+
+impl UpstreamError {
+    /// 是否为超时错误，包含连接超时
+    pub fn is_timeout(&self) -> bool {
+        match self {
+            UpstreamError::Timeout => true,
+            UpstreamError::Request(e) => e.is_timeout(),
+            UpstreamError::CircuitOpen
+            | UpstreamError::PayloadTooLarge
+            | UpstreamError::ConnectionLimit
+            | UpstreamError::ClientAbort => false,
+        }
+    }
+
+    /// 错误对应的状态码，如果有的话
+    pub fn status(&self) -> Option<reqwest::StatusCode> {
+        match self {
+            UpstreamError::Timeout
+            | UpstreamError::CircuitOpen
+            | UpstreamError::ConnectionLimit
+            | UpstreamError::ClientAbort => None,
+            UpstreamError::PayloadTooLarge => Some(reqwest::StatusCode::PAYLOAD_TOO_LARGE),
+            UpstreamError::Request(e) => e.status(),
+        }
+    }
+}
+
+impl std::fmt::Display for UpstreamError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UpstreamError::Timeout => write!(f, "request timeout"),
+            UpstreamError::Request(e) => write!(f, "{}", e),
+            UpstreamError::CircuitOpen => write!(f, "circuit breaker open"),
+            UpstreamError::PayloadTooLarge => write!(f, "payload too large"),
+            UpstreamError::ConnectionLimit => write!(f, "connection limit reached"),
+            UpstreamError::ClientAbort => write!(f, "client aborted"),
+        }
+    }
+}
+
 impl HttpClient {
     pub fn new() -> Self {
-        let client = ClientBuilder::default().build().unwrap();
-        Self { client }
+        Self {
+            clients: DashMap::new(),
+        }
+    }
+
+    fn client(&self, options: &ClientOptions) -> anyhow::Result<Client> {
+        if let Some(client) = self.clients.get(options) {
+            return Ok(client.clone());
+        }
+        let builder = ClientBuilder::default()
+            .connect_timeout(Duration::from_millis(options.connect_timeout))
+            .read_timeout(Duration::from_millis(options.idle_timeout))
+            .pool_max_idle_per_host(options.pool_max_idle_per_host)
+            .pool_idle_timeout(Duration::from_millis(options.pool_idle_timeout));
+        let builder = match options.protocol {
+            Protocol::Http1 => builder.http1_only(),
+            // https节点通过ALPN协商
+            Protocol::H2 => builder,
+            Protocol::H2c | Protocol::Grpc => builder.http2_prior_knowledge(),
+        };
+        let client = match &options.tls {
+            Some(tls) => upstream_tls::configure(builder, tls, options.origin.as_deref())?,
+            None => builder,
+        }
+        .build()?;
+        self.clients.insert(options.clone(), client.clone());
+        Ok(client)
+    }
+
+    /// 获取请求使用的Client，服务配置了SNI时替换请求地址中的域名
+    fn prepare(&self, options: &ClientOptions, mut url: Url) -> anyhow::Result<(Client, Url)> {
+        match &options.tls {
+            Some(tls) => {
+                let origin = upstream_tls::override_server_name(&mut url, tls)?;
+                let options = ClientOptions {
+                    origin,
+                    ..options.clone()
+                };
+                Ok((self.client(&options)?, url))
+            }
+            None => Ok((self.client(options)?, url)),
+        }
     }
 
     pub async fn request(
@@ -23,15 +205,103 @@ impl HttpClient {
         url: Url,
         headers: DashMap<String, String>,
         body: impl Into<reqwest::Body>,
-    ) -> anyhow::Result<reqwest::Result<reqwest::Response>> {
-        Ok(self
-            .client
+        options: &RequestOptions,
+    ) -> anyhow::Result<Result<reqwest::Response, UpstreamError>> {
+        let (client, url) = self.prepare(&options.client, url)?;
+        let request = client
             .request(reqwest::Method::from_str(method)?, url)
             .body(body)
             .headers(headers.into_header_map())
-            .send()
-            .await)
+            .send();
+
+        // 请求超时仅限制收到响应头之前的时间，不能使用reqwest的timeout，否则会中断流式响应
+        let timeout = Duration::from_millis(options.request_timeout);
+        let response = match tokio::time::timeout(timeout, request).await {
+            Ok(response) => response.map_err(UpstreamError::Request),
+            Err(_) => Err(UpstreamError::Timeout),
+        };
+        Ok(response)
+    }
+
+    /// 流式发送请求体
+    ///
+    /// 请求体边读取边发送，不会缓冲到内存中。请求超时从请求体发送完成后开始计算，避免上传大文件时超时。
+    ///
+    /// - body: 客户端的请求体
+    /// - limit: 请求体的最大长度，超出时中断请求
+    pub async fn request_stream<R: AsyncRead + Send>(
+        &self,
+        method: &str,
+        url: Url,
+        headers: DashMap<String, String>,
+        body: R,
+        limit: u64,
+        options: &RequestOptions,
+    ) -> anyhow::Result<Result<reqwest::Response, UpstreamError>> {
+        let (tx, rx) = mpsc::channel(STREAM_CHANNEL_SIZE);
+        let receiver = stream::unfold(rx, |mut rx| async move {
+            rx.recv().await.map(|chunk| (chunk, rx))
+        });
+        let (client, url) = self.prepare(&options.client, url)?;
+        let request = client
+            .request(reqwest::Method::from_str(method)?, url)
+            .body(reqwest::Body::wrap_stream(receiver))
+            .headers(headers.into_header_map())
+            .send();
+        tokio::pin!(request);
+        let upload = pump(body, limit, tx);
+        tokio::pin!(upload);
+
+        let timeout = Duration::from_millis(options.request_timeout);
+        let response = tokio::select! {
+            biased;
+            uploaded = &mut upload => match uploaded {
+                Ok(_) => match tokio::time::timeout(timeout, request).await {
+                    Ok(response) => response.map_err(UpstreamError::Request),
+                    Err(_) => Err(UpstreamError::Timeout),
+                },
+                Err(e) => Err(e),
+            },
+            // 请求体发送完成前，服务可能已经返回响应，如拒绝请求
+            response = &mut request => response.map_err(UpstreamError::Request),
+        };
+        Ok(response)
+    }
+}
+
+/// 读取客户端的请求体并发送到请求中
+///
+/// 读取失败或超出大小限制时，向请求发送错误以中断请求，避免服务收到不完整的请求体。
+/// 读取失败是客户端的问题，返回[`UpstreamError::ClientAbort`]，不计入节点的健康状态。
+async fn pump<R: AsyncRead>(
+    body: R,
+    limit: u64,
+    tx: mpsc::Sender<std::io::Result<Bytes>>,
+) -> Result<(), UpstreamError> {
+    let stream = ReaderStream::with_capacity(body, STREAM_CHUNK_SIZE);
+    tokio::pin!(stream);
+    let mut size = 0;
+    while let Some(chunk) = stream.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                let _ = tx.send(Err(e)).await;
+                return Err(UpstreamError::ClientAbort);
+            }
+        };
+        size += chunk.len() as u64;
+        if size > limit {
+            let _ = tx
+                .send(Err(std::io::Error::other("payload too large")))
+                .await;
+            return Err(UpstreamError::PayloadTooLarge);
+        }
+        // 请求已结束，不再需要请求体
+        if tx.send(Ok(chunk)).await.is_err() {
+            break;
+        }
     }
+    Ok(())
 }
 
 pub trait IntoHeaderMap {
@@ -53,3 +323,51 @@ impl IntoHeaderMap for DashMap<String, String> {
         headers
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+    use tokio::io::ReadBuf;
+
+    /// 读取一段数据后连接被重置的客户端请求体
+    struct AbortedBody {
+        read: bool,
+    }
+
+    impl AsyncRead for AbortedBody {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            if self.read {
+                return Poll::Ready(Err(std::io::ErrorKind::ConnectionReset.into()));
+            }
+            self.read = true;
+            buf.put_slice(b"hello");
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pump() {
+        let (tx, mut rx) = mpsc::channel(STREAM_CHANNEL_SIZE);
+        assert!(pump(&b"hello"[..], 5, tx).await.is_ok());
+        assert_eq!(rx.recv().await.unwrap().unwrap(), Bytes::from("hello"));
+        assert!(rx.recv().await.is_none());
+
+        let (tx, mut rx) = mpsc::channel(STREAM_CHANNEL_SIZE);
+        let result = pump(&b"hello"[..], 4, tx).await;
+        assert!(matches!(result, Err(UpstreamError::PayloadTooLarge)));
+        assert!(rx.recv().await.unwrap().is_err());
+
+        // 客户端断开时中断请求，但不是服务的错误
+        let (tx, mut rx) = mpsc::channel(STREAM_CHANNEL_SIZE);
+        let result = pump(AbortedBody { read: false }, 1024, tx).await;
+        assert!(matches!(result, Err(UpstreamError::ClientAbort)));
+        assert_eq!(rx.recv().await.unwrap().unwrap(), Bytes::from("hello"));
+        assert!(rx.recv().await.unwrap().is_err());
+    }
+}
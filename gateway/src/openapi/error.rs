@@ -1,3 +1,4 @@
+use context::Headers;
 use rocket::Request;
 use rocket::response::Responder;
 
@@ -11,6 +12,18 @@ pub enum GatewayError {
     ///
     /// 当服务本身错误，如无响应时，返回该错误
     ServiceUnavailable,
+    /// 服务超时，对应状态码：504
+    ///
+    /// 当请求服务超时，如连接超时、未在超时时间内收到响应时，返回该错误
+    GatewayTimeout,
+    /// 请求体过大，对应状态码：413
+    ///
+    /// 当流式请求体超出大小限制时，返回该错误
+    PayloadTooLarge,
+    /// 客户端已断开，对应状态码：499
+    ///
+    /// 当读取客户端的流式请求体失败时，返回该错误
+    ClientClosedRequest,
     // /// 鉴权错误，对应状态码：401
     // #[deprecated]
     // Unauthorized,
@@ -31,6 +44,18 @@ impl<'r> Responder<'r, 'r> for GatewayError {
             GatewayError::ServiceUnavailable => rocket::response::Response::build()
                 .status(rocket::http::Status::ServiceUnavailable)
                 .ok(),
+            GatewayError::GatewayTimeout => rocket::response::Response::build()
+                .status(rocket::http::Status::GatewayTimeout)
+                // 记录错误原因，由Logger写入请求日志
+                .raw_header(Headers::ERROR_REASON, "upstream_timeout")
+                .ok(),
+            GatewayError::PayloadTooLarge => rocket::response::Response::build()
+                .status(rocket::http::Status::PayloadTooLarge)
+                .ok(),
+            GatewayError::ClientClosedRequest => rocket::response::Response::build()
+                .status(rocket::http::Status::new(499))
+                .raw_header(Headers::ERROR_REASON, "client_abort")
+                .ok(),
             // GatewayError::Unauthorized => rocket::response::Response::build()
             //     .status(rocket::http::Status::Unauthorized)
             //     .ok(),
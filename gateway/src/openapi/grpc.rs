@@ -0,0 +1,115 @@
+//! # gRPC响应
+//!
+//! 服务返回的gRPC响应以流的方式返回给客户端，响应结束后：
+//! - 将服务返回的trailers发送到gRPC监听器，由监听器返回给客户端，见[`grpc`](crate::components::grpc)。
+//! - 状态码在trailers中时，记录请求日志和状态统计。
+//!
+//! 客户端断开或读取服务响应出错时，状态码记为CANCELLED。
+//!
+use crate::components::grpc;
+use crate::openapi::response::ResponseExt;
+use aiway_protocol::gateway::{RequestContext, ResponseContext};
+use context::Headers;
+use http_body_util::BodyExt;
+use hyper::HeaderMap;
+use reqwest::header;
+use rocket::futures::stream;
+
+/// 是否为gRPC响应
+pub fn is_grpc(response: &reqwest::Response) -> bool {
+    grpc::is_grpc(
+        response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok()),
+    )
+}
+
+/// 设置gRPC响应到上下文中
+pub fn into_context(
+    response: reqwest::Response,
+    request: &RequestContext,
+    context: &ResponseContext,
+) {
+    response.head_into_context(context);
+
+    let header = |name: &str| response.headers().get(name).and_then(|v| v.to_str().ok());
+    let deferred = grpc::status_code(
+        response.status().as_u16(),
+        header(Headers::CONTENT_TYPE),
+        header(Headers::GRPC_STATUS),
+    )
+    .is_none();
+    if deferred {
+        grpc::expect(&request.request_id);
+    }
+    let completion = Completion {
+        request_id: request.request_id.clone(),
+        call: request
+            .headers
+            .get(Headers::GRPC_CALL)
+            .map(|v| v.value().clone()),
+        deferred,
+        trailers: None,
+        ended: false,
+    };
+
+    let body = hyper::Response::<reqwest::Body>::from(response).into_body();
+    let stream = stream::unfold(
+        (body, completion),
+        |(mut body, mut completion)| async move {
+            loop {
+                let Some(frame) = body.frame().await else {
+                    // 响应结束，执行完成处理
+                    completion.ended = true;
+                    drop(completion);
+                    return None;
+                };
+                match frame {
+                    Ok(frame) => match frame.into_data() {
+                        Ok(data) => return Some((Ok(data.to_vec()), (body, completion))),
+                        Err(frame) => completion.trailers = frame.into_trailers().ok(),
+                    },
+                    Err(e) => return Some((Err(e.into()), (body, completion))),
+                }
+            }
+        },
+    );
+    context.set_stream_body(Box::pin(stream));
+}
+
+/// gRPC响应的完成处理，在响应流被丢弃时执行
+struct Completion {
+    request_id: String,
+    /// gRPC监听器的调用标识
+    call: Option<String>,
+    /// 状态码是否在trailers中
+    deferred: bool,
+    trailers: Option<HeaderMap>,
+    /// 是否已读取完服务的响应
+    ended: bool,
+}
+
+impl Drop for Completion {
+    fn drop(&mut self) {
+        let code = self
+            .trailers
+            .as_ref()
+            .and_then(|trailers| trailers.get(Headers::GRPC_STATUS))
+            .and_then(|v| v.to_str().ok())
+            .map(grpc::parse_status)
+            .unwrap_or(if self.ended {
+                grpc::UNKNOWN
+            } else {
+                grpc::CANCELLED
+            });
+        if let Some(call) = &self.call
+            && let Some(trailers) = self.trailers.take()
+        {
+            grpc::send_trailers(call, trailers);
+        }
+        if self.deferred {
+            grpc::complete(&self.request_id, code);
+        }
+    }
+}
@@ -0,0 +1,143 @@
+//! # 请求镜像
+//!
+//! 按路由配置的[`MirrorPolicy`]，将采样的请求异步复制到镜像服务，用于以真实流量测试新版本的服务。
+//!
+//! - 镜像请求在后台发送，响应被丢弃，镜像请求失败不影响客户端的响应。
+//! - 镜像请求与原请求使用相同的请求方法、路径、请求头和请求体，节点按镜像服务的负载均衡策略选择。
+//! - 仅镜像已读取到上下文中的请求体，流式请求体只能发送一次，不镜像。
+//! - 镜像请求的状态码和耗时记录在原请求的日志中，见[`mirror`](crate::components::mirror)。
+//!
+use crate::components::Servicer;
+use crate::components::mirror::{self, MirrorResult};
+use crate::openapi::build_url;
+use crate::openapi::client::{HTTP_CLIENT, RequestOptions};
+use aiway_protocol::gateway::RequestContext;
+use aiway_protocol::gateway::route::MirrorPolicy;
+use aiway_protocol::gateway::service::HashKey;
+use dashmap::DashMap;
+use std::time::{Duration, Instant};
+use tokio_util::bytes::Bytes;
+
+/// 按采样比例发送镜像请求
+///
+/// - path: 转发到服务的请求路径
+/// - headers: 转发到服务的请求头
+/// - body: 已读取到上下文中的请求体，流式请求体为None
+pub fn send(
+    policy: &MirrorPolicy,
+    request_context: &RequestContext,
+    path: &str,
+    headers: &DashMap<String, String>,
+    body: Option<&Bytes>,
+) {
+    let Some(body) = body else {
+        return;
+    };
+    if !policy.is_sampled(fastrand::u32(..)) {
+        return;
+    }
+
+    let service = policy.service.clone();
+    let hash_key = |key: &HashKey| match key {
+        HashKey::ClientIp => Some(request_context.get_client_ip().to_string()),
+        HashKey::Header(name) => request_context.get_header(&name.to_lowercase()),
+        HashKey::Query(name) => request_context.get_query(name),
+    };
+    let node = match Servicer::get_instance(&service, hash_key) {
+        Some(node) if !node.is_empty() => node,
+        _ => {
+            log::warn!("No available instance for mirror service: {}", service);
+            return;
+        }
+    };
+    // 熔断中的节点不发送镜像请求
+    let Some(permit) = Servicer::acquire(&service, &node) else {
+        Servicer::release(&service, &node);
+        return;
+    };
+    let url = match build_url(&node, path, &request_context.query) {
+        Ok(url) => url,
+        Err(e) => {
+            log::error!("build mirror url error: {}", e);
+            Servicer::release(&service, &node);
+            return;
+        }
+    };
+
+    // 镜像请求使用镜像服务的连接配置，超时以镜像配置为准
+    let connection = Servicer::get_connection_config(&service);
+    let mut timeouts = connection.timeouts.unwrap_or_default();
+    timeouts.request = Some(policy.timeout);
+    let options = RequestOptions::new(
+        &timeouts,
+        connection.pool.as_ref(),
+        connection.protocol,
+        connection.tls,
+    );
+
+    let request_id = request_context.request_id.clone();
+    let method = request_context.get_method().unwrap_or_default().to_string();
+    let headers = headers.clone();
+    let body = body.clone();
+    mirror::start(&request_id);
+    tokio::spawn(async move {
+        // 节点的连接数已达上限时不等待，直接放弃镜像请求
+        let connection = match Servicer::acquire_connection(&service, &node, Duration::ZERO).await {
+            Ok(connection) => connection,
+            Err(e) => {
+                log::warn!("skip mirror request {}: {}", request_id, e);
+                Servicer::release(&service, &node);
+                mirror::complete(
+                    &request_id,
+                    MirrorResult {
+                        service,
+                        status_code: None,
+                        elapsed: 0,
+                        error_reason: Some("connection_limit".to_string()),
+                    },
+                );
+                return;
+            }
+        };
+        let start = Instant::now();
+        let response = HTTP_CLIENT
+            .request(&method, url, headers, body, &options)
+            .await;
+        let elapsed = start.elapsed().as_millis() as u64;
+        Servicer::release(&service, &node);
+
+        let (status_code, error_reason) = match response {
+            Ok(Ok(response)) => {
+                let status = response.status();
+                permit.report(!status.is_server_error(), elapsed);
+                // 丢弃响应体，流式响应不需要等待读取完成，同时归还连接许可
+                drop(response);
+                drop(connection);
+                (Some(status.as_u16()), None)
+            }
+            Ok(Err(e)) => {
+                log::warn!("mirror request {} to {} error: {}", request_id, node, e);
+                permit.report(false, elapsed);
+                let reason = match e.is_timeout() {
+                    true => "upstream_timeout",
+                    false => "upstream_error",
+                };
+                (e.status().map(|s| s.as_u16()), Some(reason.to_string()))
+            }
+            Err(e) => {
+                log::error!("mirror request {} error: {}", request_id, e);
+                (None, Some("gateway_error".to_string()))
+            }
+        };
+
+        mirror::complete(
+            &request_id,
+            MirrorResult {
+                service,
+                status_code,
+                elapsed: elapsed as i64,
+                error_reason,
+            },
+        );
+    });
+}
@@ -5,112 +5,344 @@
 //! - 接口内部不处理任何业务逻辑，需转发到具体服务上处理
 //! - 同时支持流式和非流式响应
 //! - 流式响应支持恢复（插件实现）
+//! - 支持WebSocket代理，见[`websocket`]
+//! - 支持gRPC代理，见[`grpc`]
+//! - 支持请求镜像，见[`mirror`]
+//! - 支持由网关直接响应的路由，如重定向、固定响应和维护中，见[`action`]
 //!
+mod action;
+mod body;
 mod client;
 mod error;
+mod grpc;
+mod mirror;
+mod proxy_headers;
 mod response;
+mod retry;
 #[deprecated]
 #[allow(unused)]
 mod sse;
+mod websocket;
 
-use crate::openapi::client::HTTP_CLIENT;
+use crate::components::{ActiveInstance, Servicer};
+use crate::openapi::body::UpstreamBody;
+pub use crate::openapi::body::{DEFAULT_STREAM_LIMIT, STREAM_LIMIT};
+use crate::openapi::client::{RequestOptions, UpstreamError};
 use crate::openapi::error::GatewayError;
 use crate::openapi::response::{GatewayResponse, ResponseExt};
+use crate::openapi::websocket::WebSocketRelay;
+use aiway_protocol::gateway::service::Protocol;
 use alert::Alert;
-use context::HttpContextWrapper;
+use context::{Headers, HttpContextWrapper};
+use dashmap::DashMap;
 use reqwest::{StatusCode, Url};
+use rocket::Data;
+use rocket::data::Limits;
 use rocket::{delete, get, head, options, patch, post, put};
 use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tokio_util::bytes::Bytes;
 
 #[get("/<path..>")]
-pub async fn call_get(wrapper: HttpContextWrapper, path: PathBuf) -> GatewayResponse {
-    handle(wrapper, path).await
+pub async fn call_get(
+    wrapper: HttpContextWrapper,
+    active: &ActiveInstance,
+    path: PathBuf,
+) -> GatewayResponse {
+    let body = UpstreamBody::buffered(&wrapper.0.request);
+    handle(wrapper, active, path, body).await
 }
 
-#[post("/<path..>")]
-pub async fn call_post(wrapper: HttpContextWrapper, path: PathBuf) -> GatewayResponse {
-    handle(wrapper, path).await
+#[post("/<path..>", data = "<data>")]
+pub async fn call_post(
+    wrapper: HttpContextWrapper,
+    active: &ActiveInstance,
+    path: PathBuf,
+    limits: &Limits,
+    data: Data<'_>,
+) -> GatewayResponse {
+    let body = UpstreamBody::new(&wrapper.0.request, data, limits);
+    handle(wrapper, active, path, body).await
 }
 
-#[put("/<path..>")]
-pub async fn call_put(wrapper: HttpContextWrapper, path: PathBuf) -> GatewayResponse {
-    handle(wrapper, path).await
+#[put("/<path..>", data = "<data>")]
+pub async fn call_put(
+    wrapper: HttpContextWrapper,
+    active: &ActiveInstance,
+    path: PathBuf,
+    limits: &Limits,
+    data: Data<'_>,
+) -> GatewayResponse {
+    let body = UpstreamBody::new(&wrapper.0.request, data, limits);
+    handle(wrapper, active, path, body).await
 }
 
-#[patch("/<path..>")]
-pub async fn call_patch(wrapper: HttpContextWrapper, path: PathBuf) -> GatewayResponse {
-    handle(wrapper, path).await
+#[patch("/<path..>", data = "<data>")]
+pub async fn call_patch(
+    wrapper: HttpContextWrapper,
+    active: &ActiveInstance,
+    path: PathBuf,
+    limits: &Limits,
+    data: Data<'_>,
+) -> GatewayResponse {
+    let body = UpstreamBody::new(&wrapper.0.request, data, limits);
+    handle(wrapper, active, path, body).await
 }
 
-#[delete("/<path..>")]
-pub async fn call_delete(wrapper: HttpContextWrapper, path: PathBuf) -> GatewayResponse {
-    handle(wrapper, path).await
+#[delete("/<path..>", data = "<data>")]
+pub async fn call_delete(
+    wrapper: HttpContextWrapper,
+    active: &ActiveInstance,
+    path: PathBuf,
+    limits: &Limits,
+    data: Data<'_>,
+) -> GatewayResponse {
+    let body = UpstreamBody::new(&wrapper.0.request, data, limits);
+    handle(wrapper, active, path, body).await
 }
 #[head("/<path..>")]
-pub async fn call_head(wrapper: HttpContextWrapper, path: PathBuf) -> GatewayResponse {
-    handle(wrapper, path).await
+pub async fn call_head(
+    wrapper: HttpContextWrapper,
+    active: &ActiveInstance,
+    path: PathBuf,
+) -> GatewayResponse {
+    let body = UpstreamBody::buffered(&wrapper.0.request);
+    handle(wrapper, active, path, body).await
 }
 
 #[options("/<path..>")]
-pub async fn call_options(wrapper: HttpContextWrapper, path: PathBuf) -> GatewayResponse {
-    handle(wrapper, path).await
+pub async fn call_options(
+    wrapper: HttpContextWrapper,
+    active: &ActiveInstance,
+    path: PathBuf,
+) -> GatewayResponse {
+    let body = UpstreamBody::buffered(&wrapper.0.request);
+    handle(wrapper, active, path, body).await
 }
 
-async fn handle(wrapper: HttpContextWrapper, _path: PathBuf) -> GatewayResponse {
+async fn handle(
+    wrapper: HttpContextWrapper,
+    active: &ActiveInstance,
+    _path: PathBuf,
+    mut body: UpstreamBody<'_>,
+) -> GatewayResponse {
     let request_context = &wrapper.0.request;
 
-    // 实际路由路径
-    let path = &request_context.get_path();
+    // SAFE: 能执行到这里，路由一定存在
+    let route = request_context.get_route().unwrap();
+
+    // 非转发的路由由网关直接响应
+    if !route.action.is_proxy() {
+        return action::respond(&route.action, request_context, &wrapper.0.response);
+    }
+
+    // 实际路由路径，如果路由配置了路径重写，则使用重写后的路径
+    let path = &route.build_path(&request_context.get_path(), &request_context.path_params);
 
     // 路由的实际地址，该地址已经由负载均衡处理过，可能是IP或域名
-    let routing_url = request_context.get_routing_url().unwrap();
-    let mut url = match Url::parse(&format!(
-        "{}/{}",
-        routing_url.trim_end_matches('/'),
-        path.trim_start_matches("/")
-    )) {
-        Ok(url) => url,
-        // 理论上不会执行到这里
-        Err(e) => {
-            log::error!("parse load balance url error: {}", e);
-            return GatewayResponse::Error(GatewayError::BadGateway);
-        }
-    };
+    // 重试时会切换到其他节点
+    let mut routing_url = request_context.get_routing_url().unwrap().clone();
 
-    // 添加query参数，如果有的话
-    {
-        let mut query_pairs = url.query_pairs_mut();
-        query_pairs.clear();
-        for q in request_context.query.iter() {
-            query_pairs.append_pair(q.key(), q.value());
-        }
-    }
+    // 转发的服务，路由配置了加权后端时为负载均衡时选中的服务
+    let service = request_context.get_service().unwrap_or(&route.service);
 
-    // 请求头
-    let headers = request_context.headers.clone();
+    // 请求头，移除逐跳头部，并按路由配置注入代理头
+    let headers = proxy_headers::request_headers(request_context, route).await;
 
     //log::info!("最终请求地址：{} {}", context.method, url);
 
     // 请求方法
     let method = request_context.get_method().unwrap_or_default();
 
-    // 这里clone可能有性能问题
-    let body = request_context.get_body().cloned();
+    // WebSocket升级请求，仅路由开启了WebSocket时转发升级请求头，否则按普通请求转发
+    let is_websocket =
+        route.websocket && method == "GET" && websocket::is_upgrade(&request_context.headers);
+    if is_websocket {
+        websocket::set_upgrade_headers(&headers);
+    } else if let Some(policy) = &route.mirror {
+        // 异步发送镜像请求，不影响原请求
+        mirror::send(policy, request_context, path, &headers, body.as_buffered());
+    }
+
+    // 超时、连接池配置、节点协议和TLS配置，路由上的超时配置优先
+    let connection = Servicer::get_connection_config(service);
+    let protocol = connection.protocol;
+    let timeouts = route
+        .timeouts
+        .clone()
+        .unwrap_or_default()
+        .merge(connection.timeouts.as_ref());
+    let options = RequestOptions::new(
+        &timeouts,
+        connection.pool.as_ref(),
+        protocol,
+        connection.tls,
+    );
+
+    // gRPC服务需要声明支持trailers
+    if protocol == Protocol::Grpc {
+        headers.insert(Headers::TE.to_string(), "trailers".to_string());
+    }
+
+    // 重试策略，仅允许重试的方法生效，流式请求体只能发送一次，不重试
+    let retry_policy = route
+        .retry
+        .as_ref()
+        .filter(|policy| policy.is_retryable_method(method) && !body.is_stream());
+    if retry_policy.is_some() {
+        retry::record_request(route);
+    }
+
+    let mut tried = vec![];
+    let mut attempt = 1;
+    let response = loop {
+        let url = match build_url(&routing_url, path, &request_context.query) {
+            Ok(url) => url,
+            // 理论上不会执行到这里
+            Err(e) => {
+                log::error!("parse load balance url error: {}", e);
+                return GatewayResponse::Error(GatewayError::BadGateway);
+            }
+        };
+
+        // 熔断中的节点不转发，切换到其他节点，所有节点都熔断时返回降级响应
+        let Some(permit) = Servicer::acquire(service, &routing_url) else {
+            tried.push(routing_url.clone());
+            match Servicer::get_retry_instance(service, &tried) {
+                Some(instance) if !tried.contains(&instance) => {
+                    active.set(service, &instance);
+                    routing_url = instance;
+                    continue;
+                }
+                Some(instance) => {
+                    active.set(service, &instance);
+                    break Ok(Err(UpstreamError::CircuitOpen));
+                }
+                None => break Ok(Err(UpstreamError::CircuitOpen)),
+            }
+        };
+
+        // 节点的连接数已达上限时等待，超过连接超时后不再转发，未上报结果的熔断许可会被归还
+        match Servicer::acquire_connection(service, &routing_url, options.connect_timeout()).await {
+            Ok(Some(connection)) => active.hold(connection),
+            Ok(None) => {}
+            Err(e) => {
+                log::warn!("{}", e);
+                break Ok(Err(UpstreamError::ConnectionLimit));
+            }
+        }
+
+        // 转发请求
+        let start = Instant::now();
+        let response = body.send(method, url, headers.clone(), &options).await;
+        let elapsed = start.elapsed().as_millis() as u64;
+
+        // 被动健康检查和熔断统计，连接错误、超时和5xx响应记为失败
+        // 未上报结果时丢弃许可即归还熔断器的名额
+        match &response {
+            Ok(Ok(response)) => permit.report(!response.status().is_server_error(), elapsed),
+            // 请求体超出限制和客户端断开是客户端的问题，不影响节点的健康状态
+            Ok(Err(UpstreamError::PayloadTooLarge | UpstreamError::ClientAbort)) => {}
+            Ok(Err(_)) => permit.report(false, elapsed),
+            Err(_) => {}
+        }
+
+        let policy = match retry_policy {
+            Some(policy) => policy,
+            None => break response,
+        };
+        if attempt >= policy.max_attempts
+            || !retry::should_retry(policy, &response)
+            || !retry::acquire(route, policy)
+        {
+            break response;
+        }
+
+        // 选择其他节点重试
+        tried.push(routing_url.clone());
+        match Servicer::get_retry_instance(service, &tried) {
+            Some(instance) => {
+                active.set(service, &instance);
+                routing_url = instance;
+            }
+            None => break response,
+        }
+
+        let backoff = policy.backoff_millis(attempt);
+        log::warn!(
+            "retry request {} to {} after {}ms, attempt {}",
+            request_context.request_id,
+            routing_url,
+            backoff,
+            attempt + 1
+        );
+        tokio::time::sleep(Duration::from_millis(backoff)).await;
+        attempt += 1;
+    };
 
-    // 转发请求
-    let response = HTTP_CLIENT
-        .request(method, url, headers, body.unwrap_or_default())
-        .await;
+    // 发生了重试，记录最终请求的节点
+    if attempt > 1 {
+        request_context.set_routing_url(routing_url);
+    }
 
     let response_context = &wrapper.0.response;
     // 获取响应
     match response {
         Ok(response) => match response {
+            // 服务同意升级，由Rocket完成客户端连接的升级后转发数据
+            Ok(response)
+                if is_websocket && response.status() == StatusCode::SWITCHING_PROTOCOLS =>
+            {
+                response.head_into_context(response_context);
+                match response.upgrade().await {
+                    Ok(upstream) => GatewayResponse::Upgrade(WebSocketRelay::new(upstream)),
+                    Err(e) => {
+                        log::error!("upgrade websocket error: {}", e);
+                        response_context.set_status(502);
+                        GatewayResponse::Error(GatewayError::BadGateway)
+                    }
+                }
+            }
+            // gRPC响应，保留trailers
+            Ok(response) if protocol == Protocol::Grpc && grpc::is_grpc(&response) => {
+                grpc::into_context(response, request_context, response_context);
+                GatewayResponse::Success
+            }
             // 返回响应（服务本身返回异常，如4xx、5xx时也会走这里）
             Ok(response) => {
                 response.into_context(response_context).await;
                 GatewayResponse::Success
             }
+            // 所有节点都熔断，返回降级响应
+            Err(UpstreamError::CircuitOpen) => match Servicer::get_fallback(service) {
+                Some(fallback) => {
+                    response_context.set_status(fallback.status);
+                    response_context.set_headers(fallback.headers);
+                    response_context.set_body(Bytes::from(fallback.body));
+                    GatewayResponse::Success
+                }
+                None => {
+                    response_context.set_status(503);
+                    GatewayResponse::Error(GatewayError::ServiceUnavailable)
+                }
+            },
+            // 流式请求体超出大小限制
+            Err(UpstreamError::PayloadTooLarge) => {
+                response_context.set_status(413);
+                GatewayResponse::Error(GatewayError::PayloadTooLarge)
+            }
+            // 读取客户端的流式请求体失败，客户端通常已断开
+            Err(UpstreamError::ClientAbort) => {
+                log::warn!("client aborted request {}", request_context.request_id);
+                response_context.set_status(499);
+                GatewayResponse::Error(GatewayError::ClientClosedRequest)
+            }
+            // 请求服务超时，包括连接超时
+            Err(e) if e.is_timeout() => {
+                log::error!("call service timeout: {}", e);
+                response_context.set_status(504);
+                GatewayResponse::Error(GatewayError::GatewayTimeout)
+            }
             // 请求服务时错误，如无响应等
             Err(e) => {
                 log::error!("call service error: {:?}", e);
@@ -131,3 +363,27 @@ async fn handle(wrapper: HttpContextWrapper, _path: PathBuf) -> GatewayResponse
         }
     }
 }
+
+/// 构建请求服务的URL
+fn build_url(
+    routing_url: &str,
+    path: &str,
+    query: &DashMap<String, String>,
+) -> anyhow::Result<Url> {
+    let mut url = Url::parse(&format!(
+        "{}/{}",
+        routing_url.trim_end_matches('/'),
+        path.trim_start_matches("/")
+    ))?;
+
+    // 添加query参数，如果有的话
+    {
+        let mut query_pairs = url.query_pairs_mut();
+        query_pairs.clear();
+        for q in query.iter() {
+            query_pairs.append_pair(q.key(), q.value());
+        }
+    }
+
+    Ok(url)
+}
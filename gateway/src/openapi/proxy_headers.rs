@@ -0,0 +1,173 @@
+//! # 代理请求头
+//!
+//! 构建转发到服务的请求头，以及过滤服务返回的响应头。
+//!
+//! - 逐跳（hop-by-hop）头部在请求和响应方向都会被移除，包括[`BAN_HEADERS`]及`Connection`头中列出的头部。
+//! - 按路由的[`ProxyHeaders`]配置注入标准代理头，再移除、覆盖、添加请求头。
+//! - 对端为受信代理时，在其传入的`X-Forwarded-For`、`Forwarded`后追加对端IP，并保留其传入的
+//!   `X-Forwarded-Proto`、`X-Forwarded-Host`；否则按网关收到的请求重新生成，避免客户端伪造。
+//! - 未配置注入的标准代理头，按普通请求头透传。
+//!
+use crate::components::Firewalld;
+use aiway_protocol::common::constants::BAN_HEADERS;
+use aiway_protocol::gateway::RequestContext;
+use aiway_protocol::gateway::route::{ForwardedHeader, ProxyHeaders, Route};
+use context::Headers;
+use dashmap::DashMap;
+use std::collections::HashSet;
+use std::net::IpAddr;
+use std::sync::LazyLock;
+
+/// 未知请求协议时使用的默认协议
+const DEFAULT_PROTO: &str = "http";
+
+static DEFAULT_PROXY_HEADERS: LazyLock<ProxyHeaders> = LazyLock::new(ProxyHeaders::default);
+
+/// 构建转发到服务的请求头
+pub async fn request_headers(ctx: &RequestContext, route: &Route) -> DashMap<String, String> {
+    let headers = ctx.headers.clone();
+    strip_hop_by_hop(&headers);
+    // 网关内部使用的请求头
+    headers.remove(Headers::GRPC_CALL);
+
+    let config = route
+        .proxy_headers
+        .as_ref()
+        .unwrap_or(&DEFAULT_PROXY_HEADERS);
+
+    let peer = ctx.get_peer_ip().parse::<IpAddr>().ok();
+    let trusted = match peer {
+        Some(peer) if !config.forwarded.is_empty() => Firewalld::is_trusted_proxy(peer).await,
+        _ => false,
+    };
+    // 受信代理传入的请求头
+    let trusted_header = |name: &str| {
+        headers
+            .get(name)
+            .filter(|_| trusted)
+            .map(|v| v.value().clone())
+    };
+    let peer = ctx.get_peer_ip();
+    let client_ip = ctx.get_client_ip();
+    let proto = match ctx.get_scheme() {
+        "" => DEFAULT_PROTO,
+        scheme => scheme,
+    };
+
+    for header in &config.forwarded {
+        let (name, value) = match header {
+            ForwardedHeader::XForwardedFor => (
+                Headers::X_FORWARDED_FOR,
+                match trusted_header(Headers::X_FORWARDED_FOR) {
+                    Some(value) => format!("{}, {}", value, peer),
+                    None => client_ip.to_string(),
+                },
+            ),
+            ForwardedHeader::XForwardedProto => (
+                Headers::X_FORWARDED_PROTO,
+                trusted_header(Headers::X_FORWARDED_PROTO).unwrap_or_else(|| proto.to_string()),
+            ),
+            ForwardedHeader::XForwardedHost => (
+                Headers::X_FORWARDED_HOST,
+                trusted_header(Headers::X_FORWARDED_HOST).unwrap_or_else(|| ctx.host.clone()),
+            ),
+            ForwardedHeader::Forwarded => (
+                Headers::FORWARDED,
+                match trusted_header(Headers::FORWARDED) {
+                    Some(value) => {
+                        format!("{}, {}", value, forwarded_element(peer, &ctx.host, proto))
+                    }
+                    None => forwarded_element(client_ip, &ctx.host, proto),
+                },
+            ),
+            ForwardedHeader::XRequestId => (Headers::X_REQUEST_ID, ctx.request_id.clone()),
+        };
+        headers.insert(name.to_string(), value);
+    }
+
+    for name in &config.remove {
+        headers.remove(&name.to_lowercase());
+    }
+    for (name, value) in &config.set {
+        headers.insert(name.to_lowercase(), value.clone());
+    }
+    for (name, value) in &config.add {
+        headers
+            .entry(name.to_lowercase())
+            .or_insert_with(|| value.clone());
+    }
+
+    headers
+}
+
+/// 移除逐跳头部
+pub fn strip_hop_by_hop(headers: &DashMap<String, String>) {
+    let connection = connection_headers(
+        headers
+            .get(Headers::CONNECTION)
+            .map(|v| v.value().clone())
+            .as_deref(),
+    );
+    headers.retain(|name, _| !is_hop_by_hop(name, &connection));
+}
+
+/// `Connection`头中列出的头部，名称为小写
+pub fn connection_headers(value: Option<&str>) -> HashSet<String> {
+    value
+        .unwrap_or_default()
+        .split(',')
+        .map(|name| name.trim().to_lowercase())
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// 是否为逐跳头部，name需为小写
+pub fn is_hop_by_hop(name: &str, connection: &HashSet<String>) -> bool {
+    BAN_HEADERS.contains(name) || connection.contains(name)
+}
+
+/// RFC 7239的`Forwarded`元素，如：`for=192.0.2.1;host=example.com;proto=http`
+fn forwarded_element(ip: &str, host: &str, proto: &str) -> String {
+    // IPv6需要加上方括号并使用引号
+    let node = if ip.contains(':') {
+        format!("\"[{}]\"", ip)
+    } else {
+        ip.to_string()
+    };
+    // 带端口的host不是合法的token，需要使用引号
+    let host = if host.contains(':') {
+        format!("\"{}\"", host)
+    } else {
+        host.to_string()
+    };
+    format!("for={};host={};proto={}", node, host, proto)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_hop_by_hop() {
+        let headers = DashMap::new();
+        headers.insert("connection".to_string(), "keep-alive, X-Custom".to_string());
+        headers.insert("x-custom".to_string(), "1".to_string());
+        headers.insert("transfer-encoding".to_string(), "chunked".to_string());
+        headers.insert("accept".to_string(), "*/*".to_string());
+        strip_hop_by_hop(&headers);
+        assert_eq!(headers.len(), 1);
+        assert!(headers.contains_key("accept"));
+    }
+
+    #[test]
+    fn test_forwarded_element() {
+        assert_eq!(
+            forwarded_element("192.0.2.1", "example.com", "http"),
+            "for=192.0.2.1;host=example.com;proto=http"
+        );
+        assert_eq!(
+            forwarded_element("2001:db8::1", "example.com:8080", "https"),
+            "for=\"[2001:db8::1]\";host=\"example.com:8080\";proto=https"
+        );
+    }
+}
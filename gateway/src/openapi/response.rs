@@ -1,6 +1,8 @@
 //! # 网关响应定义
 //! 执行顺序：respond_to -> response fairing
 use crate::openapi::error::GatewayError;
+use crate::openapi::proxy_headers;
+use crate::openapi::websocket::{self, WebSocketRelay};
 use crate::report::STATE;
 use aiway_protocol::gateway::ResponseContext;
 use reqwest::header;
@@ -13,6 +15,8 @@ pub enum GatewayResponse {
     Success,
     /// 错误响应
     Error(GatewayError),
+    /// WebSocket升级，由Rocket完成升级后转发数据
+    Upgrade(WebSocketRelay),
 }
 
 impl<'r> Responder<'r, 'r> for GatewayResponse {
@@ -20,6 +24,9 @@ impl<'r> Responder<'r, 'r> for GatewayResponse {
         match self {
             GatewayResponse::Success => rocket::response::Response::build().ok(),
             GatewayResponse::Error(e) => e.respond_to(request),
+            GatewayResponse::Upgrade(relay) => rocket::response::Response::build()
+                .upgrade(websocket::PROTOCOL, relay)
+                .ok(),
         }
     }
 }
@@ -27,6 +34,9 @@ impl<'r> Responder<'r, 'r> for GatewayResponse {
 pub trait ResponseExt {
     fn is_sse(&self) -> bool;
 
+    /// 设置状态码和响应头，不包含响应体
+    fn head_into_context(&self, context: &ResponseContext);
+
     async fn into_context(self, context: &ResponseContext);
 }
 impl ResponseExt for reqwest::Response {
@@ -38,13 +48,21 @@ impl ResponseExt for reqwest::Response {
         false
     }
 
-    async fn into_context(self, context: &ResponseContext) {
-        use rocket::futures::StreamExt;
+    fn head_into_context(&self, context: &ResponseContext) {
         // 设置状态码
         context.set_status(self.status().as_u16());
 
-        // 设置响应头
-        context.set_headers(self.headers().iter().map(|(k, v)| {
+        // 设置响应头，逐跳头部不透传
+        let connection = proxy_headers::connection_headers(
+            self.headers()
+                .get(header::CONNECTION)
+                .and_then(|v| v.to_str().ok()),
+        );
+        let headers = self
+            .headers()
+            .iter()
+            .filter(|(k, _)| !proxy_headers::is_hop_by_hop(k.as_str(), &connection));
+        context.set_headers(headers.map(|(k, v)| {
             (
                 k.as_str().to_owned(),
                 v.to_str()
@@ -52,6 +70,11 @@ impl ResponseExt for reqwest::Response {
                     .unwrap_or_else(|_| String::from_utf8_lossy(v.as_bytes()).to_string()),
             )
         }));
+    }
+
+    async fn into_context(self, context: &ResponseContext) {
+        use rocket::futures::StreamExt;
+        self.head_into_context(context);
 
         // 处理SSE流
         if self.is_sse() {
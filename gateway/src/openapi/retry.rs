@@ -0,0 +1,93 @@
+//! # 请求重试
+//!
+//! 按路由配置的[`RetryPolicy`]判断是否需要重试，并限制重试预算。
+//!
+//! 重试预算按路由统计，在每个统计窗口内，重试次数不能超过请求数的`budget_percent`%，
+//! 但至少允许[`MIN_RETRIES_PER_WINDOW`]次，避免低流量时无法重试。
+//!
+use crate::openapi::client::UpstreamError;
+use aiway_protocol::gateway::Route;
+use aiway_protocol::gateway::route::RetryPolicy;
+use dashmap::DashMap;
+use std::sync::LazyLock;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+
+/// 重试预算的统计窗口，单位：毫秒
+const BUDGET_WINDOW: i64 = 10_000;
+/// 每个统计窗口内至少允许的重试次数
+const MIN_RETRIES_PER_WINDOW: u64 = 3;
+
+/// 路由的重试预算，key为路由的host+path
+static BUDGETS: LazyLock<DashMap<String, RetryBudget>> = LazyLock::new(DashMap::new);
+
+#[derive(Default)]
+struct RetryBudget {
+    /// 当前窗口的开始时间，毫秒
+    window_start: AtomicI64,
+    /// 当前窗口内的请求数
+    requests: AtomicU64,
+    /// 当前窗口内的重试数
+    retries: AtomicU64,
+}
+
+impl RetryBudget {
+    /// 如果窗口已过期，则重置计数
+    fn roll(&self) {
+        let now = chrono::Local::now().timestamp_millis();
+        let start = self.window_start.load(Ordering::Relaxed);
+        if now - start >= BUDGET_WINDOW
+            && self
+                .window_start
+                .compare_exchange(start, now, Ordering::Relaxed, Ordering::Relaxed)
+                .is_ok()
+        {
+            self.requests.store(0, Ordering::Relaxed);
+            self.retries.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+fn budget_key(route: &Route) -> String {
+    format!("{}{}", route.host, route.path)
+}
+
+/// 记录一次可重试的请求
+pub fn record_request(route: &Route) {
+    let budget = BUDGETS.entry(budget_key(route)).or_default();
+    budget.roll();
+    budget.requests.fetch_add(1, Ordering::Relaxed);
+}
+
+/// 尝试获取一次重试机会，超出重试预算时返回false
+pub fn acquire(route: &Route, policy: &RetryPolicy) -> bool {
+    let budget = BUDGETS.entry(budget_key(route)).or_default();
+    budget.roll();
+    let requests = budget.requests.load(Ordering::Relaxed);
+    let limit = MIN_RETRIES_PER_WINDOW.max(requests * policy.budget_percent as u64 / 100);
+    if budget.retries.fetch_add(1, Ordering::Relaxed) >= limit {
+        budget.retries.fetch_sub(1, Ordering::Relaxed);
+        log::warn!("retry budget exhausted for route {}", route.path);
+        return false;
+    }
+    true
+}
+
+/// 请求结果是否需要重试
+pub fn should_retry(
+    policy: &RetryPolicy,
+    result: &anyhow::Result<Result<reqwest::Response, UpstreamError>>,
+) -> bool {
+    match result {
+        Ok(Ok(response)) => policy.retry_statuses.contains(&response.status().as_u16()),
+        // 熔断时已切换过所有节点，无需重试
+        Ok(Err(UpstreamError::CircuitOpen)) => false,
+        // 请求体超出限制，重试也不会成功
+        Ok(Err(UpstreamError::PayloadTooLarge)) => false,
+        // 客户端已断开，无需重试
+        Ok(Err(UpstreamError::ClientAbort)) => false,
+        Ok(Err(e)) if e.is_timeout() => policy.retry_on_timeout,
+        Ok(Err(_)) => policy.retry_on_error,
+        // 网关内部错误，重试无意义
+        Err(_) => false,
+    }
+}
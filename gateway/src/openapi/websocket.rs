@@ -0,0 +1,81 @@
+//! # WebSocket代理
+//!
+//! 路由开启了[`websocket`](aiway_protocol::gateway::route::Route::websocket)时，支持HTTP/1.1的协议升级：
+//! - 升级请求与普通请求一样，经过安全、鉴权、路由、负载均衡等Fairing的处理。
+//! - 转发时补充`Connection`、`Upgrade`请求头（逐跳头部默认会被移除），服务返回101后获取升级后的服务连接。
+//! - 响应客户端时由Rocket完成升级，之后在客户端连接和服务连接之间双向转发数据，不解析帧。
+//! - 服务未返回101时，按普通响应返回给客户端。
+//!
+//! WebSocket连接数记录在[`MomentCounter`](aiway_protocol::gateway::state::MomentCounter)中。
+//!
+use crate::report::STATE;
+use context::Headers;
+use dashmap::DashMap;
+use rocket::data::{IoHandler, IoStream};
+use std::pin::Pin;
+
+/// 升级的协议
+pub const PROTOCOL: &str = "websocket";
+
+/// 是否为WebSocket升级请求，headers的名称为小写
+pub fn is_upgrade(headers: &DashMap<String, String>) -> bool {
+    let has_token = |name: &str, token: &str| {
+        headers.get(name).is_some_and(|value| {
+            value
+                .split(',')
+                .any(|v| v.trim().eq_ignore_ascii_case(token))
+        })
+    };
+    has_token(Headers::CONNECTION, Headers::UPGRADE) && has_token(Headers::UPGRADE, PROTOCOL)
+}
+
+/// 补充升级请求头，转发请求头中的逐跳头部已被移除
+pub fn set_upgrade_headers(headers: &DashMap<String, String>) {
+    headers.insert(
+        Headers::CONNECTION.to_string(),
+        Headers::UPGRADE.to_string(),
+    );
+    headers.insert(Headers::UPGRADE.to_string(), PROTOCOL.to_string());
+}
+
+/// 在客户端连接和服务连接之间双向转发数据
+pub struct WebSocketRelay {
+    upstream: reqwest::Upgraded,
+}
+
+impl WebSocketRelay {
+    pub fn new(upstream: reqwest::Upgraded) -> Self {
+        Self { upstream }
+    }
+}
+
+#[rocket::async_trait]
+impl IoHandler for WebSocketRelay {
+    async fn io(self: Pin<Box<Self>>, mut io: IoStream) -> std::io::Result<()> {
+        let mut upstream = Pin::into_inner(self).upstream;
+        STATE.inc_websocket_connect_count(1);
+        let result = tokio::io::copy_bidirectional(&mut io, &mut upstream).await;
+        STATE.inc_websocket_connect_count(-1);
+        result.map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_upgrade() {
+        let headers = DashMap::new();
+        headers.insert("connection".to_string(), "keep-alive, Upgrade".to_string());
+        headers.insert("upgrade".to_string(), "WebSocket".to_string());
+        assert!(is_upgrade(&headers));
+
+        headers.insert("upgrade".to_string(), "h2c".to_string());
+        assert!(!is_upgrade(&headers));
+
+        headers.remove("connection");
+        headers.insert("upgrade".to_string(), "websocket".to_string());
+        assert!(!is_upgrade(&headers));
+    }
+}
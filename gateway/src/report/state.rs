@@ -1,3 +1,4 @@
+use crate::components::Servicer;
 use aiway_protocol::gateway::state::{DiskState, MemState, NetState, NodeInfo, State, SystemState};
 use std::fs;
 use std::sync::{Arc, LazyLock, Mutex};
@@ -15,7 +16,8 @@ impl GatewayState {
         // 锁定
         let mut state_guard = self.state.lock().unwrap();
         // 旧状态
-        let old = state_guard.clone();
+        let mut old = state_guard.clone();
+        old.node_health = Servicer::health_states();
 
         // 更新状态并重置计数器
         *state_guard = State {
@@ -24,6 +26,7 @@ impl GatewayState {
             system_state,
             counter: Default::default(),
             moment_counter: old.moment_counter.clone(),
+            node_health: vec![],
         };
 
         old
@@ -116,11 +119,13 @@ impl GatewayState {
         self.state.lock().unwrap().counter.response_time_since_last += time;
     }
 
-    pub fn inc_http_connect_count(&self, n: isize) {
+    /// 更新http连接数，返回更新后的连接数
+    pub fn inc_http_connect_count(&self, n: isize) -> isize {
         let state = &mut self.state.lock().unwrap();
         // 这里减的时候可能导致小于0，需要保证不能小于0
         state.moment_counter.http_connect_count =
             0.max(state.moment_counter.http_connect_count + n);
+        state.moment_counter.http_connect_count
     }
 
     pub fn inc_sse_connect_count(&self, n: isize) {
@@ -129,10 +134,21 @@ impl GatewayState {
         state.moment_counter.sse_connect_count = 0.max(state.moment_counter.sse_connect_count + n);
     }
 
+    pub fn inc_websocket_connect_count(&self, n: isize) {
+        let state = &mut self.state.lock().unwrap();
+        // 这里减的时候可能导致小于0，需要保证不能小于0
+        state.moment_counter.websocket_connect_count =
+            0.max(state.moment_counter.websocket_connect_count + n);
+    }
+
     pub fn inc_request_invalid_count(&self, n: usize) {
         self.state.lock().unwrap().counter.request_invalid_count += n;
     }
 
+    pub fn inc_request_ratelimit_count(&self, n: usize) {
+        self.state.lock().unwrap().counter.request_ratelimit_count += n;
+    }
+
     #[allow(unused)]
     pub fn get_http_connect_count(&self) -> isize {
         self.state.lock().unwrap().moment_counter.http_connect_count
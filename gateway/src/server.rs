@@ -36,21 +36,31 @@
 //!
 //!
 //!
+use crate::components::{grpc, proxy_protocol, tls};
 use crate::{Args, fairing, openapi};
 use rocket::data::{ByteUnit, Limits};
 use rocket::fairing::AdHoc;
 use rocket::{Config, catchers, routes};
-use std::net::IpAddr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::process::exit;
 use std::str::FromStr;
 
 pub async fn start_http_server(args: &Args) -> anyhow::Result<()> {
+    let address = IpAddr::from_str(args.address.as_str())?;
+    // 启用PROXY协议时，由前置监听器监听网关地址，Rocket监听本机回环地址上由系统分配的端口
+    let listen = match args.proxy_protocol {
+        true => SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0),
+        false => SocketAddr::new(address, args.port),
+    };
+
     let mut builder = rocket::build().configure(Config {
-        address: IpAddr::from_str(args.address.as_str())?,
-        port: args.port,
+        address: listen.ip(),
+        port: listen.port(),
         limits: Limits::default()
             .limit("json", ByteUnit::Mebibyte(5))
             .limit("data-form", ByteUnit::Mebibyte(100))
-            .limit("file", ByteUnit::Mebibyte(100)),
+            .limit("file", ByteUnit::Mebibyte(100))
+            .limit(openapi::STREAM_LIMIT, openapi::DEFAULT_STREAM_LIMIT),
         log_level: rocket::config::LogLevel::Off,
         cli_colors: false,
         ..Config::debug_default()
@@ -65,10 +75,14 @@ pub async fn start_http_server(args: &Args) -> anyhow::Result<()> {
     builder = builder.attach(fairing::request::RequestData::new());
     // 路由匹配
     builder = builder.attach(fairing::routing::Routing::new());
+    // 提取请求体，流式转发请求体的路由不提取
+    builder = builder.attach(fairing::body::RequestBody::new());
     // 全局前置过滤器，可自由配置，串联执行，对整个网关生效，可做全局安全验证、监控、日志记录等。
     builder = builder.attach(fairing::global_filter::GlobalPreFilter::new());
     // 鉴权，即验证API Key
     builder = builder.attach(fairing::auth::Authentication::new());
+    // 限流，在鉴权之后执行，以便按API Key限流。超出限制时返回429
+    builder = builder.attach(fairing::ratelimit::RateLimiter::new());
     // 路由前置过滤器，可自由配置，串联执行，对单个路由生效，由于插件本身要求设计为无状态，所以，理论上各个路由的相同插件互不影响
     // 注意：是在路由匹配之后执行，因为要先匹配到路由，才能获取路由对应的插件，这点可能和命名有点歧义。
     builder = builder.attach(fairing::filter::PreFilter::new());
@@ -102,6 +116,24 @@ pub async fn start_http_server(args: &Args) -> anyhow::Result<()> {
     );
     //builder = builder.mount("/eep", eep::routes());
 
+    // Rocket完成监听后再启动前置监听器，此时可以获取Rocket实际监听的端口
+    let listeners = Listeners {
+        address,
+        port: args.port,
+        proxy_protocol: args.proxy_protocol,
+        grpc_port: args.grpc_port,
+        tls_port: args.tls_port,
+    };
+    builder = builder.attach(AdHoc::on_liftoff("Listeners", move |rocket| {
+        let port = rocket.config().port;
+        Box::pin(async move {
+            if let Err(e) = listeners.serve(port).await {
+                log::error!("start listeners error: {}", e);
+                exit(1);
+            }
+        })
+    }));
+
     builder = builder.attach(AdHoc::on_liftoff("Print Banner", |_| {
         Box::pin(async {
             print_banner();
@@ -112,8 +144,11 @@ pub async fn start_http_server(args: &Args) -> anyhow::Result<()> {
         "/",
         catchers![
             fairing::catchers::catch_401,
+            fairing::catchers::catch_402,
             fairing::catchers::catch_403,
-            fairing::catchers::catch_404
+            fairing::catchers::catch_404,
+            fairing::catchers::catch_429,
+            fairing::catchers::catch_503
         ],
     );
 
@@ -122,6 +157,40 @@ pub async fn start_http_server(args: &Args) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// 转发到Rocket的前置监听器
+#[derive(Clone, Copy)]
+struct Listeners {
+    address: IpAddr,
+    port: u16,
+    proxy_protocol: bool,
+    grpc_port: Option<u16>,
+    tls_port: Option<u16>,
+}
+
+impl Listeners {
+    /// 启动前置监听器，`rocket_port`为Rocket实际监听的端口
+    async fn serve(self, rocket_port: u16) -> anyhow::Result<()> {
+        // 前置监听器通过本机回环地址将请求转发到Rocket
+        let ip = match self.address {
+            _ if self.proxy_protocol => Ipv4Addr::LOCALHOST.into(),
+            IpAddr::V4(ip) if ip.is_unspecified() => Ipv4Addr::LOCALHOST.into(),
+            IpAddr::V6(ip) if ip.is_unspecified() => Ipv6Addr::LOCALHOST.into(),
+            address => address,
+        };
+        let upstream = SocketAddr::new(ip, rocket_port);
+        if self.proxy_protocol {
+            proxy_protocol::serve(SocketAddr::new(self.address, self.port), upstream).await?;
+        }
+        if let Some(port) = self.grpc_port {
+            grpc::serve(SocketAddr::new(self.address, port), upstream).await?;
+        }
+        if let Some(port) = self.tls_port {
+            tls::serve(SocketAddr::new(self.address, port), upstream).await?;
+        }
+        Ok(())
+    }
+}
+
 fn print_banner() {
     use clap::Parser;
     let args = Args::parse();
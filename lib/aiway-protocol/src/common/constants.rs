@@ -7,7 +7,9 @@ pub const ENCRYPT_KEY: &[u8; 32] = b"00000000000000000000000000000000";
 /// 状态上报间隔秒数
 pub const REPORT_STATE_INTERVAL: u64 = 5;
 
-/// 禁止透传的HTTP头部
+/// 禁止透传的HTTP头部，包含逐跳（hop-by-hop）头部，请求和响应方向都不透传
+///
+/// 除此之外，`Connection`头中列出的头部同样是逐跳的，也不应透传。
 pub static BAN_HEADERS: LazyLock<HashSet<&'static str>> = LazyLock::new(|| {
     [
         "content-length",
@@ -17,8 +19,13 @@ pub static BAN_HEADERS: LazyLock<HashSet<&'static str>> = LazyLock::new(|| {
         "server",
         "transfer-encoding",
         "connection",
+        "keep-alive",
+        "proxy-connection",
+        "proxy-authenticate",
+        "proxy-authorization",
         "te",
         "trailer",
+        "upgrade",
         "permissions-policy",
     ]
     .iter()
@@ -35,6 +35,9 @@ pub struct AlertConfig {
     pub feishu: FeishuConfig,
     /// 自定义WebHook
     pub custom: CustomConfig,
+    /// API Key到期前多少天发送提醒，为空时默认7天，为0时不提醒
+    #[serde(default)]
+    pub api_key_expire_days: Option<u32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
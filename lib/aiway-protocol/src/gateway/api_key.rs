@@ -1,6 +1,7 @@
 use base58::{FromBase58, ToBase58};
 use chacha20poly1305::aead::Aead;
 use chacha20poly1305::{AeadCore, ChaCha20Poly1305, KeyInit, Nonce};
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 #[derive(Debug)]
@@ -19,7 +20,7 @@ impl std::fmt::Display for ApiKeyError {
 pub struct ApiKey {
     // 随机值，固定12字符，不参与正文加解密
     nonce: [u8; 12],
-    // 预留标记1，固定1字节
+    // 加密密钥ID，固定1字节，用于从密钥环中选择密钥，轮换前签发的为0
     s1: u8,
     // 预留标记2，固定1字节
     s2: u8,
@@ -59,6 +60,17 @@ impl ApiKey {
         }
     }
 
+    /// 设置加密密钥ID
+    pub fn with_key_id(mut self, key_id: u8) -> Self {
+        self.s1 = key_id;
+        self
+    }
+
+    /// 加密密钥ID
+    pub fn key_id(&self) -> u8 {
+        self.s1
+    }
+
     #[inline]
     fn generate_nonce() -> [u8; 12] {
         let uuid = Uuid::new_v4();
@@ -131,11 +143,292 @@ impl ApiKey {
             principal,
         })
     }
+
+    /// 按密钥环解密，依次尝试每个密钥，解密成功且密钥ID一致时返回
+    ///
+    /// - keys: (密钥ID, 密钥)，当前密钥应放在最前面
+    pub fn decrypt_with_keyring<'a, K: IntoIterator<Item = (u8, &'a [u8; 32])>>(
+        keys: K,
+        ciphertext: &str,
+    ) -> Result<ApiKey, ApiKeyError> {
+        keys.into_iter()
+            .filter_map(|(key_id, key)| {
+                Self::decrypt(key, ciphertext)
+                    .ok()
+                    .filter(|api_key| api_key.key_id() == key_id)
+            })
+            .next()
+            .ok_or(ApiKeyError::InvalidApiKey)
+    }
+}
+
+/// 缓存中API Key对应的值，由控制台在新增、修改API Key时写入，网关鉴权时校验
+///
+/// 兼容旧版本写入的值：空值表示永久有效且不限制授权范围，仅有授权范围的值表示永久有效。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiKeyEntry {
+    /// 是否启用，禁用的API Key验证失败
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// 生效时间，秒级时间戳，为空时立即生效
+    #[serde(default)]
+    pub eff_time: Option<i64>,
+    /// 失效时间，秒级时间戳，为空时永久有效
+    #[serde(default)]
+    pub exp_time: Option<i64>,
+    /// 授权范围
+    #[serde(flatten)]
+    pub scope: ApiKeyScope,
+    /// 月度配额
+    #[serde(default)]
+    pub quota: ApiKeyQuota,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl Default for ApiKeyEntry {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            eff_time: None,
+            exp_time: None,
+            scope: ApiKeyScope::default(),
+            quota: ApiKeyQuota::default(),
+        }
+    }
+}
+
+impl ApiKeyEntry {
+    /// 校验API Key在`now`（秒级时间戳）时是否可用，不可用时返回原因
+    pub fn check_valid(&self, now: i64) -> Result<(), String> {
+        if !self.enabled {
+            return Err("api key is disabled".to_string());
+        }
+        if let Some(eff_time) = self.eff_time
+            && now < eff_time
+        {
+            return Err("api key is not yet effective".to_string());
+        }
+        if let Some(exp_time) = self.exp_time
+            && now >= exp_time
+        {
+            return Err("api key is expired".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// API Key的授权范围
+///
+/// 保存在缓存中API Key对应的值中，见[`ApiKeyEntry`]，网关鉴权时校验，超出范围的请求返回403。
+/// 各项为空时不限制，多项同时配置时需全部满足。
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ApiKeyScope {
+    /// 允许访问的路由名称
+    #[serde(default)]
+    pub routes: Vec<String>,
+    /// 允许访问的服务名称，路由配置了加权后端时，所有后端的服务都需要在范围内
+    #[serde(default)]
+    pub services: Vec<String>,
+    /// 允许的请求方法，大写，如：GET、POST
+    #[serde(default)]
+    pub methods: Vec<String>,
+    /// 允许调用的模型名称，按请求体中的`model`字段校验
+    ///
+    /// 无法确定模型时拒绝请求，如请求体不是JSON、没有该字段，或路由流式转发请求体。
+    #[serde(default)]
+    pub models: Vec<String>,
+}
+
+impl ApiKeyScope {
+    /// 是否不限制
+    pub fn is_unrestricted(&self) -> bool {
+        self.routes.is_empty()
+            && self.services.is_empty()
+            && self.methods.is_empty()
+            && self.models.is_empty()
+    }
+
+    /// 校验请求是否在授权范围内，不在范围内时返回原因
+    ///
+    /// - route: 路由名称
+    /// - services: 路由转发的服务，配置了加权后端时为所有后端的服务
+    /// - method: 请求方法
+    /// - model: 请求体中的模型名称，无法确定时为空
+    pub fn check<'a, S: IntoIterator<Item = &'a str>>(
+        &self,
+        route: &str,
+        services: S,
+        method: &str,
+        model: Option<&str>,
+    ) -> Result<(), String> {
+        if !self.routes.is_empty() && !self.routes.iter().any(|r| r == route) {
+            return Err(format!("route {} is out of scope", route));
+        }
+        if !self.services.is_empty()
+            && let Some(service) = services
+                .into_iter()
+                .find(|service| !self.services.iter().any(|s| s == service))
+        {
+            return Err(format!("service {} is out of scope", service));
+        }
+        if !self.methods.is_empty() && !self.methods.iter().any(|m| m.eq_ignore_ascii_case(method))
+        {
+            return Err(format!("method {} is out of scope", method));
+        }
+        if !self.models.is_empty() {
+            match model {
+                Some(model) if self.models.iter().any(|m| m == model) => {}
+                Some(model) => return Err(format!("model {} is out of scope", model)),
+                None => return Err("model is required by the scope".to_string()),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// API Key的月度配额，按自然月统计，超出时返回402
+///
+/// 用量按API Key的主体标识统计，同一主体的多个API Key共享用量，未设置主体标识的API Key不计量。
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ApiKeyQuota {
+    /// 每月的请求次数上限，为空时不限制
+    #[serde(default)]
+    pub requests: Option<u64>,
+    /// 每月的Token用量上限，为空时不限制
+    ///
+    /// 按响应中的`usage.total_tokens`统计，仅模型接口的响应包含该字段。
+    #[serde(default)]
+    pub tokens: Option<u64>,
+}
+
+impl ApiKeyQuota {
+    /// 用量的计量项：请求次数
+    pub const REQUESTS: &'static str = "requests";
+    /// 用量的计量项：Token数
+    pub const TOKENS: &'static str = "tokens";
+    /// 按天统计的周期格式，如：20250101
+    pub const DAY_FORMAT: &'static str = "%Y%m%d";
+    /// 按月统计的周期格式，如：202501，用于校验月度配额
+    pub const MONTH_FORMAT: &'static str = "%Y%m";
+
+    /// 是否不限制
+    pub fn is_unlimited(&self) -> bool {
+        self.requests.is_none() && self.tokens.is_none()
+    }
+
+    /// 按本月已用的请求次数和Token数校验，用尽时返回原因
+    pub fn check(&self, requests: u64, tokens: u64) -> Result<(), String> {
+        if let Some(limit) = self.requests
+            && requests >= limit
+        {
+            return Err(format!("request quota {} is exhausted", limit));
+        }
+        if let Some(limit) = self.tokens
+            && tokens >= limit
+        {
+            return Err(format!("token quota {} is exhausted", limit));
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    #[test]
+    fn test_scope() {
+        let scope = ApiKeyScope::default();
+        assert!(scope.is_unrestricted());
+        assert!(
+            scope
+                .check("chat", ["openai"], "POST", Some("gpt-4o"))
+                .is_ok()
+        );
+
+        let scope = ApiKeyScope {
+            routes: vec!["chat".to_string()],
+            services: vec!["openai".to_string()],
+            methods: vec!["POST".to_string()],
+            models: vec!["gpt-4o".to_string()],
+        };
+        assert!(
+            scope
+                .check("chat", ["openai"], "post", Some("gpt-4o"))
+                .is_ok()
+        );
+        // 限制了模型时，无法确定模型的请求被拒绝
+        assert!(scope.check("chat", ["openai"], "POST", None).is_err());
+        assert!(
+            scope
+                .check("admin", ["openai"], "POST", Some("gpt-4o"))
+                .is_err()
+        );
+        assert!(
+            scope
+                .check("chat", ["openai", "azure"], "POST", Some("gpt-4o"))
+                .is_err()
+        );
+        assert!(
+            scope
+                .check("chat", ["openai"], "DELETE", Some("gpt-4o"))
+                .is_err()
+        );
+        assert!(scope.check("chat", ["openai"], "POST", Some("o1")).is_err());
+
+        let scope = ApiKeyScope {
+            routes: vec!["chat".to_string()],
+            ..Default::default()
+        };
+        assert!(scope.check("chat", ["openai"], "POST", None).is_ok());
+    }
+
+    #[test]
+    fn test_entry() {
+        // 旧版本写入的值
+        let entry: Option<ApiKeyEntry> = serde_json::from_str("null").unwrap();
+        assert!(entry.unwrap_or_default().check_valid(100).is_ok());
+        let entry: ApiKeyEntry = serde_json::from_str(r#"{"routes":["chat"]}"#).unwrap();
+        assert_eq!(entry.scope.routes, vec!["chat".to_string()]);
+        assert!(entry.check_valid(100).is_ok());
+
+        let entry = ApiKeyEntry {
+            eff_time: Some(100),
+            exp_time: Some(200),
+            ..Default::default()
+        };
+        assert!(entry.check_valid(99).is_err());
+        assert!(entry.check_valid(100).is_ok());
+        assert!(entry.check_valid(200).is_err());
+        let entry = ApiKeyEntry {
+            enabled: false,
+            ..entry
+        };
+        assert!(entry.check_valid(150).is_err());
+    }
+
+    #[test]
+    fn test_quota() {
+        let quota = ApiKeyQuota::default();
+        assert!(quota.is_unlimited());
+        assert!(quota.check(u64::MAX, u64::MAX).is_ok());
+
+        let quota = ApiKeyQuota {
+            requests: Some(10),
+            tokens: Some(1000),
+        };
+        assert!(quota.check(9, 999).is_ok());
+        assert!(quota.check(10, 0).is_err());
+        assert!(quota.check(0, 1000).is_err());
+
+        // 旧版本写入的值没有配额
+        let entry: ApiKeyEntry = serde_json::from_str(r#"{"routes":["chat"]}"#).unwrap();
+        assert!(entry.quota.is_unlimited());
+    }
+
     #[test]
     fn test_api_key() {
         let key = &[0; 32];
@@ -148,4 +441,27 @@ mod tests {
         let api_key = ApiKey::decrypt(key, &api_key);
         println!("{:?}", api_key);
     }
+    #[test]
+    fn test_keyring() {
+        let old = &[1; 32];
+        let new = &[2; 32];
+        let legacy = ApiKey::new_with_principal("u1").encrypt(old);
+        let rotated = ApiKey::new_with_principal("u1").with_key_id(1).encrypt(new);
+
+        let keyring = [(1, new), (0, old)];
+        let api_key = ApiKey::decrypt_with_keyring(keyring, &legacy).unwrap();
+        assert_eq!(api_key.key_id(), 0);
+        assert_eq!(api_key.principal, "u1");
+        assert_eq!(
+            ApiKey::decrypt_with_keyring(keyring, &rotated)
+                .unwrap()
+                .key_id(),
+            1
+        );
+
+        // 旧密钥已移出密钥环
+        assert!(ApiKey::decrypt_with_keyring([(1, new)], &legacy).is_err());
+        // 密钥ID与密钥不一致
+        assert!(ApiKey::decrypt_with_keyring([(2, new)], &rotated).is_err());
+    }
 }
@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::{Debug, Formatter};
+
+/// TLS证书
+///
+/// 网关在TLS握手时按客户端的SNI选择证书：
+/// - 优先精确匹配域名，再匹配通配符域名，如`api.example.com`可匹配`*.example.com`。
+/// - 通配符仅匹配一级子域名，`*.example.com`不匹配`example.com`及`a.b.example.com`。
+/// - 没有匹配的证书时，握手失败。
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Certificate {
+    /// 证书名称，全局唯一
+    pub name: String,
+    /// 证书适用的域名，小写，支持通配符，如：example.com、*.example.com
+    pub hosts: Vec<String>,
+    /// 证书链，PEM格式，第一个为服务端证书
+    pub cert: String,
+    /// 私钥，PEM格式，支持PKCS#1、PKCS#8和SEC1
+    pub key: String,
+    /// 验证客户端证书的CA证书，PEM格式，可包含多个，为空时不请求客户端证书
+    ///
+    /// 配置后，握手时请求客户端证书，但不强制提供，由路由的
+    /// [`client_cert`](crate::gateway::Route::client_cert)决定是否必须。
+    #[serde(default)]
+    pub client_ca: Option<String>,
+}
+
+impl Debug for Certificate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // 不输出证书及私钥内容
+        f.debug_struct("Certificate")
+            .field("name", &self.name)
+            .field("hosts", &self.hosts)
+            .field("client_ca", &self.client_ca.is_some())
+            .finish()
+    }
+}
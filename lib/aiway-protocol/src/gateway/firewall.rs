@@ -1,7 +1,9 @@
 use crate::common::constants::ENCRYPT_KEY;
+use crate::gateway::rate_limit::RateLimit;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::HashSet;
 use std::fmt::{Debug, Display, Formatter};
+use std::net::IpAddr;
 
 /// 防火墙配置
 ///
@@ -11,32 +13,51 @@ use std::fmt::{Debug, Display, Formatter};
 pub struct Firewall {
     /// IP策略模式，allow或deny
     pub ip_policy_mode: AllowDenyPolicy,
-    /// IP策略值，例如：192.168.1.1
-    /// TODO 暂不支持网段，后面再支持
+    /// IP策略值，支持IPv4、IPv6及网段，例如：192.168.1.1、192.168.0.0/16、2001:db8::/32
     pub ip_policy: HashSet<String>,
-    /// 受信IP
+    /// 受信IP，格式同`ip_policy`
     ///
     /// 受信IP将直接放行，不受访问策略的影响
     pub trust_ips: HashSet<String>,
+    /// 受信代理，格式同`ip_policy`，例如前置的负载均衡器
+    ///
+    /// 仅当直连网关的对端在受信代理中时，才从`X-Forwarded-For`、`X-Real-IP`
+    /// 或PROXY协议中提取真实的客户端IP，否则使用对端IP，避免客户端伪造IP。
+    #[serde(default)]
+    pub trusted_proxies: HashSet<String>,
     /// Referer策略模式，allow或deny
     pub referer_policy_mode: AllowDenyPolicy,
     /// Referer策略值，例如：https://aaa.com
     pub referer_policy: HashSet<String>,
     /// 是否允许空Referer
     pub allow_empty_referer: bool,
-    /// 单个网关节点的最大连接数限制
-    // /// 例如：127.0.0.1:8080/1000，
-    // /// 对所有节点限制：*/2000，
-    // /// 如果配置了具体的节点限制，则优先使用具体配置。
-    pub max_connections: Option<usize>,
+    /// 单个网关节点的最大连接数限制，为空时不限制
+    /// 例如：127.0.0.1:8080/1000，
+    /// 对所有节点限制：*/2000，
+    /// 如果配置了具体的节点限制，则优先使用具体配置。
+    ///
+    /// 兼容旧版本的数字配置，如2000，等同于*/2000
+    #[serde(default, deserialize_with = "deserialize_max_connections")]
+    pub max_connections: HashSet<String>,
     /// API密钥的加密密钥，长度固定为32位，由控制台验证长度。
     /// 可能为空字符串，为空时使用默认密钥
+    ///
+    /// 只能通过控制台的密钥轮换修改，见[`retired_api_secret_keys`](Self::retired_api_secret_keys)。
     #[serde(
         default = "default_api_secret_encrypt_key",
         serialize_with = "serialize_encrypt_key",
         deserialize_with = "deserialize_encrypt_key"
     )]
     pub api_secret_encrypt_key: [u8; 32],
+    /// 加密密钥ID，写入签发的API Key中，每次轮换时变更，未轮换过时为0
+    #[serde(default)]
+    pub api_secret_key_id: u8,
+    /// 已轮换的旧加密密钥，在宽限期内，用旧密钥签发的API Key依然可以通过验证
+    #[serde(default)]
+    pub retired_api_secret_keys: Vec<RetiredSecretKey>,
+    /// 全局限流，对所有路由生效，为空时不限流
+    #[serde(default)]
+    pub rate_limit: Option<RateLimit>,
 }
 
 impl Default for Firewall {
@@ -45,15 +66,137 @@ impl Default for Firewall {
             ip_policy_mode: AllowDenyPolicy::Disable,
             ip_policy: Default::default(),
             trust_ips: Default::default(),
+            trusted_proxies: Default::default(),
             referer_policy_mode: Default::default(),
             referer_policy: Default::default(),
             allow_empty_referer: false,
             max_connections: Default::default(),
             api_secret_encrypt_key: *ENCRYPT_KEY,
+            api_secret_key_id: 0,
+            retired_api_secret_keys: vec![],
+            rate_limit: None,
+        }
+    }
+}
+
+/// 已轮换的旧加密密钥
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RetiredSecretKey {
+    /// 加密密钥ID
+    pub id: u8,
+    /// 加密密钥
+    #[serde(
+        serialize_with = "serialize_encrypt_key",
+        deserialize_with = "deserialize_encrypt_key"
+    )]
+    pub key: [u8; 32],
+    /// 宽限期的结束时间，秒级时间戳，之后不再用于验证
+    pub expire_time: i64,
+}
+
+impl Debug for RetiredSecretKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // 不输出密钥内容
+        f.debug_struct("RetiredSecretKey")
+            .field("id", &self.id)
+            .field("expire_time", &self.expire_time)
+            .finish()
+    }
+}
+
+impl Firewall {
+    /// 是否在使用默认的加密密钥
+    pub fn is_default_encrypt_key(&self) -> bool {
+        self.api_secret_encrypt_key == *ENCRYPT_KEY
+    }
+
+    /// 在`now`（秒级时间戳）时可用于验证API Key的加密密钥，返回(密钥ID, 密钥)，当前密钥在最前面
+    pub fn api_secret_keys(&self, now: i64) -> Vec<(u8, [u8; 32])> {
+        let retired = self
+            .retired_api_secret_keys
+            .iter()
+            .filter(|retired| retired.expire_time > now)
+            .map(|retired| (retired.id, retired.key));
+        std::iter::once((self.api_secret_key_id, self.api_secret_encrypt_key))
+            .chain(retired)
+            .collect()
+    }
+
+    /// 获取网关节点的最大连接数限制，节点格式为ip:port
+    pub fn max_connections_of(&self, node: &str) -> Option<usize> {
+        let mut all = None;
+        for rule in &self.max_connections {
+            match parse_max_connections(rule) {
+                Ok((target, limit)) if target == node => return Some(limit),
+                Ok(("*", limit)) => all = Some(limit),
+                _ => {}
+            }
         }
+        all
     }
 }
 
+/// 解析IP或网段，返回(网络地址, 前缀长度)，单个IP的前缀长度为32（IPv4）或128（IPv6）
+///
+/// 例如：192.168.1.1、192.168.0.0/16、2001:db8::/32
+pub fn parse_ip_range(value: &str) -> Result<(IpAddr, u8), String> {
+    let value = value.trim();
+    let (ip, prefix) = match value.split_once('/') {
+        Some((ip, prefix)) => (ip, Some(prefix)),
+        None => (value, None),
+    };
+    let ip = ip
+        .parse::<IpAddr>()
+        .map_err(|_| format!("invalid ip: {}", value))?
+        .to_canonical();
+    let max = if ip.is_ipv4() { 32 } else { 128 };
+    let prefix = match prefix {
+        Some(prefix) => prefix
+            .parse::<u8>()
+            .ok()
+            .filter(|prefix| *prefix <= max)
+            .ok_or_else(|| format!("invalid ip prefix: {}", value))?,
+        None => max,
+    };
+    Ok((ip, prefix))
+}
+
+/// 解析最大连接数配置，返回(节点, 最大连接数)，节点为*时对所有节点生效
+pub fn parse_max_connections(rule: &str) -> Result<(&str, usize), String> {
+    let (node, limit) = rule
+        .trim()
+        .rsplit_once('/')
+        .ok_or_else(|| format!("invalid max connections: {}", rule))?;
+    let limit = limit
+        .trim()
+        .parse::<usize>()
+        .map_err(|_| format!("invalid max connections: {}", rule))?;
+    let node = node.trim();
+    if node.is_empty() {
+        return Err(format!("invalid max connections: {}", rule));
+    }
+    Ok((node, limit))
+}
+
+fn deserialize_max_connections<'de, D>(deserializer: D) -> Result<HashSet<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum MaxConnections {
+        /// 旧版本的配置，对所有节点生效
+        Legacy(usize),
+        Rules(HashSet<String>),
+    }
+
+    Ok(match Option::<MaxConnections>::deserialize(deserializer)? {
+        None => HashSet::new(),
+        Some(MaxConnections::Legacy(limit)) => HashSet::from([format!("*/{}", limit)]),
+        Some(MaxConnections::Rules(rules)) => rules,
+    })
+}
+
 fn serialize_encrypt_key<S>(key: &[u8; 32], serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
@@ -111,6 +254,7 @@ impl Debug for Firewall {
             .field("ip_policy_mode", &self.ip_policy_mode)
             .field("ip_policy", &self.ip_policy)
             .field("trust_ips", &self.trust_ips)
+            .field("trusted_proxies", &self.trusted_proxies)
             .field("referer_policy_mode", &self.referer_policy_mode)
             .field("referer_policy", &self.referer_policy)
             .field("allow_empty_referer", &self.allow_empty_referer)
@@ -122,6 +266,85 @@ impl Debug for Firewall {
                     String::from_utf8(self.api_secret_encrypt_key[0..5].to_vec()).unwrap()
                 ),
             )
+            .field("api_secret_key_id", &self.api_secret_key_id)
+            .field("retired_api_secret_keys", &self.retired_api_secret_keys)
+            .field("rate_limit", &self.rate_limit)
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_max_connections() {
+        let firewall: Firewall = serde_json::from_value(serde_json::json!({
+            "ip_policy_mode": "Disable",
+            "ip_policy": [],
+            "trust_ips": [],
+            "referer_policy_mode": "Disable",
+            "referer_policy": [],
+            "allow_empty_referer": true,
+            "max_connections": ["127.0.0.1:8080/1000", "*/2000"],
+            "api_secret_encrypt_key": ""
+        }))
+        .unwrap();
+        assert_eq!(firewall.max_connections_of("127.0.0.1:8080"), Some(1000));
+        assert_eq!(firewall.max_connections_of("127.0.0.1:8081"), Some(2000));
+
+        // 兼容旧版本的数字配置
+        let firewall: Firewall = serde_json::from_value(serde_json::json!({
+            "ip_policy_mode": "Disable",
+            "ip_policy": [],
+            "trust_ips": [],
+            "referer_policy_mode": "Disable",
+            "referer_policy": [],
+            "allow_empty_referer": true,
+            "max_connections": 500,
+            "api_secret_encrypt_key": ""
+        }))
+        .unwrap();
+        assert_eq!(firewall.max_connections_of("127.0.0.1:8080"), Some(500));
+    }
+
+    #[test]
+    fn test_api_secret_keys() {
+        let mut firewall = Firewall::default();
+        assert!(firewall.is_default_encrypt_key());
+        assert_eq!(firewall.api_secret_keys(0), vec![(0, *ENCRYPT_KEY)]);
+
+        firewall.retired_api_secret_keys = vec![RetiredSecretKey {
+            id: 0,
+            key: *ENCRYPT_KEY,
+            expire_time: 100,
+        }];
+        firewall.api_secret_key_id = 1;
+        firewall.api_secret_encrypt_key = [b'1'; 32];
+        assert!(!firewall.is_default_encrypt_key());
+        assert_eq!(
+            firewall.api_secret_keys(99),
+            vec![(1, [b'1'; 32]), (0, *ENCRYPT_KEY)]
+        );
+        // 宽限期已结束
+        assert_eq!(firewall.api_secret_keys(100), vec![(1, [b'1'; 32])]);
+    }
+
+    #[test]
+    fn test_parse_ip_range() {
+        assert_eq!(
+            parse_ip_range("192.168.0.0/16"),
+            Ok(("192.168.0.0".parse().unwrap(), 16))
+        );
+        assert_eq!(
+            parse_ip_range("10.0.0.1"),
+            Ok(("10.0.0.1".parse().unwrap(), 32))
+        );
+        assert_eq!(
+            parse_ip_range("2001:db8::/32"),
+            Ok(("2001:db8::".parse().unwrap(), 32))
+        );
+        assert!(parse_ip_range("10.0.0.0/33").is_err());
+        assert!(parse_ip_range("10.0.0/8").is_err());
+    }
+}
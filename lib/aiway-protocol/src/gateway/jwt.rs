@@ -0,0 +1,124 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt::{Debug, Formatter};
+
+/// JWT签发方
+///
+/// 路由的鉴权方式为JWT时引用，见[`RouteAuth::Jwt`](crate::gateway::route::RouteAuth::Jwt)。
+/// 验证签名的密钥按以下顺序选择：
+/// - 配置了[`key`](Self::key)时，使用该密钥。
+/// - 配置了[`jwks`](Self::jwks)时，按JWT头中的`kid`从JWKS中选择密钥，JWT未指定`kid`时使用第一个。
+/// - 都未配置时，读取网关节点上的[`jwks_file`](Self::jwks_file)，用于无法在控制台维护JWKS的离线环境。
+#[derive(Clone, Serialize, Deserialize)]
+pub struct JwtProvider {
+    /// 签发方名称，全局唯一
+    pub name: String,
+    /// 签名算法，JWT头中的`alg`必须与之一致
+    pub algorithm: JwtAlgorithm,
+    /// 验证签名的密钥：HS256为共享密钥，RS256和ES256为PEM格式的公钥
+    #[serde(default)]
+    pub key: Option<String>,
+    /// JWKS文档，JSON格式，如：`{"keys":[{"kty":"RSA","kid":"k1","n":"...","e":"AQAB"}]}`
+    #[serde(default)]
+    pub jwks: Option<String>,
+    /// 网关节点上的JWKS文件路径
+    #[serde(default)]
+    pub jwks_file: Option<String>,
+    /// 签发者，配置后`iss`必须与之一致
+    #[serde(default)]
+    pub issuer: Option<String>,
+    /// 受众，配置后`aud`必须包含其中之一
+    #[serde(default)]
+    pub audiences: Vec<String>,
+    /// 校验`exp`和`nbf`时允许的时钟偏差，单位：秒，默认60
+    #[serde(default = "default_leeway")]
+    pub leeway: u64,
+}
+
+fn default_leeway() -> u64 {
+    60
+}
+
+impl Debug for JwtProvider {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // 不输出密钥内容
+        f.debug_struct("JwtProvider")
+            .field("name", &self.name)
+            .field("algorithm", &self.algorithm)
+            .field("key", &self.key.is_some())
+            .field("jwks", &self.jwks.is_some())
+            .field("jwks_file", &self.jwks_file)
+            .field("issuer", &self.issuer)
+            .field("audiences", &self.audiences)
+            .field("leeway", &self.leeway)
+            .finish()
+    }
+}
+
+/// JWT签名算法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum JwtAlgorithm {
+    /// HMAC SHA-256，共享密钥
+    #[default]
+    Hs256,
+    /// RSASSA-PKCS1-v1_5 SHA-256
+    Rs256,
+    /// ECDSA P-256 SHA-256
+    Es256,
+}
+
+/// 按名称查找声明，支持用`.`访问嵌套的声明，如：`realm_access.roles`
+pub fn find_claim<'a>(claims: &'a Value, name: &str) -> Option<&'a Value> {
+    // 优先按完整名称查找，声明名称本身可能包含`.`，如：`https://example.com/tenant`
+    if let Some(value) = claims.get(name) {
+        return Some(value);
+    }
+    name.split('.')
+        .try_fold(claims, |value, key| value.get(key))
+        .filter(|value| !value.is_null())
+}
+
+/// 声明转为字符串，用于设置请求头：字符串不加引号，数组以`,`连接，其他类型按JSON格式
+pub fn claim_to_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Array(values) => values
+            .iter()
+            .map(claim_to_string)
+            .collect::<Vec<_>>()
+            .join(","),
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_find_claim() {
+        let claims = json!({
+            "sub": "u1",
+            "https://example.com/tenant": "t1",
+            "realm_access": {"roles": ["admin", "user"]},
+            "age": 18,
+        });
+        let find = |name| find_claim(&claims, name).map(claim_to_string);
+        assert_eq!(find("sub").as_deref(), Some("u1"));
+        assert_eq!(find("https://example.com/tenant").as_deref(), Some("t1"));
+        assert_eq!(find("realm_access.roles").as_deref(), Some("admin,user"));
+        assert_eq!(find("age").as_deref(), Some("18"));
+        assert_eq!(find("realm_access.groups"), None);
+        assert_eq!(find("email"), None);
+    }
+
+    #[test]
+    fn test_algorithm() {
+        let provider: JwtProvider =
+            serde_json::from_str(r#"{"name":"idp","algorithm":"RS256"}"#).unwrap();
+        assert_eq!(provider.algorithm, JwtAlgorithm::Rs256);
+        assert_eq!(provider.leeway, 60);
+    }
+}
@@ -5,16 +5,21 @@
 //! 2. 网关与插件交互协议
 //! 3. 路由配置
 //! 4. 服务配置
+//! 5. TLS证书
+//! 6. JWT签发方
 //!
 
 #[cfg(feature = "alert")]
 pub mod alert;
 #[cfg(feature = "api-key")]
 mod api_key;
+pub mod certificate;
 mod firewall;
 mod global_filter;
 pub mod http_context;
+pub mod jwt;
 pub mod plugin;
+pub mod rate_limit;
 pub mod request_context;
 pub mod request_log;
 pub mod response_context;
@@ -25,12 +30,24 @@ pub mod config;
 
 #[cfg(feature = "api-key")]
 pub use api_key::ApiKey;
+#[cfg(feature = "api-key")]
+pub use api_key::ApiKeyEntry;
+#[cfg(feature = "api-key")]
+pub use api_key::ApiKeyQuota;
+#[cfg(feature = "api-key")]
+pub use api_key::ApiKeyScope;
+pub use certificate::Certificate;
 pub use firewall::AllowDenyPolicy;
 pub use firewall::Firewall;
+pub use firewall::RetiredSecretKey;
+pub use firewall::parse_ip_range;
+pub use firewall::parse_max_connections;
 pub use global_filter::GlobalFilter;
 pub use http_context::HttpContext;
+pub use jwt::JwtProvider;
 pub use plugin::ConfiguredPlugin;
 pub use plugin::Plugin;
+pub use rate_limit::RateLimit;
 pub use request_context::RequestContext;
 pub use response_context::ResponseContext;
 pub use route::Route;
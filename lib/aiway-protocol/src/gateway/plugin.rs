@@ -26,6 +26,15 @@ pub struct ConfiguredPlugin {
     pub name: String,
     /// 插件配置
     pub config: serde_json::Value,
+    /// 插件是否需要读取请求体，默认需要
+    ///
+    /// 路由开启流式请求体时，只要有一个插件需要读取请求体，仍会将请求体读取到上下文中。
+    #[serde(default = "default_inspect_body")]
+    pub inspect_body: bool,
+}
+
+fn default_inspect_body() -> bool {
+    true
 }
 
 impl Plugin {
@@ -0,0 +1,54 @@
+use serde::{Deserialize, Serialize};
+
+/// 限流配置
+///
+/// 可在防火墙中配置全局限流，对所有路由生效，也可在路由上单独配置，两者同时生效。
+/// 限流计数保存在缓存中，使用Redis缓存时，所有网关节点共享计数。
+///
+/// 超出限制时返回429，并设置`Retry-After`响应头。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RateLimit {
+    /// 限流算法
+    #[serde(default)]
+    pub algorithm: RateLimitAlgorithm,
+    /// 限流key的来源
+    #[serde(default)]
+    pub key: RateLimitKey,
+    /// 周期内允许的请求数。
+    /// 固定窗口：窗口内的最大请求数；令牌桶：每个周期补充的令牌数
+    pub limit: u64,
+    /// 周期，单位：秒
+    #[serde(default = "default_window_secs")]
+    pub window_secs: u64,
+    /// 令牌桶容量，即允许的突发请求数，为空时等于`limit`，仅令牌桶有效
+    #[serde(default)]
+    pub burst: Option<u64>,
+}
+
+/// 限流算法
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitAlgorithm {
+    /// 固定窗口，实现简单，但窗口边界处可能出现两倍的突发流量
+    #[default]
+    FixedWindow,
+    /// 令牌桶，按固定速率补充令牌，允许一定的突发流量
+    TokenBucket,
+}
+
+/// 限流key的来源，key取不到时不限流
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "name", rename_all = "snake_case")]
+pub enum RateLimitKey {
+    /// API Key的主体标识，未开启鉴权的路由不限流
+    ApiKey,
+    /// 客户端IP
+    #[default]
+    ClientIp,
+    /// 请求头，例如：{"type": "header", "name": "x-user-id"}
+    Header(String),
+}
+
+fn default_window_secs() -> u64 {
+    1
+}
@@ -1,5 +1,5 @@
 use crate::SV;
-use crate::gateway::route::Route;
+use crate::gateway::route::{Route, SplitDecision};
 use bytes::Bytes;
 use dashmap::DashMap;
 use serde::Serialize;
@@ -26,6 +26,15 @@ pub struct RequestContext {
     /// http1.1及以前版本，取Header里的Host，
     /// http2中，取:authority
     pub host: String,
+    /// 客户端IP
+    ///
+    /// 对端为受信代理时，为从`X-Forwarded-For`、`X-Real-IP`或PROXY协议中解析出的真实IP，
+    /// 否则为对端IP。
+    pub client_ip: String,
+    /// 直连网关的对端IP，启用PROXY协议时为协议头中的源地址
+    pub peer_ip: String,
+    /// 网关收到请求的协议：`http` | `https`，经TLS监听器收到的请求为`https`
+    pub scheme: String,
     /// 请求路径。
     pub path: SV<String>,
     /// 请求头
@@ -33,6 +42,8 @@ pub struct RequestContext {
     /// 请求参数
     pub query: DashMap<String, String>,
     /// 请求体
+    ///
+    /// 流式转发请求体时不会被设置，见[`Route::is_stream_body`]。
     pub body: SV<Bytes>,
     /// 自定义的扩展数据
     pub state: DashMap<String, Value>,
@@ -41,8 +52,18 @@ pub struct RequestContext {
     /// 路由由网关根据当前请求的path匹配得到，通常情况下，路由不应该手动修改。
     /// 由于Route是网关级别的配置，对全局有效，所以使用Arc来保持共享状态。
     pub route: SV<Arc<Route>>,
+    /// 需要转发的服务，由负载均衡Fairing设置
+    ///
+    /// 路由配置了加权后端时为选中的后端服务，见[`Route::select_backend`]。
+    pub service: SV<String>,
+    /// 加权后端的选择方式，未配置加权后端时不设置
+    pub split: SV<SplitDecision>,
     /// 路由目标地址，可以是域名或IP，由负载均衡Fairing设置
     pub routing_url: SV<String>,
+    /// 路径参数，由路由匹配时提取
+    ///
+    /// key为通配符参数名：路径中第n个`*`对应`pn`，末尾的`**`对应`p`
+    pub path_params: DashMap<String, String>,
     // /// 实际路由路径
     // ///
     // /// 默认为网关接收到的原始路径，可以通过插件改写。
@@ -50,6 +71,17 @@ pub struct RequestContext {
 }
 
 impl RequestContext {
+    /// 鉴权通过后的主体标识，保存在[`state`](Self::state)中，如API Key的principal
+    pub const STATE_PRINCIPAL: &'static str = "principal";
+    /// 验证通过的客户端证书主体，保存在[`state`](Self::state)中，格式如：`CN=client,O=Example`。
+    /// 仅在路由要求客户端证书时设置，同时作为主体标识，开启鉴权时主体标识为API Key的principal或JWT中的声明
+    pub const STATE_CLIENT_CERT_SUBJECT: &'static str = "client_cert_subject";
+    /// JWT鉴权通过后的全部声明，保存在[`state`](Self::state)中，JSON对象
+    pub const STATE_JWT_CLAIMS: &'static str = "jwt_claims";
+    /// 计量用量的主体标识，保存在[`state`](Self::state)中。
+    /// 仅在API Key鉴权通过且设置了主体标识时设置，响应时按此记录用量
+    pub const STATE_USAGE_PRINCIPAL: &'static str = "usage_principal";
+
     pub fn get_request_ts(&self) -> i64 {
         self.request_ts
     }
@@ -57,6 +89,15 @@ impl RequestContext {
     pub fn get_host(&self) -> &str {
         &self.host
     }
+    pub fn get_client_ip(&self) -> &str {
+        &self.client_ip
+    }
+    pub fn get_peer_ip(&self) -> &str {
+        &self.peer_ip
+    }
+    pub fn get_scheme(&self) -> &str {
+        &self.scheme
+    }
     pub fn get_method(&self) -> Option<&str> {
         self.method.get().map(|s| s.as_str())
     }
@@ -76,6 +117,16 @@ impl RequestContext {
         self.headers.get(key).map(|v| v.value().clone())
     }
 
+    /// 从Cookie请求头中获取指定名称的Cookie值
+    pub fn get_cookie(&self, name: &str) -> Option<String> {
+        let cookie = self.headers.get("cookie")?;
+        cookie
+            .split(';')
+            .filter_map(|pair| pair.trim().split_once('='))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value.trim_matches('"').to_string())
+    }
+
     pub fn remove_header(&self, key: &str) {
         self.headers.remove(key);
     }
@@ -96,6 +147,11 @@ impl RequestContext {
         self.body.get()
     }
 
+    /// 请求体是否为流式转发，流式转发时请求体不会被读取到上下文中
+    pub fn is_stream_body(&self) -> bool {
+        self.body.get().is_none()
+    }
+
     pub fn set_route(&self, route: Arc<Route>) {
         self.route.set(route);
     }
@@ -104,6 +160,21 @@ impl RequestContext {
         self.route.get()
     }
 
+    pub fn set_service(&self, service: String, split: Option<SplitDecision>) {
+        self.service.set(service);
+        if let Some(split) = split {
+            self.split.set(split);
+        }
+    }
+
+    pub fn get_service(&self) -> Option<&String> {
+        self.service.get()
+    }
+
+    pub fn get_split(&self) -> Option<SplitDecision> {
+        self.split.get().copied()
+    }
+
     pub fn set_routing_url(&self, url: String) {
         self.routing_url.set(url);
     }
@@ -112,6 +183,17 @@ impl RequestContext {
         self.routing_url.get()
     }
 
+    pub fn set_path_params<P: IntoIterator<Item = (String, String)>>(&self, params: P) {
+        self.path_params.clear();
+        for (key, value) in params {
+            self.path_params.insert(key, value);
+        }
+    }
+
+    pub fn get_path_param(&self, name: &str) -> Option<String> {
+        self.path_params.get(name).map(|v| v.value().clone())
+    }
+
     // pub fn set_routing_path(&self, path: String) {
     //     self.routing_path.set(path);
     // }
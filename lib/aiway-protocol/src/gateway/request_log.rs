@@ -36,4 +36,25 @@ pub struct RequestLog {
     pub referer: Option<String>,
     /// 网关节点地址，格式：ip:port，该字段用于记录请求被哪个网关节点处理
     pub node_address: String,
+    /// 网关返回错误的原因，如：upstream_timeout，正常响应为None
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_reason: Option<String>,
+    /// 转发的服务，未匹配到路由或未转发时为None
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service: Option<String>,
+    /// 加权后端的选择方式：weight | override，路由未配置加权后端时为None
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub split: Option<String>,
+    /// 镜像服务，路由未配置请求镜像或请求未被采样时为None
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mirror_service: Option<String>,
+    /// 镜像请求的HTTP状态码，镜像请求失败时为None
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mirror_status_code: Option<u16>,
+    /// 镜像请求的耗时，单位：毫秒
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mirror_elapsed: Option<i64>,
+    /// 镜像请求失败的原因，如：upstream_timeout
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mirror_error_reason: Option<String>,
 }
@@ -1,7 +1,12 @@
+use crate::gateway::GlobalFilter;
 use crate::gateway::plugin::ConfiguredPlugin;
+use crate::gateway::rate_limit::RateLimit;
+use crate::gateway::service::Timeouts;
+use dashmap::DashMap;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
-use std::str::FromStr;
+use std::sync::OnceLock;
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Route {
@@ -10,29 +15,50 @@ pub struct Route {
     /// Host，可选，* 代表不限制。
     /// 支持泛域名，格式为 *.example.com，通配符只能出现在域名开头，需要在控制台校验。
     pub host: String,
-    /// 路径，支持通配符，必须以"/"开头，全局唯一。
-    /// 同一域名下的路径不能存在冲突，如/api/*和/api/**就是冲突的路径，在控制台保存时需要校验。
-    /// 所有为 * 的域名下的路径也不能冲突。
+    /// 路径，必须以"/"开头，按[`path_type`](Self::path_type)匹配。
+    ///
+    /// 通配符格式中，`*`匹配单级路径，`**`匹配剩余的所有路径，仅能出现在末尾。
+    /// 同一路径可以配置多个路由，按请求头、请求参数等条件区分。
     pub path: String,
     /// 转换后的匹配路径。
     /// 由于路径匹配组件使用的是`matchit`，格式为`/xxx/{*any}`，
     /// 它不符合常用的`/api/**`的格式，而`path`储存的是常用格式，
-    /// 所以这里需要做一次转换。
+    /// 所以这里需要做一次转换。正则路径不转换。
     pub match_path: String,
+    /// 路径的匹配方式
+    #[serde(default)]
+    pub path_type: PathType,
+    /// 优先级，数值越大越优先匹配，默认为0
+    ///
+    /// 匹配顺序：优先级降序 -> Host精确程度 -> 路径精确程度 -> 条件个数降序，
+    /// 路径精确程度为：静态路径 -> 含`*`的路径 -> 正则路径 -> 含`**`的路径，
+    /// 依次匹配路径、Host、请求方法、请求头和请求参数，全部满足的第一个路由生效。
+    /// 条件不满足时继续匹配后续的路由，如：`/api/**`可以作为`/api/users`的兜底路由。
+    #[serde(default)]
+    pub priority: i32,
+    /// 路由的处理方式，默认转发到服务
+    ///
+    /// 重定向、固定响应和维护中由网关直接响应，不经过负载均衡，也不需要配置服务。
+    #[serde(default)]
+    pub action: RouteAction,
     /// 需要路由到的服务ID
     pub service: String,
+    /// 加权后端，用于灰度发布，配置后[`service`](Self::service)不再生效
+    ///
+    /// 请求按权重分配到各服务，如95%转发到`orders-v1`，5%转发到`orders-v2`。
+    /// 后端配置了灰度条件时，满足条件的请求固定转发到该后端，见[`select_backend`](Self::select_backend)。
+    #[serde(default)]
+    pub backends: Vec<WeightedBackend>,
     /// 请求方法：get | post | put | delete | patch | options
     /// 支持配置多个。
     /// 不参与路由唯一性验证。
     pub methods: Vec<String>,
-    /// header匹配条件
-    /// 不参与路由唯一性验证。
+    /// header匹配条件，key为请求头名称，所有条件都满足时才匹配
     #[serde(alias = "header_condition", alias = "header-condition")]
-    pub header: BTreeMap<String, String>,
-    /// query匹配条件
-    /// 不参与路由唯一性验证。
+    pub header: BTreeMap<String, MatchCondition>,
+    /// query匹配条件，key为参数名，所有条件都满足时才匹配
     #[serde(alias = "query_condition", alias = "query-condition")]
-    pub query: BTreeMap<String, String>,
+    pub query: BTreeMap<String, MatchCondition>,
     /// 前置过滤器插件，在请求阶段执行，多个按顺序串联执行
     #[serde(default = "Vec::default", alias = "pre_filters", alias = "pre-filters")]
     pub pre_filters: Vec<ConfiguredPlugin>,
@@ -45,48 +71,948 @@ pub struct Route {
     pub post_filters: Vec<ConfiguredPlugin>,
     /// 是否开启鉴权
     ///
-    /// 备注：网关本身只支持API Key鉴权即可，如果需要其他类型的鉴权方式，可将此处鉴权关闭，然后通过插件实现。
-    /// 考虑提供一些内置的鉴权插件。
+    /// 备注：网关内置API Key和JWT鉴权，由[`auth`](Self::auth)选择。
+    /// 如果需要其他类型的鉴权方式，可将此处鉴权关闭，然后通过插件实现。
     #[serde(default = "bool::default", alias = "is_auth", alias = "is-auth")]
     pub is_auth: bool,
+    /// 鉴权方式，开启鉴权时生效，默认为API Key
+    #[serde(default)]
+    pub auth: RouteAuth,
     /// 鉴权路径白名单
     pub auth_white_list: Vec<String>,
+    /// 路径重写配置，在转发到服务前执行
+    #[serde(default)]
+    pub rewrite: Option<PathRewrite>,
+    /// 重试策略，为空时不重试
+    #[serde(default)]
+    pub retry: Option<RetryPolicy>,
+    /// 超时配置，优先于服务上配置的超时
+    #[serde(default)]
+    pub timeouts: Option<Timeouts>,
+    /// 限流配置，为空时不限流。与防火墙中的全局限流同时生效
+    #[serde(default)]
+    pub rate_limit: Option<RateLimit>,
+    /// 转发到服务时的请求头配置，为空时注入全部标准代理头
+    #[serde(default)]
+    pub proxy_headers: Option<ProxyHeaders>,
+    /// 是否流式转发请求体
+    ///
+    /// 开启后，请求体不会被读取到上下文中，而是在转发时直接发送到服务，适用于上传大文件等场景。
+    /// 流式请求体只能发送一次，因此不会重试。
+    /// 仅对POST、PUT、PATCH、DELETE请求生效，见[`is_stream_body`](Self::is_stream_body)。
+    /// gRPC调用不受此配置影响，没有需要读取请求体的插件时总是流式转发。
+    #[serde(default)]
+    pub stream_body: bool,
+    /// 是否允许WebSocket
+    ///
+    /// 开启后，GET请求携带`Upgrade: websocket`时，网关将升级请求转发到服务，
+    /// 服务返回101后在客户端和服务之间双向转发数据。未开启时按普通HTTP请求转发，升级相关的请求头会被移除。
+    #[serde(default)]
+    pub websocket: bool,
+    /// 是否要求客户端证书
+    ///
+    /// 开启后，仅接受经网关TLS端口访问、且在握手时提供了有效客户端证书的请求，否则返回401。
+    /// 客户端证书由[`Certificate::client_ca`](crate::gateway::Certificate::client_ca)验证，
+    /// 证书主体作为鉴权主体保存在请求上下文的`state`中，见[`RequestContext`](crate::gateway::RequestContext)。
+    #[serde(default)]
+    pub client_cert: bool,
+    /// 请求镜像配置，为空时不镜像
+    ///
+    /// 按采样比例将请求异步复制到镜像服务，镜像服务的响应被丢弃，不影响客户端的响应。
+    /// 流式请求体和WebSocket升级请求不镜像。
+    #[serde(default)]
+    pub mirror: Option<MirrorPolicy>,
+}
+
+/// 路由的鉴权方式
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RouteAuth {
+    /// 验证`Authorization: Bearer sk-...`中的API Key
+    #[default]
+    ApiKey,
+    /// 验证`Authorization: Bearer <JWT>`中的JWT，如：`{"type": "jwt", "provider": "keycloak"}`
+    Jwt(JwtAuth),
+}
+
+/// JWT鉴权配置
+///
+/// 验证通过后，全部声明保存到请求上下文的`state`中，见
+/// [`STATE_JWT_CLAIMS`](crate::gateway::RequestContext::STATE_JWT_CLAIMS)。
+/// 声明名称支持用`.`访问嵌套的声明，见[`find_claim`](crate::gateway::jwt::find_claim)。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JwtAuth {
+    /// JWT签发方名称，见[`JwtProvider`](crate::gateway::JwtProvider)
+    pub provider: String,
+    /// 作为主体标识的声明，默认为`sub`
+    #[serde(default = "default_principal_claim")]
+    pub principal_claim: String,
+    /// 转发到服务的请求头，key为请求头名称，value为声明名称，如：`{"X-User-Id": "sub"}`
+    ///
+    /// JWT中不存在该声明时移除该请求头，避免客户端伪造。
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// 保存到请求上下文`state`中的声明，key为state名称，value为声明名称，供插件使用
+    #[serde(default)]
+    pub state: BTreeMap<String, String>,
+}
+
+fn default_principal_claim() -> String {
+    "sub".to_string()
+}
+
+/// 路由的处理方式
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RouteAction {
+    /// 转发到服务
+    #[default]
+    Proxy,
+    /// 重定向，如：`{"type": "redirect", "url": "https://new.example.com/{p}", "status": 301}`
+    Redirect(RedirectAction),
+    /// 返回固定响应，如模拟数据：`{"type": "static", "body": "{\"id\": 1}"}`
+    Static(StaticResponse),
+    /// 维护中，返回503
+    Maintenance(MaintenancePage),
+}
+
+impl RouteAction {
+    /// 是否转发到服务
+    pub fn is_proxy(&self) -> bool {
+        matches!(self, RouteAction::Proxy)
+    }
+}
+
+/// 重定向
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedirectAction {
+    /// 重定向地址，可以是完整的URL或路径
+    ///
+    /// 支持路由路径中的通配符参数，如`{p1}`、`{p}`，分别对应路径中的第1个`*`和末尾的`**`。
+    pub url: String,
+    /// 状态码：301 | 302 | 303 | 307 | 308，默认为302
+    #[serde(default = "default_redirect_status")]
+    pub status: u16,
+    /// 是否将请求参数追加到重定向地址
+    #[serde(default)]
+    pub keep_query: bool,
+}
+
+impl RedirectAction {
+    /// 重定向的状态码
+    pub const STATUSES: [u16; 5] = [301, 302, 303, 307, 308];
+
+    /// 替换重定向地址中的通配符参数
+    ///
+    /// 参数取自原始请求路径，替换前进行百分号编码；
+    /// 配置的地址不是`//`开头时，合并结果开头的多个`/`，避免请求路径构造出跳转到其他站点的地址。
+    pub fn location(&self, params: &DashMap<String, String>) -> String {
+        let location = substitute_params(&self.url, |name| {
+            params.get(name).map(|value| encode_path(value.value()))
+        });
+        if !self.url.starts_with("//") && location.starts_with("//") {
+            format!("/{}", location.trim_start_matches('/'))
+        } else {
+            location
+        }
+    }
+}
+
+/// 将模板中的`{name}`替换为参数值，未知的参数保持原样。
+///
+/// 只扫描一遍模板，参数值中的`{name}`不会被再次替换；`${name}`为正则捕获组，不做替换。
+fn substitute_params(template: &str, value: impl Fn(&str) -> Option<String>) -> String {
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let replaced = match after.find('}') {
+            Some(end) if !result.ends_with('$') => value(&after[..end]).map(|v| (v, end)),
+            _ => None,
+        };
+        match replaced {
+            Some((v, end)) => {
+                result.push_str(&v);
+                rest = &after[end + 1..];
+            }
+            None => {
+                result.push('{');
+                rest = after;
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// 对路径中的字符进行百分号编码，保留`/`、`%`及路径中允许出现的字符
+fn encode_path(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' => encoded.push(byte as char),
+            b'-' | b'.' | b'_' | b'~' | b'/' | b'%' => encoded.push(byte as char),
+            b'!' | b'$' | b'&' | b'\'' | b'(' | b')' | b'*' | b'+' | b',' | b';' | b'=' => {
+                encoded.push(byte as char)
+            }
+            b':' | b'@' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn default_redirect_status() -> u16 {
+    302
+}
+
+/// 固定响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StaticResponse {
+    /// 状态码，默认为200
+    #[serde(default = "default_static_status")]
+    pub status: u16,
+    /// 响应头，未设置`Content-Type`时为`application/json`
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// 响应体
+    #[serde(default)]
+    pub body: String,
+}
+
+fn default_static_status() -> u16 {
+    200
 }
 
+/// 维护页面
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenancePage {
+    /// 建议客户端重试的时间，单位：秒，设置后返回`Retry-After`响应头
+    #[serde(default)]
+    pub retry_after: Option<u64>,
+    /// 响应头，未设置`Content-Type`时为`application/json`
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// 响应体
+    #[serde(default = "default_maintenance_body")]
+    pub body: String,
+}
+
+impl Default for MaintenancePage {
+    fn default() -> Self {
+        Self {
+            retry_after: None,
+            headers: BTreeMap::new(),
+            body: default_maintenance_body(),
+        }
+    }
+}
+
+fn default_maintenance_body() -> String {
+    r#"{"code":503,"message":"Service Under Maintenance"}"#.to_string()
+}
+
+/// 路径的匹配方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PathType {
+    /// 通配符匹配，如：`/api/*/users`、`/api/**`
+    #[default]
+    Wildcard,
+    /// 正则匹配，需要匹配完整的请求路径，如：`/api/v\d+/users`
+    ///
+    /// 捕获组作为路径参数，命名捕获组的参数名为组名，否则为`p`加组序号，如`p1`。
+    Regex,
+}
+
+/// 编译正则路径，需要匹配完整的请求路径
+pub fn path_regex(path: &str) -> Result<Regex, regex::Error> {
+    Regex::new(&format!("^(?:{})$", path))
+}
+
+/// 请求头和请求参数的匹配条件
+///
+/// 字符串表示精确匹配，兼容旧的配置，如：`{"x-env": "gray"}`。
+/// 其他匹配方式使用对象，如：`{"x-env": {"op": "regex", "value": "^gray-\\d+$"}}`。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MatchCondition {
+    /// 精确匹配
+    Exact(String),
+    /// 指定匹配方式
+    Rule(MatchRule),
+}
+
+/// 匹配方式
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum MatchRule {
+    /// 精确匹配
+    Exact { value: String },
+    /// 前缀匹配
+    Prefix { value: String },
+    /// 正则匹配，匹配值的任意部分，需要完整匹配时使用`^`和`$`
+    Regex {
+        value: String,
+        /// 编译后的正则，首次使用时编译
+        #[serde(skip)]
+        regex: OnceLock<Option<Regex>>,
+    },
+    /// 存在即匹配，不限制值
+    Present,
+    /// 不存在时匹配
+    Absent,
+}
+
+impl MatchCondition {
+    /// 是否满足条件
+    ///
+    /// - value: 请求头或请求参数的值，不存在时为None
+    pub fn matches(&self, value: Option<&str>) -> bool {
+        let rule = match self {
+            MatchCondition::Exact(expected) => return value == Some(expected.as_str()),
+            MatchCondition::Rule(rule) => rule,
+        };
+        match (rule, value) {
+            (MatchRule::Absent, value) => value.is_none(),
+            (_, None) => false,
+            (MatchRule::Exact { value: expected }, Some(value)) => value == expected,
+            (MatchRule::Prefix { value: prefix }, Some(value)) => value.starts_with(prefix),
+            (
+                MatchRule::Regex {
+                    value: pattern,
+                    regex,
+                },
+                Some(value),
+            ) => regex
+                .get_or_init(|| Regex::new(pattern).ok())
+                .as_ref()
+                .is_some_and(|regex| regex.is_match(value)),
+            (MatchRule::Present, Some(_)) => true,
+        }
+    }
+
+    /// 校验匹配条件，主要校验正则是否合法，由控制台在保存时调用
+    pub fn validate(&self) -> Result<(), String> {
+        if let MatchCondition::Rule(MatchRule::Regex { value, .. }) = self
+            && let Err(e) = Regex::new(value)
+        {
+            return Err(format!("invalid condition pattern {}: {}", value, e));
+        }
+        Ok(())
+    }
+}
+
+/// 加权后端
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WeightedBackend {
+    /// 服务ID
+    pub service: String,
+    /// 权重，按各后端权重的占比分配请求，为0时仅接收满足灰度条件的请求
+    #[serde(default)]
+    pub weight: u32,
+    /// 灰度请求头条件，key为请求头名称
+    #[serde(default)]
+    pub header: BTreeMap<String, MatchCondition>,
+    /// 灰度Cookie条件，key为Cookie名称
+    #[serde(default)]
+    pub cookie: BTreeMap<String, MatchCondition>,
+}
+
+impl WeightedBackend {
+    /// 是否配置了灰度条件
+    pub fn has_override(&self) -> bool {
+        !self.header.is_empty() || !self.cookie.is_empty()
+    }
+}
+
+/// 加权后端的选择方式，记录在请求日志中
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SplitDecision {
+    /// 按权重分配
+    #[default]
+    Weight,
+    /// 满足灰度条件
+    Override,
+}
+
+impl SplitDecision {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SplitDecision::Weight => "weight",
+            SplitDecision::Override => "override",
+        }
+    }
+}
+
+/// 重试策略
+///
+/// 请求服务失败时，按负载均衡策略选择其他节点重试，所有节点都已尝试过时允许重试已尝试的节点。
+/// 默认仅重试幂等方法（GET、HEAD、OPTIONS、PUT、DELETE、TRACE）。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    /// 最大尝试次数，包含首次请求
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+    /// 是否在连接错误等请求错误时重试
+    #[serde(default = "default_true")]
+    pub retry_on_error: bool,
+    /// 是否在请求超时时重试
+    #[serde(default = "default_true")]
+    pub retry_on_timeout: bool,
+    /// 需要重试的响应状态码
+    #[serde(default = "default_retry_statuses")]
+    pub retry_statuses: Vec<u16>,
+    /// 首次重试的退避时间，单位：毫秒，之后每次翻倍
+    #[serde(default = "default_backoff")]
+    pub backoff: u64,
+    /// 最大退避时间，单位：毫秒
+    #[serde(default = "default_max_backoff")]
+    pub max_backoff: u64,
+    /// 是否允许非幂等方法重试
+    #[serde(default)]
+    pub retry_non_idempotent: bool,
+    /// 重试预算，重试请求数占请求总数的最大百分比，防止服务故障时重试放大流量
+    #[serde(default = "default_budget_percent")]
+    pub budget_percent: u32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: default_max_attempts(),
+            retry_on_error: true,
+            retry_on_timeout: true,
+            retry_statuses: default_retry_statuses(),
+            backoff: default_backoff(),
+            max_backoff: default_max_backoff(),
+            retry_non_idempotent: false,
+            budget_percent: default_budget_percent(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// 请求方法是否允许重试
+    pub fn is_retryable_method(&self, method: &str) -> bool {
+        self.retry_non_idempotent
+            || matches!(
+                method.to_uppercase().as_str(),
+                "GET" | "HEAD" | "OPTIONS" | "PUT" | "DELETE" | "TRACE"
+            )
+    }
+
+    /// 第`retry`次重试前的退避时间，单位：毫秒，从1开始
+    pub fn backoff_millis(&self, retry: u32) -> u64 {
+        self.backoff
+            .saturating_mul(1 << (retry.saturating_sub(1)).min(16))
+            .min(self.max_backoff)
+    }
+}
+
+fn default_max_attempts() -> u32 {
+    3
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_retry_statuses() -> Vec<u16> {
+    vec![502, 503, 504]
+}
+
+fn default_backoff() -> u64 {
+    50
+}
+
+fn default_max_backoff() -> u64 {
+    1000
+}
+
+fn default_budget_percent() -> u32 {
+    20
+}
+
+/// 请求镜像配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MirrorPolicy {
+    /// 镜像服务ID
+    pub service: String,
+    /// 采样百分比，0到100，默认为100
+    #[serde(default = "default_mirror_percent")]
+    pub percent: u32,
+    /// 镜像请求的超时时间，单位：毫秒。请求日志在镜像请求完成后记录，因此不宜过长
+    #[serde(default = "default_mirror_timeout")]
+    pub timeout: u64,
+}
+
+impl MirrorPolicy {
+    /// 请求是否被采样
+    ///
+    /// - random: 随机数，用于按采样百分比采样
+    pub fn is_sampled(&self, random: u32) -> bool {
+        random % 100 < self.percent
+    }
+}
+
+fn default_mirror_percent() -> u32 {
+    100
+}
+
+fn default_mirror_timeout() -> u64 {
+    5000
+}
+
+/// 转发到服务时的请求头配置
+///
+/// 执行顺序：注入标准代理头 -> 移除 -> 覆盖 -> 添加。
+/// 逐跳（hop-by-hop）请求头始终会被移除，不受该配置影响。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProxyHeaders {
+    /// 需要注入的标准代理头，默认全部注入
+    #[serde(default = "default_forwarded")]
+    pub forwarded: Vec<ForwardedHeader>,
+    /// 需要移除的请求头，不区分大小写
+    #[serde(default)]
+    pub remove: Vec<String>,
+    /// 需要覆盖的请求头，已存在时覆盖
+    #[serde(default)]
+    pub set: BTreeMap<String, String>,
+    /// 需要添加的请求头，已存在时不添加
+    #[serde(default)]
+    pub add: BTreeMap<String, String>,
+}
+
+impl Default for ProxyHeaders {
+    fn default() -> Self {
+        Self {
+            forwarded: default_forwarded(),
+            remove: vec![],
+            set: Default::default(),
+            add: Default::default(),
+        }
+    }
+}
+
+/// 标准代理头
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ForwardedHeader {
+    /// 客户端及经过的代理的IP列表
+    XForwardedFor,
+    /// 客户端请求的协议，http或https
+    XForwardedProto,
+    /// 客户端请求的Host
+    XForwardedHost,
+    /// RFC 7239定义的`Forwarded`
+    Forwarded,
+    /// 网关生成的请求ID，用于关联网关和服务的日志
+    XRequestId,
+}
+
+fn default_forwarded() -> Vec<ForwardedHeader> {
+    vec![
+        ForwardedHeader::XForwardedFor,
+        ForwardedHeader::XForwardedProto,
+        ForwardedHeader::XForwardedHost,
+        ForwardedHeader::Forwarded,
+        ForwardedHeader::XRequestId,
+    ]
+}
+
+/// 路径重写配置
+///
+/// 执行顺序：移除前缀 -> 正则重写 -> 拼接基础路径
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PathRewrite {
+    /// 需要移除的路径前缀，例如：/api/v1
+    ///
+    /// 仅当请求路径以该前缀开头时移除。
+    #[serde(default)]
+    pub strip_prefix: Option<String>,
+    /// 正则重写规则，多个按顺序执行
+    #[serde(default)]
+    pub rules: Vec<RewriteRule>,
+    /// 上游服务的基础路径，例如：/v2，会拼接在重写后的路径前面
+    #[serde(default)]
+    pub base_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct RewriteRule {
     /// 匹配模式（正则表达式）
     pub pattern: String,
     /// 替换字符串
+    ///
+    /// - 支持正则捕获组，如`$1`、`${name}`
+    /// - 支持路由路径中的通配符参数，如`{p1}`、`{p}`，分别对应路径中的第1个`*`和末尾的`**`
     pub replacement: String,
+    /// 编译后的正则，首次使用时编译
+    #[serde(skip)]
+    regex: OnceLock<Option<Regex>>,
 }
+
+impl RewriteRule {
+    pub fn new<P: Into<String>, R: Into<String>>(pattern: P, replacement: R) -> Self {
+        Self {
+            pattern: pattern.into(),
+            replacement: replacement.into(),
+            regex: OnceLock::new(),
+        }
+    }
+
+    fn regex(&self) -> Option<&Regex> {
+        self.regex
+            .get_or_init(|| Regex::new(&self.pattern).ok())
+            .as_ref()
+    }
+
+    /// 执行重写，正则无效时返回原路径
+    fn apply(&self, path: &str, params: &DashMap<String, String>) -> String {
+        let regex = match self.regex() {
+            Some(regex) => regex,
+            None => return path.to_string(),
+        };
+        // 先替换模板中的通配符参数，参数值中的`$`需要转义，避免被当作捕获组
+        let replacement = substitute_params(&self.replacement, |name| {
+            params.get(name).map(|value| value.replace('$', "$$"))
+        });
+        regex
+            .replace_all(path, |caps: &regex::Captures| {
+                let mut dst = String::new();
+                caps.expand(&replacement, &mut dst);
+                dst
+            })
+            .to_string()
+    }
+}
+
+impl PathRewrite {
+    /// 校验重写配置，主要校验正则是否合法，由控制台在保存时调用
+    pub fn validate(&self) -> Result<(), String> {
+        for rule in self.rules.iter() {
+            if let Err(e) = Regex::new(&rule.pattern) {
+                return Err(format!("invalid rewrite pattern {}: {}", rule.pattern, e));
+            }
+        }
+        if let Some(base_path) = &self.base_path
+            && !base_path.is_empty()
+            && !base_path.starts_with('/')
+        {
+            return Err(format!("base path must start with '/': {}", base_path));
+        }
+        Ok(())
+    }
+
+    /// 重写路径
+    ///
+    /// - path: 当前请求路径
+    /// - params: 路由匹配时提取的通配符参数
+    pub fn apply(&self, path: &str, params: &DashMap<String, String>) -> String {
+        let mut result = path.to_string();
+
+        if let Some(prefix) = &self.strip_prefix
+            && !prefix.is_empty()
+            && let Some(stripped) = result.strip_prefix(prefix.trim_end_matches('/'))
+            && (stripped.is_empty() || stripped.starts_with('/'))
+        {
+            result = stripped.to_string();
+        }
+
+        for rule in self.rules.iter() {
+            result = rule.apply(&result, params);
+        }
+
+        if let Some(base_path) = &self.base_path
+            && !base_path.is_empty()
+        {
+            result = format!(
+                "{}/{}",
+                base_path.trim_end_matches('/'),
+                result.trim_start_matches('/')
+            );
+        }
+
+        if !result.starts_with('/') {
+            result.insert(0, '/');
+        }
+        result
+    }
+}
+
 impl Route {
     pub fn get_service(&self) -> &String {
         &self.service
     }
 
+    /// 选择需要转发的服务
+    ///
+    /// 未配置加权后端时返回[`service`](Self::service)，选择方式为None。
+    /// 否则按顺序返回第一个满足全部灰度条件的后端，都不满足时按权重分配，所有权重都为0时返回None。
+    ///
+    /// - header: 按名称获取请求头，名称为小写
+    /// - cookie: 按名称获取Cookie
+    /// - random: 随机数，用于按权重分配
+    pub fn select_backend(
+        &self,
+        header: impl Fn(&str) -> Option<String>,
+        cookie: impl Fn(&str) -> Option<String>,
+        random: u64,
+    ) -> Option<(&str, Option<SplitDecision>)> {
+        if self.backends.is_empty() {
+            return Some((&self.service, None));
+        }
+
+        let forced = self.backends.iter().find(|backend| {
+            backend.has_override()
+                && backend.header.iter().all(|(name, condition)| {
+                    condition.matches(header(&name.to_lowercase()).as_deref())
+                })
+                && backend
+                    .cookie
+                    .iter()
+                    .all(|(name, condition)| condition.matches(cookie(name).as_deref()))
+        });
+        if let Some(backend) = forced {
+            return Some((&backend.service, Some(SplitDecision::Override)));
+        }
+
+        let total = self.backends.iter().map(|b| b.weight as u64).sum::<u64>();
+        if total == 0 {
+            return None;
+        }
+        let mut point = random % total;
+        for backend in &self.backends {
+            if point < backend.weight as u64 {
+                return Some((&backend.service, Some(SplitDecision::Weight)));
+            }
+            point -= backend.weight as u64;
+        }
+        None
+    }
+
     /// 构建请求路径
     ///
-    /// - path 原始的请求路径
+    /// - path 当前的请求路径
+    /// - params 路由匹配时提取的通配符参数
     ///
     /// 能执行到这里，说明已经匹配到该路由了。
-    /// (废弃，由插件实现)根据`strip_prefix`决定是否移除前缀
-    pub fn build_path(&self, path: &str) -> String {
-        /*let rewritten_path = self.apply_path_rewrite(path);*/
-        path.to_string()
-    }
-
-    /*pub fn apply_path_rewrite(&self, path: &str) -> String {
-        if let Some(rules) = &self.rewrite_rules {
-            let mut result = path.to_string();
-            for rule in rules {
-                if let Ok(regex) = regex::Regex::new(&rule.pattern) {
-                    result = regex.replace_all(&result, &rule.replacement).to_string();
-                }
+    /// 如果配置了路径重写，则按重写规则处理，否则返回原路径。
+    pub fn build_path(&self, path: &str, params: &DashMap<String, String>) -> String {
+        match &self.rewrite {
+            Some(rewrite) => rewrite.apply(path, params),
+            None => path.to_string(),
+        }
+    }
+
+    /// 是否流式转发请求体
+    ///
+    /// 路由开启了流式请求体，且路由和全局过滤器中都没有需要读取请求体的插件时，才流式转发。
+    pub fn is_stream_body(&self, method: &str, global_filter: &GlobalFilter) -> bool {
+        self.stream_body
+            && matches!(
+                method.to_uppercase().as_str(),
+                "POST" | "PUT" | "PATCH" | "DELETE"
+            )
+            && !self.inspects_body(global_filter)
+    }
+
+    /// 路由或全局过滤器中是否有需要读取请求体的插件
+    pub fn inspects_body(&self, global_filter: &GlobalFilter) -> bool {
+        self.pre_filters
+            .iter()
+            .chain(&self.post_filters)
+            .chain(&global_filter.pre_filters)
+            .chain(&global_filter.post_filters)
+            .any(|plugin| plugin.inspect_body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_rewrite() {
+        let rewrite = PathRewrite {
+            strip_prefix: Some("/api/v1".to_string()),
+            rules: vec![],
+            base_path: None,
+        };
+        let params = DashMap::new();
+        assert_eq!(rewrite.apply("/api/v1/users/1", &params), "/users/1");
+        assert_eq!(rewrite.apply("/api/v1", &params), "/");
+        assert_eq!(rewrite.apply("/api/v10/users", &params), "/api/v10/users");
+
+        let rewrite = PathRewrite {
+            strip_prefix: Some("/api".to_string()),
+            rules: vec![RewriteRule::new("^/users/(\\d+)$", "/members/$1/{p1}")],
+            base_path: Some("/v2/".to_string()),
+        };
+        params.insert("p1".to_string(), "profile".to_string());
+        assert_eq!(
+            rewrite.apply("/api/users/1", &params),
+            "/v2/members/1/profile"
+        );
+        // 捕获内容中的`{p}`、参数值中的`$1`保持原样
+        let rewrite = PathRewrite {
+            rules: vec![RewriteRule::new("^/files/(.+)$", "/{p}/$1")],
+            ..Default::default()
+        };
+        params.insert("p".to_string(), "$1".to_string());
+        assert_eq!(rewrite.apply("/files/{p}", &params), "/$1/{p}");
+        assert!(rewrite.validate().is_ok());
+        assert!(
+            PathRewrite {
+                rules: vec![RewriteRule::new("(", "")],
+                ..Default::default()
             }
-            result
-        } else {
-            path.to_string()
+            .validate()
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_match_condition() {
+        let conditions: BTreeMap<String, MatchCondition> = serde_json::from_str(
+            r#"{
+                "a": "gray",
+                "b": {"op": "prefix", "value": "v1."},
+                "c": {"op": "regex", "value": "^\\d+$"},
+                "d": {"op": "present"},
+                "e": {"op": "absent"}
+            }"#,
+        )
+        .unwrap();
+        assert!(conditions["a"].matches(Some("gray")));
+        assert!(!conditions["a"].matches(Some("gray-1")));
+        assert!(conditions["b"].matches(Some("v1.2")));
+        assert!(!conditions["b"].matches(None));
+        assert!(conditions["c"].matches(Some("123")));
+        assert!(!conditions["c"].matches(Some("12a")));
+        assert!(conditions["d"].matches(Some("")));
+        assert!(!conditions["d"].matches(None));
+        assert!(conditions["e"].matches(None));
+        assert!(!conditions["e"].matches(Some("1")));
+        assert!(conditions.values().all(|c| c.validate().is_ok()));
+
+        let invalid: MatchCondition =
+            serde_json::from_str(r#"{"op": "regex", "value": "("}"#).unwrap();
+        assert!(invalid.validate().is_err());
+        assert!(!invalid.matches(Some("(")));
+        // 序列化后格式不变
+        assert_eq!(
+            serde_json::to_string(&conditions["a"]).unwrap(),
+            r#""gray""#
+        );
+        assert_eq!(
+            serde_json::to_string(&conditions["b"]).unwrap(),
+            r#"{"op":"prefix","value":"v1."}"#
+        );
+    }
+
+    #[test]
+    fn test_select_backend() {
+        let mut route = Route {
+            service: "orders".to_string(),
+            ..Default::default()
+        };
+        let none = |_: &str| None;
+        assert_eq!(route.select_backend(none, none, 0), Some(("orders", None)));
+
+        route.backends = serde_json::from_str(
+            r#"[
+                {"service": "orders-v1", "weight": 95},
+                {"service": "orders-v2", "weight": 5, "cookie": {"canary": "1"}},
+                {"service": "orders-v3", "header": {"X-Canary": {"op": "present"}}}
+            ]"#,
+        )
+        .unwrap();
+        let weight = Some(SplitDecision::Weight);
+        assert_eq!(
+            route.select_backend(none, none, 0),
+            Some(("orders-v1", weight))
+        );
+        assert_eq!(
+            route.select_backend(none, none, 94),
+            Some(("orders-v1", weight))
+        );
+        assert_eq!(
+            route.select_backend(none, none, 95),
+            Some(("orders-v2", weight))
+        );
+        assert_eq!(
+            route.select_backend(none, none, 100),
+            Some(("orders-v1", weight))
+        );
+
+        // 满足灰度条件时固定转发，权重为0的后端仅接收灰度请求
+        let forced = Some(SplitDecision::Override);
+        let canary = |name: &str| (name == "canary").then(|| "1".to_string());
+        assert_eq!(
+            route.select_backend(none, canary, 0),
+            Some(("orders-v2", forced))
+        );
+        let header = |name: &str| (name == "x-canary").then(String::new);
+        assert_eq!(
+            route.select_backend(header, none, 0),
+            Some(("orders-v3", forced))
+        );
+
+        for backend in route.backends.iter_mut() {
+            backend.weight = 0;
         }
-    }*/
+        assert_eq!(route.select_backend(none, none, 0), None);
+    }
+
+    #[test]
+    fn test_route_action() {
+        let action: RouteAction = serde_json::from_str(
+            r#"{"type": "redirect", "url": "https://new.example.com/{p}?from={p1}"}"#,
+        )
+        .unwrap();
+        let RouteAction::Redirect(redirect) = &action else {
+            panic!("expect redirect action");
+        };
+        assert_eq!(redirect.status, 302);
+        let params = DashMap::new();
+        params.insert("p1".to_string(), "v1".to_string());
+        params.insert("p".to_string(), "users/1".to_string());
+        assert_eq!(
+            redirect.location(&params),
+            "https://new.example.com/users/1?from=v1"
+        );
+
+        // 双斜杠开头的路径不能跳转到其他站点
+        let redirect = RedirectAction {
+            url: "/{p}".to_string(),
+            status: 302,
+            keep_query: false,
+        };
+        params.insert("p".to_string(), "/evil.com".to_string());
+        assert_eq!(redirect.location(&params), "/evil.com");
+        params.insert("p".to_string(), "\\evil.com/a b".to_string());
+        assert_eq!(redirect.location(&params), "/%5Cevil.com/a%20b");
+
+        let action: RouteAction = serde_json::from_str(r#"{"type": "maintenance"}"#).unwrap();
+        let RouteAction::Maintenance(page) = &action else {
+            panic!("expect maintenance action");
+        };
+        assert_eq!(page.body, default_maintenance_body());
+        assert!(!action.is_proxy());
+        assert!(RouteAction::default().is_proxy());
+    }
+
+    #[test]
+    fn test_retry_policy() {
+        let policy = RetryPolicy::default();
+        assert!(policy.is_retryable_method("get"));
+        assert!(!policy.is_retryable_method("POST"));
+        assert_eq!(policy.backoff_millis(1), 50);
+        assert_eq!(policy.backoff_millis(2), 100);
+        assert_eq!(policy.backoff_millis(10), 1000);
+    }
 }
@@ -1,4 +1,6 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
 
 /// 服务信息
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -10,6 +12,47 @@ pub struct Service {
     /// 负载均衡策略
     #[serde(default = "LbStrategy::default")]
     pub lb: LbStrategy,
+    /// 节点权重，key为节点地址，未配置的节点权重为1，配置的权重必须大于0，由控制台校验。
+    /// 用于加权轮询、最少活跃请求和一致性哈希。
+    #[serde(default)]
+    pub weights: HashMap<String, u32>,
+    /// 一致性哈希的key来源，仅在负载均衡策略为一致性哈希时有效，默认为客户端IP
+    #[serde(default)]
+    pub hash_key: Option<HashKey>,
+    /// 健康检查配置，为空时不检查
+    #[serde(default)]
+    pub health_check: Option<HealthCheck>,
+    /// 超时配置，路由上配置的超时优先
+    #[serde(default)]
+    pub timeouts: Option<Timeouts>,
+    /// 连接池配置
+    #[serde(default)]
+    pub pool: Option<PoolConfig>,
+    /// 熔断配置，为空时不熔断
+    #[serde(default)]
+    pub circuit_breaker: Option<CircuitBreaker>,
+    /// 服务节点的协议，默认为HTTP/1.1
+    #[serde(default)]
+    pub protocol: Protocol,
+    /// 请求https节点时的TLS配置，为空时使用系统根证书验证节点证书
+    #[serde(default)]
+    pub tls: Option<UpstreamTls>,
+}
+
+/// 服务节点的协议
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Protocol {
+    /// HTTP/1.1
+    #[default]
+    Http1,
+    /// 明文HTTP/2，不经过协商，直接使用HTTP/2连接
+    H2c,
+    /// 基于TLS的HTTP/2，通过ALPN协商，节点地址需为https，节点不支持时降级为HTTP/1.1
+    H2,
+    /// gRPC，使用HTTP/2连接，服务返回的trailers会保留并返回给客户端，
+    /// 客户端需要通过网关的gRPC端口访问，见网关启动参数`--grpc-port`
+    Grpc,
 }
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub enum LbStrategy {
@@ -20,4 +63,290 @@ pub enum LbStrategy {
     /// 轮询
     #[serde(rename = "random_robin")]
     RoundRobin,
+    /// 平滑加权轮询
+    #[serde(rename = "weighted_round_robin")]
+    WeightedRoundRobin,
+    /// 最少活跃请求
+    #[serde(rename = "least_active")]
+    LeastActive,
+    /// 一致性哈希，相同key的请求转发到相同节点，可用于会话保持
+    #[serde(rename = "consistent_hash")]
+    ConsistentHash,
+}
+
+/// 一致性哈希的key来源
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "name", rename_all = "snake_case")]
+pub enum HashKey {
+    /// 客户端IP
+    #[default]
+    ClientIp,
+    /// 请求头，例如：{"type": "header", "name": "x-user-id"}
+    Header(String),
+    /// 请求参数，例如：{"type": "query", "name": "session_id"}
+    Query(String),
+}
+
+/// 上游超时配置，单位：毫秒，为空时使用网关默认值
+///
+/// 可在服务和路由上配置，路由上配置的值优先。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Timeouts {
+    /// 连接超时
+    #[serde(default)]
+    pub connect: Option<u64>,
+    /// 请求超时，从发送请求到收到响应头的最大时间，不包含读取响应体的时间
+    #[serde(default)]
+    pub request: Option<u64>,
+    /// 空闲超时，读取响应时两次收到数据之间的最大间隔，流式响应同样适用
+    #[serde(default)]
+    pub idle: Option<u64>,
+}
+
+impl Timeouts {
+    /// 合并配置，当前配置中为空的值使用`other`中的值
+    pub fn merge(&self, other: Option<&Timeouts>) -> Timeouts {
+        match other {
+            Some(other) => Timeouts {
+                connect: self.connect.or(other.connect),
+                request: self.request.or(other.request),
+                idle: self.idle.or(other.idle),
+            },
+            None => self.clone(),
+        }
+    }
+}
+
+/// 连接池配置，为空时使用网关默认值
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PoolConfig {
+    /// 每个节点的最大空闲连接数
+    #[serde(default)]
+    pub max_idle_per_host: Option<usize>,
+    /// 空闲连接的保持时间，单位：毫秒
+    #[serde(default)]
+    pub idle_timeout: Option<u64>,
+    /// 每个节点的最大并发连接数，在单个网关节点内生效，为空时不限制
+    ///
+    /// 按转发中的请求计数，直到响应结束。超出时等待，超过连接超时后返回503。
+    #[serde(default)]
+    pub max_connections_per_host: Option<usize>,
+}
+
+/// 请求https节点时的TLS配置
+#[derive(Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct UpstreamTls {
+    /// 验证节点证书的CA证书，PEM格式，可包含多个。配置后仅信任这些CA，不再使用系统根证书
+    #[serde(default)]
+    pub ca: Option<String>,
+    /// 双向TLS时网关提供的客户端证书链，PEM格式，需同时配置`key`
+    #[serde(default)]
+    pub cert: Option<String>,
+    /// 客户端证书的私钥，PEM格式
+    #[serde(default)]
+    pub key: Option<String>,
+    /// 覆盖SNI及验证节点证书时使用的域名，为空时使用节点地址中的域名。
+    /// 适用于节点地址为IP，而证书签发给域名的情况
+    #[serde(default)]
+    pub server_name: Option<String>,
+    /// 是否跳过节点证书的验证，仅用于开发测试
+    #[serde(default)]
+    pub insecure_skip_verify: bool,
+}
+
+impl Debug for UpstreamTls {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // 不输出证书及私钥内容
+        f.debug_struct("UpstreamTls")
+            .field("ca", &self.ca.is_some())
+            .field("cert", &self.cert.is_some())
+            .field("server_name", &self.server_name)
+            .field("insecure_skip_verify", &self.insecure_skip_verify)
+            .finish()
+    }
+}
+
+/// 熔断配置
+///
+/// 每个节点独立熔断，状态流转：
+/// - 关闭：正常转发，统计滑动窗口内的失败率和慢调用率，任一达到阈值后打开。
+/// - 打开：不转发到该节点，切换到其他节点，无可用节点时返回降级响应。持续`open_secs`后半开。
+/// - 半开：允许`half_open_calls`个请求通过，全部成功则关闭，否则重新打开。
+///
+/// 失败：连接错误、超时或5xx响应。慢调用：收到响应头的耗时超过`slow_call_ms`。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CircuitBreaker {
+    /// 滑动窗口时长，单位：秒
+    #[serde(default = "default_window_secs")]
+    pub window_secs: u64,
+    /// 窗口内的最小请求数，请求数不足时不熔断
+    #[serde(default = "default_min_calls")]
+    pub min_calls: u32,
+    /// 失败率阈值，百分比
+    #[serde(default = "default_failure_rate")]
+    pub failure_rate: u32,
+    /// 慢调用时长，单位：毫秒
+    #[serde(default = "default_slow_call_ms")]
+    pub slow_call_ms: u64,
+    /// 慢调用率阈值，百分比，为100时仅在全部为慢调用时熔断
+    #[serde(default = "default_slow_call_rate")]
+    pub slow_call_rate: u32,
+    /// 打开状态的持续时长，单位：秒
+    #[serde(default = "default_open_secs")]
+    pub open_secs: u64,
+    /// 半开状态允许通过的请求数
+    #[serde(default = "default_half_open_calls")]
+    pub half_open_calls: u32,
+    /// 降级响应，所有节点都熔断时返回，为空时返回503
+    #[serde(default)]
+    pub fallback: Option<FallbackResponse>,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self {
+            window_secs: default_window_secs(),
+            min_calls: default_min_calls(),
+            failure_rate: default_failure_rate(),
+            slow_call_ms: default_slow_call_ms(),
+            slow_call_rate: default_slow_call_rate(),
+            open_secs: default_open_secs(),
+            half_open_calls: default_half_open_calls(),
+            fallback: None,
+        }
+    }
+}
+
+/// 降级响应
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FallbackResponse {
+    /// 状态码
+    #[serde(default = "default_fallback_status")]
+    pub status: u16,
+    /// 响应头
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// 响应体
+    #[serde(default)]
+    pub body: String,
+}
+
+/// 健康检查配置
+///
+/// 主动检查和被动检查可同时开启：
+/// - 主动检查：网关定时请求节点的检查地址，连续失败达到阈值后摘除节点，连续成功达到阈值后恢复。
+/// - 被动检查：网关在转发请求时统计节点的连接错误和5xx响应，连续失败达到阈值后摘除节点。
+///
+/// 被摘除的节点不参与负载均衡。未开启主动检查时，被动摘除的节点在`eject_secs`后自动恢复。
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HealthCheck {
+    /// 主动检查
+    #[serde(default)]
+    pub active: Option<ActiveHealthCheck>,
+    /// 被动检查
+    #[serde(default)]
+    pub passive: Option<PassiveHealthCheck>,
+}
+
+/// 主动健康检查
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActiveHealthCheck {
+    /// 检查路径，例如：/health
+    pub path: String,
+    /// 检查间隔，单位：秒
+    #[serde(default = "default_interval")]
+    pub interval: u64,
+    /// 检查请求超时时间，单位：毫秒
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+    /// 连续成功多少次后标记为健康
+    #[serde(default = "default_threshold")]
+    pub healthy_threshold: u32,
+    /// 连续失败多少次后标记为不健康
+    #[serde(default = "default_threshold")]
+    pub unhealthy_threshold: u32,
+}
+
+impl Default for ActiveHealthCheck {
+    fn default() -> Self {
+        Self {
+            path: "/".to_string(),
+            interval: default_interval(),
+            timeout: default_timeout(),
+            healthy_threshold: default_threshold(),
+            unhealthy_threshold: default_threshold(),
+        }
+    }
+}
+
+/// 被动健康检查
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PassiveHealthCheck {
+    /// 连续失败（连接错误或5xx响应）多少次后摘除节点
+    #[serde(default = "default_max_failures")]
+    pub max_failures: u32,
+    /// 摘除时长，单位：秒，仅在未开启主动检查时生效
+    #[serde(default = "default_eject_secs")]
+    pub eject_secs: u64,
+}
+
+impl Default for PassiveHealthCheck {
+    fn default() -> Self {
+        Self {
+            max_failures: default_max_failures(),
+            eject_secs: default_eject_secs(),
+        }
+    }
+}
+
+fn default_interval() -> u64 {
+    5
+}
+
+fn default_timeout() -> u64 {
+    1000
+}
+
+fn default_threshold() -> u32 {
+    2
+}
+
+fn default_max_failures() -> u32 {
+    5
+}
+
+fn default_eject_secs() -> u64 {
+    30
+}
+
+fn default_window_secs() -> u64 {
+    10
+}
+
+fn default_min_calls() -> u32 {
+    20
+}
+
+fn default_failure_rate() -> u32 {
+    50
+}
+
+fn default_slow_call_ms() -> u64 {
+    5000
+}
+
+fn default_slow_call_rate() -> u32 {
+    100
+}
+
+fn default_open_secs() -> u64 {
+    30
+}
+
+fn default_half_open_calls() -> u32 {
+    5
+}
+
+fn default_fallback_status() -> u16 {
+    503
 }
@@ -12,6 +12,9 @@ pub struct State {
     pub counter: Counter,
     /// 瞬时计数器
     pub moment_counter: MomentCounter,
+    /// 服务节点健康状态，仅包含开启了健康检查的服务
+    #[serde(default)]
+    pub node_health: Vec<NodeHealthState>,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -82,6 +85,11 @@ pub struct Counter {
     /// - 由安全组件拦截到的无效、非法、恶意请求等
     /// - 拦截的请求会返回403错误，也会被计算到4xx响应数中
     pub request_invalid_count: usize,
+    /// 被限流的请求数
+    /// 统计范围：
+    /// - 超出全局或路由限流配置，返回429的请求
+    #[serde(default)]
+    pub request_ratelimit_count: usize,
     /// 自从上次统计到现在的 2xx 响应数
     pub response_2xx_count: usize,
     /// 自从上次统计到现在的 3xx 响应数
@@ -111,12 +119,28 @@ pub struct MomentCounter {
     /// 统计范围：
     /// - 所有请求
     /// - 请求时，+1，清理或发生panic时，-1
-    /// - 不含被防火墙拦截的请求
+    /// - 包含被防火墙拦截的请求，拦截的请求同样在清理时-1
     ///
     pub http_connect_count: isize,
     // TODO SSE计数？
     pub sse_connect_count: isize,
+    /// 统计时刻的WebSocket连接数
+    /// 升级成功后+1，连接关闭时-1
+    #[serde(default)]
+    pub websocket_connect_count: isize,
+}
 
+/// 服务节点健康状态
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NodeHealthState {
+    /// 服务名
+    pub service: String,
+    /// 服务节点
+    pub node: String,
+    /// 是否健康
+    pub healthy: bool,
+    /// 连续失败次数
+    pub failures: u32,
 }
 
 impl State {
@@ -124,6 +148,7 @@ impl State {
         self.counter.request_count = 0;
         self.counter.response_time_since_last = 0;
         self.counter.request_invalid_count = 0;
+        self.counter.request_ratelimit_count = 0;
         self.counter.response_2xx_count = 0;
         self.counter.response_3xx_count = 0;
         self.counter.response_4xx_count = 0;
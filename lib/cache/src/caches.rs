@@ -9,4 +9,17 @@ pub enum CacheKey {
     /// API Key
     #[strum(to_string = "aiway:api:key:{0}")]
     ApiKey(String),
+
+    /// 限流计数
+    /// 0: 限流范围，global或路由
+    /// 1: 限流key，如客户端IP
+    #[strum(to_string = "aiway:ratelimit:{0}:{1}")]
+    RateLimit(String, String),
+
+    /// API Key用量计数
+    /// 0: 主体标识
+    /// 1: 统计周期，按天如20250101，按月如202501
+    /// 2: 计量项，requests或tokens
+    #[strum(to_string = "aiway:usage:{0}:{1}:{2}")]
+    Usage(String, String, String),
 }
@@ -13,6 +13,8 @@ use std::sync::OnceLock;
 mod local_cache;
 
 pub mod caches;
+mod rate_limit;
+pub use rate_limit::RateLimitResult;
 #[cfg(feature = "redis-cache")]
 mod redis_cache;
 #[cfg(feature = "share-cache")]
@@ -42,6 +44,26 @@ pub trait Cache: Send + Sync {
     async fn expire(&self, key: &str, ttl: i64) -> anyhow::Result<()>;
     /// 限流
     async fn ratelimit(&self, key: &str, limit: i32, time_window: i32) -> anyhow::Result<bool>;
+    /// 固定窗口限流
+    /// - limit: 窗口内允许的最大请求数
+    /// - window: 窗口时长，单位：秒
+    async fn fixed_window(
+        &self,
+        key: &str,
+        limit: u64,
+        window: u64,
+    ) -> anyhow::Result<RateLimitResult>;
+    /// 令牌桶限流
+    /// - capacity: 桶容量，即允许的突发请求数
+    /// - refill: 每个周期补充的令牌数
+    /// - window: 补充周期，单位：秒
+    async fn token_bucket(
+        &self,
+        key: &str,
+        capacity: u64,
+        refill: u64,
+        window: u64,
+    ) -> anyhow::Result<RateLimitResult>;
     /// 锁
     /// 简单实现的排他锁，主要用于防止定时任重复执行
     /// 除了定时任务外，尽量不要使用
@@ -161,6 +183,15 @@ pub async fn increment(key: &str, value: i64) -> anyhow::Result<i64> {
     }
 }
 
+/// 设置缓存的过期时间，单位：秒
+pub async fn expire(key: &str, ttl: i64) -> anyhow::Result<()> {
+    if let Some(cache) = CACHE.get() {
+        cache.expire(key, ttl).await
+    } else {
+        Err(anyhow::anyhow!("Cache not initialized"))
+    }
+}
+
 pub async fn ratelimit(key: &str, limit: i32, time_window: i32) -> anyhow::Result<bool> {
     if let Some(cache) = CACHE.get() {
         cache.ratelimit(key, limit, time_window).await
@@ -169,6 +200,27 @@ pub async fn ratelimit(key: &str, limit: i32, time_window: i32) -> anyhow::Resul
     }
 }
 
+pub async fn fixed_window(key: &str, limit: u64, window: u64) -> anyhow::Result<RateLimitResult> {
+    if let Some(cache) = CACHE.get() {
+        cache.fixed_window(key, limit, window).await
+    } else {
+        Err(anyhow::anyhow!("Cache not initialized"))
+    }
+}
+
+pub async fn token_bucket(
+    key: &str,
+    capacity: u64,
+    refill: u64,
+    window: u64,
+) -> anyhow::Result<RateLimitResult> {
+    if let Some(cache) = CACHE.get() {
+        cache.token_bucket(key, capacity, refill, window).await
+    } else {
+        Err(anyhow::anyhow!("Cache not initialized"))
+    }
+}
+
 pub async fn lock(key: &str, ttl: u64) -> anyhow::Result<()> {
     if let Some(cache) = CACHE.get() {
         cache.lock(key, ttl).await
@@ -1,9 +1,13 @@
+use crate::RateLimitResult;
+use crate::rate_limit::RateLimitState;
 use async_trait::async_trait;
 use moka::sync::Cache;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::fmt::Debug;
 use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CacheEntry {
@@ -21,6 +25,8 @@ pub struct CacheEntry {
 pub struct LocalCache {
     memory_cache: Cache<String, CacheEntry>,
     disk_db: sled::Db,
+    /// 限流状态，仅保存在内存中，不持久化
+    rate_limits: Cache<String, Arc<Mutex<RateLimitState>>>,
 }
 
 impl LocalCache {
@@ -33,9 +39,16 @@ impl LocalCache {
             .max_capacity(100_000)
             .build();
 
+        let rate_limits = Cache::builder()
+            .max_capacity(100_000)
+            // 长时间未访问的限流状态自动移除，移除后重新计数
+            .time_to_idle(Duration::from_secs(3600))
+            .build();
+
         let persistent_cache = Self {
             memory_cache: cache,
             disk_db: db,
+            rate_limits,
         };
 
         // 从磁盘加载
@@ -227,6 +240,40 @@ impl LocalCache {
         }
         Ok(count > limit as i64)
     }
+
+    fn rate_limit_state(&self, key: &str) -> Arc<Mutex<RateLimitState>> {
+        self.rate_limits.get_with(key.to_string(), || {
+            Arc::new(Mutex::new(RateLimitState::default()))
+        })
+    }
+
+    pub fn fixed_window(&self, key: &str, limit: u64, window: u64) -> RateLimitResult {
+        let now = Self::current_time_millis();
+        let state = self.rate_limit_state(key);
+        state.lock().unwrap().fixed_window(now, limit, window)
+    }
+
+    pub fn token_bucket(
+        &self,
+        key: &str,
+        capacity: u64,
+        refill: u64,
+        window: u64,
+    ) -> RateLimitResult {
+        let now = Self::current_time_millis();
+        let state = self.rate_limit_state(key);
+        state
+            .lock()
+            .unwrap()
+            .token_bucket(now, capacity, refill, window)
+    }
+
+    fn current_time_millis() -> i64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis() as i64)
+            .unwrap_or(0)
+    }
 }
 
 impl Drop for LocalCache {
@@ -269,6 +316,25 @@ impl crate::Cache for LocalCache {
         self.ratelimit(key, limit, time_window)
     }
 
+    async fn fixed_window(
+        &self,
+        key: &str,
+        limit: u64,
+        window: u64,
+    ) -> anyhow::Result<RateLimitResult> {
+        Ok(self.fixed_window(key, limit, window))
+    }
+
+    async fn token_bucket(
+        &self,
+        key: &str,
+        capacity: u64,
+        refill: u64,
+        window: u64,
+    ) -> anyhow::Result<RateLimitResult> {
+        Ok(self.token_bucket(key, capacity, refill, window))
+    }
+
     async fn lock(&self, _key: &str, _ttl: u64) -> anyhow::Result<()> {
         Ok(())
     }
@@ -0,0 +1,130 @@
+//! # 限流
+//! 固定窗口和令牌桶两种限流算法，本地缓存直接使用[`RateLimitState`]计算，
+//! Redis缓存使用Lua脚本实现相同的逻辑，以保证多个网关节点共享计数。
+//!
+
+/// 限流结果
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RateLimitResult {
+    /// 是否允许通过
+    pub allowed: bool,
+    /// 剩余可用请求数
+    pub remaining: u64,
+    /// 配额重置的剩余时间，单位：秒。
+    /// 固定窗口为当前窗口的剩余时间，令牌桶为令牌补满所需时间。
+    pub reset: u64,
+    /// 被拒绝时，建议客户端多久后重试，单位：秒。通过时为0
+    pub retry_after: u64,
+}
+
+/// 本地限流状态
+#[derive(Debug, Default)]
+pub(crate) struct RateLimitState {
+    /// 固定窗口：窗口开始时间；令牌桶：上次补充令牌的时间。单位：毫秒
+    ts: i64,
+    /// 固定窗口：窗口内的请求数；令牌桶：剩余令牌数
+    value: f64,
+}
+
+impl RateLimitState {
+    /// 固定窗口
+    ///
+    /// - limit: 窗口内允许的最大请求数
+    /// - window: 窗口时长，单位：秒
+    pub(crate) fn fixed_window(&mut self, now: i64, limit: u64, window: u64) -> RateLimitResult {
+        let window = (window.max(1) * 1000) as i64;
+        if now - self.ts >= window {
+            self.ts = now;
+            self.value = 0.0;
+        }
+        self.value += 1.0;
+
+        let count = self.value as u64;
+        let reset = ceil_secs(self.ts + window - now);
+        let allowed = count <= limit;
+        RateLimitResult {
+            allowed,
+            remaining: limit.saturating_sub(count),
+            reset,
+            retry_after: if allowed { 0 } else { reset },
+        }
+    }
+
+    /// 令牌桶
+    ///
+    /// - capacity: 桶容量，即允许的突发请求数
+    /// - refill: 每个周期补充的令牌数
+    /// - window: 补充周期，单位：秒
+    pub(crate) fn token_bucket(
+        &mut self,
+        now: i64,
+        capacity: u64,
+        refill: u64,
+        window: u64,
+    ) -> RateLimitResult {
+        // 每毫秒补充的令牌数
+        let rate = refill.max(1) as f64 / (window.max(1) * 1000) as f64;
+        let capacity = capacity as f64;
+        if self.ts == 0 {
+            self.value = capacity;
+        } else {
+            self.value = capacity.min(self.value + (now - self.ts).max(0) as f64 * rate);
+        }
+        self.ts = now;
+
+        let allowed = self.value >= 1.0;
+        if allowed {
+            self.value -= 1.0;
+        }
+        RateLimitResult {
+            allowed,
+            remaining: self.value as u64,
+            reset: ceil_secs(((capacity - self.value) / rate).ceil() as i64),
+            retry_after: if allowed {
+                0
+            } else {
+                ceil_secs(((1.0 - self.value) / rate).ceil() as i64)
+            },
+        }
+    }
+}
+
+/// 毫秒向上取整为秒
+pub(crate) fn ceil_secs(millis: i64) -> u64 {
+    (millis.max(0) as u64).div_ceil(1000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixed_window() {
+        let mut state = RateLimitState::default();
+        let now = 1_000_000;
+        assert!(state.fixed_window(now, 2, 10).allowed);
+        assert_eq!(state.fixed_window(now + 1000, 2, 10).remaining, 0);
+        let result = state.fixed_window(now + 2000, 2, 10);
+        assert!(!result.allowed);
+        assert_eq!(result.retry_after, 8);
+
+        // 下一个窗口重新计数
+        assert!(state.fixed_window(now + 10_000, 2, 10).allowed);
+    }
+
+    #[test]
+    fn test_token_bucket() {
+        let mut state = RateLimitState::default();
+        let now = 1_000_000;
+        // 容量为2，每秒补充1个
+        assert!(state.token_bucket(now, 2, 1, 1).allowed);
+        assert!(state.token_bucket(now, 2, 1, 1).allowed);
+        let result = state.token_bucket(now, 2, 1, 1);
+        assert!(!result.allowed);
+        assert_eq!(result.retry_after, 1);
+        assert_eq!(result.reset, 2);
+
+        assert!(state.token_bucket(now + 1000, 2, 1, 1).allowed);
+        assert!(!state.token_bucket(now + 1000, 2, 1, 1).allowed);
+    }
+}
@@ -1,8 +1,61 @@
+use crate::RateLimitResult;
+use crate::rate_limit::ceil_secs;
 use anyhow::anyhow;
 use async_trait::async_trait;
 use deadpool_redis::Runtime;
-use deadpool_redis::redis::{AsyncTypedCommands, IntegerReplyOrNoOp};
+use deadpool_redis::redis::{AsyncTypedCommands, IntegerReplyOrNoOp, Script};
 use serde_json::Value;
+use std::sync::LazyLock;
+
+/// 固定窗口限流脚本
+///
+/// 返回：{请求数, 窗口剩余时间(毫秒)}
+static FIXED_WINDOW_SCRIPT: LazyLock<Script> = LazyLock::new(|| {
+    Script::new(
+        r#"
+local count = redis.call('INCR', KEYS[1])
+local ttl = redis.call('PTTL', KEYS[1])
+if ttl < 0 then
+    redis.call('PEXPIRE', KEYS[1], ARGV[1])
+    ttl = tonumber(ARGV[1])
+end
+return {count, ttl}
+"#,
+    )
+});
+
+/// 令牌桶限流脚本，使用Redis服务器时间，避免各网关节点的时钟不一致
+///
+/// 返回：{是否通过, 剩余令牌数, 补满所需时间(毫秒), 下一个令牌所需时间(毫秒)}
+static TOKEN_BUCKET_SCRIPT: LazyLock<Script> = LazyLock::new(|| {
+    Script::new(
+        r#"
+local capacity = tonumber(ARGV[1])
+local rate = tonumber(ARGV[2])
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+local state = redis.call('HMGET', KEYS[1], 'tokens', 'ts')
+local tokens = tonumber(state[1])
+if tokens == nil then
+    tokens = capacity
+else
+    tokens = math.min(capacity, tokens + math.max(0, now - tonumber(state[2])) * rate)
+end
+local allowed = 0
+local wait = 0
+if tokens >= 1 then
+    tokens = tokens - 1
+    allowed = 1
+else
+    wait = math.ceil((1 - tokens) / rate)
+end
+local full = math.ceil((capacity - tokens) / rate)
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'ts', now)
+redis.call('PEXPIRE', KEYS[1], full + 1000)
+return {allowed, math.floor(tokens), full, wait}
+"#,
+    )
+});
 
 /// 单节点的Redis缓存
 pub struct RedisCache {
@@ -122,6 +175,46 @@ macro_rules! redis_ratelimit {
     }};
 }
 
+macro_rules! redis_fixed_window {
+    ($pool:expr, $key:expr, $limit:expr, $window:expr) => {{
+        let mut conn = $pool.get().await?;
+        let (count, ttl): (u64, i64) = FIXED_WINDOW_SCRIPT
+            .key($key)
+            .arg($window.max(1) * 1000)
+            .invoke_async(&mut conn)
+            .await
+            .map_err(|e| anyhow!("Redis error: {}", e))?;
+        let allowed = count <= $limit;
+        Ok(RateLimitResult {
+            allowed,
+            remaining: $limit.saturating_sub(count),
+            reset: ceil_secs(ttl),
+            retry_after: if allowed { 0 } else { ceil_secs(ttl) },
+        })
+    }};
+}
+
+macro_rules! redis_token_bucket {
+    ($pool:expr, $key:expr, $capacity:expr, $refill:expr, $window:expr) => {{
+        let mut conn = $pool.get().await?;
+        // 每毫秒补充的令牌数
+        let rate = $refill.max(1) as f64 / ($window.max(1) * 1000) as f64;
+        let (allowed, remaining, full, wait): (i64, u64, i64, i64) = TOKEN_BUCKET_SCRIPT
+            .key($key)
+            .arg($capacity)
+            .arg(rate)
+            .invoke_async(&mut conn)
+            .await
+            .map_err(|e| anyhow!("Redis error: {}", e))?;
+        Ok(RateLimitResult {
+            allowed: allowed == 1,
+            remaining,
+            reset: ceil_secs(full),
+            retry_after: ceil_secs(wait),
+        })
+    }};
+}
+
 macro_rules! redis_lock {
     ($pool:expr, $key:expr, $ttl:expr) => {{
         let mut conn = $pool.get().await?;
@@ -177,6 +270,25 @@ impl crate::Cache for RedisCache {
         redis_ratelimit!(self.pool, key, limit, time_window)
     }
 
+    async fn fixed_window(
+        &self,
+        key: &str,
+        limit: u64,
+        window: u64,
+    ) -> anyhow::Result<RateLimitResult> {
+        redis_fixed_window!(self.pool, key, limit, window)
+    }
+
+    async fn token_bucket(
+        &self,
+        key: &str,
+        capacity: u64,
+        refill: u64,
+        window: u64,
+    ) -> anyhow::Result<RateLimitResult> {
+        redis_token_bucket!(self.pool, key, capacity, refill, window)
+    }
+
     async fn lock(&self, key: &str, ttl: u64) -> anyhow::Result<()> {
         redis_lock!(self.pool, key, ttl)
     }
@@ -232,6 +344,25 @@ impl crate::Cache for RedisClusterCache {
         redis_ratelimit!(self.pool, key, limit, time_window as i64)
     }
 
+    async fn fixed_window(
+        &self,
+        key: &str,
+        limit: u64,
+        window: u64,
+    ) -> anyhow::Result<RateLimitResult> {
+        redis_fixed_window!(self.pool, key, limit, window)
+    }
+
+    async fn token_bucket(
+        &self,
+        key: &str,
+        capacity: u64,
+        refill: u64,
+        window: u64,
+    ) -> anyhow::Result<RateLimitResult> {
+        redis_token_bucket!(self.pool, key, capacity, refill, window)
+    }
+
     async fn lock(&self, key: &str, ttl: u64) -> anyhow::Result<()> {
         redis_lock!(self.pool, key, ttl)
     }
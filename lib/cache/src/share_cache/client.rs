@@ -16,6 +16,10 @@ trait ShareCacheClient {
     async fn remove(&self, key: &str) -> anyhow::Result<()>;
 
     async fn ttl(&self, key: &str) -> anyhow::Result<i64>;
+
+    async fn increment(&self, key: &str, value: i64) -> anyhow::Result<i64>;
+
+    async fn expire(&self, key: &str, ttl: i64) -> anyhow::Result<()>;
 }
 
 #[derive(Debug, Clone)]
@@ -74,15 +78,34 @@ impl crate::Cache for ShareCache {
         unimplemented!()
     }
 
-    async fn increment(&self, _key: &str, _value: i64) -> anyhow::Result<i64> {
+    async fn increment(&self, key: &str, value: i64) -> anyhow::Result<i64> {
+        self.proxy.increment(key, value).await
+    }
+
+    async fn expire(&self, key: &str, ttl: i64) -> anyhow::Result<()> {
+        self.proxy.expire(key, ttl).await
+    }
+
+    async fn ratelimit(&self, _key: &str, _limit: i32, _time_window: i32) -> anyhow::Result<bool> {
         unimplemented!()
     }
 
-    async fn expire(&self, _key: &str, _ttl: i64) -> anyhow::Result<()> {
+    async fn fixed_window(
+        &self,
+        _key: &str,
+        _limit: u64,
+        _window: u64,
+    ) -> anyhow::Result<crate::RateLimitResult> {
         unimplemented!()
     }
 
-    async fn ratelimit(&self, _key: &str, _limit: i32, _time_window: i32) -> anyhow::Result<bool> {
+    async fn token_bucket(
+        &self,
+        _key: &str,
+        _capacity: u64,
+        _refill: u64,
+        _window: u64,
+    ) -> anyhow::Result<crate::RateLimitResult> {
         unimplemented!()
     }
 
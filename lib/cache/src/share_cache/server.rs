@@ -47,6 +47,18 @@ impl ShareCacheServer {
             .ttl(key)
             .map_err(|e| zbus::fdo::Error::Failed(e.to_string()))
     }
+
+    async fn increment(&self, key: &str, value: i64) -> Result<i64, zbus::fdo::Error> {
+        self.local_cache
+            .increment(key.to_string(), value)
+            .map_err(|e| zbus::fdo::Error::Failed(e.to_string()))
+    }
+
+    async fn expire(&self, key: &str, ttl: i64) -> Result<(), zbus::fdo::Error> {
+        self.local_cache
+            .expire(key.to_string(), ttl)
+            .map_err(|e| zbus::fdo::Error::Failed(e.to_string()))
+    }
 }
 
 /// 启动zbus服务
@@ -4,12 +4,41 @@ pub struct Headers;
 impl Headers {
     pub const REQUEST_ID: &'static str = "x-aiway-request-id";
     pub const REQUEST_TIME: &'static str = "x-aiway-request-time";
+    /// 解析后的客户端IP，由网关在收到请求时设置，会覆盖客户端传入的同名Header
+    pub const CLIENT_IP: &'static str = "x-aiway-client-ip";
+    /// 直连网关的对端IP，由网关在收到请求时设置，会覆盖客户端传入的同名Header
+    pub const PEER_IP: &'static str = "x-aiway-peer-ip";
+    /// 网关收到请求的协议，http或https，由网关在收到请求时设置，会覆盖客户端传入的同名Header
+    pub const SCHEME: &'static str = "x-aiway-scheme";
+    pub const X_FORWARDED_FOR: &'static str = "x-forwarded-for";
+    pub const X_REAL_IP: &'static str = "x-real-ip";
+    pub const X_FORWARDED_PROTO: &'static str = "x-forwarded-proto";
+    pub const X_FORWARDED_HOST: &'static str = "x-forwarded-host";
+    pub const FORWARDED: &'static str = "forwarded";
+    pub const X_REQUEST_ID: &'static str = "x-request-id";
+    pub const CONNECTION: &'static str = "connection";
+    pub const UPGRADE: &'static str = "upgrade";
+    pub const TE: &'static str = "te";
+    pub const GRPC_STATUS: &'static str = "grpc-status";
+    pub const GRPC_MESSAGE: &'static str = "grpc-message";
+    /// gRPC监听器转发到Rocket的调用标识，仅网关内部使用
+    pub const GRPC_CALL: &'static str = "x-aiway-grpc-call";
     pub const AUTHORIZATION: &'static str = "authorization";
     pub const ERROR_CODE: &'static str = "x-error-code";
     pub const ERROR_MESSAGE: &'static str = "x-error-message";
+    /// 网关错误原因，仅在网关内部的响应中传递，用于记录请求日志
+    pub const ERROR_REASON: &'static str = "x-aiway-error-reason";
     pub const REFERER: &'static str = "referer";
     pub const USER_AGENT: &'static str = "user-agent";
     pub const CONTENT_TYPE: &'static str = "content-type";
+    /// 限流：周期内允许的请求数
+    pub const RATELIMIT_LIMIT: &'static str = "x-ratelimit-limit";
+    /// 限流：剩余可用请求数
+    pub const RATELIMIT_REMAINING: &'static str = "x-ratelimit-remaining";
+    /// 限流：配额重置的剩余时间，单位：秒
+    pub const RATELIMIT_RESET: &'static str = "x-ratelimit-reset";
+    pub const RETRY_AFTER: &'static str = "retry-after";
+    pub const LOCATION: &'static str = "location";
 }
 
 impl Headers {
@@ -19,4 +48,15 @@ impl Headers {
             .unwrap()
             .to_string()
     }
+
+    /// 获取客户端IP，优先取网关解析后的IP，取不到时使用对端IP
+    pub fn get_client_ip(req: &Request) -> Option<String> {
+        req.headers()
+            .get_one(Headers::CLIENT_IP)
+            .map(|s| s.to_string())
+            .or_else(|| {
+                req.remote()
+                    .map(|addr| addr.ip().to_canonical().to_string())
+            })
+    }
 }
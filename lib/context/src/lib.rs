@@ -37,14 +37,11 @@ impl Fairing for HttpContextFairing {
         }
     }
 
-    async fn on_request(&self, req: &mut Request<'_>, data: &mut Data<'_>) {
+    async fn on_request(&self, req: &mut Request<'_>, _data: &mut Data<'_>) {
         skip_if_error!(req);
+        // 请求体在路由匹配后按路由配置提取，见[`take_body`]
         let context = HttpContextOnce::from_request(req).0;
 
-        // 提取body，后续的body将不可用，仅能通过context获取。
-        let body = data.take().await;
-        context.request.set_body(bytes::Bytes::from(body));
-
         let request_id = context.request.request_id.clone();
 
         // 设置请求上下文
@@ -53,6 +50,12 @@ impl Fairing for HttpContextFairing {
     }
 }
 
+/// 提取请求体到上下文，提取后原始的请求体将不可用，仅能通过上下文获取。
+pub async fn take_body(context: &HttpContext, data: &mut Data<'_>) {
+    let body = data.take().await;
+    context.request.set_body(bytes::Bytes::from(body));
+}
+
 /// 请求上下文包装器
 ///
 /// - 注意需要调用[HttpContextFairing]，将上下文保存到HCM。
@@ -97,7 +100,13 @@ impl HttpContextOnce {
             .headers()
             .iter()
             // 移除不需要透传到下游服务的Header
-            .filter(|h| h.name().ne("content-length") && h.name().ne("authorization"))
+            .filter(|h| {
+                h.name().ne("content-length")
+                    && h.name().ne("authorization")
+                    && h.name().ne(Headers::CLIENT_IP)
+                    && h.name().ne(Headers::PEER_IP)
+                    && h.name().ne(Headers::SCHEME)
+            })
             .map(|h| (h.name().to_string(), h.value().to_string()))
             .collect::<DashMap<String, String>>();
 
@@ -129,9 +138,23 @@ impl HttpContextOnce {
             body: Default::default(),
             state: Default::default(),
             route: SV::empty(),
+            service: SV::empty(),
+            split: SV::empty(),
             routing_url: SV::empty(),
+            path_params: Default::default(),
             //routing_path: SV::new(req.uri().path().to_string()),
             host: req.host().unwrap().to_string(),
+            client_ip: Headers::get_client_ip(req).unwrap_or_default(),
+            peer_ip: req
+                .headers()
+                .get_one(Headers::PEER_IP)
+                .map(|s| s.to_string())
+                .unwrap_or_default(),
+            scheme: req
+                .headers()
+                .get_one(Headers::SCHEME)
+                .map(|s| s.to_string())
+                .unwrap_or_default(),
         };
 
         // 响应上下文
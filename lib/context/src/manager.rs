@@ -30,6 +30,11 @@ impl HttpContextManager {
         }
     }
 
+    /// 通过请求ID查找上下文，不存在时返回None，如请求在构建上下文前已被拒绝
+    pub fn find(&self, id: &str) -> Option<Arc<HttpContext>> {
+        self.contexts.get(id).map(|v| v.value().clone())
+    }
+
     /// 从rocket的request中获取上下文
     pub fn get_from_request(&self, req: &Request) -> Arc<HttpContext> {
         let id = req
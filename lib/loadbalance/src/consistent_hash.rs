@@ -0,0 +1,134 @@
+use crate::LoadBalance;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+
+/// 每个权重对应的虚拟节点数
+const VIRTUAL_NODES: u32 = 160;
+
+/// 哈希环，按哈希值排序的虚拟节点：(哈希值, 实例下标)
+type Ring = Vec<(u64, usize)>;
+
+/// 一致性哈希负载均衡
+///
+/// 按调用方提供的key（如客户端IP、请求头、请求参数）选择实例，相同的key总是落在同一实例上，
+/// 实例增减时仅影响少部分key。
+///
+/// - 每个实例的虚拟节点数为`160 * 权重`，未配置权重的实例权重为1。
+/// - 哈希环按传入的实例列表构建并缓存，实例列表变化（如节点被健康检查摘除）时重建。
+/// - 未提供key时随机选择。
+pub struct ConsistentHashLoadBalance<T> {
+    /// 实例权重
+    weights: HashMap<T, u32>,
+    /// 哈希环缓存：(实例列表, 哈希环)
+    ring: Mutex<Option<(Vec<T>, Ring)>>,
+}
+
+impl<T: Clone + Eq + Hash> ConsistentHashLoadBalance<T> {
+    pub fn new(weights: HashMap<T, u32>) -> Self {
+        Self {
+            weights,
+            ring: Mutex::new(None),
+        }
+    }
+
+    fn build_ring(&self, instances: &[T]) -> Ring {
+        let mut ring = Vec::new();
+        for (index, instance) in instances.iter().enumerate() {
+            let weight = self.weights.get(instance).copied().unwrap_or(1);
+            for i in 0..VIRTUAL_NODES * weight {
+                let mut hasher = StableHasher::new();
+                instance.hash(&mut hasher);
+                i.hash(&mut hasher);
+                ring.push((hasher.finish(), index));
+            }
+        }
+        ring.sort_unstable();
+        ring
+    }
+}
+
+impl<T: Clone + Eq + Hash + Send + Sync> LoadBalance<T> for ConsistentHashLoadBalance<T> {
+    fn select(&self, instances: &[T]) -> Option<T> {
+        if instances.is_empty() {
+            return None;
+        }
+        Some(instances[fastrand::usize(0..instances.len())].clone())
+    }
+
+    fn select_with_key(&self, instances: &[T], key: Option<&str>) -> Option<T> {
+        let key = match key {
+            Some(key) if instances.len() > 1 => key,
+            _ => return self.select(instances),
+        };
+
+        let mut cache = self.ring.lock().unwrap();
+        if cache.as_ref().is_none_or(|(cached, _)| cached != instances) {
+            *cache = Some((instances.to_vec(), self.build_ring(instances)));
+        }
+        let (_, ring) = cache.as_ref()?;
+
+        let mut hasher = StableHasher::new();
+        key.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        // 顺时针找到第一个虚拟节点，超过末尾时回到开头
+        let index = ring.partition_point(|(h, _)| *h < hash);
+        let (_, instance) = ring.get(index).or(ring.first())?;
+        Some(instances[*instance].clone())
+    }
+}
+
+/// FNV-1a哈希，结果与进程和编译版本无关，保证多个网关节点对相同key的选择一致
+struct StableHasher(u64);
+
+impl StableHasher {
+    fn new() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+}
+
+impl Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        // fmix64，改善FNV在短key上的分布
+        let mut h = self.0;
+        h ^= h >> 33;
+        h = h.wrapping_mul(0xff51afd7ed558ccd);
+        h ^= h >> 33;
+        h = h.wrapping_mul(0xc4ceb9fe1a85ec53);
+        h ^= h >> 33;
+        h
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_consistent_hash() {
+        let lb = ConsistentHashLoadBalance::new(HashMap::new());
+        let instances = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+
+        // 相同key选择相同实例
+        let selected = lb.select_with_key(&instances, Some("127.0.0.1"));
+        for _ in 0..10 {
+            assert_eq!(lb.select_with_key(&instances, Some("127.0.0.1")), selected);
+        }
+
+        // 移除未被选中的实例，不影响选择结果
+        let remain = instances
+            .iter()
+            .filter(|i| Some(*i) == selected.as_ref() || *i == "a")
+            .cloned()
+            .collect::<Vec<_>>();
+        assert_eq!(lb.select_with_key(&remain, Some("127.0.0.1")), selected);
+    }
+}
@@ -0,0 +1,107 @@
+use crate::LoadBalance;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Mutex;
+
+/// 最少活跃请求负载均衡
+///
+/// 选择活跃请求数与权重之比最小的实例，存在多个时随机选择一个。
+/// 选中后活跃请求数加1，调用方需在请求完成后调用[`LoadBalance::release`]减1。
+///
+/// 未配置权重的实例权重为1。
+pub struct LeastActiveLoadBalance<T> {
+    /// 实例权重
+    weights: HashMap<T, u32>,
+    /// 实例的活跃请求数
+    active: Mutex<HashMap<T, usize>>,
+}
+
+impl<T: Clone + Eq + Hash> LeastActiveLoadBalance<T> {
+    pub fn new(weights: HashMap<T, u32>) -> Self {
+        Self {
+            weights,
+            active: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn weight(&self, instance: &T) -> usize {
+        self.weights.get(instance).copied().unwrap_or(1) as usize
+    }
+}
+
+impl<T: Clone + Eq + Hash + Send + Sync> LoadBalance<T> for LeastActiveLoadBalance<T> {
+    fn select(&self, instances: &[T]) -> Option<T> {
+        if instances.is_empty() {
+            return None;
+        }
+
+        let mut active = self.active.lock().unwrap();
+
+        // 活跃请求数最少的实例，比较 a1/w1 < a2/w2 即 a1*w2 < a2*w1
+        let mut candidates: Vec<&T> = Vec::new();
+        let mut least: Option<(usize, usize)> = None;
+        for instance in instances {
+            let count = active.get(instance).copied().unwrap_or(0);
+            let weight = self.weight(instance);
+            match least {
+                Some((c, w)) if count * w > c * weight => {}
+                Some((c, w)) if count * w == c * weight => candidates.push(instance),
+                _ => {
+                    least = Some((count, weight));
+                    candidates.clear();
+                    candidates.push(instance);
+                }
+            }
+        }
+
+        let selected = match candidates.len() {
+            0 => return None,
+            1 => candidates[0],
+            len => candidates[fastrand::usize(0..len)],
+        };
+        *active.entry(selected.clone()).or_insert(0) += 1;
+
+        Some(selected.clone())
+    }
+
+    fn release(&self, instance: &T) {
+        let mut active = self.active.lock().unwrap();
+        if let Some(count) = active.get_mut(instance) {
+            *count = count.saturating_sub(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_least_active() {
+        let weights = HashMap::from([("a".to_string(), 2)]);
+        let lb = LeastActiveLoadBalance::new(weights);
+        let instances = vec!["a".to_string(), "b".to_string()];
+
+        // a的权重为2，活跃请求数按2:1分布
+        let mut counts = HashMap::new();
+        for _ in 0..30 {
+            *counts.entry(lb.select(&instances).unwrap()).or_insert(0) += 1;
+        }
+        assert_eq!(counts["a"], 20);
+        assert_eq!(counts["b"], 10);
+
+        // 释放后优先选择活跃请求数少的实例
+        for _ in 0..3 {
+            lb.release(&"b".to_string());
+        }
+        for _ in 0..3 {
+            assert_eq!(lb.select(&instances).unwrap(), "b");
+        }
+
+        // 释放不存在或已为0的实例不会溢出
+        lb.release(&"c".to_string());
+        let lb = LeastActiveLoadBalance::new(HashMap::new());
+        lb.release(&"a".to_string());
+        assert_eq!(lb.active.lock().unwrap().get("a"), None);
+    }
+}
@@ -1,11 +1,17 @@
 //! # 网关服务端的负载均衡
 //!
 //!
+mod consistent_hash;
+mod least_active;
 mod random;
 mod round;
+mod weighted_round;
 
+pub use consistent_hash::ConsistentHashLoadBalance;
+pub use least_active::LeastActiveLoadBalance;
 pub use random::RandomLoadBalance;
 pub use round::RoundRobinLoadBalance;
+pub use weighted_round::WeightedRoundRobinLoadBalance;
 
 /// 实例提供者
 pub trait Instances<T: Clone> {
@@ -23,6 +29,17 @@ impl Instances<String> for Vec<String> {
 pub trait LoadBalance<T: Clone>: Sync + Send {
     /// 从实例中选择一个
     fn select(&self, instances: &[T]) -> Option<T>;
+
+    /// 按key从实例中选择一个，仅一致性哈希使用key，其他策略忽略key
+    fn select_with_key(&self, instances: &[T], key: Option<&str>) -> Option<T> {
+        let _ = key;
+        self.select(instances)
+    }
+
+    /// 释放实例，在请求完成后调用，仅最少活跃请求使用
+    fn release(&self, instance: &T) {
+        let _ = instance;
+    }
 }
 
 /// 负载均衡错误类型
@@ -55,6 +72,12 @@ pub enum LoadBalanceStrategy {
     Random,
     /// 轮询
     RoundRobin,
+    /// 平滑加权轮询
+    WeightedRoundRobin,
+    /// 最少活跃请求
+    LeastActive,
+    /// 一致性哈希
+    ConsistentHash,
 }
 
 impl LoadBalanceStrategy {
@@ -63,6 +86,9 @@ impl LoadBalanceStrategy {
         match self {
             LoadBalanceStrategy::Random => "r",
             LoadBalanceStrategy::RoundRobin => "rr",
+            LoadBalanceStrategy::WeightedRoundRobin => "wrr",
+            LoadBalanceStrategy::LeastActive => "la",
+            LoadBalanceStrategy::ConsistentHash => "ch",
         }
     }
 }
@@ -0,0 +1,89 @@
+use crate::LoadBalance;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Mutex;
+
+/// 平滑加权轮询负载均衡
+///
+/// 与nginx的实现一致：每次选择时，所有实例的当前权重加上其配置权重，
+/// 选择当前权重最大的实例，并将其当前权重减去总权重。
+/// 相比普通加权轮询，同一实例不会被连续选中，分布更均匀。
+///
+/// 未配置权重的实例权重为1。
+pub struct WeightedRoundRobinLoadBalance<T> {
+    /// 实例权重
+    weights: HashMap<T, u32>,
+    /// 实例的当前权重
+    current: Mutex<HashMap<T, i64>>,
+}
+
+impl<T: Clone + Eq + Hash> WeightedRoundRobinLoadBalance<T> {
+    pub fn new(weights: HashMap<T, u32>) -> Self {
+        Self {
+            weights,
+            current: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn weight(&self, instance: &T) -> i64 {
+        self.weights.get(instance).copied().unwrap_or(1) as i64
+    }
+}
+
+impl<T: Clone + Eq + Hash + Send + Sync> LoadBalance<T> for WeightedRoundRobinLoadBalance<T> {
+    fn select(&self, instances: &[T]) -> Option<T> {
+        if instances.is_empty() {
+            return None;
+        }
+
+        if instances.len() == 1 {
+            return Some(instances[0].clone());
+        }
+
+        let mut current = self.current.lock().unwrap();
+        let mut total = 0;
+        let mut best: Option<(&T, i64)> = None;
+        for instance in instances {
+            let weight = self.weight(instance);
+            let current_weight = current.entry(instance.clone()).or_insert(0);
+            *current_weight += weight;
+            total += weight;
+            if best.is_none_or(|(_, w)| *current_weight > w) {
+                best = Some((instance, *current_weight));
+            }
+        }
+
+        let (best, _) = best?;
+        if let Some(current_weight) = current.get_mut(best) {
+            *current_weight -= total;
+        }
+        Some(best.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_weighted_round_robin() {
+        let weights = HashMap::from([("a".to_string(), 5), ("b".to_string(), 1)]);
+        let lb = WeightedRoundRobinLoadBalance::new(weights);
+        let instances = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+
+        // 与nginx的平滑加权轮询顺序一致，c未配置权重，按1处理
+        let sequence = (0..7)
+            .map(|_| lb.select(&instances).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(sequence, ["a", "a", "b", "a", "c", "a", "a"]);
+
+        // 按权重比例分布
+        let mut counts = HashMap::new();
+        for _ in 0..700 {
+            *counts.entry(lb.select(&instances).unwrap()).or_insert(0) += 1;
+        }
+        assert_eq!(counts["a"], 500);
+        assert_eq!(counts["b"], 100);
+        assert_eq!(counts["c"], 100);
+    }
+}
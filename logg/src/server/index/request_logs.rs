@@ -12,11 +12,13 @@ use tantivy::aggregation::{AggregationCollector, AggregationLimitsGuard};
 use tantivy::collector::TopDocs;
 use tantivy::directory::MmapDirectory;
 use tantivy::query::{Query, QueryParser};
-use tantivy::schema::{DateOptions, DateTimePrecision, FAST, Field, STORED, Schema, TEXT, Value};
+use tantivy::schema::{
+    DateOptions, DateTimePrecision, FAST, Field, STORED, STRING, Schema, TEXT, Value,
+};
 use tantivy::tokenizer::{LowerCaser, TextAnalyzer};
 use tantivy::{
-    DateTime, Document, Index, IndexReader, IndexWriter, Order, ReloadPolicy, TantivyDocument,
-    TantivyError,
+    DateTime, DocAddress, Document, Index, IndexReader, IndexSettings, IndexWriter, Order,
+    ReloadPolicy, TantivyDocument, TantivyError,
 };
 
 struct Fields {
@@ -35,6 +37,13 @@ struct Fields {
     user_agent: Field,
     referer: Field,
     node_address: Field,
+    error_reason: Field,
+    service: Field,
+    split: Field,
+    mirror_service: Field,
+    mirror_status_code: Field,
+    mirror_elapsed: Field,
+    mirror_error_reason: Field,
 }
 
 impl Fields {
@@ -55,6 +64,13 @@ impl Fields {
             user_agent: schema.get_field("user_agent").unwrap(),
             referer: schema.get_field("referer").unwrap(),
             node_address: schema.get_field("node_address").unwrap(),
+            error_reason: schema.get_field("error_reason").unwrap(),
+            service: schema.get_field("service").unwrap(),
+            split: schema.get_field("split").unwrap(),
+            mirror_service: schema.get_field("mirror_service").unwrap(),
+            mirror_status_code: schema.get_field("mirror_status_code").unwrap(),
+            mirror_elapsed: schema.get_field("mirror_elapsed").unwrap(),
+            mirror_error_reason: schema.get_field("mirror_error_reason").unwrap(),
         }
     }
 }
@@ -112,14 +128,102 @@ impl Logg {
         sb.add_text_field("user_agent", TEXT | STORED);
         sb.add_text_field("referer", TEXT | STORED);
         sb.add_text_field("node_address", TEXT | STORED);
+        sb.add_text_field("error_reason", TEXT | STORED | FAST);
+        sb.add_text_field("service", STRING | STORED | FAST);
+        sb.add_text_field("split", STRING | STORED | FAST);
+        sb.add_text_field("mirror_service", STRING | STORED | FAST);
+        sb.add_u64_field("mirror_status_code", FAST | STORED);
+        sb.add_i64_field("mirror_elapsed", STORED);
+        sb.add_text_field("mirror_error_reason", STRING | STORED | FAST);
 
         let schema = sb.build();
 
+        let backup = Self::backup_dir(dir);
         if !Path::new(dir).exists() {
-            fs::create_dir_all(dir)?;
+            if Path::new(&backup).exists() {
+                // 迁移过程中替换索引目录时中断，恢复旧索引后重新迁移
+                eprintln!("request log index is missing, restore it from {}", backup);
+                fs::rename(&backup, dir)?;
+            } else {
+                fs::create_dir_all(dir)?;
+            }
+        }
+
+        let directory = MmapDirectory::open(dir)?;
+        if Index::exists(&directory)? {
+            let index = Index::open(directory)?;
+            if index.schema() == schema {
+                // 迁移完成但未删除备份时，新索引已可用，删除备份
+                if Path::new(&backup).exists() {
+                    fs::remove_dir_all(&backup)?;
+                }
+                return Ok(index);
+            }
+            // 旧版本创建的索引缺少新增的字段，按新的Schema重建索引
+            return Self::migrate_index(dir, index, schema);
+        }
+        Index::create(directory, schema, IndexSettings::default())
+    }
+
+    /// 将旧索引中的日志按字段名复制到新Schema的索引中，完成后替换旧索引
+    ///
+    /// 所有字段都是STORED，可以从文档存储中完整读取。新索引先写入临时目录，
+    /// 重建失败时返回错误，旧索引保持不变。
+    fn migrate_index(dir: &str, old: Index, schema: Schema) -> Result<Index, TantivyError> {
+        let dir = dir.trim_end_matches('/');
+        let migrating = format!("{}.migrating", dir);
+        let backup = Self::backup_dir(dir);
+        if Path::new(&migrating).exists() {
+            fs::remove_dir_all(&migrating)?;
+        }
+        fs::create_dir_all(&migrating)?;
+
+        let index = Index::create(
+            MmapDirectory::open(&migrating)?,
+            schema.clone(),
+            IndexSettings::default(),
+        )?;
+        let mut index_writer: IndexWriter = index.writer(Self::MEMORY_BUDGET_IN_BYTES)?;
+        let old_schema = old.schema();
+        let searcher = old.reader()?.searcher();
+        let mut count = 0;
+        for (segment_ord, segment_reader) in searcher.segment_readers().iter().enumerate() {
+            for doc_id in 0..segment_reader.max_doc() {
+                if segment_reader.is_deleted(doc_id) {
+                    continue;
+                }
+                let doc: TantivyDocument =
+                    searcher.doc(DocAddress::new(segment_ord as u32, doc_id))?;
+                let doc = TantivyDocument::parse_json(&schema, &doc.to_json(&old_schema))
+                    .map_err(|e| TantivyError::InvalidArgument(e.to_string()))?;
+                index_writer.add_document(doc)?;
+                count += 1;
+            }
         }
+        index_writer.commit()?;
+        index_writer.wait_merging_threads()?;
+        drop(searcher);
+        drop(old);
+        drop(index);
+
+        if Path::new(&backup).exists() {
+            fs::remove_dir_all(&backup)?;
+        }
+        // 替换过程中中断时，启动时从备份恢复旧索引，见[`open_or_create_index`](Self::open_or_create_index)
+        fs::rename(dir, &backup)?;
+        fs::rename(&migrating, dir)?;
+        let index = Index::open_in_dir(dir)?;
+        fs::remove_dir_all(&backup)?;
+        eprintln!(
+            "request log index schema is outdated, migrated {} logs to the new schema",
+            count
+        );
+        Ok(index)
+    }
 
-        Index::open_or_create(MmapDirectory::open(dir)?, schema)
+    /// 迁移时旧索引的备份目录
+    fn backup_dir(dir: &str) -> String {
+        format!("{}.old", dir.trim_end_matches('/'))
     }
 
     fn register_tokenizer(index: &Index) {
@@ -179,6 +283,28 @@ impl Logg {
             }
             doc.add_text(self.fields.node_address, &entry.node_address);
 
+            if let Some(reason) = &entry.error_reason {
+                doc.add_text(self.fields.error_reason, reason);
+            }
+            if let Some(service) = &entry.service {
+                doc.add_text(self.fields.service, service);
+            }
+            if let Some(split) = &entry.split {
+                doc.add_text(self.fields.split, split);
+            }
+            if let Some(service) = &entry.mirror_service {
+                doc.add_text(self.fields.mirror_service, service);
+            }
+            if let Some(status) = entry.mirror_status_code {
+                doc.add_u64(self.fields.mirror_status_code, status as u64);
+            }
+            if let Some(elapsed) = entry.mirror_elapsed {
+                doc.add_i64(self.fields.mirror_elapsed, elapsed);
+            }
+            if let Some(reason) = &entry.mirror_error_reason {
+                doc.add_text(self.fields.mirror_error_reason, reason);
+            }
+
             let _ = index_writer.add_document(doc);
         });
         index_writer.commit().unwrap();
@@ -309,6 +435,27 @@ impl Logg {
                         log_entry.node_address =
                             value.as_str().map(|s| s.to_string()).unwrap_or_default();
                     }
+                    fid if fid == self.fields.error_reason.field_id() => {
+                        log_entry.error_reason = value.as_str().map(|s| s.to_string());
+                    }
+                    fid if fid == self.fields.service.field_id() => {
+                        log_entry.service = value.as_str().map(|s| s.to_string());
+                    }
+                    fid if fid == self.fields.split.field_id() => {
+                        log_entry.split = value.as_str().map(|s| s.to_string());
+                    }
+                    fid if fid == self.fields.mirror_service.field_id() => {
+                        log_entry.mirror_service = value.as_str().map(|s| s.to_string());
+                    }
+                    fid if fid == self.fields.mirror_status_code.field_id() => {
+                        log_entry.mirror_status_code = value.as_u64().map(|v| v as u16);
+                    }
+                    fid if fid == self.fields.mirror_elapsed.field_id() => {
+                        log_entry.mirror_elapsed = value.as_i64();
+                    }
+                    fid if fid == self.fields.mirror_error_reason.field_id() => {
+                        log_entry.mirror_error_reason = value.as_str().map(|s| s.to_string());
+                    }
 
                     _ => {}
                 }